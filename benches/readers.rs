@@ -0,0 +1,382 @@
+//! Benchmarks of the ASCII readers, with the monkey of Blender repeated until it has about a
+//! million vertices.
+//!
+//! The readers are compared with the readers as they were before the byte tokenizer, ported
+//! here for the sections of the scaled files: they split lines with a regular expression and
+//! parse items with `str::parse`, but create the same entities into the same mesh.
+
+#[macro_use]
+extern crate criterion;
+extern crate multimesh;
+extern crate regex;
+
+use criterion::{Criterion, Throughput};
+use multimesh::{
+    data::{
+        attribute::{keys, AttributeContainerMut, AttributeMap, AttributeName, AttributeValue},
+        columnar::ColumnarMesh,
+        CellType, EntityBox, EntityKind, EntityMut, SetMesh, SetMeshGroup,
+    },
+    de::{read_events, Deserializer, Event},
+    error::Error,
+    format::{
+        medit::{MeditDeserializer, ParallelMeditDeserializer},
+        naming::{Format, Name},
+        ply::{ParallelPlyDeserializer, PlyDeserializer},
+    },
+};
+use regex::Regex;
+use std::{collections::VecDeque, fmt::Write, str::Lines};
+
+const MONKEY: &str = include_str!("../tests/files/blender-monkey.mesh");
+const COPIES: usize = 2000;
+
+/// The vertices (with reference) and triangles (one-based, with reference) of the monkey.
+fn monkey() -> (Vec<Vec<&'static str>>, Vec<Vec<usize>>) {
+    let lines: Vec<&str> = MONKEY.lines().collect();
+    let section = |keyword: &str| {
+        let start = lines.iter().position(|l| l.trim() == keyword).unwrap();
+        let len: usize = lines[start + 1].trim().parse().unwrap();
+        &lines[start + 2..start + 2 + len]
+    };
+    let vertices = section("Vertices")
+        .iter()
+        .map(|l| l.split_whitespace().collect())
+        .collect();
+    let triangles = section("Triangles")
+        .iter()
+        .map(|l| l.split_whitespace().map(|i| i.parse().unwrap()).collect())
+        .collect();
+    (vertices, triangles)
+}
+
+fn scaled_medit() -> String {
+    let (vertices, triangles) = monkey();
+    let mut text = String::new();
+    writeln!(text, "MeshVersionFormatted 1\nDimension 3\n").unwrap();
+    writeln!(text, "Vertices\n{}", vertices.len() * COPIES).unwrap();
+    for _ in 0..COPIES {
+        for vertex in &vertices {
+            writeln!(text, "{}", vertex.join(" ")).unwrap();
+        }
+    }
+    writeln!(text, "\nTriangles\n{}", triangles.len() * COPIES).unwrap();
+    for copy in 0..COPIES {
+        let offset = copy * vertices.len();
+        for t in &triangles {
+            writeln!(
+                text,
+                "{} {} {} {}",
+                t[0] + offset,
+                t[1] + offset,
+                t[2] + offset,
+                t[3]
+            )
+            .unwrap();
+        }
+    }
+    writeln!(text, "\nEnd").unwrap();
+    text
+}
+
+fn scaled_ply() -> String {
+    let (vertices, triangles) = monkey();
+    let mut text = String::new();
+    writeln!(text, "ply\nformat ascii 1.0").unwrap();
+    writeln!(text, "element vertex {}", vertices.len() * COPIES).unwrap();
+    writeln!(text, "property float x\nproperty float y\nproperty float z").unwrap();
+    writeln!(text, "element face {}", triangles.len() * COPIES).unwrap();
+    writeln!(text, "property list uchar int vertex_indices\nend_header").unwrap();
+    for _ in 0..COPIES {
+        for vertex in &vertices {
+            writeln!(text, "{}", vertex[..3].join(" ")).unwrap();
+        }
+    }
+    for copy in 0..COPIES {
+        // Note: Medit indices are one-based, PLY indices zero-based.
+        let offset = copy * vertices.len();
+        for t in &triangles {
+            writeln!(
+                text,
+                "3 {} {} {}",
+                t[0] - 1 + offset,
+                t[1] - 1 + offset,
+                t[2] - 1 + offset
+            )
+            .unwrap();
+        }
+    }
+    text
+}
+
+/// The former tokenizer of the readers: lines are split by a regular expression into a queue,
+/// from which items are taken with `VecDeque::remove(0)`.
+struct RegexItems<'s> {
+    whitespace: &'s Regex,
+    lines: Lines<'s>,
+    items: VecDeque<&'s str>,
+}
+
+impl<'s> RegexItems<'s> {
+    fn new(whitespace: &'s Regex, text: &'s str) -> Self {
+        RegexItems {
+            whitespace,
+            lines: text.lines(),
+            items: VecDeque::new(),
+        }
+    }
+
+    fn next_parse<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        let item = self
+            .next()
+            .ok_or_else(|| Error::Syntax("Unexpected EOF".into()))?;
+        item.parse()
+            .map_err(|_| Error::Syntax(format!("Bad value: {}", item)))
+    }
+}
+
+impl<'s> Iterator for RegexItems<'s> {
+    type Item = &'s str;
+
+    fn next(&mut self) -> Option<&'s str> {
+        loop {
+            while let Some(item) = self.items.remove(0) {
+                if !item.trim().is_empty() {
+                    return Some(item);
+                }
+            }
+            let line = self.lines.next()?;
+            if !line.starts_with('#') && !line.trim().is_empty() {
+                self.items.extend(self.whitespace.split(line));
+            }
+        }
+    }
+}
+
+/// The former Medit reader, for `Vertices` and `Triangles`: every item is taken by a new
+/// tokenizer of the rest of the data, and entities are passed on as events.
+struct FormerMedit<'s> {
+    whitespace: &'s Regex,
+    data: &'s str,
+    /// Byte offset of the rest of `data`.
+    pos: usize,
+    dimension: usize,
+    /// The open section, with its cell type for elements, and its number of remaining entities.
+    section: Option<(Option<CellType>, usize)>,
+    done: bool,
+}
+
+impl<'s> FormerMedit<'s> {
+    fn next_item(&mut self) -> Option<&'s str> {
+        let rest = &self.data[self.pos..];
+        let item = RegexItems::new(self.whitespace, rest).next()?;
+        self.pos += item.as_ptr() as usize - rest.as_ptr() as usize + item.len();
+        Some(item)
+    }
+
+    fn next_parse<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        let item = self
+            .next_item()
+            .ok_or_else(|| Error::Syntax("Unexpected EOF".into()))?;
+        item.parse()
+            .map_err(|_| Error::Syntax(format!("Bad value: {}", item)))
+    }
+
+    fn read_entity(&mut self, cell: Option<CellType>) -> Result<EntityBox, Error> {
+        let mut attr = AttributeMap::new();
+        match cell {
+            None => {
+                for key in &keys::POSITION[..self.dimension] {
+                    let x: f64 = self.next_parse()?;
+                    attr.set(AttributeName::key(key), x.into());
+                }
+                let reference: i64 = self.next_parse()?;
+                attr.set(AttributeName::key(keys::REF), reference.into());
+                Ok(EntityBox::new(EntityKind::Node, attr))
+            }
+            Some(cell) => {
+                let mut indices = vec![0; cell.num_nodes()];
+                for index in indices.iter_mut() {
+                    let i: usize = self.next_parse()?;
+                    if i == 0 {
+                        return Err(Error::Syntax("Vertex index 0 in element.".into()));
+                    }
+                    *index = i - 1;
+                }
+                let reference: i64 = self.next_parse()?;
+                attr.set(AttributeName::key(keys::REF), reference.into());
+                attr.set(AttributeName::key(keys::CELL), cell.name().into());
+                let mut element = EntityBox::new(EntityKind::Element, attr);
+                element.set_node_indices(&indices);
+                Ok(element)
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some((cell, remaining)) = self.section {
+            if remaining == 0 {
+                self.section = None;
+                return Ok(Some(Event::GroupEnd));
+            }
+            self.section = Some((cell, remaining - 1));
+            return self.read_entity(cell).map(|e| Some(Event::Entity(e)));
+        }
+
+        loop {
+            let keyword = match self.next_item() {
+                Some(keyword) => keyword,
+                None => return Ok(None),
+            };
+            let (cell, kind) = match keyword {
+                "MeshVersionFormatted" => {
+                    self.next_item();
+                    continue;
+                }
+                "Dimension" => {
+                    self.dimension = self.next_parse()?;
+                    return Ok(Some(Event::Dimension(self.dimension as u8)));
+                }
+                "Vertices" => (None, EntityKind::Node),
+                "Triangles" => (Some(CellType::Triangle), EntityKind::Element),
+                "End" => return Ok(None),
+                other => return Err(Error::Syntax(format!("Unsupported keyword: {}", other))),
+            };
+            let len: usize = self.next_parse()?;
+            self.section = Some((cell, len));
+            return Ok(Some(Event::GroupStart {
+                name: Name::parse(keyword.into(), Format::Medit, kind).unwrap(),
+                kind,
+                len: Some(len),
+            }));
+        }
+    }
+}
+
+impl<'s> Iterator for FormerMedit<'s> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event();
+        self.done = !matches!(event, Ok(Some(_)));
+        match event {
+            Ok(event) => event.map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn former_medit(whitespace: &Regex, text: &str) -> Result<ColumnarMesh, Error> {
+    let events = FormerMedit {
+        whitespace,
+        data: text,
+        pos: 0,
+        dimension: 0,
+        section: None,
+        done: false,
+    };
+    let mut mesh = ColumnarMesh::default();
+    read_events(events, &mut mesh)?;
+    Ok(mesh)
+}
+
+/// The former PLY reader, for vertices with float coordinates and faces with a list of `int`
+/// indices: items are parsed into attribute values, the indices are converted from them.
+fn former_ply(whitespace: &Regex, text: &str) -> Result<ColumnarMesh, Error> {
+    let (header, body) = text.split_at(text.find("end_header\n").unwrap() + 11);
+    let count = |element: &str| -> usize {
+        let line = header.lines().find(|l| l.starts_with(element)).unwrap();
+        line[element.len()..].trim().parse().unwrap()
+    };
+    let (num_vertices, num_faces) = (count("element vertex"), count("element face"));
+    let mut items = RegexItems::new(whitespace, body);
+    let mut mesh = ColumnarMesh::default();
+    mesh.set_dimension(3);
+
+    let name = Name::parse("vertex".into(), Format::Ply, EntityKind::Node).unwrap();
+    let mut group = mesh.add_group(name, EntityKind::Node)?;
+    group.reserve(num_vertices)?;
+    for _ in 0..num_vertices {
+        let mut attr = AttributeMap::new();
+        for key in keys::POSITION {
+            let x: f32 = items.next_parse()?;
+            attr.set((*key).into(), x.into());
+        }
+        group.add_entity(EntityBox::new(EntityKind::Node, attr))?;
+    }
+    group.end()?;
+
+    let name = Name::parse("face".into(), Format::Ply, EntityKind::Element).unwrap();
+    let mut group = mesh.add_group(name, EntityKind::Element)?;
+    group.reserve(num_faces)?;
+    for _ in 0..num_faces {
+        let len: u8 = items.next_parse()?;
+        let mut list = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            list.push(AttributeValue::from(items.next_parse::<i32>()?));
+        }
+        let nodes = AttributeValue::List(list)
+            .to_usize_list()
+            .ok_or_else(|| Error::Syntax("Bad vertex indices.".into()))?;
+        if let Some(&i) = nodes.iter().find(|&&i| i >= num_vertices) {
+            return Err(Error::Syntax(format!("Undefined vertex {}.", i)));
+        }
+        let mut attr = AttributeMap::new();
+        attr.set(
+            AttributeName::key(keys::CELL),
+            CellType::Triangle.name().into(),
+        );
+        let mut face = EntityBox::new(EntityKind::Element, attr);
+        face.set_node_indices(&nodes);
+        group.add_entity(face)?;
+    }
+    group.end()?;
+    Ok(mesh)
+}
+
+/// Compare the sequential reader `D` with the parallel reader `P` and the former reader.
+fn bench_reader<D: Deserializer, P: Deserializer>(
+    c: &mut Criterion,
+    name: &str,
+    text: &str,
+    former: fn(&Regex, &str) -> Result<ColumnarMesh, Error>,
+) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    let whitespace = Regex::new(r"\s+").unwrap();
+    group.bench_function("columnar-former", |b| {
+        b.iter(|| former(&whitespace, text).unwrap())
+    });
+    group.bench_function("columnar", |b| {
+        b.iter(|| {
+            let mut mesh = ColumnarMesh::default();
+            D::deserialize_into(text.as_bytes(), &mut mesh).unwrap();
+            mesh
+        })
+    });
+    group.bench_function("columnar-parallel", |b| {
+        b.iter(|| {
+            let mut mesh = ColumnarMesh::default();
+            P::deserialize_into(text.as_bytes(), &mut mesh).unwrap();
+            mesh
+        })
+    });
+    group.finish();
+}
+
+fn readers(c: &mut Criterion) {
+    bench_reader::<MeditDeserializer, ParallelMeditDeserializer>(
+        c,
+        "medit",
+        &scaled_medit(),
+        former_medit,
+    );
+    bench_reader::<PlyDeserializer, ParallelPlyDeserializer>(c, "ply", &scaled_ply(), former_ply);
+}
+
+criterion_group!(benches, readers);
+criterion_main!(benches);
@@ -0,0 +1,202 @@
+//! Command-line interface to convert, inspect and validate meshes.
+//!
+//! Formats are chosen by the extension of a file, or by its content if the extension is
+//! unknown; `--from` and `--to` take the name of a format instead. Files compressed with gzip,
+//! zstd or xz are decompressed on the fly, and written compressed if their name ends with `.gz`,
+//! `.zst` or `.xz`.
+
+extern crate clap;
+extern crate log;
+extern crate multimesh;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use multimesh::{
+    data::{columnar::ColumnarMesh, printing, validation},
+    error::Error,
+    format::registry::{default_registry, FormatEntry, FormatRegistry},
+};
+use std::{
+    io::{self, Write},
+    process,
+};
+
+/// Exit code if a mesh has problems.
+const EXIT_PROBLEMS: i32 = 1;
+/// Exit code if a command fails.
+const EXIT_ERROR: i32 = 2;
+
+/// Prints warnings of readers and writers, like skipped entities, to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let level = match record.level() {
+                Level::Error => "error",
+                _ => "warning",
+            };
+            eprintln!("{}: {}", level, record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn format_arg<'a, 'b>(name: &'a str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .value_name("FORMAT")
+        .help(help)
+}
+
+fn file_arg<'a, 'b>(name: &'a str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name).required(true).help(help)
+}
+
+fn main() {
+    // Note: Only fails if a logger is set already.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(LevelFilter::Warn);
+
+    let registry = default_registry();
+    let names: Vec<&str> = registry
+        .formats()
+        .iter()
+        .map(|entry| entry.name())
+        .collect();
+    let formats = format!("Formats: {}", names.join(", "));
+
+    let matches = App::new("multimesh")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Converts, inspects and validates meshes.")
+        .after_help(formats.as_str())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Converts a mesh into another format")
+                .arg(format_arg("from", "Format of the input file"))
+                .arg(format_arg("to", "Format of the output file"))
+                .arg(file_arg("input", "File to read"))
+                .arg(file_arg("output", "File to write")),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Prints the groups of a mesh with statistics of their attributes")
+                .arg(format_arg("format", "Format of the file"))
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Prints the summary as JSON"),
+                )
+                .arg(file_arg("file", "File to read")),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Reports structural problems of a mesh, exits with 1 if there are any")
+                .arg(format_arg("format", "Format of the file"))
+                .arg(file_arg("file", "File to read")),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("convert", Some(args)) => convert(&registry, args),
+        ("info", Some(args)) => info(&registry, args),
+        ("validate", Some(args)) => validate(&registry, args),
+        _ => unreachable!("a subcommand is required"),
+    };
+    match result {
+        Ok(true) => (),
+        Ok(false) => process::exit(EXIT_PROBLEMS),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(EXIT_ERROR);
+        }
+    }
+}
+
+fn by_name<'r>(registry: &'r FormatRegistry, name: &str) -> Result<&'r FormatEntry, Error> {
+    registry
+        .by_name(name)
+        .ok_or_else(|| Error::Unsupported(format!("Unknown format `{}`.", name)))
+}
+
+/// Read a file in the format named `format`, by default in the format of its extension or the
+/// format recognized by its content.
+fn read<'r>(
+    registry: &'r FormatRegistry,
+    path: &str,
+    format: Option<&str>,
+) -> Result<(ColumnarMesh, &'r FormatEntry), Error> {
+    let mut mesh = ColumnarMesh::default();
+    let entry = match format {
+        Some(name) => {
+            let entry = by_name(registry, name)?;
+            entry.read_path(path, &mut mesh)?;
+            entry
+        }
+        None => registry.read_path(path, &mut mesh)?,
+    };
+    Ok((mesh, entry))
+}
+
+fn convert(registry: &FormatRegistry, args: &ArgMatches) -> Result<bool, Error> {
+    // Note: Arguments are required, so `value_of` can't fail.
+    let input = args.value_of("input").unwrap();
+    let output = args.value_of("output").unwrap();
+    let entry = match args.value_of("to") {
+        Some(name) => by_name(registry, name)?,
+        None => registry.by_path(output).ok_or_else(|| {
+            Error::Unsupported(format!("No format for `{}`, choose one with --to.", output))
+        })?,
+    };
+    if !entry.capabilities().write {
+        return Err(Error::Unsupported(format!(
+            "Format {} can't be written.",
+            entry.name()
+        )));
+    }
+
+    let (mesh, _) = read(registry, input, args.value_of("from"))?;
+    entry.write_path(&mesh, output)?;
+    Ok(true)
+}
+
+fn info(registry: &FormatRegistry, args: &ArgMatches) -> Result<bool, Error> {
+    let path = args.value_of("file").unwrap();
+    let (mesh, entry) = read(registry, path, args.value_of("format"))?;
+
+    let stdout = io::stdout();
+    let mut w = stdout.lock();
+    if args.is_present("json") {
+        printing::write_metadata_json(&mesh, &mut w)?;
+        return Ok(true);
+    }
+    writeln!(w, "File: {}", path)?;
+    writeln!(w, "Format: {}\n", entry.name())?;
+    printing::write_metadata(&mesh, &mut w)?;
+    Ok(true)
+}
+
+fn validate(registry: &FormatRegistry, args: &ArgMatches) -> Result<bool, Error> {
+    let path = args.value_of("file").unwrap();
+    let (mesh, _) = read(registry, path, args.value_of("format"))?;
+
+    let problems = validation::validate(&mesh);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if problems.is_empty() {
+        println!("{}: no problems found", path);
+    } else {
+        println!("{}: {} problems found", path, problems.len());
+    }
+    Ok(problems.is_empty())
+}
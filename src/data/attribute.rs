@@ -1,7 +1,9 @@
 use error::Error;
 use std::{
+    borrow::Cow,
     collections::{btree_map, BTreeMap},
-    fmt::Debug,
+    convert::TryFrom,
+    fmt::{self, Debug},
     iter::FromIterator,
 };
 
@@ -13,7 +15,7 @@ use std::{
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum AttributeName {
     Index(usize),
-    Key(String),
+    Key(Cow<'static, str>),
 }
 
 impl From<usize> for AttributeName {
@@ -24,7 +26,248 @@ impl From<usize> for AttributeName {
 
 impl From<String> for AttributeName {
     fn from(k: String) -> Self {
-        AttributeName::Key(k)
+        AttributeName::Key(Cow::Owned(k))
+    }
+}
+
+impl<'a> From<&'a str> for AttributeName {
+    fn from(k: &'a str) -> Self {
+        AttributeName::Key(Cow::Owned(k.to_string()))
+    }
+}
+
+/// Attribute keys with a meaning shared between all formats.
+///
+/// Geometry and connectivity of entities are passed through their attribute containers,
+/// which is how readers and writers of different formats understand each other.
+pub mod keys {
+    /// First coordinate of a node.
+    pub const X: &str = "x";
+    /// Second coordinate of a node.
+    pub const Y: &str = "y";
+    /// Third coordinate of a node.
+    pub const Z: &str = "z";
+    /// Node indices of an element, as list (or as string separated by whitespace).
+    ///
+    /// Indices start at zero and count the nodes of all node groups of a mesh in order.
+    pub const NODES: &str = "nodes";
+    /// Reference tag (also called label, marker, zone or domain index) of an entity.
+    pub const REF: &str = "ref";
+    /// Type of an element, the [name](::data::cell::CellType::name) of a cell type.
+    ///
+    /// Elements without type are classified by their number of nodes.
+    pub const CELL: &str = "cell";
+
+    /// The coordinate keys, in order.
+    pub const POSITION: &[&str] = &[X, Y, Z];
+}
+
+/// The value of an attribute.
+///
+/// Readers keep the types declared by a format, or the natural type of values with a fixed
+/// meaning, like `f64` coordinates and lists of node indices. Values without declared type stay
+/// strings. The `to_*` conversions accept every variant which converts without loss, including
+/// strings which parse as the target type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    /// A list of values, displayed separated by spaces.
+    List(Vec<AttributeValue>),
+    String(String),
+}
+
+/// Integers with an absolute value up to this bound are exact as `f64`.
+const MAX_EXACT_INTEGER: u64 = 1 << f64::MANTISSA_DIGITS;
+
+impl AttributeValue {
+    /// The value if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            AttributeValue::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The items if the value is a list.
+    pub fn as_list(&self) -> Option<&[AttributeValue]> {
+        match *self {
+            AttributeValue::List(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        !matches!(*self, AttributeValue::List(_) | AttributeValue::String(_))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match *self {
+            AttributeValue::I8(x) => Some(x.into()),
+            AttributeValue::I16(x) => Some(x.into()),
+            AttributeValue::I32(x) => Some(x.into()),
+            AttributeValue::I64(x) => Some(x),
+            AttributeValue::U8(x) => Some(x.into()),
+            AttributeValue::U16(x) => Some(x.into()),
+            AttributeValue::U32(x) => Some(x.into()),
+            AttributeValue::U64(x) => i64::try_from(x).ok(),
+            AttributeValue::F32(x) => float_to_i64(x.into()),
+            AttributeValue::F64(x) => float_to_i64(x),
+            AttributeValue::List(_) => None,
+            AttributeValue::String(ref s) => s.trim().parse().ok(),
+        }
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match *self {
+            AttributeValue::U64(x) => Some(x),
+            AttributeValue::String(ref s) => s.trim().parse().ok(),
+            _ => self.to_i64().and_then(|x| u64::try_from(x).ok()),
+        }
+    }
+
+    pub fn to_usize(&self) -> Option<usize> {
+        self.to_u64().and_then(|x| usize::try_from(x).ok())
+    }
+
+    pub fn to_f64(&self) -> Option<f64> {
+        match *self {
+            AttributeValue::F32(x) => Some(x.into()),
+            AttributeValue::F64(x) => Some(x),
+            AttributeValue::I64(x) if x.unsigned_abs() > MAX_EXACT_INTEGER => None,
+            AttributeValue::U64(x) if x > MAX_EXACT_INTEGER => None,
+            AttributeValue::I64(x) => Some(x as f64),
+            AttributeValue::U64(x) => Some(x as f64),
+            AttributeValue::List(_) => None,
+            AttributeValue::String(ref s) => s.trim().parse().ok(),
+            // All smaller integers are exact.
+            _ => self.to_i64().map(|x| x as f64),
+        }
+    }
+
+    /// Convert a list, or a string of items separated by whitespace, item by item.
+    ///
+    /// Single numbers are lists of one item.
+    pub fn to_list<T, F>(&self, convert: F) -> Option<Vec<T>>
+    where
+        F: Fn(&AttributeValue) -> Option<T>,
+    {
+        match *self {
+            AttributeValue::List(ref items) => items.iter().map(convert).collect(),
+            AttributeValue::String(ref s) => s
+                .split_whitespace()
+                .map(|item| convert(&AttributeValue::String(item.into())))
+                .collect(),
+            ref value => convert(value).map(|x| vec![x]),
+        }
+    }
+
+    pub fn to_usize_list(&self) -> Option<Vec<usize>> {
+        self.to_list(AttributeValue::to_usize)
+    }
+
+    pub fn to_f64_list(&self) -> Option<Vec<f64>> {
+        self.to_list(AttributeValue::to_f64)
+    }
+}
+
+fn float_to_i64(x: f64) -> Option<i64> {
+    // The bounds are powers of two and exact, `i64::MAX` itself isn't.
+    if x.fract() == 0. && (-9_223_372_036_854_775_808. ..9_223_372_036_854_775_808.).contains(&x) {
+        Some(x as i64)
+    } else {
+        None
+    }
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AttributeValue::I8(x) => write!(f, "{}", x),
+            AttributeValue::I16(x) => write!(f, "{}", x),
+            AttributeValue::I32(x) => write!(f, "{}", x),
+            AttributeValue::I64(x) => write!(f, "{}", x),
+            AttributeValue::U8(x) => write!(f, "{}", x),
+            AttributeValue::U16(x) => write!(f, "{}", x),
+            AttributeValue::U32(x) => write!(f, "{}", x),
+            AttributeValue::U64(x) => write!(f, "{}", x),
+            AttributeValue::F32(x) => write!(f, "{}", x),
+            AttributeValue::F64(x) => write!(f, "{}", x),
+            AttributeValue::List(ref items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            AttributeValue::String(ref s) => f.write_str(s),
+        }
+    }
+}
+
+/// Only strings equal text, numbers don't equal their decimal representation.
+impl PartialEq<str> for AttributeValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for AttributeValue {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+macro_rules! value_from {
+    ($($t:ty => $variant:ident),*) => {
+        $(
+            impl From<$t> for AttributeValue {
+                fn from(x: $t) -> Self {
+                    AttributeValue::$variant(x)
+                }
+            }
+        )*
+    };
+}
+
+value_from!(
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    f32 => F32, f64 => F64, String => String
+);
+
+/// Indices are stored as `u64`, independent of the platform.
+impl From<usize> for AttributeValue {
+    fn from(x: usize) -> Self {
+        AttributeValue::U64(x as u64)
+    }
+}
+
+impl<'a> From<&'a str> for AttributeValue {
+    fn from(s: &'a str) -> Self {
+        AttributeValue::String(s.into())
+    }
+}
+
+impl<T: Into<AttributeValue>> From<Vec<T>> for AttributeValue {
+    fn from(items: Vec<T>) -> Self {
+        AttributeValue::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<'a, T: Clone + Into<AttributeValue>> From<&'a [T]> for AttributeValue {
+    fn from(items: &'a [T]) -> Self {
+        AttributeValue::List(items.iter().cloned().map(Into::into).collect())
     }
 }
 
@@ -32,28 +275,59 @@ pub trait AttributeContainer: Clone + Debug {
     /// The number of contained attributes.
     fn len(&self) -> usize;
 
+    /// Whether there are no attributes at all.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Get the attribute with the specified name.
-    fn get(&self, name: &AttributeName) -> Option<&String>;
+    fn get(&self, name: &AttributeName) -> Option<&AttributeValue>;
+
+    /// Get the attribute with the specified name if it is a string.
+    fn get_str(&self, name: &AttributeName) -> Option<&str> {
+        self.get(name).and_then(AttributeValue::as_str)
+    }
+
+    /// Get the attribute with the specified name if it converts to `i64` without loss.
+    fn get_i64(&self, name: &AttributeName) -> Option<i64> {
+        self.get(name).and_then(AttributeValue::to_i64)
+    }
+
+    /// Get the attribute with the specified name if it converts to `u64` without loss.
+    fn get_u64(&self, name: &AttributeName) -> Option<u64> {
+        self.get(name).and_then(AttributeValue::to_u64)
+    }
+
+    /// Get the attribute with the specified name if it converts to `usize` without loss.
+    fn get_usize(&self, name: &AttributeName) -> Option<usize> {
+        self.get(name).and_then(AttributeValue::to_usize)
+    }
+
+    /// Get the attribute with the specified name if it converts to `f64` without loss.
+    fn get_f64(&self, name: &AttributeName) -> Option<f64> {
+        self.get(name).and_then(AttributeValue::to_f64)
+    }
 
-    fn iter<'a>(&'a self) -> Box<Iterator<Item = (&'a AttributeName, &'a String)> + 'a>;
+    fn iter<'a>(&'a self)
+        -> Box<dyn Iterator<Item = (&'a AttributeName, &'a AttributeValue)> + 'a>;
 
-    fn iter_names<'a>(&'a self) -> Box<Iterator<Item = &'a AttributeName> + 'a> {
+    fn iter_names<'a>(&'a self) -> Box<dyn Iterator<Item = &'a AttributeName> + 'a> {
         Box::new(self.iter().map(|(k, _)| k))
     }
 }
 
 pub trait AttributeContainerMut: AttributeContainer {
     /// Set the attribute to a value.
-    fn set(&mut self, name: AttributeName, value: String);
+    fn set(&mut self, name: AttributeName, value: AttributeValue);
 }
 
 /// Default implementation of an [AttributeContainerMut].
 ///
 /// Currently this is not optimized for real world performance, and
 /// no guarantees about asymptotic costs are made.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct AttributeMap {
-    data: BTreeMap<AttributeName, String>,
+    data: BTreeMap<AttributeName, AttributeValue>,
 }
 
 impl AttributeContainer for AttributeMap {
@@ -61,26 +335,32 @@ impl AttributeContainer for AttributeMap {
         self.data.len()
     }
 
-    fn get(&self, name: &AttributeName) -> Option<&String> {
+    fn get(&self, name: &AttributeName) -> Option<&AttributeValue> {
         self.data.get(name)
     }
 
-    fn iter<'a>(&'a self) -> Box<Iterator<Item = (&'a AttributeName, &'a String)> + 'a> {
+    fn iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = (&'a AttributeName, &'a AttributeValue)> + 'a> {
         Box::new(self.data.iter())
     }
 
-    fn iter_names<'a>(&'a self) -> Box<Iterator<Item = &'a AttributeName> + 'a> {
+    fn iter_names<'a>(&'a self) -> Box<dyn Iterator<Item = &'a AttributeName> + 'a> {
         Box::new(self.data.keys())
     }
 }
 
 impl AttributeContainerMut for AttributeMap {
-    fn set(&mut self, name: AttributeName, value: String) {
+    fn set(&mut self, name: AttributeName, value: AttributeValue) {
         self.data.insert(name, value);
     }
 }
 
 impl AttributeMap {
+    pub fn new() -> Self {
+        AttributeMap::default()
+    }
+
     pub fn from_container<A: AttributeContainer>(c: &A) -> Self {
         let mut data = BTreeMap::new();
         for (k, v) in c.iter() {
@@ -91,6 +371,11 @@ impl AttributeMap {
 }
 
 impl AttributeName {
+    /// A key which doesn't need an allocation, like those of [keys].
+    pub const fn key(key: &'static str) -> Self {
+        AttributeName::Key(Cow::Borrowed(key))
+    }
+
     pub fn is_index(&self) -> bool {
         match *self {
             AttributeName::Index(_) => true,
@@ -106,11 +391,50 @@ impl AttributeName {
     }
 }
 
-impl ToString for AttributeName {
-    fn to_string(&self) -> String {
+impl fmt::Display for AttributeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            AttributeName::Index(ref i) => format!("{}", i),
-            AttributeName::Key(ref s) => s.clone(),
+            AttributeName::Index(ref i) => write!(f, "{}", i),
+            AttributeName::Key(ref s) => write!(f, "{}", s),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless_conversions() {
+        assert_eq!(AttributeValue::U8(7).to_i64(), Some(7));
+        assert_eq!(AttributeValue::I32(-1).to_u64(), None);
+        assert_eq!(AttributeValue::F64(3.).to_usize(), Some(3));
+        assert_eq!(AttributeValue::F64(3.5).to_i64(), None);
+        assert_eq!(AttributeValue::F32(0.5).to_f64(), Some(0.5));
+        assert_eq!(AttributeValue::U64(u64::MAX).to_f64(), None);
+        assert_eq!(AttributeValue::U64(u64::MAX).to_i64(), None);
+        assert_eq!(AttributeValue::from("12").to_u64(), Some(12));
+        assert_eq!(AttributeValue::from("x").to_f64(), None);
+
+        let nodes = AttributeValue::from(vec![4usize, 5, 6]);
+        assert_eq!(nodes.to_string(), "4 5 6");
+        assert_eq!(nodes.to_usize_list(), Some(vec![4, 5, 6]));
+        assert_eq!(
+            AttributeValue::from("1 2.5").to_f64_list(),
+            Some(vec![1., 2.5])
+        );
+        assert_eq!(AttributeValue::from("1 a").to_f64_list(), None);
+    }
+
+    #[test]
+    fn container_helpers() {
+        let mut attr = AttributeMap::new();
+        attr.set("ref".into(), 3u32.into());
+        attr.set("name".into(), "wall".into());
+        assert_eq!(attr.get_i64(&"ref".into()), Some(3));
+        assert_eq!(attr.get_str(&"ref".into()), None);
+        assert_eq!(attr.get_str(&"name".into()), Some("wall"));
+        assert_eq!(attr.get_f64(&"name".into()), None);
+        assert_eq!(attr.get(&"name".into()).unwrap(), "wall");
+    }
+}
@@ -0,0 +1,300 @@
+//! Types of cells (elements) and their reference elements.
+//!
+//! The node ordering of the reference elements is the canonical ordering of multimesh, which
+//! is stored in the attribute [keys::NODES](::data::attribute::keys::NODES) of elements. It
+//! follows the convention of Exodus and Abaqus, so every cell with this ordering has a positive
+//! volume (or area, seen from the positive z axis):
+//!
+//! - The base of pyramids, prisms and hexahedra comes first, numbered counter-clockwise when
+//!   seen from inside of the cell, followed by the apex or the nodes opposite of the base in
+//!   the same order. The first three nodes of tetrahedra are their base in the same sense.
+//! - Quadratic cells append the midpoints of the edges, in the order of [CellType::edges].
+//!
+//! Formats with a different node ordering are converted when reading and writing, see
+//! [ordering](::format::ordering).
+
+/// The types of cells, stored in the attribute [keys::CELL](::data::attribute::keys::CELL).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum CellType {
+    Vertex,
+    Line,
+    Line3,
+    Triangle,
+    Triangle6,
+    Quadrilateral,
+    Quadrilateral8,
+    Tetrahedron,
+    Tetrahedron10,
+    Pyramid,
+    Prism,
+    Prism15,
+    Hexahedron,
+    Hexahedron20,
+}
+
+const VERTEX: &[[f64; 3]] = &[[0., 0., 0.]];
+const LINE: &[[f64; 3]] = &[[0., 0., 0.], [1., 0., 0.]];
+const TRIANGLE: &[[f64; 3]] = &[[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]];
+const QUADRILATERAL: &[[f64; 3]] = &[[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+const TETRAHEDRON: &[[f64; 3]] = &[[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+const PYRAMID: &[[f64; 3]] = &[
+    [0., 0., 0.],
+    [1., 0., 0.],
+    [1., 1., 0.],
+    [0., 1., 0.],
+    [0.5, 0.5, 1.],
+];
+const PRISM: &[[f64; 3]] = &[
+    [0., 0., 0.],
+    [1., 0., 0.],
+    [0., 1., 0.],
+    [0., 0., 1.],
+    [1., 0., 1.],
+    [0., 1., 1.],
+];
+const HEXAHEDRON: &[[f64; 3]] = &[
+    [0., 0., 0.],
+    [1., 0., 0.],
+    [1., 1., 0.],
+    [0., 1., 0.],
+    [0., 0., 1.],
+    [1., 0., 1.],
+    [1., 1., 1.],
+    [0., 1., 1.],
+];
+
+const LINE_EDGES: &[[usize; 2]] = &[[0, 1]];
+const TRIANGLE_EDGES: &[[usize; 2]] = &[[0, 1], [1, 2], [2, 0]];
+const QUADRILATERAL_EDGES: &[[usize; 2]] = &[[0, 1], [1, 2], [2, 3], [3, 0]];
+const TETRAHEDRON_EDGES: &[[usize; 2]] = &[[0, 1], [1, 2], [2, 0], [0, 3], [1, 3], [2, 3]];
+const PYRAMID_EDGES: &[[usize; 2]] = &[
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [0, 4],
+    [1, 4],
+    [2, 4],
+    [3, 4],
+];
+const PRISM_EDGES: &[[usize; 2]] = &[
+    [0, 1],
+    [1, 2],
+    [2, 0],
+    [3, 4],
+    [4, 5],
+    [5, 3],
+    [0, 3],
+    [1, 4],
+    [2, 5],
+];
+const HEXAHEDRON_EDGES: &[[usize; 2]] = &[
+    [0, 1],
+    [1, 2],
+    [2, 3],
+    [3, 0],
+    [4, 5],
+    [5, 6],
+    [6, 7],
+    [7, 4],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+impl CellType {
+    pub const ALL: &'static [CellType] = &[
+        CellType::Vertex,
+        CellType::Line,
+        CellType::Line3,
+        CellType::Triangle,
+        CellType::Triangle6,
+        CellType::Quadrilateral,
+        CellType::Quadrilateral8,
+        CellType::Tetrahedron,
+        CellType::Tetrahedron10,
+        CellType::Pyramid,
+        CellType::Prism,
+        CellType::Prism15,
+        CellType::Hexahedron,
+        CellType::Hexahedron20,
+    ];
+
+    /// The name used in attributes, like `tetrahedron10`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CellType::Vertex => "vertex",
+            CellType::Line => "line",
+            CellType::Line3 => "line3",
+            CellType::Triangle => "triangle",
+            CellType::Triangle6 => "triangle6",
+            CellType::Quadrilateral => "quadrilateral",
+            CellType::Quadrilateral8 => "quadrilateral8",
+            CellType::Tetrahedron => "tetrahedron",
+            CellType::Tetrahedron10 => "tetrahedron10",
+            CellType::Pyramid => "pyramid",
+            CellType::Prism => "prism",
+            CellType::Prism15 => "prism15",
+            CellType::Hexahedron => "hexahedron",
+            CellType::Hexahedron20 => "hexahedron20",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CellType> {
+        CellType::ALL
+            .iter()
+            .cloned()
+            .find(|cell| cell.name() == name)
+    }
+
+    /// The linear cell with the same shape.
+    pub fn linear(self) -> CellType {
+        match self {
+            CellType::Line3 => CellType::Line,
+            CellType::Triangle6 => CellType::Triangle,
+            CellType::Quadrilateral8 => CellType::Quadrilateral,
+            CellType::Tetrahedron10 => CellType::Tetrahedron,
+            CellType::Prism15 => CellType::Prism,
+            CellType::Hexahedron20 => CellType::Hexahedron,
+            linear => linear,
+        }
+    }
+
+    /// The quadratic cell with the same shape, if there is one.
+    pub fn quadratic(self) -> Option<CellType> {
+        match self.linear() {
+            CellType::Line => Some(CellType::Line3),
+            CellType::Triangle => Some(CellType::Triangle6),
+            CellType::Quadrilateral => Some(CellType::Quadrilateral8),
+            CellType::Tetrahedron => Some(CellType::Tetrahedron10),
+            CellType::Prism => Some(CellType::Prism15),
+            CellType::Hexahedron => Some(CellType::Hexahedron20),
+            _ => None,
+        }
+    }
+
+    pub fn is_quadratic(self) -> bool {
+        self != self.linear()
+    }
+
+    /// The topological dimension: 0 for vertices, 1 for lines, 2 for faces and 3 for solids.
+    pub fn dimension(self) -> u8 {
+        match self.linear() {
+            CellType::Vertex => 0,
+            CellType::Line => 1,
+            CellType::Triangle | CellType::Quadrilateral => 2,
+            _ => 3,
+        }
+    }
+
+    /// The corner nodes of the reference element, in canonical order.
+    pub fn corners(self) -> &'static [[f64; 3]] {
+        match self.linear() {
+            CellType::Vertex => VERTEX,
+            CellType::Line => LINE,
+            CellType::Triangle => TRIANGLE,
+            CellType::Quadrilateral => QUADRILATERAL,
+            CellType::Tetrahedron => TETRAHEDRON,
+            CellType::Pyramid => PYRAMID,
+            CellType::Prism => PRISM,
+            _ => HEXAHEDRON,
+        }
+    }
+
+    /// The edges of the reference element as pairs of corner indices, in canonical order.
+    pub fn edges(self) -> &'static [[usize; 2]] {
+        match self.linear() {
+            CellType::Vertex => &[],
+            CellType::Line => LINE_EDGES,
+            CellType::Triangle => TRIANGLE_EDGES,
+            CellType::Quadrilateral => QUADRILATERAL_EDGES,
+            CellType::Tetrahedron => TETRAHEDRON_EDGES,
+            CellType::Pyramid => PYRAMID_EDGES,
+            CellType::Prism => PRISM_EDGES,
+            _ => HEXAHEDRON_EDGES,
+        }
+    }
+
+    pub fn num_nodes(self) -> usize {
+        let corners = self.corners().len();
+        if self.is_quadratic() {
+            corners + self.edges().len()
+        } else {
+            corners
+        }
+    }
+
+    /// All nodes of the reference element in canonical order: the corners, followed by the
+    /// midpoints of the edges for quadratic cells.
+    pub fn reference_nodes(self) -> Vec<[f64; 3]> {
+        let corners = self.corners();
+        let mut nodes = corners.to_vec();
+        if self.is_quadratic() {
+            for &[a, b] in self.edges() {
+                let (a, b) = (corners[a], corners[b]);
+                nodes.push([(a[0] + b[0]) / 2., (a[1] + b[1]) / 2., (a[2] + b[2]) / 2.]);
+            }
+        }
+        nodes
+    }
+
+    /// Guess the type of a cell by its number of nodes.
+    ///
+    /// Four nodes are ambiguous, they are a quadrilateral if `flat` and a tetrahedron
+    /// otherwise; so are six nodes, which are a quadratic triangle if `flat`, and eight nodes,
+    /// which are a quadratic quadrilateral if `flat`.
+    pub fn infer(num_nodes: usize, flat: bool) -> Option<CellType> {
+        match (num_nodes, flat) {
+            (1, _) => Some(CellType::Vertex),
+            (2, _) => Some(CellType::Line),
+            (3, _) => Some(CellType::Triangle),
+            (4, true) => Some(CellType::Quadrilateral),
+            (4, false) => Some(CellType::Tetrahedron),
+            (5, _) => Some(CellType::Pyramid),
+            (6, true) => Some(CellType::Triangle6),
+            (6, false) => Some(CellType::Prism),
+            (8, true) => Some(CellType::Quadrilateral8),
+            (8, false) => Some(CellType::Hexahedron),
+            (10, _) => Some(CellType::Tetrahedron10),
+            (15, _) => Some(CellType::Prism15),
+            (20, _) => Some(CellType::Hexahedron20),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Volume of the tetrahedron spanned by four points.
+    fn volume(p: &[[f64; 3]]) -> f64 {
+        let d = |i: usize| [p[i][0] - p[0][0], p[i][1] - p[0][1], p[i][2] - p[0][2]];
+        let (u, v, w) = (d(1), d(2), d(3));
+        (u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+            + u[2] * (v[0] * w[1] - v[1] * w[0]))
+            / 6.
+    }
+
+    #[test]
+    fn reference_elements() {
+        for &cell in CellType::ALL {
+            assert_eq!(CellType::from_name(cell.name()), Some(cell));
+            assert_eq!(cell.reference_nodes().len(), cell.num_nodes());
+            if cell.dimension() == 3 {
+                // The first three corners of the base and the first corner above it span a
+                // positively oriented tetrahedron.
+                let corners = cell.corners();
+                let above = match cell.linear() {
+                    CellType::Tetrahedron | CellType::Prism => 3,
+                    _ => 4,
+                };
+                let tet = [corners[0], corners[1], corners[2], corners[above]];
+                assert!(volume(&tet) > 0., "{:?}", cell);
+            }
+        }
+        assert_eq!(CellType::Hexahedron20.reference_nodes()[12], [0.5, 0., 1.]);
+        assert_eq!(CellType::infer(8, false), Some(CellType::Hexahedron));
+    }
+}
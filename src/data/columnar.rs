@@ -0,0 +1,860 @@
+//! Columnar mesh representation.
+//!
+//! Every group stores one contiguous typed array per attribute name instead of one attribute
+//! map per entity, which takes a fraction of the memory of [face_vertex](::data::face_vertex)
+//! for large meshes. Entities are assembled from the columns when a group is read, see
+//! [ColumnarGroup::entity].
+//!
+//! When a group ends, the `f64` coordinates of its nodes are interleaved into one array and the
+//! node indices of elements with the same number of nodes are narrowed to `u32`, so readers
+//! borrow them through [GetMeshGroup::positions] and [GetMeshGroup::connectivity].
+
+use crate::format::naming::Name;
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    view::Strided,
+    Entity, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh,
+    SetMeshGroup, MAX_RESERVE,
+};
+use error::Error;
+use std::{convert::TryFrom, sync::Arc};
+
+/// The values of one attribute of all entities of a group, as typed array.
+///
+/// Every variant holds exactly one value per entity. Columns start with the type of the first
+/// value and become [ColumnData::Mixed] if a value of another type follows.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnData {
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    /// Lists, the items of entity `i` are `items[offsets[i]..offsets[i + 1]]`.
+    List {
+        offsets: Vec<usize>,
+        items: Box<ColumnData>,
+    },
+    String(Vec<String>),
+    /// Values of different types.
+    Mixed(Vec<AttributeValue>),
+    /// One coordinate of nodes, the value of entity `i` is `values[i * stride + offset]`.
+    ///
+    /// The coordinate columns of a group share `values`.
+    Interleaved {
+        values: Arc<Vec<f64>>,
+        offset: usize,
+        stride: usize,
+    },
+    /// Lists of `width` node indices each, which are read as `U64` lists.
+    Indices {
+        width: usize,
+        items: Vec<u32>,
+    },
+}
+
+macro_rules! for_scalars {
+    ($data:expr, $v:ident => $scalar:expr, $other:pat => $rest:expr) => {
+        match $data {
+            ColumnData::I8(ref $v) => $scalar,
+            ColumnData::I16(ref $v) => $scalar,
+            ColumnData::I32(ref $v) => $scalar,
+            ColumnData::I64(ref $v) => $scalar,
+            ColumnData::U8(ref $v) => $scalar,
+            ColumnData::U16(ref $v) => $scalar,
+            ColumnData::U32(ref $v) => $scalar,
+            ColumnData::U64(ref $v) => $scalar,
+            ColumnData::F32(ref $v) => $scalar,
+            ColumnData::F64(ref $v) => $scalar,
+            ColumnData::String(ref $v) => $scalar,
+            ColumnData::Mixed(ref $v) => $scalar,
+            ColumnData::Interleaved {
+                ref values, stride, ..
+            } => values.len() / stride,
+            ColumnData::Indices { width, ref items } => items.len() / width,
+            $other => $rest,
+        }
+    };
+}
+
+impl ColumnData {
+    /// An empty column for values of the type of `value`.
+    fn for_value(value: &AttributeValue) -> ColumnData {
+        match *value {
+            AttributeValue::I8(_) => ColumnData::I8(Vec::new()),
+            AttributeValue::I16(_) => ColumnData::I16(Vec::new()),
+            AttributeValue::I32(_) => ColumnData::I32(Vec::new()),
+            AttributeValue::I64(_) => ColumnData::I64(Vec::new()),
+            AttributeValue::U8(_) => ColumnData::U8(Vec::new()),
+            AttributeValue::U16(_) => ColumnData::U16(Vec::new()),
+            AttributeValue::U32(_) => ColumnData::U32(Vec::new()),
+            AttributeValue::U64(_) => ColumnData::U64(Vec::new()),
+            AttributeValue::F32(_) => ColumnData::F32(Vec::new()),
+            AttributeValue::F64(_) => ColumnData::F64(Vec::new()),
+            AttributeValue::List(ref items) => ColumnData::List {
+                offsets: vec![0],
+                items: Box::new(match items.first() {
+                    Some(item) => ColumnData::for_value(item),
+                    None => ColumnData::Mixed(Vec::new()),
+                }),
+            },
+            AttributeValue::String(_) => ColumnData::String(Vec::new()),
+        }
+    }
+
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        for_scalars!(*self, v => v.len(), ColumnData::List { ref offsets, .. } => offsets.len() - 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The value of entity `i`.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of bounds.
+    pub fn get(&self, i: usize) -> AttributeValue {
+        match *self {
+            ColumnData::I8(ref v) => v[i].into(),
+            ColumnData::I16(ref v) => v[i].into(),
+            ColumnData::I32(ref v) => v[i].into(),
+            ColumnData::I64(ref v) => v[i].into(),
+            ColumnData::U8(ref v) => v[i].into(),
+            ColumnData::U16(ref v) => v[i].into(),
+            ColumnData::U32(ref v) => v[i].into(),
+            ColumnData::U64(ref v) => v[i].into(),
+            ColumnData::F32(ref v) => v[i].into(),
+            ColumnData::F64(ref v) => v[i].into(),
+            ColumnData::List {
+                ref offsets,
+                ref items,
+            } => AttributeValue::List((offsets[i]..offsets[i + 1]).map(|j| items.get(j)).collect()),
+            ColumnData::String(ref v) => v[i].clone().into(),
+            ColumnData::Mixed(ref v) => v[i].clone(),
+            ColumnData::Interleaved {
+                ref values,
+                offset,
+                stride,
+            } => values[i * stride + offset].into(),
+            ColumnData::Indices { width, ref items } => AttributeValue::List(
+                items[i * width..(i + 1) * width]
+                    .iter()
+                    .map(|&item| u64::from(item).into())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The same values in a column which values can be appended to.
+    fn expand(&mut self) {
+        match *self {
+            ColumnData::Interleaved {
+                ref values,
+                offset,
+                stride,
+            } => {
+                *self = ColumnData::F64(
+                    values
+                        .iter()
+                        .skip(offset)
+                        .step_by(stride)
+                        .cloned()
+                        .collect(),
+                )
+            }
+            ColumnData::Indices { width, ref items } => {
+                *self = ColumnData::List {
+                    offsets: (0..=items.len() / width).map(|i| i * width).collect(),
+                    items: Box::new(ColumnData::U64(
+                        items.iter().map(|&item| item.into()).collect(),
+                    )),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `value` can be appended without changing the type of the column.
+    fn accepts(&self, value: &AttributeValue) -> bool {
+        match (self, value) {
+            (ColumnData::List { items, .. }, AttributeValue::List(values)) => {
+                let first_type;
+                let items = if items.is_empty() {
+                    match values.first() {
+                        Some(first) => {
+                            first_type = ColumnData::for_value(first);
+                            &first_type
+                        }
+                        None => return true,
+                    }
+                } else {
+                    &**items
+                };
+                values.iter().all(|value| items.accepts(value))
+            }
+            (&ColumnData::Mixed(_), _) => true,
+            (data, value) => {
+                ::std::mem::discriminant(data)
+                    == ::std::mem::discriminant(&ColumnData::for_value(value))
+            }
+        }
+    }
+
+    /// Append a value, which has to be [accepted](ColumnData::accepts).
+    fn push_accepted(&mut self, value: &AttributeValue) {
+        match (self, value) {
+            (&mut ColumnData::I8(ref mut v), &AttributeValue::I8(x)) => v.push(x),
+            (&mut ColumnData::I16(ref mut v), &AttributeValue::I16(x)) => v.push(x),
+            (&mut ColumnData::I32(ref mut v), &AttributeValue::I32(x)) => v.push(x),
+            (&mut ColumnData::I64(ref mut v), &AttributeValue::I64(x)) => v.push(x),
+            (&mut ColumnData::U8(ref mut v), &AttributeValue::U8(x)) => v.push(x),
+            (&mut ColumnData::U16(ref mut v), &AttributeValue::U16(x)) => v.push(x),
+            (&mut ColumnData::U32(ref mut v), &AttributeValue::U32(x)) => v.push(x),
+            (&mut ColumnData::U64(ref mut v), &AttributeValue::U64(x)) => v.push(x),
+            (&mut ColumnData::F32(ref mut v), &AttributeValue::F32(x)) => v.push(x),
+            (&mut ColumnData::F64(ref mut v), &AttributeValue::F64(x)) => v.push(x),
+            (&mut ColumnData::String(ref mut v), AttributeValue::String(x)) => v.push(x.clone()),
+            (
+                &mut ColumnData::List {
+                    ref mut offsets,
+                    ref mut items,
+                },
+                AttributeValue::List(values),
+            ) => {
+                if items.is_empty() {
+                    if let Some(first) = values.first() {
+                        **items = ColumnData::for_value(first);
+                    }
+                }
+                for value in values {
+                    items.push_accepted(value);
+                }
+                offsets.push(items.len());
+            }
+            (&mut ColumnData::Mixed(ref mut v), value) => v.push(value.clone()),
+            _ => unreachable!("value not accepted by column"),
+        }
+    }
+
+    /// Append a value, the column becomes mixed if the value has another type.
+    fn push(&mut self, value: &AttributeValue) {
+        self.expand();
+        if !self.accepts(value) {
+            let values = (0..self.len()).map(|i| self.get(i)).collect();
+            *self = ColumnData::Mixed(values);
+        }
+        self.push_accepted(value);
+    }
+
+    /// Append a placeholder for an entity without value.
+    fn push_placeholder(&mut self) {
+        self.expand();
+        match *self {
+            ColumnData::I8(ref mut v) => v.push(0),
+            ColumnData::I16(ref mut v) => v.push(0),
+            ColumnData::I32(ref mut v) => v.push(0),
+            ColumnData::I64(ref mut v) => v.push(0),
+            ColumnData::U8(ref mut v) => v.push(0),
+            ColumnData::U16(ref mut v) => v.push(0),
+            ColumnData::U32(ref mut v) => v.push(0),
+            ColumnData::U64(ref mut v) => v.push(0),
+            ColumnData::F32(ref mut v) => v.push(0.),
+            ColumnData::F64(ref mut v) => v.push(0.),
+            ColumnData::List {
+                ref mut offsets,
+                ref items,
+            } => offsets.push(items.len()),
+            ColumnData::String(ref mut v) => v.push(String::new()),
+            ColumnData::Mixed(ref mut v) => v.push(AttributeValue::List(Vec::new())),
+            ColumnData::Interleaved { .. } | ColumnData::Indices { .. } => {
+                unreachable!("column expanded above")
+            }
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        match *self {
+            ColumnData::I8(ref mut v) => v.reserve(additional),
+            ColumnData::I16(ref mut v) => v.reserve(additional),
+            ColumnData::I32(ref mut v) => v.reserve(additional),
+            ColumnData::I64(ref mut v) => v.reserve(additional),
+            ColumnData::U8(ref mut v) => v.reserve(additional),
+            ColumnData::U16(ref mut v) => v.reserve(additional),
+            ColumnData::U32(ref mut v) => v.reserve(additional),
+            ColumnData::U64(ref mut v) => v.reserve(additional),
+            ColumnData::F32(ref mut v) => v.reserve(additional),
+            ColumnData::F64(ref mut v) => v.reserve(additional),
+            ColumnData::List {
+                ref mut offsets, ..
+            } => offsets.reserve(additional),
+            ColumnData::String(ref mut v) => v.reserve(additional),
+            ColumnData::Mixed(ref mut v) => v.reserve(additional),
+            ColumnData::Interleaved { .. } | ColumnData::Indices { .. } => {}
+        }
+    }
+}
+
+/// The values of one attribute of all entities of a group.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    data: ColumnData,
+    /// Which entities have the attribute, `None` if all of them have it.
+    present: Option<Vec<bool>>,
+}
+
+impl Column {
+    /// The values, with placeholders for entities without the attribute.
+    pub fn data(&self) -> &ColumnData {
+        &self.data
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Whether entity `i` has the attribute.
+    pub fn is_present(&self, i: usize) -> bool {
+        match self.present {
+            Some(ref present) => present.get(i).cloned().unwrap_or(false),
+            None => i < self.len(),
+        }
+    }
+
+    /// The value of entity `i`, `None` if it doesn't have the attribute.
+    pub fn get(&self, i: usize) -> Option<AttributeValue> {
+        if self.is_present(i) {
+            Some(self.data.get(i))
+        } else {
+            None
+        }
+    }
+
+    /// A column for `value` of entity `index`, after entities without the attribute.
+    fn new(value: &AttributeValue, index: usize, capacity: usize) -> Column {
+        let mut column = Column {
+            data: ColumnData::for_value(value),
+            present: None,
+        };
+        column.data.reserve(capacity);
+        for _ in 0..index {
+            column.push_absent();
+        }
+        column.data.push(value);
+        if let Some(ref mut present) = column.present {
+            present.push(true);
+        }
+        column
+    }
+
+    fn push(&mut self, value: &AttributeValue) {
+        self.data.push(value);
+        if let Some(ref mut present) = self.present {
+            present.push(true);
+        }
+    }
+
+    fn push_absent(&mut self) {
+        let len = self.len();
+        self.data.push_placeholder();
+        self.present
+            .get_or_insert_with(|| vec![true; len])
+            .push(false);
+    }
+}
+
+/// A mesh stored in columns, see the [module documentation](self).
+///
+/// Groups are ordered like the groups of [face_vertex::Mesh](::data::face_vertex::Mesh): by
+/// kind (nodes, elements, vectors, others) and then in the order they were added.
+#[derive(Default, Debug)]
+pub struct ColumnarMesh {
+    dimension: u8,
+
+    nodes: Vec<ColumnarGroup>,
+    elements: Vec<ColumnarGroup>,
+    vectors: Vec<ColumnarGroup>,
+    others: Vec<ColumnarGroup>,
+}
+
+impl ColumnarMesh {
+    pub fn dimension(&self) -> u8 {
+        self.dimension
+    }
+
+    /// All groups of the mesh, ordered by kind and then in the order they were added.
+    pub fn all_groups(&self) -> impl Iterator<Item = &ColumnarGroup> {
+        self.nodes
+            .iter()
+            .chain(self.elements.iter())
+            .chain(self.vectors.iter())
+            .chain(self.others.iter())
+    }
+
+    pub fn node_groups(&self) -> &[ColumnarGroup] {
+        &self.nodes
+    }
+
+    pub fn element_groups(&self) -> &[ColumnarGroup] {
+        &self.elements
+    }
+
+    pub fn vector_groups(&self) -> &[ColumnarGroup] {
+        &self.vectors
+    }
+
+    pub fn other_groups(&self) -> &[ColumnarGroup] {
+        &self.others
+    }
+}
+
+/// A group of entities, with one [Column] per attribute name.
+///
+/// All entities have the kind of the group.
+#[derive(Clone, Debug)]
+pub struct ColumnarGroup {
+    name: Name,
+    kind: EntityKind,
+    attributes: AttributeMap,
+    len: usize,
+    columns: Vec<(AttributeName, Column)>,
+}
+
+impl ColumnarGroup {
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn kind(&self) -> EntityKind {
+        self.kind
+    }
+
+    /// Attributes of the group itself.
+    pub fn attributes(&self) -> &AttributeMap {
+        &self.attributes
+    }
+
+    /// The number of entities.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The columns, ordered by attribute name.
+    pub fn columns(&self) -> impl Iterator<Item = (&AttributeName, &Column)> {
+        self.columns.iter().map(|(name, column)| (name, column))
+    }
+
+    pub fn column(&self, name: &AttributeName) -> Option<&Column> {
+        self.columns
+            .binary_search_by(|(n, _)| n.cmp(name))
+            .ok()
+            .map(|i| &self.columns[i].1)
+    }
+
+    /// Assemble entity `i` from the columns.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of bounds.
+    pub fn entity(&self, i: usize) -> EntityBox {
+        assert!(i < self.len, "entity {} of {}", i, self.len);
+        let mut attr = AttributeMap::new();
+        for (name, column) in &self.columns {
+            if let Some(value) = column.get(i) {
+                attr.set(name.clone(), value);
+            }
+        }
+        EntityBox::new(self.kind, attr)
+    }
+
+    fn column_index(&self, name: &'static str) -> Option<usize> {
+        self.columns
+            .binary_search_by(|(n, _)| n.cmp(&AttributeName::key(name)))
+            .ok()
+    }
+
+    /// Interleave the coordinates of nodes if all of them are `f64`.
+    fn interleave_positions(&mut self, dimension: usize) {
+        if dimension == 0 || dimension > keys::POSITION.len() {
+            return;
+        }
+        let mut indices = Vec::with_capacity(dimension);
+        for name in &keys::POSITION[..dimension] {
+            match self.column_index(name) {
+                Some(i) if self.columns[i].1.present.is_none() => indices.push(i),
+                _ => return,
+            }
+        }
+        let mut coordinates = Vec::with_capacity(dimension);
+        for &i in &indices {
+            match self.columns[i].1.data {
+                ColumnData::F64(ref values) => coordinates.push(values),
+                _ => return,
+            }
+        }
+        let mut values = Vec::with_capacity(self.len * dimension);
+        for j in 0..self.len {
+            values.extend(coordinates.iter().map(|coordinate| coordinate[j]));
+        }
+        let values = Arc::new(values);
+        for (offset, &i) in indices.iter().enumerate() {
+            self.columns[i].1.data = ColumnData::Interleaved {
+                values: values.clone(),
+                offset,
+                stride: dimension,
+            };
+        }
+    }
+
+    /// Narrow the node indices of elements to `u32` if all elements have the same number.
+    fn narrow_nodes(&mut self) {
+        let column = match self.column_index(keys::NODES) {
+            Some(i) => &mut self.columns[i].1,
+            None => return,
+        };
+        let narrowed = match *column {
+            Column {
+                data:
+                    ColumnData::List {
+                        ref offsets,
+                        ref items,
+                    },
+                present: None,
+            } => match **items {
+                ColumnData::U64(ref items) => {
+                    let width = offsets.get(1).map_or(0, |&end| end - offsets[0]);
+                    if width > 0 && offsets.windows(2).all(|w| w[1] - w[0] == width) {
+                        items
+                            .iter()
+                            .map(|&item| u32::try_from(item).ok())
+                            .collect::<Option<Vec<_>>>()
+                            .map(|items| ColumnData::Indices { width, items })
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(data) = narrowed {
+            column.data = data;
+        }
+    }
+}
+
+impl SetMesh for ColumnarMesh {
+    type GroupSetter<'m> = ColumnarGroupSetter<'m>;
+
+    fn set_dimension(&mut self, dim: u8) {
+        self.dimension = dim;
+    }
+
+    fn add_group(&mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter<'_>, Error> {
+        Ok(ColumnarGroupSetter {
+            mesh: self,
+            capacity: 0,
+            group: ColumnarGroup {
+                name,
+                kind,
+                attributes: AttributeMap::new(),
+                len: 0,
+                columns: Vec::new(),
+            },
+        })
+    }
+}
+
+pub struct ColumnarGroupSetter<'m> {
+    mesh: &'m mut ColumnarMesh,
+    /// The reserved number of entities, at most [MAX_RESERVE], the capacity of new columns.
+    capacity: usize,
+    group: ColumnarGroup,
+}
+
+impl<'m> SetMeshGroup for ColumnarGroupSetter<'m> {
+    fn reserve(&mut self, num: usize) -> Result<(), Error> {
+        self.capacity = num.min(MAX_RESERVE);
+        for (_, column) in &mut self.group.columns {
+            column
+                .data
+                .reserve(self.capacity.saturating_sub(column.len()));
+        }
+        Ok(())
+    }
+
+    fn set_attribute(&mut self, name: AttributeName, value: AttributeValue) -> Result<(), Error> {
+        self.group.attributes.set(name, value);
+        Ok(())
+    }
+
+    fn add_entity<E: Entity>(&mut self, entity: E) -> Result<(), Error> {
+        let index = self.group.len;
+        let capacity = self.capacity.saturating_sub(index);
+        let columns = &mut self.group.columns;
+        for (name, value) in entity.attributes().iter() {
+            match columns.binary_search_by(|(n, _)| n.cmp(name)) {
+                Ok(i) => columns[i].1.push(value),
+                Err(i) => columns.insert(i, (name.clone(), Column::new(value, index, capacity))),
+            }
+        }
+        // Entities without some attributes leave gaps.
+        for (_, column) in columns.iter_mut() {
+            if column.len() == index {
+                column.push_absent();
+            }
+        }
+        self.group.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        let mesh = self.mesh;
+        let mut group = self.group;
+        match group.kind {
+            EntityKind::Node => group.interleave_positions(mesh.dimension as usize),
+            EntityKind::Element => group.narrow_nodes(),
+            EntityKind::Vector | EntityKind::Other => {}
+        }
+        match group.kind {
+            EntityKind::Node => mesh.nodes.push(group),
+            EntityKind::Element => mesh.elements.push(group),
+            EntityKind::Vector => mesh.vectors.push(group),
+            EntityKind::Other => mesh.others.push(group),
+        }
+        Ok(())
+    }
+}
+
+impl GetMesh for ColumnarMesh {
+    type Entity = EntityBox;
+    type GroupReader<'m> = ColumnarGroupReader<'m>;
+    type GroupReaders<'m> = Box<dyn Iterator<Item = ColumnarGroupReader<'m>> + 'm>;
+
+    fn metadata(&self) -> MeshMetadata {
+        MeshMetadata {
+            dimension: self.dimension,
+        }
+    }
+
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        Box::new(self.all_groups().map(move |group| ColumnarGroupReader {
+            group,
+            dimension: self.dimension,
+            next: 0,
+        }))
+    }
+}
+
+pub struct ColumnarGroupReader<'m> {
+    group: &'m ColumnarGroup,
+    dimension: u8,
+    next: usize,
+}
+
+impl<'m> GetMeshGroup for ColumnarGroupReader<'m> {
+    fn metadata(&self) -> GroupMetadata {
+        GroupMetadata {
+            name: self.group.name.clone(),
+            kind: self.group.kind,
+            size: self.group.len,
+            attributes: self.group.attributes.clone(),
+        }
+    }
+
+    /// Available if all nodes have `f64` coordinates, which were interleaved when the group
+    /// ended.
+    fn positions(&self) -> Option<Strided<'_, f64>> {
+        let dimension = self.dimension as usize;
+        if self.group.kind != EntityKind::Node || dimension == 0 {
+            return None;
+        }
+        let mut shared: Option<&Arc<Vec<f64>>> = None;
+        for (j, name) in keys::POSITION.iter().take(dimension).enumerate() {
+            let column = self.group.column(&AttributeName::key(name))?;
+            match column.data {
+                ColumnData::Interleaved {
+                    ref values,
+                    offset,
+                    stride,
+                } if offset == j
+                    && stride == dimension
+                    && shared.is_none_or(|shared| Arc::ptr_eq(shared, values)) =>
+                {
+                    shared = Some(values)
+                }
+                _ => return None,
+            }
+        }
+        shared.map(|values| Strided::contiguous(values, dimension))
+    }
+
+    /// Available if all elements have the same number of node indices, which fit into `u32`.
+    fn connectivity(&self) -> Option<Strided<'_, u32>> {
+        match self.group.column(&AttributeName::key(keys::NODES))?.data {
+            ColumnData::Indices { width, ref items } => Some(Strided::contiguous(items, width)),
+            _ => None,
+        }
+    }
+}
+
+impl<'m> Iterator for ColumnarGroupReader<'m> {
+    type Item = EntityBox;
+
+    fn next(&mut self) -> Option<EntityBox> {
+        if self.next < self.group.len {
+            self.next += 1;
+            Some(self.group.entity(self.next - 1))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.group.len - self.next;
+        (n, Some(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::face_vertex::Mesh;
+    use de::Deserializer;
+    use format::{naming::Format, netgen::NetgenDeserializer};
+    use util::geometry;
+
+    #[test]
+    fn typed_columns() {
+        let mut mesh = ColumnarMesh::default();
+        let name = Name::parse("cells".into(), Format::Netgen, EntityKind::Element).unwrap();
+        let mut group = mesh.add_group(name, EntityKind::Element).unwrap();
+        let mut tagged = AttributeMap::new();
+        tagged.set("ref".into(), 7u32.into());
+        let elements = vec![
+            geometry::entity_with_nodes(EntityKind::Element, &[0, 1, 2], AttributeMap::new()),
+            geometry::entity_with_nodes(EntityKind::Element, &[2, 3, 4, 5], tagged),
+            geometry::entity_with_nodes(EntityKind::Element, &[], AttributeMap::new()),
+        ];
+        for element in elements {
+            group.add_entity(element).unwrap();
+        }
+        let mut mixed = AttributeMap::new();
+        mixed.set("ref".into(), "wall".into());
+        group
+            .add_entity(EntityBox::new(EntityKind::Element, mixed))
+            .unwrap();
+        group.end().unwrap();
+
+        let group = &mesh.element_groups()[0];
+        assert_eq!(group.len(), 4);
+        let nodes = group.column(&"nodes".into()).unwrap();
+        match *nodes.data() {
+            ColumnData::List {
+                ref offsets,
+                ref items,
+            } => {
+                assert_eq!(offsets, &[0, 3, 7, 7, 7]);
+                assert_eq!(**items, ColumnData::U64(vec![0, 1, 2, 2, 3, 4, 5]));
+            }
+            ref data => panic!("not a list: {:?}", data),
+        }
+        assert!(!nodes.is_present(3));
+
+        let tags = group.column(&"ref".into()).unwrap();
+        assert_eq!(tags.get(0), None);
+        assert_eq!(tags.get(1), Some(AttributeValue::U32(7)));
+        assert_eq!(tags.get(3), Some("wall".into()));
+        assert_eq!(group.entity(1).node_indices().unwrap(), vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn same_entities_as_face_vertex() {
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        let mut mesh = Mesh::default();
+        NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+        let mut columnar = ColumnarMesh::default();
+        NetgenDeserializer::deserialize_into(&data[..], &mut columnar).unwrap();
+
+        assert_eq!(columnar.dimension(), mesh.dimension());
+        let groups: Vec<_> = mesh.groups().collect();
+        let columnar_groups: Vec<_> = columnar.groups().collect();
+        assert_eq!(groups.len(), columnar_groups.len());
+        for (group, columnar_group) in groups.into_iter().zip(columnar_groups) {
+            let metadata = columnar_group.metadata();
+            assert_eq!(
+                metadata.name().get_original(),
+                group.metadata().name().get_original()
+            );
+            assert_eq!(metadata.len(), group.metadata().len());
+            for (entity, columnar_entity) in group.zip(columnar_group) {
+                assert_eq!(entity.attributes(), columnar_entity.attributes());
+            }
+        }
+    }
+
+    #[test]
+    fn borrowed_views() {
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        let mut mesh = ColumnarMesh::default();
+        NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+        let dimension = mesh.dimension() as usize;
+        let mut viewed = 0;
+        for group in mesh.groups() {
+            let positions: Option<Vec<Vec<f64>>> = group
+                .positions()
+                .map(|positions| positions.iter().map(<[f64]>::to_vec).collect());
+            let connectivity: Option<Vec<Vec<usize>>> = group.connectivity().map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|nodes| nodes.iter().map(|&i| i as usize).collect())
+                    .collect()
+            });
+            let entities: Vec<_> = group.collect();
+            if let Some(positions) = positions {
+                let expected: Vec<_> = entities
+                    .iter()
+                    .map(|node| {
+                        node.position(dimension).unwrap().coords.as_slice()[..dimension].to_vec()
+                    })
+                    .collect();
+                assert_eq!(positions, expected);
+                viewed += 1;
+            }
+            if let Some(connectivity) = connectivity {
+                let expected: Vec<_> = entities.iter().map(|e| e.node_indices().unwrap()).collect();
+                assert_eq!(connectivity, expected);
+                viewed += 1;
+            }
+        }
+        assert!(viewed >= 2);
+
+        // Appending after narrowing restores the usual columns.
+        let mut data = mesh.element_groups()[0]
+            .column(&"nodes".into())
+            .unwrap()
+            .data()
+            .clone();
+        data.push(&AttributeValue::List(vec![1u64.into()]));
+        assert!(matches!(data, ColumnData::List { .. }));
+    }
+}
@@ -0,0 +1,209 @@
+//! Object safe counterparts of the mesh traits.
+//!
+//! [SetMesh] and [GetMesh] have generic methods and associated types, so they can't be used as
+//! trait objects. The traits of this module can, and every implementation of the former
+//! implements them automatically. In the other direction `&mut dyn DynSetMesh` implements
+//! [SetMesh] and `&dyn DynGetMesh` implements [GetMesh], so any deserializer and serializer
+//! works with them.
+//!
+//! Entities pass through as [EntityBox], which costs a copy of their attributes.
+
+use data::{
+    attribute::{AttributeName, AttributeValue},
+    view::Strided,
+    Entity, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh,
+    SetMeshGroup,
+};
+use error::Error;
+use format::naming::Name;
+
+/// Object safe version of [SetMeshGroup].
+pub trait DynSetMeshGroup {
+    fn dyn_reserve(&mut self, num: usize) -> Result<(), Error>;
+    fn dyn_set_attribute(
+        &mut self,
+        name: AttributeName,
+        value: AttributeValue,
+    ) -> Result<(), Error>;
+    fn dyn_add_entity(&mut self, entity: EntityBox) -> Result<(), Error>;
+    fn dyn_end(self: Box<Self>) -> Result<(), Error>;
+}
+
+impl<G: SetMeshGroup> DynSetMeshGroup for G {
+    fn dyn_reserve(&mut self, num: usize) -> Result<(), Error> {
+        self.reserve(num)
+    }
+
+    fn dyn_set_attribute(
+        &mut self,
+        name: AttributeName,
+        value: AttributeValue,
+    ) -> Result<(), Error> {
+        self.set_attribute(name, value)
+    }
+
+    fn dyn_add_entity(&mut self, entity: EntityBox) -> Result<(), Error> {
+        self.add_entity(entity)
+    }
+
+    fn dyn_end(self: Box<Self>) -> Result<(), Error> {
+        (*self).end()
+    }
+}
+
+impl<'m> SetMeshGroup for Box<dyn DynSetMeshGroup + 'm> {
+    fn reserve(&mut self, num: usize) -> Result<(), Error> {
+        (**self).dyn_reserve(num)
+    }
+
+    fn set_attribute(&mut self, name: AttributeName, value: AttributeValue) -> Result<(), Error> {
+        (**self).dyn_set_attribute(name, value)
+    }
+
+    fn add_entity<E: Entity>(&mut self, entity: E) -> Result<(), Error> {
+        (**self).dyn_add_entity(EntityBox::from_entity(&entity))
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.dyn_end()
+    }
+}
+
+/// Object safe version of [SetMesh].
+pub trait DynSetMesh {
+    fn dyn_set_dimension(&mut self, dim: u8);
+    fn dyn_add_group(
+        &mut self,
+        name: Name,
+        kind: EntityKind,
+    ) -> Result<Box<dyn DynSetMeshGroup + '_>, Error>;
+}
+
+impl<T: SetMesh> DynSetMesh for T {
+    fn dyn_set_dimension(&mut self, dim: u8) {
+        self.set_dimension(dim)
+    }
+
+    fn dyn_add_group(
+        &mut self,
+        name: Name,
+        kind: EntityKind,
+    ) -> Result<Box<dyn DynSetMeshGroup + '_>, Error> {
+        Ok(Box::new(self.add_group(name, kind)?))
+    }
+}
+
+impl<'a, 'b> SetMesh for &'a mut (dyn DynSetMesh + 'b) {
+    type GroupSetter<'m>
+        = Box<dyn DynSetMeshGroup + 'm>
+    where
+        Self: 'm;
+
+    fn set_dimension(&mut self, dim: u8) {
+        (**self).dyn_set_dimension(dim)
+    }
+
+    fn add_group(&mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter<'_>, Error> {
+        (**self).dyn_add_group(name, kind)
+    }
+}
+
+/// Object safe version of [GetMeshGroup].
+pub trait DynGetMeshGroup {
+    fn dyn_metadata(&self) -> GroupMetadata;
+    fn dyn_next(&mut self) -> Option<EntityBox>;
+    fn dyn_size_hint(&self) -> (usize, Option<usize>);
+    fn dyn_positions(&self) -> Option<Strided<'_, f64>>;
+    fn dyn_connectivity(&self) -> Option<Strided<'_, u32>>;
+}
+
+impl<G> DynGetMeshGroup for G
+where
+    G: GetMeshGroup,
+    G::Item: Entity,
+{
+    fn dyn_metadata(&self) -> GroupMetadata {
+        self.metadata()
+    }
+
+    fn dyn_next(&mut self) -> Option<EntityBox> {
+        self.next().map(|entity| EntityBox::from_entity(&entity))
+    }
+
+    fn dyn_size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint()
+    }
+
+    fn dyn_positions(&self) -> Option<Strided<'_, f64>> {
+        self.positions()
+    }
+
+    fn dyn_connectivity(&self) -> Option<Strided<'_, u32>> {
+        self.connectivity()
+    }
+}
+
+/// A boxed [DynGetMeshGroup], which is a [GetMeshGroup] again.
+pub struct DynGroupReader<'m>(Box<dyn DynGetMeshGroup + 'm>);
+
+impl<'m> Iterator for DynGroupReader<'m> {
+    type Item = EntityBox;
+
+    fn next(&mut self) -> Option<EntityBox> {
+        self.0.dyn_next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.dyn_size_hint()
+    }
+}
+
+impl<'m> GetMeshGroup for DynGroupReader<'m> {
+    fn metadata(&self) -> GroupMetadata {
+        self.0.dyn_metadata()
+    }
+
+    fn positions(&self) -> Option<Strided<'_, f64>> {
+        self.0.dyn_positions()
+    }
+
+    fn connectivity(&self) -> Option<Strided<'_, u32>> {
+        self.0.dyn_connectivity()
+    }
+}
+
+/// Object safe version of [GetMesh].
+pub trait DynGetMesh {
+    fn dyn_metadata(&self) -> MeshMetadata;
+    fn dyn_groups(&self) -> Box<dyn Iterator<Item = DynGroupReader<'_>> + '_>;
+}
+
+impl<M: GetMesh> DynGetMesh for M {
+    fn dyn_metadata(&self) -> MeshMetadata {
+        self.metadata()
+    }
+
+    fn dyn_groups(&self) -> Box<dyn Iterator<Item = DynGroupReader<'_>> + '_> {
+        Box::new(self.groups().map(|group| DynGroupReader(Box::new(group))))
+    }
+}
+
+impl<'a, 'b> GetMesh for &'a (dyn DynGetMesh + 'b) {
+    type Entity = EntityBox;
+    type GroupReader<'m>
+        = DynGroupReader<'m>
+    where
+        Self: 'm;
+    type GroupReaders<'m>
+        = Box<dyn Iterator<Item = DynGroupReader<'m>> + 'm>
+    where
+        Self: 'm;
+
+    fn metadata(&self) -> MeshMetadata {
+        (**self).dyn_metadata()
+    }
+
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        (**self).dyn_groups()
+    }
+}
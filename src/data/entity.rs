@@ -1,15 +1,84 @@
-use data::attribute::{AttributeContainer, AttributeContainerMut, AttributeMap};
+use data::attribute::{
+    keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+};
+use error::Error;
+use nalgebra::{Point3, Vector3};
 use std::fmt::Debug;
 
+/// An entity of a mesh.
+///
+/// Geometry and connectivity are stored as attributes (see [keys](::data::attribute::keys)),
+/// the provided methods read them as numbers.
 pub trait Entity: Clone + Debug {
     type Attr: AttributeContainer;
 
     fn kind(&self) -> EntityKind;
     fn attributes(&self) -> &Self::Attr;
+
+    /// The first `dim` coordinates of a node, the remaining coordinates are zero.
+    fn position(&self, dim: usize) -> Result<Point3<f64>, Error> {
+        Ok(Point3::from(self.components(dim)?))
+    }
+
+    /// The first `dim` components of a vector, the remaining components are zero.
+    ///
+    /// Vectors store their components under the same keys as nodes their coordinates.
+    fn components(&self, dim: usize) -> Result<Vector3<f64>, Error> {
+        if dim > keys::POSITION.len() {
+            return Err(Error::BrokenInvariant(format!(
+                "Unsupported dimension: {}",
+                dim
+            )));
+        }
+
+        let mut components = Vector3::zeros();
+        for (i, key) in keys::POSITION[..dim].iter().enumerate() {
+            let value = self
+                .attributes()
+                .get(&AttributeName::key(key))
+                .ok_or_else(|| {
+                    Error::BrokenInvariant(format!("Entity without coordinate `{}`.", key))
+                })?;
+            components[i] = value.to_f64().ok_or_else(|| {
+                Error::BrokenInvariant(format!("Coordinate `{}` is not a number: {}", key, value))
+            })?;
+        }
+        Ok(components)
+    }
+
+    /// The node indices of an element.
+    fn node_indices(&self) -> Result<Vec<usize>, Error> {
+        let value = self
+            .attributes()
+            .get(&AttributeName::key(keys::NODES))
+            .ok_or_else(|| Error::BrokenInvariant("Entity without node indices.".into()))?;
+        value
+            .to_usize_list()
+            .ok_or_else(|| Error::BrokenInvariant(format!("Invalid node indices: {}", value)))
+    }
 }
 
 pub trait EntityMut<Attr: AttributeContainerMut>: Entity<Attr = Attr> {
     fn attributes_mut(&mut self) -> &mut Attr;
+
+    /// Set the first `dim` (at most three) coordinates of a node.
+    fn set_position(&mut self, position: &Point3<f64>, dim: usize) {
+        self.set_components(&position.coords, dim);
+    }
+
+    /// Set the first `dim` (at most three) components of a vector.
+    fn set_components(&mut self, components: &Vector3<f64>, dim: usize) {
+        for (key, value) in keys::POSITION.iter().zip(components.iter()).take(dim) {
+            self.attributes_mut()
+                .set(AttributeName::key(key), (*value).into());
+        }
+    }
+
+    /// Set the node indices of an element.
+    fn set_node_indices(&mut self, nodes: &[usize]) {
+        self.attributes_mut()
+            .set(AttributeName::key(keys::NODES), nodes.into());
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Copy)]
@@ -49,6 +118,10 @@ impl EntityMut<AttributeMap> for EntityBox {
 }
 
 impl EntityBox {
+    pub fn new(kind: EntityKind, attr: AttributeMap) -> Self {
+        EntityBox { kind, attr }
+    }
+
     pub fn from_entity<E, A>(e: &E) -> Self
     where
         E: Entity<Attr = A>,
@@ -60,3 +133,27 @@ impl EntityBox {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry_accessors() {
+        let mut node = EntityBox::new(EntityKind::Node, AttributeMap::new());
+        assert!(node.position(2).is_err());
+        node.set_position(&Point3::new(1., 2., 3.), 2);
+        assert_eq!(node.position(2).unwrap(), Point3::new(1., 2., 0.));
+        assert!(node.position(3).is_err());
+        assert!(node.position(4).is_err());
+
+        node.attributes_mut().set("z".into(), "up".into());
+        assert!(node.components(3).is_err());
+        node.set_components(&Vector3::new(0., 0., -1.5), 3);
+        assert_eq!(node.components(3).unwrap(), Vector3::new(0., 0., -1.5));
+
+        let mut element = EntityBox::new(EntityKind::Element, AttributeMap::new());
+        element.set_node_indices(&[3, 1, 4]);
+        assert_eq!(element.node_indices().unwrap(), vec![3, 1, 4]);
+    }
+}
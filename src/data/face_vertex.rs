@@ -3,13 +3,12 @@
 
 use crate::format::naming::Name;
 use data::{
-    attribute::{AttributeMap, AttributeName},
+    attribute::{AttributeContainerMut, AttributeMap, AttributeName, AttributeValue},
     Entity, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh,
-    SetMeshGroup, *,
+    SetMeshGroup, MAX_RESERVE,
 };
 use error::Error;
-use nalgebra::DVector;
-use std::{borrow::Cow, fmt};
+use std::slice;
 
 /// A mesh represented in face-vertex form, referred to as elements and nodes in the following.
 ///
@@ -25,18 +24,19 @@ pub struct Mesh {
     others: Vec<EntityGroup>,
 }
 
-impl<'m> SetMesh<'m> for &'m mut Mesh {
-    type GroupSetter = MeshGroupSetter<'m>;
+impl SetMesh for Mesh {
+    type GroupSetter<'m> = MeshGroupSetter<'m>;
 
-    fn set_dimension(&'m mut self, dim: u8) {
+    fn set_dimension(&mut self, dim: u8) {
         self.dimension = dim;
     }
 
-    fn add_group(&'m mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter, Error> {
+    fn add_group(&mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter<'_>, Error> {
         Ok(MeshGroupSetter {
             name,
             kind,
             mesh: self,
+            attributes: AttributeMap::new(),
             entities: Vec::new(),
         })
     }
@@ -46,10 +46,21 @@ pub struct MeshGroupSetter<'m> {
     name: Name,
     kind: EntityKind,
     mesh: &'m mut Mesh,
+    attributes: AttributeMap,
     entities: Vec<EntityBox>,
 }
 
-impl<'m> SetMeshGroup<'m> for MeshGroupSetter<'m> {
+impl<'m> SetMeshGroup for MeshGroupSetter<'m> {
+    fn reserve(&mut self, num: usize) -> Result<(), Error> {
+        self.entities.reserve_exact(num.min(MAX_RESERVE));
+        Ok(())
+    }
+
+    fn set_attribute(&mut self, name: AttributeName, value: AttributeValue) -> Result<(), Error> {
+        self.attributes.set(name, value);
+        Ok(())
+    }
+
     fn add_entity<E: Entity>(&mut self, entity: E) -> Result<(), Error> {
         self.entities.push(EntityBox::from_entity(&entity));
         Ok(())
@@ -59,6 +70,7 @@ impl<'m> SetMeshGroup<'m> for MeshGroupSetter<'m> {
         let group = EntityGroup {
             name: self.name,
             kind: self.kind,
+            attributes: self.attributes,
             entities: self.entities,
         };
         match self.kind {
@@ -75,13 +87,65 @@ impl<'m> SetMeshGroup<'m> for MeshGroupSetter<'m> {
 pub struct EntityGroup {
     name: Name,
     kind: EntityKind,
+    attributes: AttributeMap,
     entities: Vec<EntityBox>,
 }
 
-impl<'m> GetMesh<'m> for &'m Mesh {
+impl EntityGroup {
+    pub fn name(&self) -> &Name {
+        &self.name
+    }
+
+    pub fn kind(&self) -> EntityKind {
+        self.kind
+    }
+
+    /// Attributes of the group itself.
+    pub fn attributes(&self) -> &AttributeMap {
+        &self.attributes
+    }
+
+    pub fn entities(&self) -> &[EntityBox] {
+        &self.entities
+    }
+}
+
+impl Mesh {
+    pub fn dimension(&self) -> u8 {
+        self.dimension
+    }
+
+    /// All groups of the mesh, ordered by kind (nodes, elements, vectors, others) and then in
+    /// the order they were added.
+    pub fn all_groups(&self) -> impl Iterator<Item = &EntityGroup> {
+        self.nodes
+            .iter()
+            .chain(self.elements.iter())
+            .chain(self.vectors.iter())
+            .chain(self.others.iter())
+    }
+
+    pub fn node_groups(&self) -> &[EntityGroup] {
+        &self.nodes
+    }
+
+    pub fn element_groups(&self) -> &[EntityGroup] {
+        &self.elements
+    }
+
+    pub fn vector_groups(&self) -> &[EntityGroup] {
+        &self.vectors
+    }
+
+    pub fn other_groups(&self) -> &[EntityGroup] {
+        &self.others
+    }
+}
+
+impl GetMesh for Mesh {
     type Entity = EntityBox;
-    type GroupReader = MeshGroupReader<'m>;
-    type GroupReaders = Box<Iterator<Item = Self::GroupReader> + 'm>;
+    type GroupReader<'m> = MeshGroupReader<'m>;
+    type GroupReaders<'m> = Box<dyn Iterator<Item = MeshGroupReader<'m>> + 'm>;
 
     fn metadata(&self) -> MeshMetadata {
         MeshMetadata {
@@ -89,24 +153,27 @@ impl<'m> GetMesh<'m> for &'m Mesh {
         }
     }
 
-    fn groups(&self) -> Self::GroupReaders {
-        /*
-        let groups_it = self.nodes.iter().chain(self.elements.iter()).chain(self.vectors.iter()).chain(self.others.iter());
-        Box::new(groups_it.map(|group| MeshGroupReader {mesh: self, entity_group: group, index: 0}))
-        */
-        unimplemented!()
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        Box::new(self.all_groups().map(|group| MeshGroupReader {
+            entity_group: group,
+            entities: group.entities.iter(),
+        }))
     }
 }
 
 pub struct MeshGroupReader<'m> {
-    mesh: &'m Mesh,
     entity_group: &'m EntityGroup,
-    index: usize,
+    entities: slice::Iter<'m, EntityBox>,
 }
 
 impl<'m> GetMeshGroup for MeshGroupReader<'m> {
     fn metadata(&self) -> GroupMetadata {
-        unimplemented!()
+        GroupMetadata {
+            name: self.entity_group.name.clone(),
+            kind: self.entity_group.kind,
+            size: self.entity_group.entities.len(),
+            attributes: self.entity_group.attributes.clone(),
+        }
     }
 }
 
@@ -114,6 +181,10 @@ impl<'m> Iterator for MeshGroupReader<'m> {
     type Item = EntityBox;
 
     fn next(&mut self) -> Option<<Self as Iterator>::Item> {
-        unimplemented!()
+        self.entities.next().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entities.size_hint()
     }
 }
@@ -1,70 +1,143 @@
 use crate::{
-    data::entity::{Entity, EntityKind},
+    data::{
+        attribute::{AttributeMap, AttributeName, AttributeValue},
+        entity::{Entity, EntityKind},
+        view::Strided,
+    },
     error::Error,
     format::naming::Name,
 };
 
-pub trait SetMeshGroup<'m> {
+/// The largest number of entities which setters reserve memory for in advance.
+///
+/// Reserved sizes usually come from the headers of files, which can't be trusted, so larger
+/// groups grow as their entities are added.
+pub const MAX_RESERVE: usize = 1 << 20;
+
+pub trait SetMeshGroup {
     /// Will not necessarily be called by every deserializer, but if it is called, then the contract
     /// is that the size will not change anymore.
+    ///
+    /// Implementors should reserve at most [MAX_RESERVE] entities, since `num` is often read from
+    /// the input without further checks.
     fn reserve(&mut self, _num: usize) -> Result<(), Error> {
         Ok(())
     }
 
+    /// Set an attribute of the group itself, like the material of its entities.
+    ///
+    /// Implementors which don't store group attributes may ignore them.
+    fn set_attribute(&mut self, _name: AttributeName, _value: AttributeValue) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn add_entity<E: Entity>(&mut self, entity: E) -> Result<(), Error>;
 
     fn end(self) -> Result<(), Error>;
 }
 
-pub trait SetMesh<'m> {
-    //type Entity: Entity;
-    //type GroupSetter: SetMeshGroup<'m, Entity=Self::Entity> + 'm;
-    type GroupSetter: SetMeshGroup<'m> + 'm;
+pub trait SetMesh {
+    type GroupSetter<'m>: SetMeshGroup + 'm
+    where
+        Self: 'm;
 
-    fn set_dimension(&'m mut self, dim: u8);
+    fn set_dimension(&mut self, dim: u8);
 
-    fn add_group(&'m mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter, Error>;
+    fn add_group(&mut self, name: Name, kind: EntityKind) -> Result<Self::GroupSetter<'_>, Error>;
 }
 
 pub trait GetMeshGroup: Iterator {
     fn metadata(&self) -> GroupMetadata;
+
+    /// The coordinates of all nodes of a node group, borrowed without copying.
+    ///
+    /// Every node has as many coordinates as the mesh has dimensions. This is a fast path for
+    /// implementations which store the coordinates as `f64` anyway, callers fall back to the
+    /// positions of the entities if it is `None`.
+    fn positions(&self) -> Option<Strided<'_, f64>> {
+        None
+    }
+
+    /// The zero-based node indices of all elements of a group, borrowed without copying.
+    ///
+    /// Only available if all elements have the same number of nodes, callers fall back to the
+    /// node indices of the entities if it is `None`.
+    fn connectivity(&self) -> Option<Strided<'_, u32>> {
+        None
+    }
 }
 
-pub trait GetMesh<'m> {
-    type Entity;
-    type GroupReader: GetMeshGroup<Item = Self::Entity> + 'm;
-    type GroupReaders: Iterator<Item = Self::GroupReader> + 'm;
+pub trait GetMesh {
+    type Entity: Entity;
+    type GroupReader<'m>: GetMeshGroup<Item = Self::Entity> + 'm
+    where
+        Self: 'm;
+    type GroupReaders<'m>: Iterator<Item = Self::GroupReader<'m>> + 'm
+    where
+        Self: 'm;
 
     fn metadata(&self) -> MeshMetadata;
-    fn groups(&self) -> Self::GroupReaders;
+    fn groups(&self) -> Self::GroupReaders<'_>;
 }
 
 #[derive(Clone, Debug)]
 pub struct MeshMetadata {
     /// The dimensionality of the mesh, usually `2` or `3`.
-    // TODO: builder/constructor or public
     pub(crate) dimension: u8,
 }
 
 impl MeshMetadata {
+    pub fn new(dimension: u8) -> Self {
+        MeshMetadata { dimension }
+    }
+
     /// The dimensionality of the mesh, usually `2` or `3`.
     pub fn dimension(&self) -> u8 {
         self.dimension
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct GroupMetadata {
-    // TODO: builder/constructor or public
     pub(crate) name: Name,
+    pub(crate) kind: EntityKind,
     pub(crate) size: usize,
+    pub(crate) attributes: AttributeMap,
 }
 
 impl GroupMetadata {
+    pub fn new(name: Name, kind: EntityKind, size: usize) -> Self {
+        GroupMetadata {
+            name,
+            kind,
+            size,
+            attributes: AttributeMap::new(),
+        }
+    }
+
+    pub fn with_attributes(mut self, attributes: AttributeMap) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
     pub fn name(&self) -> &Name {
         &self.name
     }
 
+    pub fn kind(&self) -> EntityKind {
+        self.kind
+    }
+
     pub fn len(&self) -> usize {
         self.size
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Attributes of the group itself.
+    pub fn attributes(&self) -> &AttributeMap {
+        &self.attributes
+    }
 }
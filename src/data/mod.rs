@@ -8,15 +8,23 @@ use crate::format::naming::Name;
 use std::hash::{Hash, Hasher};
 
 pub mod attribute;
-//pub mod printing;
+pub mod cell;
+pub mod columnar;
+pub mod printing;
+pub mod validation;
+pub mod view;
 
+pub mod dynamic;
 pub mod entity;
 pub mod face_vertex;
 pub mod mesh;
 
 pub use self::{
-    entity::{Entity, EntityBox, EntityKind},
-    mesh::{GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh, SetMeshGroup},
+    cell::CellType,
+    entity::{Entity, EntityBox, EntityKind, EntityMut},
+    mesh::{
+        GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh, SetMeshGroup, MAX_RESERVE,
+    },
 };
 
 use data::attribute::{AttributeContainer, AttributeMap};
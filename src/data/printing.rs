@@ -1,55 +1,449 @@
 //! Contains utils to print meshes.
-use prettytable::{self, cell, row, Cell, Row, Table};
-use data::GetMesh;
-use std::io::{self, Write};
-use std::collections::HashSet;
+//!
+//! A [MeshSummary] collects the groups of a mesh with statistics of their attributes and the
+//! reference tags of their entities. It can be printed as tables with [write_metadata], or as
+//! JSON with [write_json] for scripts.
+use data::{
+    attribute::{keys, AttributeContainer, AttributeName, AttributeValue},
+    Entity, EntityKind, GetMesh, GetMeshGroup,
+};
+use prettytable::{self, cell, row, Table};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as FmtWrite,
+    io::{self, Write},
+};
 
-pub fn print_metadata<M: GetMesh>(mesh: M) -> io::Result<()> {
+/// Distinct values of an attribute are only counted up to this number.
+pub const MAX_DISTINCT: usize = 256;
+
+/// Summary of a mesh, see [summarize].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshSummary {
+    pub dimension: u8,
+    pub groups: Vec<GroupSummary>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSummary {
+    pub name: String,
+    pub kind: EntityKind,
+    pub len: usize,
+    /// Statistics of the attributes of the entities, ordered by name.
+    pub attributes: Vec<AttributeSummary>,
+    /// The reference tags of the entities with the number of entities having them, ordered
+    /// numerically if possible.
+    pub references: Vec<(String, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeSummary {
+    pub name: String,
+    /// Number of entities with the attribute.
+    pub count: usize,
+    /// Number of distinct values, `None` if there are more than [MAX_DISTINCT].
+    pub distinct: Option<usize>,
+    /// Statistics of the numbers in the values, if all values are numbers, lists of numbers or
+    /// text of numbers separated by whitespace, like coordinates or node indices.
+    pub numeric: Option<NumericSummary>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumericSummary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// A value of an attribute, as far as counting distinct values is concerned.
+#[derive(Eq, Hash, PartialEq)]
+enum Distinct {
+    /// The bits of the numbers of a numeric value, so `1` and `"1.0"` are the same.
+    Numbers(Vec<u64>),
+    Text(String),
+}
+
+/// Accumulates the statistics of one attribute.
+struct AttributeStats {
+    count: usize,
+    distinct: Option<HashSet<Distinct>>,
+    numeric: bool,
+    num_values: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl AttributeStats {
+    fn new() -> Self {
+        AttributeStats {
+            count: 0,
+            distinct: Some(HashSet::new()),
+            numeric: true,
+            num_values: 0,
+            sum: 0.,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn add(&mut self, value: &AttributeValue) {
+        self.count += 1;
+        // Note: Only textual values are parsed, numbers and lists of them are converted.
+        let numbers = if self.numeric || self.distinct.is_some() {
+            value.to_f64_list()
+        } else {
+            None
+        };
+        if let Some(ref mut distinct) = self.distinct {
+            distinct.insert(match numbers {
+                // Adding zero turns -0 into 0.
+                Some(ref numbers) => {
+                    Distinct::Numbers(numbers.iter().map(|x| (x + 0.).to_bits()).collect())
+                }
+                None => Distinct::Text(value.to_string()),
+            });
+            if distinct.len() > MAX_DISTINCT {
+                self.distinct = None;
+            }
+        }
+        if self.numeric {
+            match numbers {
+                Some(numbers) => {
+                    for x in numbers {
+                        self.num_values += 1;
+                        self.sum += x;
+                        self.min = self.min.min(x);
+                        self.max = self.max.max(x);
+                    }
+                }
+                None => self.numeric = false,
+            }
+        }
+    }
+
+    fn finish(self, name: String) -> AttributeSummary {
+        let numeric = if self.numeric && self.num_values > 0 {
+            Some(NumericSummary {
+                min: self.min,
+                max: self.max,
+                mean: self.sum / self.num_values as f64,
+            })
+        } else {
+            None
+        };
+        AttributeSummary {
+            name,
+            count: self.count,
+            distinct: self.distinct.map(|distinct| distinct.len()),
+            numeric,
+        }
+    }
+}
+
+/// Collect the groups of a mesh with the statistics of their attributes.
+pub fn summarize<M: GetMesh>(mesh: &M) -> MeshSummary {
+    let reference = AttributeName::key(keys::REF);
+    let mut groups = Vec::new();
+    for group in mesh.groups() {
+        let metadata = group.metadata();
+        let mut stats: BTreeMap<AttributeName, AttributeStats> = BTreeMap::new();
+        let mut references: BTreeMap<String, usize> = BTreeMap::new();
+        for entity in group {
+            for (name, value) in entity.attributes().iter() {
+                stats
+                    .entry(name.clone())
+                    .or_insert_with(AttributeStats::new)
+                    .add(value);
+                if *name == reference {
+                    *references.entry(value.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut references: Vec<(String, usize)> = references.into_iter().collect();
+        references.sort_by(|a, b| match (a.0.parse::<i64>(), b.0.parse::<i64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => a.0.cmp(&b.0),
+        });
+        groups.push(GroupSummary {
+            name: metadata.name().get_original().0.to_string(),
+            kind: metadata.kind(),
+            len: metadata.len(),
+            attributes: stats
+                .into_iter()
+                .map(|(name, stats)| stats.finish(name.to_string()))
+                .collect(),
+            references,
+        });
+    }
+    MeshSummary {
+        dimension: mesh.metadata().dimension(),
+        groups,
+    }
+}
+
+pub fn print_metadata<M: GetMesh>(mesh: &M) -> io::Result<()> {
     write_metadata(mesh, &mut ::std::io::stdout())
 }
 
-pub fn write_metadata<M: SerializableMesh, W: Write>(mesh: M, mut w: W) -> io::Result<()> {
+pub fn write_metadata<M: GetMesh, W: Write>(mesh: &M, w: W) -> io::Result<()> {
+    write_summary(&summarize(mesh), w)
+}
+
+/// Print a summary as tables.
+pub fn write_summary<W: Write>(summary: &MeshSummary, mut w: W) -> io::Result<()> {
     writeln!(w, "General metadata")?;
     writeln!(w, "----------------")?;
-    writeln!(w, "Mesh dimension: {}", mesh.metadata().dimension())?;
-    writeln!(w, "")?;
+    writeln!(w, "Mesh dimension: {}", summary.dimension)?;
+    writeln!(w)?;
 
-    write_metadata_groups("Node groups", mesh.node_groups(), &mut w);
-    write_metadata_groups("Element groups", mesh.element_groups(), &mut w);
-    write_metadata_groups("Vector groups", mesh.vector_groups(), &mut w);
-    write_metadata_groups("Other groups", mesh.other_groups(), &mut w);
+    write_metadata_groups("Node groups", summary, EntityKind::Node, &mut w)?;
+    write_metadata_groups("Element groups", summary, EntityKind::Element, &mut w)?;
+    write_metadata_groups("Vector groups", summary, EntityKind::Vector, &mut w)?;
+    write_metadata_groups("Other groups", summary, EntityKind::Other, &mut w)?;
+
+    for group in &summary.groups {
+        write_group_details(group, &mut w)?;
+    }
 
     Ok(())
 }
 
-fn write_metadata_groups<G, GI, GII, W>(header: &str, groups: G, mut w: W) -> io::Result<()>
-where
-    G: Iterator<Item = GI>,
-    GI: SerializableGroup<Item = GII>,
-    GII: ReadEntity,
-    W: Write,
-{
+fn write_metadata_groups<W: Write>(
+    header: &str,
+    summary: &MeshSummary,
+    kind: EntityKind,
+    mut w: W,
+) -> io::Result<()> {
     writeln!(w, "{}:", header)?;
     let mut table = Table::new();
     table.set_titles(row!["#", "name", "len", "attrs"]);
-    let mut i = 0;
-    for group in groups {
-        // Collect all available attrs.
-        let gd = group.metadata();
-        let mut attr_names = HashSet::new();
-        for i_item in 0..gd.len() {
-            let item = group.item_at(i_item).expect("broken invariant");
-            let n_attrs = item.attrs_len();
-        }
-
-        table.add_row(row![i, gd.name().get_original().0, gd.len(), ""]);
-        i += 1;
-    }
-    if i > 0 {
-        table.set_format(prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE.clone());
-        table.print(&mut w);
+    let groups = summary.groups.iter().filter(|group| group.kind == kind);
+    for (i, group) in groups.enumerate() {
+        let attr_names: Vec<&str> = group.attributes.iter().map(|a| a.name.as_str()).collect();
+        table.add_row(row![i, group.name, group.len, attr_names.join(", ")]);
+    }
+    if !table.is_empty() {
+        table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        table.print(&mut w)?;
     } else {
         writeln!(w, "There are no such groups.")?;
     }
+    writeln!(w)?;
     Ok(())
 }
+
+fn write_group_details<W: Write>(group: &GroupSummary, mut w: W) -> io::Result<()> {
+    if group.attributes.is_empty() {
+        return Ok(());
+    }
+    writeln!(
+        w,
+        "Attributes of {} group `{}`:",
+        kind_name(group.kind),
+        group.name
+    )?;
+    let mut table = Table::new();
+    table.set_titles(row!["name", "count", "distinct", "min", "max", "mean"]);
+    for attr in &group.attributes {
+        let distinct = match attr.distinct {
+            Some(n) => n.to_string(),
+            None => format!(">{}", MAX_DISTINCT),
+        };
+        let (min, max, mean) = match attr.numeric {
+            Some(n) => (n.min.to_string(), n.max.to_string(), n.mean.to_string()),
+            None => (String::new(), String::new(), String::new()),
+        };
+        table.add_row(row![attr.name, attr.count, distinct, min, max, mean]);
+    }
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.print(&mut w)?;
+
+    if !group.references.is_empty() {
+        let references: Vec<String> = group
+            .references
+            .iter()
+            .map(|(tag, count)| format!("{} ({})", tag, count))
+            .collect();
+        writeln!(w, "References: {}", references.join(", "))?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn kind_name(kind: EntityKind) -> &'static str {
+    match kind {
+        EntityKind::Node => "node",
+        EntityKind::Element => "element",
+        EntityKind::Vector => "vector",
+        EntityKind::Other => "other",
+    }
+}
+
+pub fn write_metadata_json<M: GetMesh, W: Write>(mesh: &M, w: W) -> io::Result<()> {
+    write_json(&summarize(mesh), w)
+}
+
+/// Write a summary as one JSON object, followed by a newline.
+///
+/// Statistics which are not available, like the distinct values of attributes with too many
+/// of them, are `null`.
+pub fn write_json<W: Write>(summary: &MeshSummary, mut w: W) -> io::Result<()> {
+    let mut s = String::new();
+    // Writing into a string never fails.
+    write!(s, "{{\"dimension\":{},\"groups\":[", summary.dimension).unwrap();
+    for (i, group) in summary.groups.iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        write!(
+            s,
+            "{{\"name\":{},\"kind\":\"{}\",\"len\":{},\"attributes\":[",
+            json_string(&group.name),
+            kind_name(group.kind),
+            group.len
+        )
+        .unwrap();
+        for (j, attr) in group.attributes.iter().enumerate() {
+            if j > 0 {
+                s.push(',');
+            }
+            let distinct = attr
+                .distinct
+                .map(|n| n.to_string())
+                .unwrap_or_else(|| "null".into());
+            write!(
+                s,
+                "{{\"name\":{},\"count\":{},\"distinct\":{}",
+                json_string(&attr.name),
+                attr.count,
+                distinct
+            )
+            .unwrap();
+            match attr.numeric {
+                Some(n) => write!(
+                    s,
+                    ",\"min\":{},\"max\":{},\"mean\":{}}}",
+                    json_number(n.min),
+                    json_number(n.max),
+                    json_number(n.mean)
+                )
+                .unwrap(),
+                None => s.push_str(",\"min\":null,\"max\":null,\"mean\":null}"),
+            }
+        }
+        s.push_str("],\"references\":[");
+        for (j, (tag, count)) in group.references.iter().enumerate() {
+            if j > 0 {
+                s.push(',');
+            }
+            write!(s, "{{\"ref\":{},\"count\":{}}}", json_string(tag), count).unwrap();
+        }
+        s.push_str("]}");
+    }
+    s.push_str("]}");
+    writeln!(w, "{}", s)
+}
+
+fn json_string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            // Writing into a string never fails.
+            c if (c as u32) < 0x20 => write!(s, "\\u{:04x}", c as u32).unwrap(),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+/// JSON has no infinite numbers, they become `null`.
+fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::face_vertex::Mesh;
+    use de::Deserializer;
+    use format::netgen::NetgenDeserializer;
+
+    #[test]
+    fn summarize_netgen() {
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        let mut mesh = Mesh::default();
+        NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+        let summary = summarize(&mesh);
+        let points = &summary.groups[0];
+        assert_eq!(points.len, 5);
+        assert_eq!(points.attributes[0].name, "x");
+        assert_eq!(points.attributes[0].distinct, Some(2));
+        assert_eq!(
+            points.attributes[0].numeric,
+            Some(NumericSummary {
+                min: 0.,
+                max: 1.,
+                mean: 0.4
+            })
+        );
+        let fixed = &summary.groups[3];
+        assert_eq!(fixed.name, "fixed");
+        assert_eq!(fixed.references, vec![("1".to_string(), 2)]);
+        let cell = &fixed.attributes[0];
+        assert_eq!((cell.name.as_str(), cell.numeric), ("cell", None));
+
+        let mut json = Vec::new();
+        write_json(&summary, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(
+            "{\"dimension\":3,\"groups\":[{\"name\":\"points\",\"kind\":\"node\",\"len\":5,\
+             \"attributes\":[{\"name\":\"x\",\"count\":5,\"distinct\":2,\"min\":0,\"max\":1,\
+             \"mean\":0.4},"
+        ));
+        assert!(json.contains("\"references\":[{\"ref\":\"1\",\"count\":2}]"));
+    }
+
+    #[test]
+    fn attribute_values() {
+        let mut stats = AttributeStats::new();
+        stats.add(&AttributeValue::List(vec![1i32.into(), 2.5.into()]));
+        stats.add(&3u8.into());
+        stats.add(&"3.0".into());
+        let summary = stats.finish("x".into());
+        assert_eq!(summary.distinct, Some(2));
+        assert_eq!(
+            summary.numeric,
+            Some(NumericSummary {
+                min: 1.,
+                max: 3.,
+                mean: 2.375
+            })
+        );
+
+        let mut stats = AttributeStats::new();
+        stats.add(&1.into());
+        stats.add(&"a".into());
+        let summary = stats.finish("name".into());
+        assert_eq!((summary.distinct, summary.numeric), (Some(2), None));
+    }
+
+    #[test]
+    fn escape_json() {
+        assert_eq!(json_string("a \"b\"\n"), "\"a \\\"b\\\"\\n\"");
+        assert_eq!(json_number(f64::NAN), "null");
+    }
+}
@@ -0,0 +1,204 @@
+//! Checks of the structure of meshes.
+//!
+//! [validate] looks for problems which break the conventions of
+//! [keys](::data::attribute::keys), like elements referring to undefined nodes, and for
+//! inverted cells. Problems don't stop the validation, all of them are reported.
+
+use data::{
+    attribute::{keys, AttributeContainer, AttributeName},
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup,
+};
+use error::Error;
+use std::fmt;
+use util::geometry;
+
+/// A problem found by [validate].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// The name of the group with the problem.
+    pub group: String,
+    /// The index of the entity inside of the group, `None` for problems of the whole group.
+    pub entity: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.entity {
+            Some(i) => write!(f, "{}[{}]: {}", self.group, i, self.message),
+            None => write!(f, "{}: {}", self.group, self.message),
+        }
+    }
+}
+
+/// Check the structure of a mesh and return all problems found.
+pub fn validate<M: GetMesh>(mesh: &M) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let dimension = mesh.metadata().dimension() as usize;
+    if dimension > keys::POSITION.len() {
+        problems.push(Problem {
+            group: String::new(),
+            entity: None,
+            message: format!("Unsupported dimension {}.", dimension),
+        });
+        return problems;
+    }
+
+    // The positions of all nodes, `None` for nodes without valid coordinates.
+    let mut points = Vec::new();
+    for group in mesh.groups() {
+        let metadata = group.metadata();
+        if metadata.kind() != EntityKind::Node {
+            continue;
+        }
+        let name = metadata.name().get_original().0.to_string();
+        for (i, node) in group.enumerate() {
+            match node.position(dimension) {
+                Ok(p) => points.push(Some([p.x, p.y, p.z])),
+                Err(e) => {
+                    problems.push(Problem {
+                        group: name.clone(),
+                        entity: Some(i),
+                        message: message(e),
+                    });
+                    points.push(None);
+                }
+            }
+        }
+    }
+    let valid_points: Vec<[f64; 3]> = points.iter().map(|p| p.unwrap_or([0.; 3])).collect();
+
+    for group in mesh.groups() {
+        let metadata = group.metadata();
+        if metadata.kind() != EntityKind::Element {
+            continue;
+        }
+        let name = metadata.name().get_original().0.to_string();
+        for (i, element) in group.enumerate() {
+            let mut problem = |message: String| {
+                problems.push(Problem {
+                    group: name.clone(),
+                    entity: Some(i),
+                    message,
+                })
+            };
+            let nodes = match element.node_indices() {
+                Ok(nodes) => nodes,
+                Err(e) => {
+                    problem(message(e));
+                    continue;
+                }
+            };
+            if let Some(&node) = nodes.iter().find(|&&node| node >= points.len()) {
+                problem(format!(
+                    "Undefined node {}, the mesh has {} nodes.",
+                    node,
+                    points.len()
+                ));
+                continue;
+            }
+            let typed = element
+                .attributes()
+                .get(&AttributeName::key(keys::CELL))
+                .is_some();
+            let cell = match geometry::cell_type(&element, &nodes, &valid_points, dimension) {
+                Ok(cell) => cell,
+                // Elements without type may be polygons or polyhedra.
+                Err(_) if !typed => continue,
+                Err(e) => {
+                    problem(message(e));
+                    continue;
+                }
+            };
+            if dimension == 3 && cell.dimension() == 3 && nodes.iter().all(|&n| points[n].is_some())
+            {
+                let volume = corner_volume(cell, &nodes, &valid_points);
+                if volume < 0. {
+                    problem(format!("Inverted {} with volume {}.", cell.name(), volume));
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+/// The message of an error, without the prefix of its kind.
+fn message(e: Error) -> String {
+    match e {
+        Error::BrokenInvariant(message) | Error::Unsupported(message) => message,
+        e => e.to_string(),
+    }
+}
+
+/// The volume of the tetrahedron spanned by three corners of the base of a cell and the first
+/// corner above it, which is positive for cells in canonical order.
+fn corner_volume(cell: CellType, nodes: &[usize], points: &[[f64; 3]]) -> f64 {
+    let above = match cell.linear() {
+        CellType::Tetrahedron | CellType::Prism => 3,
+        _ => 4,
+    };
+    let p = [0, 1, 2, above]
+        .iter()
+        .map(|&k| points[nodes[k]])
+        .collect::<Vec<_>>();
+    let d = |i: usize| [p[i][0] - p[0][0], p[i][1] - p[0][1], p[i][2] - p[0][2]];
+    let (u, v, w) = (d(1), d(2), d(3));
+    (u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0]))
+        / 6.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{
+        attribute::{AttributeContainerMut, AttributeMap},
+        face_vertex::Mesh,
+        SetMesh, SetMeshGroup,
+    };
+    use format::naming::{Format, Name};
+
+    #[test]
+    fn report_problems() {
+        let mut mesh = Mesh::default();
+        mesh.set_dimension(3);
+        let name = Name::parse("nodes".into(), Format::Netgen, EntityKind::Node).unwrap();
+        let mut group = mesh.add_group(name, EntityKind::Node).unwrap();
+        for p in &[[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]] {
+            group
+                .add_entity(geometry::entity_with_position(
+                    EntityKind::Node,
+                    p,
+                    AttributeMap::new(),
+                ))
+                .unwrap();
+        }
+        group.end().unwrap();
+
+        let name = Name::parse("cells".into(), Format::Netgen, EntityKind::Element).unwrap();
+        let mut group = mesh.add_group(name, EntityKind::Element).unwrap();
+        let mut unknown = AttributeMap::new();
+        unknown.set(AttributeName::key(keys::CELL), "hexagon".into());
+        let elements = vec![
+            geometry::element_with_cell(CellType::Tetrahedron, &[0, 1, 2, 3], AttributeMap::new()),
+            geometry::element_with_cell(CellType::Tetrahedron, &[0, 2, 1, 3], AttributeMap::new()),
+            geometry::entity_with_nodes(EntityKind::Element, &[0, 1, 4], AttributeMap::new()),
+            geometry::entity_with_nodes(EntityKind::Element, &[0, 1, 2], unknown),
+        ];
+        for element in elements {
+            group.add_entity(element).unwrap();
+        }
+        group.end().unwrap();
+
+        let problems: Vec<String> = validate(&mesh).iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            problems,
+            vec![
+                "cells[1]: Inverted tetrahedron with volume -0.16666666666666666.",
+                "cells[2]: Undefined node 4, the mesh has 4 nodes.",
+                "cells[3]: Unknown cell type `hexagon`.",
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Borrowed views of typed values, which readers can hand out without copying.
+//!
+//! Binary formats often store one record per entity, so the values of one attribute of all
+//! entities are evenly spaced in memory. A [Strided] view borrows them in place, for example
+//! from a [memory mapped file](::de::MappedFile), see
+//! [GetMeshGroup::positions](::data::GetMeshGroup::positions).
+//!
+//! Binary files are viewed in place by [PlyView](::format::ply::PlyView),
+//! [MeditView](::format::medit::MeditView) and [StlView](::format::stl::StlView), which
+//! borrow what their layout allows. A [ColumnarMesh](::data::columnar::ColumnarMesh) lends
+//! out the coordinates and node indices it stores as typed arrays.
+
+use std::fmt;
+
+/// `width` consecutive values for each of `len` entities, which start `stride` values apart.
+///
+/// The values of all entities are contiguous if `width == stride`, see [Strided::as_slice].
+#[derive(Clone, Copy, PartialEq)]
+pub struct Strided<'a, T: 'a> {
+    values: &'a [T],
+    len: usize,
+    width: usize,
+    stride: usize,
+}
+
+impl<'a, T> Strided<'a, T> {
+    /// A view of `len` entities, the first one starting at `values[0]`.
+    ///
+    /// Returns `None` if `width` exceeds `stride` or `values` is too short.
+    pub fn new(values: &'a [T], len: usize, width: usize, stride: usize) -> Option<Self> {
+        let needed = match len {
+            0 => 0,
+            len => stride.checked_mul(len - 1)?.checked_add(width)?,
+        };
+        if width > stride || values.len() < needed {
+            return None;
+        }
+        Some(Strided {
+            values: &values[..needed],
+            len,
+            width,
+            stride,
+        })
+    }
+
+    /// A view of contiguous values, `width` per entity.
+    ///
+    /// # Panics
+    ///
+    /// If `width` is zero.
+    pub fn contiguous(values: &'a [T], width: usize) -> Self {
+        assert!(width > 0, "entities without values");
+        let len = values.len() / width;
+        Strided {
+            values: &values[..len * width],
+            len,
+            width,
+            stride: width,
+        }
+    }
+
+    /// The number of entities.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of values of every entity.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The values of entity `i`.
+    ///
+    /// # Panics
+    ///
+    /// If `i` is out of bounds.
+    pub fn get(&self, i: usize) -> &'a [T] {
+        assert!(i < self.len, "entity {} of {}", i, self.len);
+        let start = i * self.stride;
+        &self.values[start..start + self.width]
+    }
+
+    /// The values of all entities in order, if they are contiguous.
+    pub fn as_slice(&self) -> Option<&'a [T]> {
+        if self.width == self.stride {
+            Some(self.values)
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a [T]> + 'a {
+        let (values, width, stride) = (self.values, self.width, self.stride);
+        (0..self.len).map(move |i| &values[i * stride..i * stride + width])
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Strided<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strided() {
+        // Three entities with two values each, followed by a value of another attribute.
+        let values = [1, 2, 0, 3, 4, 0, 5, 6];
+        let view = Strided::new(&values, 3, 2, 3).unwrap();
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.get(2), &[5, 6]);
+        assert_eq!(
+            view.iter().collect::<Vec<_>>(),
+            vec![&[1, 2], &[3, 4], &[5, 6]]
+        );
+        assert_eq!(view.as_slice(), None);
+        assert_eq!(format!("{:?}", view), "[[1, 2], [3, 4], [5, 6]]");
+
+        assert!(Strided::new(&values, 4, 2, 3).is_none());
+        assert!(Strided::new(&values, 1, 3, 2).is_none());
+        assert!(Strided::new(&values, 0, 2, 3).unwrap().is_empty());
+
+        let view = Strided::contiguous(&values[..6], 3);
+        assert_eq!(view.as_slice(), Some(&values[..6]));
+        assert_eq!(view.get(1), &[3, 4, 0]);
+    }
+}
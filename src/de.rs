@@ -0,0 +1,392 @@
+//! Deserialization of meshes from a format into any [SetMesh] implementation.
+//!
+//! Readers produce a mesh as sequence of [events](Event), which can be filtered or transformed
+//! before they reach a [SetMesh] implementation through [read_events].
+
+use data::{
+    attribute::{AttributeName, AttributeValue},
+    Entity, EntityBox, EntityKind, SetMesh, SetMeshGroup, MAX_RESERVE,
+};
+use error::Error;
+use format::naming::Name;
+use memmap2::Mmap;
+use std::{fs::File, io::Read, iter, ops::Deref, path::Path};
+
+/// Number of bytes at the start of a file which are used to recognize its format.
+pub const SNIFF_LEN: usize = 4096;
+
+/// How sure a format is that some data belongs to it, see [Deserializer::sniff].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Confidence {
+    /// The data doesn't belong to the format, or the format can't tell.
+    No,
+    /// The data is compatible with the format, but also with others.
+    Low,
+    /// The data has the structure typical for the format.
+    Medium,
+    /// The data starts with a signature of the format.
+    High,
+}
+
+/// A step of reading a mesh, see [Deserializer::events].
+///
+/// Groups are opened by [Event::GroupStart] and closed by [Event::GroupEnd], metadata and
+/// entities always belong to the open group.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// The dimension of the mesh.
+    Dimension(u8),
+    /// The start of a group, with its number of entities if it is known in advance.
+    GroupStart {
+        name: Name,
+        kind: EntityKind,
+        len: Option<usize>,
+    },
+    /// An attribute of the open group.
+    Metadata(AttributeName, AttributeValue),
+    /// An entity of the open group.
+    Entity(EntityBox),
+    /// The end of the open group.
+    GroupEnd,
+}
+
+impl Event {
+    fn describe(&self) -> &'static str {
+        match *self {
+            Event::Dimension(_) => "Dimension",
+            Event::GroupStart { .. } => "GroupStart",
+            Event::Metadata(..) => "Metadata",
+            Event::Entity(_) => "Entity",
+            Event::GroupEnd => "GroupEnd",
+        }
+    }
+}
+
+/// The events of a mesh, which end with the first error.
+pub type Events<'s> = Box<dyn Iterator<Item = Result<Event, Error>> + 's>;
+
+/// The events of a parsed mesh, or the error of parsing it.
+///
+/// For readers which parse the whole source before they produce events, but create entities
+/// one at a time from what they parsed.
+pub(crate) fn parsed_events<'s, M, I, F>(parsed: Result<M, Error>, events: F) -> Events<'s>
+where
+    F: FnOnce(M) -> I,
+    I: Iterator<Item = Result<Event, Error>> + 's,
+{
+    match parsed {
+        Ok(mesh) => {
+            // Note: Creating an entity may fail, and events end with the first error.
+            let mut failed = false;
+            Box::new(events(mesh).take_while(move |event| {
+                let before = failed;
+                failed = event.is_err();
+                !before
+            }))
+        }
+        Err(e) => Box::new(iter::once(Err(e))),
+    }
+}
+
+/// The events of a group with the attributes `metadata`, whose entities are created one at a
+/// time.
+pub(crate) fn group_events<'s, I>(
+    name: Name,
+    kind: EntityKind,
+    metadata: Vec<(AttributeName, AttributeValue)>,
+    entities: I,
+) -> impl Iterator<Item = Result<Event, Error>> + 's
+where
+    I: ExactSizeIterator<Item = Result<EntityBox, Error>> + 's,
+{
+    let start = Event::GroupStart {
+        name,
+        kind,
+        len: Some(entities.len()),
+    };
+    iter::once(Ok(start))
+        .chain(
+            metadata
+                .into_iter()
+                .map(|(name, value)| Ok(Event::Metadata(name, value))),
+        )
+        .chain(entities.map(|entity| entity.map(Event::Entity)))
+        .chain(iter::once(Ok(Event::GroupEnd)))
+}
+
+/// Pass events to a [SetMesh] implementation, until the first error.
+pub fn read_events<I, T>(events: I, target: &mut T) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Result<Event, Error>>,
+    T: SetMesh,
+{
+    let unexpected = |event: Event, place: &str| {
+        Err(Error::BrokenInvariant(format!(
+            "Unexpected event {} {}.",
+            event.describe(),
+            place
+        )))
+    };
+
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+        match event? {
+            Event::Dimension(dim) => target.set_dimension(dim),
+            Event::GroupStart { name, kind, len } => {
+                let mut group = target.add_group(name, kind)?;
+                if let Some(len) = len {
+                    // Note: The length is usually read from a header, see `MAX_RESERVE`.
+                    group.reserve(len.min(MAX_RESERVE))?;
+                }
+                loop {
+                    match events.next() {
+                        Some(event) => match event? {
+                            Event::Metadata(name, value) => group.set_attribute(name, value)?,
+                            Event::Entity(entity) => group.add_entity(entity)?,
+                            Event::GroupEnd => break,
+                            event => return unexpected(event, "in group"),
+                        },
+                        None => return Err(Error::BrokenInvariant("Events end in group.".into())),
+                    }
+                }
+                group.end()?;
+            }
+            event => return unexpected(event, "outside of group"),
+        }
+    }
+    Ok(())
+}
+
+/// A [SetMesh] implementation recording events, for readers which push into a mesh.
+#[derive(Default)]
+pub struct EventRecorder {
+    events: Vec<Event>,
+}
+
+impl EventRecorder {
+    /// Run `read` on a recorder, and return the recorded events followed by its error.
+    ///
+    /// The events are only available after `read` finished.
+    pub fn record<'s, F>(read: F) -> Events<'s>
+    where
+        F: FnOnce(&mut EventRecorder) -> Result<(), Error>,
+    {
+        let mut recorder = EventRecorder::default();
+        let result = read(&mut recorder);
+        Box::new(
+            recorder
+                .events
+                .into_iter()
+                .map(Ok)
+                .chain(result.err().map(Err)),
+        )
+    }
+}
+
+impl SetMesh for EventRecorder {
+    type GroupSetter<'m> = RecordedGroup<'m>;
+
+    fn set_dimension(&mut self, dim: u8) {
+        self.events.push(Event::Dimension(dim));
+    }
+
+    fn add_group(&mut self, name: Name, kind: EntityKind) -> Result<RecordedGroup<'_>, Error> {
+        self.events.push(Event::GroupStart {
+            name,
+            kind,
+            len: None,
+        });
+        Ok(RecordedGroup {
+            events: &mut self.events,
+        })
+    }
+}
+
+pub struct RecordedGroup<'m> {
+    events: &'m mut Vec<Event>,
+}
+
+impl<'m> SetMeshGroup for RecordedGroup<'m> {
+    fn reserve(&mut self, num: usize) -> Result<(), Error> {
+        // Note: The group was started by the last event, unless entities were added already.
+        if let Some(Event::GroupStart { ref mut len, .. }) = self.events.last_mut() {
+            *len = Some(num);
+        }
+        Ok(())
+    }
+
+    fn set_attribute(&mut self, name: AttributeName, value: AttributeValue) -> Result<(), Error> {
+        self.events.push(Event::Metadata(name, value));
+        Ok(())
+    }
+
+    fn add_entity<E: Entity>(&mut self, entity: E) -> Result<(), Error> {
+        self.events
+            .push(Event::Entity(EntityBox::from_entity(&entity)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.events.push(Event::GroupEnd);
+        Ok(())
+    }
+}
+
+/// Reads a mesh from a source as [events](Event), or into a [SetMesh] implementation.
+pub trait Deserializer {
+    /// Read the events of a mesh.
+    ///
+    /// The Medit and PLY readers produce events while they read the source. All other readers
+    /// parse the whole source first, since their files need to be seen as a whole (like
+    /// Fluent cells, which are reconstructed from faces given later), but create entities one
+    /// at a time from what they parsed, so no entities are buffered.
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s>;
+
+    /// Read a mesh into `target`, by default through its [events](Deserializer::events).
+    fn deserialize_into<S, T>(source: S, target: &mut T) -> Result<(), Error>
+    where
+        S: Read,
+        T: SetMesh,
+    {
+        read_events(Self::events(source), target)
+    }
+
+    /// Guess whether data is in this format by its first (at most [SNIFF_LEN]) bytes.
+    fn sniff(_head: &[u8]) -> Confidence {
+        Confidence::No
+    }
+}
+
+/// A file mapped into memory, for readers which borrow their values from the file instead of
+/// copying them, like [PlyView](::format::ply::PlyView).
+///
+/// Pages are only read from disk when they are accessed.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedFile, Error> {
+        let file = File::open(path)?;
+        // Safety: Only sound as long as no other process modifies the file while it is mapped,
+        // which is the usual contract of memory mapped files.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{face_vertex::Mesh, GetMesh, GetMeshGroup};
+    use format::{
+        dolfin::DolfinDeserializer, medit::MeditDeserializer, naming::Format, ply::PlyDeserializer,
+        tecplot::TecplotDeserializer,
+    };
+
+    #[test]
+    fn filter_events() {
+        let data = include_bytes!("../tests/files/blender-monkey.mesh");
+        let mut in_elements = false;
+        let nodes_only = MeditDeserializer::events(&data[..]).filter(|event| {
+            match *event {
+                Ok(Event::GroupStart { kind, .. }) => in_elements = kind == EntityKind::Element,
+                Ok(Event::GroupEnd) if in_elements => {
+                    in_elements = false;
+                    return false;
+                }
+                _ => (),
+            }
+            !in_elements
+        });
+        let mut mesh = Mesh::default();
+        read_events(nodes_only, &mut mesh).unwrap();
+        assert_eq!(mesh.metadata().dimension(), 3);
+        let lens: Vec<_> = mesh.groups().map(|g| g.metadata().len()).collect();
+        assert_eq!(lens, vec![507]);
+    }
+
+    #[test]
+    fn events_until_error() {
+        let data = "MeshVersionFormatted 1\nDimension 2\nVertices 2\n0 0 1\n1 x 1\nEnd\n";
+        let events: Vec<_> = MeditDeserializer::events(data.as_bytes()).collect();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(events[2], Ok(Event::Entity(_))));
+        assert!(events[3].is_err());
+    }
+
+    #[test]
+    fn stream_until_error() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\n\
+                    property float y\nelement face 1\nproperty list uchar int vertex_indices\n\
+                    end_header\n0 0\n1 0\n3 0 1 x\n";
+        let events: Vec<_> = PlyDeserializer::events(data.as_bytes()).collect();
+        assert_eq!(events.len(), 7);
+        assert!(matches!(events[0], Ok(Event::Dimension(2))));
+        assert!(matches!(events[2], Ok(Event::Entity(_))));
+        assert!(matches!(
+            events[5],
+            Ok(Event::GroupStart { len: Some(1), .. })
+        ));
+        assert!(events[6].is_err());
+    }
+
+    #[test]
+    fn parsed_until_error() {
+        let data = "<dolfin><mesh celltype=\"triangle\" dim=\"2\"><vertices size=\"3\">\
+                    <vertex index=\"0\" x=\"0\" y=\"0\"/><vertex index=\"2\" x=\"1\" y=\"0\"/>\
+                    </vertices><cells size=\"0\"/></mesh></dolfin>";
+        let events: Vec<_> = DolfinDeserializer::events(data.as_bytes()).collect();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            events[1],
+            Ok(Event::GroupStart { len: Some(3), .. })
+        ));
+        assert!(matches!(events[2], Ok(Event::Entity(_))));
+        assert!(events[3].is_err());
+    }
+
+    #[test]
+    fn recorded_events() {
+        let data = include_bytes!("../tests/files/cube-wedge.dat");
+        let mut direct = Mesh::default();
+        TecplotDeserializer::deserialize_into(&data[..], &mut direct).unwrap();
+        let events = EventRecorder::record(|recorder| {
+            read_events(TecplotDeserializer::events(&data[..]), recorder)
+        });
+        let mut recorded = Mesh::default();
+        read_events(events, &mut recorded).unwrap();
+
+        let lens =
+            |mesh: &Mesh| -> Vec<usize> { mesh.groups().map(|g| g.metadata().len()).collect() };
+        assert_eq!(lens(&direct), lens(&recorded));
+        assert!(read_events(vec![Ok(Event::GroupEnd)], &mut recorded).is_err());
+    }
+
+    #[test]
+    fn bounded_reservation() {
+        let name = Name::parse("points".into(), Format::Netgen, EntityKind::Node).unwrap();
+        let events = vec![
+            Ok(Event::GroupStart {
+                name,
+                kind: EntityKind::Node,
+                len: Some(usize::MAX),
+            }),
+            Ok(Event::GroupEnd),
+        ];
+        let mut recorder = EventRecorder::default();
+        read_events(events, &mut recorder).unwrap();
+        match recorder.events[0] {
+            Event::GroupStart { len, .. } => assert_eq!(len, Some(MAX_RESERVE)),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -14,11 +14,17 @@ pub enum Error {
     #[fail(display = "Syntax error: {}", _0)]
     Syntax(String),
 
+    #[fail(display = "Unsupported: {}", _0)]
+    Unsupported(String),
+
+    #[fail(display = "No equivalent name: {}", _0)]
+    NoEquivalentName(String),
+
     #[fail(display = "Other error (internal): {}", _0)]
-    OtherInternal(Box<::std::error::Error + Send + Sync>),
+    OtherInternal(Box<dyn std::error::Error + Send + Sync>),
 
     #[fail(display = "Other error (external): {}", _0)]
-    OtherExternal(Box<::std::error::Error + Send + Sync>),
+    OtherExternal(Box<dyn std::error::Error + Send + Sync>),
 }
 
 impl From<::std::io::Error> for Error {
@@ -38,3 +44,18 @@ impl From<ParseFloatError> for Error {
         Error::Syntax(format!("Parsing float failed: {}", e))
     }
 }
+
+impl From<::roxmltree::Error> for Error {
+    fn from(e: ::roxmltree::Error) -> Self {
+        Error::Syntax(format!("Parsing XML failed: {}", e))
+    }
+}
+
+impl From<::zip::result::ZipError> for Error {
+    fn from(e: ::zip::result::ZipError) -> Self {
+        match e {
+            ::zip::result::ZipError::Io(e) => Error::Io(e),
+            e => Error::Syntax(format!("Reading zip archive failed: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+//! Implementation of AMF (Additive Manufacturing File Format) support.
+//!
+//! Definition: ISO/ASTM 52915.
+//!
+//! Every `<object>` becomes a node group with its vertices, and every `<volume>` of it an
+//! element group with its triangles. Groups are named after the `name` metadata of objects
+//! and volumes; unnamed objects are called `object-<id>`, unnamed volumes are named after
+//! their object (with `-<n>` appended if the object has more than one volume).
+//!
+//! Colors are stored as attribute `color` with the components separated by spaces, like
+//! `1 0 0` or `1 0 0 0.5`: Colors of vertices and triangles as attributes of nodes and
+//! elements, colors of volumes as group attributes. The material of a volume is stored in
+//! the group attributes `material` (its id), `material-name` and `material-color`.
+//!
+//! Files can optionally be compressed as zip archive, which the reader detects by itself.
+
+use data::{
+    attribute::{
+        AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName, AttributeValue,
+    },
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup, SetMesh, SetMeshGroup,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use roxmltree::{Document, Node};
+use ser::Serializer;
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read, Write},
+    iter,
+};
+use util::{
+    geometry, sniff,
+    xml::{self, attribute, child, children, required_child},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Attribute keys used by this format.
+const COLOR: &str = "color";
+const MATERIAL: &str = "material";
+const MATERIAL_NAME: &str = "material-name";
+const MATERIAL_COLOR: &str = "material-color";
+
+/// Whether files are compressed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    /// Plain XML.
+    None,
+    /// A zip archive containing the XML file.
+    Zip,
+}
+
+/// The value of the metadata of type `name` of an element, if any.
+fn metadata_name<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    children(node, "metadata")
+        .find(|metadata| metadata.attribute("type") == Some("name"))
+        .and_then(|metadata| metadata.text())
+        .map(|name| name.trim())
+}
+
+/// The components of the `<color>` child of an element, if it has one.
+/// The components of a color, which may be formulas of the coordinates instead of numbers.
+fn color(node: Node) -> Result<Option<AttributeValue>, Error> {
+    let color = match child(node, "color") {
+        Some(color) => color,
+        None => return Ok(None),
+    };
+    let mut components = Vec::with_capacity(4);
+    for key in &["r", "g", "b", "a"] {
+        match child(color, key) {
+            Some(c) => components.push(c.text().unwrap_or("").trim()),
+            None if *key == "a" => (),
+            None => return Err(Error::Syntax(format!("Color without component {}.", key))),
+        }
+    }
+    let numbers: Result<Vec<f64>, _> = components.iter().map(|c| c.parse()).collect();
+    Ok(Some(match numbers {
+        Ok(numbers) => numbers.into(),
+        Err(_) => components.join(" ").into(),
+    }))
+}
+
+/// The text of a child element parsed as a number.
+fn parse_child<T: ::std::str::FromStr>(node: Node, name: &str) -> Result<T, Error> {
+    let text = required_child(node, name)?.text().unwrap_or("").trim();
+    text.parse()
+        .map_err(|_| Error::Syntax(format!("Bad value of <{}>: {}", name, text)))
+}
+
+/// An `<object>` with its vertices and their colors.
+struct AmfObject {
+    name: String,
+    vertices: Vec<([f64; 3], Option<AttributeValue>)>,
+    volumes: Vec<AmfVolume>,
+}
+
+/// A `<volume>` with its group attributes and its triangles with their colors.
+struct AmfVolume {
+    name: String,
+    attributes: Vec<(AttributeName, AttributeValue)>,
+    triangles: Vec<([usize; 3], Option<AttributeValue>)>,
+}
+
+/// The attributes of an entity with the given color.
+fn color_attr(color: Option<AttributeValue>) -> AttributeMap {
+    let mut attr = AttributeMap::new();
+    if let Some(color) = color {
+        attr.set(COLOR.into(), color);
+    }
+    attr
+}
+
+/// Parse all objects, whose node indices are global.
+fn parse<S: Read>(mut source: S) -> Result<Vec<AmfObject>, Error> {
+    // Read the file into memory, extracting it from a zip archive if needed.
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
+    let data = if data.starts_with(b"PK\x03\x04") {
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        let mut file = archive.by_index(0)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        content
+    } else {
+        String::from_utf8(data).map_err(|_| Error::Syntax("AMF file is not valid UTF-8.".into()))?
+    };
+    let document = Document::parse(&data)?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "amf" {
+        return Err(Error::Syntax("Root element is not <amf>.".into()));
+    }
+
+    let mut materials = BTreeMap::new();
+    for material in children(root, "material") {
+        materials.insert(attribute(material, "id")?, material);
+    }
+
+    let mut objects = Vec::new();
+    let mut node_offset = 0;
+    for object in children(root, "object") {
+        let object_name = match metadata_name(object) {
+            Some(name) => name.to_string(),
+            None => format!("object-{}", attribute(object, "id")?),
+        };
+        let mesh = required_child(object, "mesh")?;
+
+        let mut vertices = Vec::new();
+        for vertex in children(required_child(mesh, "vertices")?, "vertex") {
+            let coordinates = required_child(vertex, "coordinates")?;
+            let position = [
+                parse_child(coordinates, "x")?,
+                parse_child(coordinates, "y")?,
+                parse_child(coordinates, "z")?,
+            ];
+            vertices.push((position, color(vertex)?));
+        }
+
+        let volume_nodes: Vec<Node> = children(mesh, "volume").collect();
+        let mut volumes = Vec::with_capacity(volume_nodes.len());
+        for (k, volume) in volume_nodes.iter().enumerate() {
+            let name = match metadata_name(*volume) {
+                Some(name) => name.to_string(),
+                None if volume_nodes.len() == 1 => object_name.clone(),
+                None => format!("{}-{}", object_name, k + 1),
+            };
+
+            let mut attributes = Vec::new();
+            if let Some(color) = color(*volume)? {
+                attributes.push((COLOR.into(), color));
+            }
+            if let Some(id) = volume.attribute("materialid") {
+                attributes.push((MATERIAL.into(), id.into()));
+                if let Some(&material) = materials.get(id) {
+                    if let Some(name) = metadata_name(material) {
+                        attributes.push((MATERIAL_NAME.into(), name.into()));
+                    }
+                    if let Some(color) = color(material)? {
+                        attributes.push((MATERIAL_COLOR.into(), color));
+                    }
+                }
+            }
+
+            let mut triangles = Vec::new();
+            for triangle in children(*volume, "triangle") {
+                let mut nodes = [0; 3];
+                for (node, key) in nodes.iter_mut().zip(&["v1", "v2", "v3"]) {
+                    let index: usize = parse_child(triangle, key)?;
+                    if index >= vertices.len() {
+                        return Err(Error::Syntax(format!("Undefined vertex {}.", index)));
+                    }
+                    *node = node_offset + index;
+                }
+                triangles.push((nodes, color(triangle)?));
+            }
+            volumes.push(AmfVolume {
+                name,
+                attributes,
+                triangles,
+            });
+        }
+
+        node_offset += vertices.len();
+        objects.push(AmfObject {
+            name: object_name,
+            vertices,
+            volumes,
+        });
+    }
+    Ok(objects)
+}
+
+/// The events of an object: its vertices followed by its volumes.
+fn object_events(object: AmfObject) -> impl Iterator<Item = Result<Event, Error>> {
+    let name = Name::parse(object.name, Format::Amf, EntityKind::Node).unwrap();
+    let vertices = object.vertices.into_iter().map(|(position, color)| {
+        let attr = color_attr(color);
+        Ok(geometry::entity_with_position(
+            EntityKind::Node,
+            &position,
+            attr,
+        ))
+    });
+    let volumes = object.volumes.into_iter().flat_map(|volume| {
+        let name = Name::parse(volume.name, Format::Amf, EntityKind::Element).unwrap();
+        let triangles = volume.triangles.into_iter().map(|(nodes, color)| {
+            let attr = color_attr(color);
+            Ok(geometry::element_with_cell(
+                CellType::Triangle,
+                &nodes,
+                attr,
+            ))
+        });
+        group_events(name, EntityKind::Element, volume.attributes, triangles)
+    });
+    group_events(name, EntityKind::Node, Vec::new(), vertices).chain(volumes)
+}
+
+pub struct AmfDeserializer {}
+
+impl Deserializer for AmfDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(parse(source), |objects| {
+            iter::once(Ok(Event::Dimension(3))).chain(objects.into_iter().flat_map(object_events))
+        })
+    }
+
+    /// Recognizes the root element `<amf>`. Zip archives could be AMF files, too.
+    fn sniff(head: &[u8]) -> Confidence {
+        if head.starts_with(b"PK\x03\x04") {
+            return Confidence::Low;
+        }
+        match sniff::text(head)
+            .as_ref()
+            .and_then(|text| sniff::xml_root(text))
+        {
+            Some("amf") => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// Write a `<color>` element for the components in `value`.
+fn write_color<W: Write>(mut target: W, value: &AttributeValue, indent: &str) -> Result<(), Error> {
+    let value = value.to_string();
+    let components: Vec<&str> = value.split_whitespace().collect();
+    if components.len() != 3 && components.len() != 4 {
+        return Err(Error::BrokenInvariant(format!("Invalid color: {}", value)));
+    }
+    write!(target, "{}<color>", indent)?;
+    for (key, c) in ["r", "g", "b", "a"].iter().zip(components) {
+        write!(target, "<{}>{}</{}>", key, xml::escape(c), key)?;
+    }
+    writeln!(target, "</color>")?;
+    Ok(())
+}
+
+/// An object to be written, with the range of its nodes.
+struct Object {
+    name: String,
+    start: usize,
+    end: usize,
+    volumes: Vec<Volume>,
+}
+
+/// A volume to be written.
+struct Volume {
+    name: String,
+    attributes: AttributeMap,
+    triangles: Vec<([usize; 3], AttributeMap)>,
+}
+
+pub struct AmfSerializer {
+    compression: Compression,
+}
+
+impl AmfSerializer {
+    pub fn new(compression: Compression) -> Self {
+        AmfSerializer { compression }
+    }
+
+    fn write_xml<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "AMF does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        // Node groups become objects, with the node range and colors of their vertices.
+        let mut objects: Vec<Object> = Vec::new();
+        let mut colors: Vec<Option<AttributeValue>> = Vec::with_capacity(points.len());
+        let mut volumes = Vec::new();
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            match metadata.kind() {
+                EntityKind::Node => {
+                    let start = colors.len();
+                    for node in group {
+                        colors.push(node.attributes().get(&COLOR.into()).cloned());
+                    }
+                    let name = metadata.name().get_original().0.to_string();
+                    objects.push(Object {
+                        name,
+                        start,
+                        end: colors.len(),
+                        volumes: Vec::new(),
+                    });
+                }
+                EntityKind::Element => {
+                    let mut triangles = Vec::with_capacity(metadata.len());
+                    for element in group {
+                        let n = element.node_indices()?;
+                        if let Some(&i) = n.iter().find(|&&i| i >= points.len()) {
+                            return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                        }
+                        let attr = AttributeMap::from_container(element.attributes());
+                        match n.len() {
+                            3 => triangles.push(([n[0], n[1], n[2]], attr)),
+                            4 if dimension == 2 || geometry::is_flat(&n, &points) => {
+                                triangles.push(([n[0], n[1], n[2]], attr.clone()));
+                                triangles.push(([n[0], n[2], n[3]], attr));
+                            }
+                            _ => {
+                                return Err(Error::Unsupported(
+                                    "AMF only supports triangles and quadrilaterals.".into(),
+                                ))
+                            }
+                        }
+                    }
+                    volumes.push(Volume {
+                        name: metadata.name().get_original().0.to_string(),
+                        attributes: metadata.attributes().clone(),
+                        triangles,
+                    });
+                }
+                EntityKind::Vector | EntityKind::Other => (),
+            }
+        }
+        for volume in volumes {
+            let first = match volume.triangles.first() {
+                Some(triangle) => triangle.0[0],
+                None => continue,
+            };
+            let object = objects
+                .iter_mut()
+                .find(|o| o.start <= first && first < o.end)
+                .unwrap();
+            if volume
+                .triangles
+                .iter()
+                .flat_map(|t| t.0.iter())
+                .any(|&i| i < object.start || i >= object.end)
+            {
+                return Err(Error::Unsupported(format!(
+                    "Elements of group {} use nodes of different node groups.",
+                    volume.name
+                )));
+            }
+            object.volumes.push(volume);
+        }
+
+        // Materials referenced by volumes.
+        let mut materials: BTreeMap<String, (Option<String>, Option<AttributeValue>)> =
+            BTreeMap::new();
+        for volume in objects.iter().flat_map(|o| o.volumes.iter()) {
+            let get = |key: &str| volume.attributes.get(&AttributeName::from(key)).cloned();
+            if let Some(id) = get(MATERIAL) {
+                materials.entry(id.to_string()).or_insert_with(|| {
+                    (
+                        get(MATERIAL_NAME).map(|n| n.to_string()),
+                        get(MATERIAL_COLOR),
+                    )
+                });
+            }
+        }
+
+        writeln!(target, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(target, "<amf unit=\"millimeter\" version=\"1.1\">")?;
+        writeln!(target, "  <metadata type=\"producer\">multimesh</metadata>")?;
+
+        for (id, (name, color)) in &materials {
+            writeln!(target, "  <material id=\"{}\">", xml::escape(id))?;
+            if let Some(name) = name {
+                writeln!(
+                    target,
+                    "    <metadata type=\"name\">{}</metadata>",
+                    xml::escape(name)
+                )?;
+            }
+            if let Some(color) = color {
+                write_color(&mut target, color, "    ")?;
+            }
+            writeln!(target, "  </material>")?;
+        }
+
+        for (id, object) in objects.iter().enumerate() {
+            let Object {
+                ref name,
+                start,
+                end,
+                ref volumes,
+            } = *object;
+            if volumes.is_empty() {
+                continue;
+            }
+            writeln!(target, "  <object id=\"{}\">", id)?;
+            writeln!(
+                target,
+                "    <metadata type=\"name\">{}</metadata>",
+                xml::escape(name)
+            )?;
+            writeln!(target, "    <mesh>")?;
+            writeln!(target, "      <vertices>")?;
+            for i in start..end {
+                let p = &points[i];
+                writeln!(target, "        <vertex>")?;
+                writeln!(
+                    target,
+                    "          <coordinates><x>{}</x><y>{}</y><z>{}</z></coordinates>",
+                    p[0], p[1], p[2]
+                )?;
+                if let Some(ref color) = colors[i] {
+                    write_color(&mut target, color, "          ")?;
+                }
+                writeln!(target, "        </vertex>")?;
+            }
+            writeln!(target, "      </vertices>")?;
+
+            for volume in volumes {
+                match volume.attributes.get(&MATERIAL.into()) {
+                    Some(id) => writeln!(
+                        target,
+                        "      <volume materialid=\"{}\">",
+                        xml::escape(&id.to_string())
+                    )?,
+                    None => writeln!(target, "      <volume>")?,
+                }
+                writeln!(
+                    target,
+                    "        <metadata type=\"name\">{}</metadata>",
+                    xml::escape(&volume.name)
+                )?;
+                if let Some(color) = volume.attributes.get(&COLOR.into()) {
+                    write_color(&mut target, color, "        ")?;
+                }
+                for (nodes, attr) in &volume.triangles {
+                    write!(
+                        target,
+                        "        <triangle><v1>{}</v1><v2>{}</v2><v3>{}</v3>",
+                        nodes[0] - start,
+                        nodes[1] - start,
+                        nodes[2] - start
+                    )?;
+                    match attr.get(&COLOR.into()) {
+                        Some(color) => {
+                            writeln!(target)?;
+                            write_color(&mut target, color, "          ")?;
+                            writeln!(target, "        </triangle>")?;
+                        }
+                        None => writeln!(target, "</triangle>")?,
+                    }
+                }
+                writeln!(target, "      </volume>")?;
+            }
+
+            writeln!(target, "    </mesh>")?;
+            writeln!(target, "  </object>")?;
+        }
+
+        writeln!(target, "</amf>")?;
+
+        Ok(())
+    }
+}
+
+impl Serializer for AmfSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        match self.compression {
+            Compression::None => self.write_xml(mesh, target),
+            Compression::Zip => {
+                // Zip archives need a seekable target, so build the archive in memory.
+                let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+                archive.start_file("mesh.amf", options)?;
+                self.write_xml(mesh, &mut archive)?;
+                let data = archive.finish()?.into_inner();
+                target.write_all(&data)?;
+                Ok(())
+            }
+        }
+    }
+}
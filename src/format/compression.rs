@@ -0,0 +1,196 @@
+//! Transparent compression of files in any format with gzip, zstd or xz.
+//!
+//! Compressed streams are recognized by their magic bytes when reading, see [decompress], and
+//! by the extension of their path when writing, like `cube.mesh.gz`, see
+//! [Compression::from_path]. The [registry](::format::registry) applies both, so formats
+//! are recognized by the decompressed data.
+
+use error::Error;
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use std::{
+    io::{self, Cursor, Read, Write},
+    path::Path,
+};
+use xz2::{read::XzDecoder, write::XzEncoder};
+
+/// A compression of whole files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+/// The length of the longest magic bytes, those of xz.
+const MAGIC_LEN: usize = 6;
+
+impl Compression {
+    pub const ALL: &'static [Compression] =
+        &[Compression::Gzip, Compression::Zstd, Compression::Xz];
+
+    /// The bytes which start every stream of this compression.
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Compression::Gzip => &[0x1f, 0x8b],
+            Compression::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            Compression::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+        }
+    }
+
+    /// The usual file extension, without leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gz",
+            Compression::Zstd => "zst",
+            Compression::Xz => "xz",
+        }
+    }
+
+    /// The compression of a file extension without leading dot, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Compression> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// The compression of a path by its last extension, like `cube.mesh.gz`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Compression> {
+        Compression::from_extension(path.as_ref().extension()?.to_str()?)
+    }
+
+    /// The compression of data starting with `head`, recognized by its magic bytes.
+    pub fn from_magic(head: &[u8]) -> Option<Compression> {
+        Compression::ALL
+            .iter()
+            .cloned()
+            .find(|compression| head.starts_with(compression.magic()))
+    }
+
+    /// Decompress `source`, which is compressed with this compression.
+    pub fn decoder<'a, R: Read + 'a>(self, source: R) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(source)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(source)?),
+            Compression::Xz => Box::new(XzDecoder::new_multi_decoder(source)),
+        })
+    }
+}
+
+/// Decompress `source` if it starts with the magic bytes of a [Compression].
+///
+/// Returns the decompressed data and the compression which was found, if any.
+pub fn decompress<'a, R: Read + 'a>(
+    mut source: R,
+) -> Result<(Box<dyn Read + 'a>, Option<Compression>), Error> {
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    source
+        .by_ref()
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut head)?;
+    let compression = Compression::from_magic(&head);
+    let source = Cursor::new(head).chain(source);
+    match compression {
+        Some(compression) => Ok((compression.decoder(source)?, Some(compression))),
+        None => Ok((Box::new(source), None)),
+    }
+}
+
+/// Writes into a target, compressed or not.
+///
+/// Compressed streams are only complete after [CompressedWriter::finish].
+pub enum CompressedWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+    Xz(XzEncoder<W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// A writer with the default level of `compression`, or without compression.
+    pub fn new(target: W, compression: Option<Compression>) -> Result<Self, Error> {
+        Ok(match compression {
+            None => CompressedWriter::Plain(target),
+            Some(Compression::Gzip) => {
+                CompressedWriter::Gzip(GzEncoder::new(target, flate2::Compression::default()))
+            }
+            Some(Compression::Zstd) => CompressedWriter::Zstd(zstd::Encoder::new(target, 0)?),
+            Some(Compression::Xz) => CompressedWriter::Xz(XzEncoder::new(target, 6)),
+        })
+    }
+
+    /// Complete the compressed stream and return the target.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(match self {
+            CompressedWriter::Plain(target) => target,
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+            CompressedWriter::Xz(encoder) => encoder.finish()?,
+        })
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            CompressedWriter::Plain(ref mut w) => w.write(buf),
+            CompressedWriter::Gzip(ref mut w) => w.write(buf),
+            CompressedWriter::Zstd(ref mut w) => w.write(buf),
+            CompressedWriter::Xz(ref mut w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            CompressedWriter::Plain(ref mut w) => w.flush(),
+            CompressedWriter::Gzip(ref mut w) => w.flush(),
+            CompressedWriter::Zstd(ref mut w) => w.flush(),
+            CompressedWriter::Xz(ref mut w) => w.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        for &compression in Compression::ALL {
+            let mut writer = CompressedWriter::new(Vec::new(), Some(compression)).unwrap();
+            writer.write_all(data).unwrap();
+            let compressed = writer.finish().unwrap();
+            assert_eq!(Compression::from_magic(&compressed), Some(compression));
+            assert!(compressed.len() < data.len());
+
+            let (mut source, found) = decompress(&compressed[..]).unwrap();
+            let mut decompressed = Vec::new();
+            source.read_to_end(&mut decompressed).unwrap();
+            assert_eq!((&decompressed[..], found), (&data[..], Some(compression)));
+        }
+
+        let (mut source, found) = decompress(&data[..]).unwrap();
+        let mut plain = Vec::new();
+        source.read_to_end(&mut plain).unwrap();
+        assert_eq!((&plain[..], found), (&data[..], None));
+        assert_eq!(decompress(&b"x"[..]).unwrap().1, None);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(
+            Compression::from_path("a/cube.mesh.GZ"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_path("cube.msh.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_path("cube.vtk.xz"), Some(Compression::Xz));
+        assert_eq!(Compression::from_path("cube.mesh"), None);
+        assert_eq!(Compression::from_path("gz"), None);
+    }
+}
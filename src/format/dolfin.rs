@@ -0,0 +1,623 @@
+//! Implementation of legacy DOLFIN (FEniCS) XML mesh format support.
+//!
+//! Definition: the `dolfin.xsd` schema shipped with DOLFIN up to version 2019.1.
+//!
+//! A DOLFIN mesh has a single cell type, given by the `celltype` attribute of `<mesh>`.
+//! The reader creates the node group `vertices` and an element group named after the cell
+//! type. Markers from `<mesh_value_collection>`s in `<domains>` become attributes named after
+//! the collection:
+//!
+//! * Markers of cells are attributes of the elements.
+//! * Markers of vertices are attributes of the nodes.
+//! * Markers of other entities of simplex cells (facets and edges) become an element group
+//!   named after the collection, with the marked entities as elements.
+//!
+//! The serializer writes the elements of the highest topological dimension as cells. Numeric
+//! attributes of cells and nodes are written as marker collections, and groups of facets or
+//! edges of the cells are written as marker collections named after the group, with the
+//! attribute named like the group (or the reference tag) as value.
+
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup, SetMesh, SetMeshGroup,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::{
+    naming::{Format, Name},
+    ordering,
+};
+use roxmltree::Document;
+use ser::Serializer;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Read, Write},
+    iter,
+};
+use util::{
+    geometry, sniff,
+    xml::{self, attribute, children, parse_attribute, required_child},
+};
+
+/// The cell types with their canonical equivalents.
+const CELL_TYPES: &[(&str, CellType)] = &[
+    ("interval", CellType::Line),
+    ("triangle", CellType::Triangle),
+    ("quadrilateral", CellType::Quadrilateral),
+    ("tetrahedron", CellType::Tetrahedron),
+    ("hexahedron", CellType::Hexahedron),
+];
+
+/// Local vertices of the edges of a tetrahedron, in UFC order.
+const TETRAHEDRON_EDGES: &[[usize; 2]] = &[[2, 3], [1, 3], [1, 2], [0, 3], [0, 2], [0, 1]];
+
+/// The local vertices of the entities of dimension `dim` of a simplex cell with `n` vertices.
+fn simplex_entities(n: usize, dim: usize) -> Option<Vec<Vec<usize>>> {
+    let tdim = n - 1;
+    if dim == tdim - 1 {
+        // Facet `i` is opposite to vertex `i`.
+        Some(
+            (0..n)
+                .map(|i| (0..n).filter(|&j| j != i).collect())
+                .collect(),
+        )
+    } else if tdim == 3 && dim == 1 {
+        Some(TETRAHEDRON_EDGES.iter().map(|e| e.to_vec()).collect())
+    } else {
+        None
+    }
+}
+
+fn is_simplex(cell_type: &str) -> bool {
+    ["interval", "triangle", "tetrahedron"].contains(&cell_type)
+}
+
+/// The nodes and attributes of an entity.
+type Nodes = (Vec<usize>, AttributeMap);
+
+/// A parsed DOLFIN mesh, whose entities are created when its events are read.
+struct DolfinMesh {
+    dimension: usize,
+    cell_type: String,
+    cell: CellType,
+    /// The position of every vertex, `None` if it is missing.
+    positions: Vec<Option<Vec<f64>>>,
+    vertex_attrs: Vec<AttributeMap>,
+    connectivity: Vec<Vec<usize>>,
+    cell_attrs: Vec<AttributeMap>,
+    /// Groups of marked facets or edges, with their nodes and markers.
+    entity_groups: Vec<(String, Vec<Nodes>)>,
+}
+
+fn parse<S: Read>(mut source: S) -> Result<DolfinMesh, Error> {
+    // Read the file into memory.
+    let mut data = String::new();
+    source.read_to_string(&mut data)?;
+    let document = Document::parse(&data)?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "dolfin" {
+        return Err(Error::Syntax("Root element is not <dolfin>.".into()));
+    }
+    let mesh = required_child(root, "mesh")?;
+    let cell_type = attribute(mesh, "celltype")?;
+    let dimension: usize = parse_attribute(mesh, "dim")?;
+    let &(_, cell) = CELL_TYPES
+        .iter()
+        .find(|&&(name, _)| name == cell_type)
+        .ok_or_else(|| Error::Syntax(format!("Unsupported cell type: {}", cell_type)))?;
+    let (nary, tdim) = (cell.num_nodes(), cell.dimension() as usize);
+    if dimension == 0 || dimension > 3 {
+        return Err(Error::Syntax(format!("Bad dimension: {}", dimension)));
+    }
+
+    // Vertices, placed by their index.
+    let vertices = required_child(mesh, "vertices")?;
+    let num_vertices: usize = parse_attribute(vertices, "size")?;
+    let mut positions: Vec<Option<Vec<f64>>> = vec![None; num_vertices];
+    for vertex in children(vertices, "vertex") {
+        let index: usize = parse_attribute(vertex, "index")?;
+        let mut position = Vec::with_capacity(dimension);
+        for key in &["x", "y", "z"][..dimension] {
+            position.push(parse_attribute(vertex, key)?);
+        }
+        *positions
+            .get_mut(index)
+            .ok_or_else(|| Error::Syntax(format!("Vertex index {} too big.", index)))? =
+            Some(position);
+    }
+
+    // Cells, placed by their index.
+    let cells = required_child(mesh, "cells")?;
+    let num_cells: usize = parse_attribute(cells, "size")?;
+    let mut connectivity: Vec<Option<Vec<usize>>> = vec![None; num_cells];
+    for cell in children(cells, cell_type) {
+        let index: usize = parse_attribute(cell, "index")?;
+        let mut nodes = Vec::with_capacity(nary);
+        for i in 0..nary {
+            let node: usize = parse_attribute(cell, &format!("v{}", i))?;
+            if node >= num_vertices {
+                return Err(Error::Syntax(format!("Undefined vertex {}.", node)));
+            }
+            nodes.push(node);
+        }
+        *connectivity
+            .get_mut(index)
+            .ok_or_else(|| Error::Syntax(format!("Cell index {} too big.", index)))? = Some(nodes);
+    }
+    let connectivity: Vec<Vec<usize>> = connectivity
+        .into_iter()
+        .enumerate()
+        .map(|(i, nodes)| nodes.ok_or_else(|| Error::Syntax(format!("Missing cell {}.", i))))
+        .collect::<Result<_, _>>()?;
+
+    // Markers.
+    let mut vertex_attrs = vec![AttributeMap::new(); num_vertices];
+    let mut cell_attrs = vec![AttributeMap::new(); num_cells];
+    let mut entity_groups = Vec::new();
+    let collections = mesh
+        .children()
+        .filter(|node| node.has_tag_name("domains"))
+        .flat_map(|domains| children(domains, "mesh_value_collection"));
+    for (k, collection) in collections.enumerate() {
+        let name = collection
+            .attribute("name")
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("collection-{}", k + 1));
+        let dim: usize = parse_attribute(collection, "dim")?;
+        let value_type = collection.attribute("type");
+        let entities = if dim == 0 || dim == tdim {
+            None
+        } else if is_simplex(cell_type) {
+            simplex_entities(nary, dim)
+        } else {
+            None
+        };
+        if dim > tdim || (dim != 0 && dim != tdim && entities.is_none()) {
+            return Err(Error::Syntax(format!(
+                "Markers of dimension {} on {} cells are not supported.",
+                dim, cell_type
+            )));
+        }
+
+        let mut elements = Vec::new();
+        for value in children(collection, "value") {
+            let cell: usize = parse_attribute(value, "cell_index")?;
+            let local: usize = parse_attribute(value, "local_entity")?;
+            let marker = parse_marker(attribute(value, "value")?, value_type)?;
+            let nodes = connectivity
+                .get(cell)
+                .ok_or_else(|| Error::Syntax(format!("Undefined cell {}.", cell)))?;
+            let bad_local = || Error::Syntax(format!("Bad local entity {}.", local));
+
+            if dim == tdim {
+                cell_attrs[cell].set(name.clone().into(), marker);
+            } else if dim == 0 {
+                let &node = nodes.get(local).ok_or_else(bad_local)?;
+                vertex_attrs[node].set(name.clone().into(), marker);
+            } else {
+                let local_nodes = entities
+                    .as_ref()
+                    .unwrap()
+                    .get(local)
+                    .ok_or_else(bad_local)?;
+                let mut attr = AttributeMap::new();
+                attr.set(name.clone().into(), marker);
+                let nodes: Vec<usize> = local_nodes.iter().map(|&j| nodes[j]).collect();
+                elements.push((nodes, attr));
+            }
+        }
+        if dim != 0 && dim != tdim {
+            entity_groups.push((name, elements));
+        }
+    }
+    Ok(DolfinMesh {
+        dimension,
+        cell_type: cell_type.to_string(),
+        cell,
+        positions,
+        vertex_attrs,
+        connectivity,
+        cell_attrs,
+        entity_groups,
+    })
+}
+
+impl DolfinMesh {
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let name = Name::parse("vertices".into(), Format::Dolfin, EntityKind::Node).unwrap();
+        let vertices = self
+            .positions
+            .into_iter()
+            .zip(self.vertex_attrs)
+            .enumerate()
+            .map(|(i, (position, attr))| {
+                let position =
+                    position.ok_or_else(|| Error::Syntax(format!("Missing vertex {}.", i)))?;
+                Ok(geometry::entity_with_position(
+                    EntityKind::Node,
+                    &position,
+                    attr,
+                ))
+            });
+        let vertices = group_events(name, EntityKind::Node, Vec::new(), vertices);
+
+        let name = Name::parse(self.cell_type, Format::Dolfin, EntityKind::Element).unwrap();
+        let cell = self.cell;
+        let cells = self
+            .connectivity
+            .into_iter()
+            .zip(self.cell_attrs)
+            .map(move |(nodes, attr)| {
+                let nodes = ordering::to_canonical(Format::Dolfin, cell, &nodes);
+                Ok(geometry::element_with_cell(cell, &nodes, attr))
+            });
+        let cells = group_events(name, EntityKind::Element, Vec::new(), cells);
+
+        let entity_groups = self.entity_groups.into_iter().flat_map(|(name, elements)| {
+            let name = Name::parse(name, Format::Dolfin, EntityKind::Element).unwrap();
+            let elements = elements.into_iter().map(|(nodes, attr)| {
+                Ok(geometry::entity_with_nodes(
+                    EntityKind::Element,
+                    &nodes,
+                    attr,
+                ))
+            });
+            group_events(name, EntityKind::Element, Vec::new(), elements)
+        });
+
+        iter::once(Ok(Event::Dimension(self.dimension as u8)))
+            .chain(vertices)
+            .chain(cells)
+            .chain(entity_groups)
+    }
+}
+
+pub struct DolfinDeserializer {}
+
+impl Deserializer for DolfinDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(parse(source), DolfinMesh::events)
+    }
+
+    /// Recognizes the root element `<dolfin>`.
+    fn sniff(head: &[u8]) -> Confidence {
+        match sniff::text(head)
+            .as_ref()
+            .and_then(|text| sniff::xml_root(text))
+        {
+            Some("dolfin") => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// The DOLFIN cell type of an element.
+fn cell_type(cell: CellType) -> Result<&'static str, Error> {
+    CELL_TYPES
+        .iter()
+        .find(|&&(_, c)| c == cell)
+        .map(|&(name, _)| name)
+        .ok_or_else(|| {
+            Error::Unsupported(format!(
+                "DOLFIN does not support cells of type {}.",
+                cell.name()
+            ))
+        })
+}
+
+/// The value type of a marker collection with the values `values`.
+fn value_type<'a, I: Iterator<Item = &'a AttributeValue>>(values: I) -> Option<&'static str> {
+    let mut value_type = "uint";
+    for value in values {
+        let signed = match *value {
+            AttributeValue::U8(_)
+            | AttributeValue::U16(_)
+            | AttributeValue::U32(_)
+            | AttributeValue::U64(_) => false,
+            AttributeValue::I8(_)
+            | AttributeValue::I16(_)
+            | AttributeValue::I32(_)
+            | AttributeValue::I64(_) => true,
+            AttributeValue::F32(_) | AttributeValue::F64(_) => {
+                value_type = "double";
+                continue;
+            }
+            AttributeValue::List(_) => return None,
+            AttributeValue::String(ref value) => {
+                if value.parse::<u64>().is_ok() {
+                    false
+                } else if value.parse::<i64>().is_ok() {
+                    true
+                } else if value.parse::<f64>().is_ok() {
+                    value_type = "double";
+                    continue;
+                } else {
+                    return None;
+                }
+            }
+        };
+        if signed && value_type == "uint" {
+            value_type = "int";
+        }
+    }
+    Some(value_type)
+}
+
+/// Parse a marker with the value type `value_type` of its collection.
+fn parse_marker(value: &str, value_type: Option<&str>) -> Result<AttributeValue, Error> {
+    let bad_value = || Error::Syntax(format!("Bad marker value: {}", value));
+    Ok(match value_type {
+        Some("uint") => value.parse::<u64>().map_err(|_| bad_value())?.into(),
+        Some("int") => value.parse::<i64>().map_err(|_| bad_value())?.into(),
+        Some("double") => value.parse::<f64>().map_err(|_| bad_value())?.into(),
+        _ => value.into(),
+    })
+}
+
+/// The numeric attributes of some entities, except the ones with geometry or connectivity.
+fn marker_names(attrs: &[AttributeMap]) -> Vec<String> {
+    let mut names: BTreeMap<String, bool> = BTreeMap::new();
+    for attr in attrs {
+        for (name, value) in attr.iter() {
+            if let AttributeName::Key(ref key) = *name {
+                if key != keys::NODES && !keys::POSITION.contains(&key.as_ref()) {
+                    let numeric = value_type(Some(value).into_iter()).is_some();
+                    *names.entry(key.to_string()).or_insert(true) &= numeric;
+                }
+            }
+        }
+    }
+    names
+        .into_iter()
+        .filter(|&(_, numeric)| numeric)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// A marker collection to be written, with (cell, local entity, value) triples.
+struct Collection {
+    name: String,
+    dim: usize,
+    values: Vec<(usize, usize, AttributeValue)>,
+}
+
+#[derive(Default)]
+pub struct DolfinSerializer {}
+
+impl DolfinSerializer {
+    pub fn new() -> Self {
+        DolfinSerializer {}
+    }
+}
+
+impl Serializer for DolfinSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension == 0 || dimension > 3 {
+            return Err(Error::Unsupported(format!(
+                "DOLFIN does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        let mut node_attrs = Vec::with_capacity(points.len());
+        let mut groups = Vec::new();
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            match metadata.kind() {
+                EntityKind::Node => {
+                    node_attrs
+                        .extend(group.map(|node| AttributeMap::from_container(node.attributes())));
+                }
+                EntityKind::Element => {
+                    let mut elements = Vec::with_capacity(metadata.len());
+                    for element in group {
+                        let nodes = element.node_indices()?;
+                        if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                            return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                        }
+                        let cell = geometry::cell_type(&element, &nodes, &points, dimension)?;
+                        let cell_type = cell_type(cell)?;
+                        let nodes = ordering::from_canonical(Format::Dolfin, cell, &nodes);
+                        let attr = AttributeMap::from_container(element.attributes());
+                        elements.push((cell_type, nodes, attr));
+                    }
+                    groups.push((metadata.name().get_original().0.to_string(), elements));
+                }
+                EntityKind::Vector | EntityKind::Other => (),
+            }
+        }
+
+        // The cells are the elements of the highest topological dimension.
+        let tdim_of = |cell_type: &str| {
+            CELL_TYPES
+                .iter()
+                .find(|&&(name, _)| name == cell_type)
+                .map(|&(_, cell)| cell.dimension() as usize)
+                .unwrap()
+        };
+        let cell_type = groups
+            .iter()
+            .flat_map(|(_, elements)| elements.iter().map(|e| e.0))
+            .max_by_key(|&cell_type| tdim_of(cell_type))
+            .unwrap_or("triangle");
+        let tdim = tdim_of(cell_type);
+
+        let mut cells: Vec<(&[usize], &AttributeMap)> = Vec::new();
+        let mut entity_groups = Vec::new();
+        for (name, elements) in &groups {
+            for element in elements {
+                if tdim_of(element.0) == tdim {
+                    if element.0 != cell_type {
+                        return Err(Error::Unsupported(format!(
+                            "DOLFIN does not support cells of types {} and {} in one mesh.",
+                            cell_type, element.0
+                        )));
+                    }
+                    cells.push((&element.1, &element.2));
+                }
+            }
+            if elements.iter().any(|e| tdim_of(e.0) != tdim) {
+                entity_groups.push((name, elements));
+            }
+        }
+
+        // Marker collections of cells, vertices and sub-entities.
+        let mut collections = Vec::new();
+        let cell_attrs: Vec<AttributeMap> = cells.iter().map(|&(_, attr)| attr.clone()).collect();
+        for name in marker_names(&cell_attrs) {
+            let key = AttributeName::from(name.as_str());
+            let values = cell_attrs
+                .iter()
+                .enumerate()
+                .filter_map(|(c, attr)| attr.get(&key).map(|value| (c, 0, value.clone())))
+                .collect();
+            collections.push(Collection {
+                name,
+                dim: tdim,
+                values,
+            });
+        }
+
+        let mut node_cells: Vec<Option<(usize, usize)>> = vec![None; points.len()];
+        for (c, &(nodes, _)) in cells.iter().enumerate() {
+            for (local, &node) in nodes.iter().enumerate() {
+                node_cells[node].get_or_insert((c, local));
+            }
+        }
+        for name in marker_names(&node_attrs) {
+            let key = AttributeName::from(name.as_str());
+            let values = node_attrs
+                .iter()
+                .zip(&node_cells)
+                .filter_map(|(attr, cell)| match (attr.get(&key), cell) {
+                    (Some(value), &Some((c, local))) => Some((c, local, value.clone())),
+                    _ => None,
+                })
+                .collect();
+            collections.push(Collection {
+                name,
+                dim: 0,
+                values,
+            });
+        }
+
+        let mut sub_entities: HashMap<Vec<usize>, (usize, usize)> = HashMap::new();
+        if !entity_groups.is_empty() {
+            if !is_simplex(cell_type) {
+                return Err(Error::Unsupported(format!(
+                    "DOLFIN markers of sub-entities of {} cells are not supported.",
+                    cell_type
+                )));
+            }
+            let n = cells.first().map(|c| c.0.len()).unwrap_or(0);
+            for dim in 1..tdim {
+                for (c, &(nodes, _)) in cells.iter().enumerate() {
+                    for (local, entity) in simplex_entities(n, dim).unwrap().iter().enumerate() {
+                        let mut key: Vec<usize> = entity.iter().map(|&j| nodes[j]).collect();
+                        key.sort_unstable();
+                        sub_entities.entry(key).or_insert((c, local));
+                    }
+                }
+            }
+        }
+        for (name, elements) in entity_groups {
+            let own_key = AttributeName::from(name.as_str());
+            let mut values = Vec::with_capacity(elements.len());
+            let mut dim = None;
+            for (element_type, nodes, attr) in elements {
+                if tdim_of(element_type) == tdim {
+                    continue;
+                }
+                let mut key = nodes.clone();
+                key.sort_unstable();
+                let &(c, local) = sub_entities.get(&key).ok_or_else(|| {
+                    Error::Unsupported(format!(
+                        "Element of group {} is not part of any cell.",
+                        name
+                    ))
+                })?;
+                let value = attr
+                    .get(&own_key)
+                    .or_else(|| attr.get(&AttributeName::key(keys::REF)))
+                    .cloned()
+                    .unwrap_or_else(|| 1u64.into());
+                values.push((c, local, value));
+                dim = Some(tdim_of(element_type));
+            }
+            collections.push(Collection {
+                name: name.clone(),
+                dim: dim.unwrap(),
+                values,
+            });
+        }
+
+        writeln!(target, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(target, "<!-- DOLFIN mesh file, generated by multimesh -->")?;
+        writeln!(target, "<dolfin xmlns:dolfin=\"http://fenicsproject.org\">")?;
+        writeln!(
+            target,
+            "  <mesh celltype=\"{}\" dim=\"{}\">",
+            cell_type, dimension
+        )?;
+
+        writeln!(target, "    <vertices size=\"{}\">", points.len())?;
+        for (i, point) in points.iter().enumerate() {
+            write!(target, "      <vertex index=\"{}\"", i)?;
+            for (key, x) in ["x", "y", "z"].iter().zip(&point[..dimension]) {
+                write!(target, " {}=\"{}\"", key, x)?;
+            }
+            writeln!(target, " />")?;
+        }
+        writeln!(target, "    </vertices>")?;
+
+        writeln!(target, "    <cells size=\"{}\">", cells.len())?;
+        for (i, &(nodes, _)) in cells.iter().enumerate() {
+            write!(target, "      <{} index=\"{}\"", cell_type, i)?;
+            for (j, node) in nodes.iter().enumerate() {
+                write!(target, " v{}=\"{}\"", j, node)?;
+            }
+            writeln!(target, " />")?;
+        }
+        writeln!(target, "    </cells>")?;
+
+        if !collections.is_empty() {
+            writeln!(target, "    <domains>")?;
+            for collection in &collections {
+                let value_type =
+                    value_type(collection.values.iter().map(|v| &v.2)).unwrap_or("uint");
+                writeln!(
+                    target,
+                    "      <mesh_value_collection name=\"{}\" type=\"{}\" dim=\"{}\" size=\"{}\">",
+                    xml::escape(&collection.name),
+                    value_type,
+                    collection.dim,
+                    collection.values.len()
+                )?;
+                for (c, local, value) in &collection.values {
+                    writeln!(
+                        target,
+                        "        <value cell_index=\"{}\" local_entity=\"{}\" value=\"{}\" />",
+                        c, local, value
+                    )?;
+                }
+                writeln!(target, "      </mesh_value_collection>")?;
+            }
+            writeln!(target, "    </domains>")?;
+        }
+
+        writeln!(target, "  </mesh>")?;
+        writeln!(target, "</dolfin>")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+//! Implementation of DXF (Drawing Exchange Format) surface import (ASCII files).
+//!
+//! Definition: the DXF reference of AutoCAD, sections `ENTITIES`, `3DFACE`, `POLYLINE` and
+//! `VERTEX`.
+//!
+//! Only the `ENTITIES` section is read, and in it only `3DFACE` entities and `POLYLINE`
+//! entities which are polyface meshes (flag 64). All other entities, like the vertices of
+//! ordinary polylines, are skipped. Faces whose fourth corner equals the third become
+//! triangles, all others quadrilaterals. Since DXF stores the corners of every face
+//! separately, points with exactly the same coordinates are welded into a single node.
+//!
+//! All points end up in one node group called `vertices`, faces are grouped by their layer
+//! (code 8) and groups are named after it. Faces without a layer are put on layer `0`, the
+//! default layer of DXF. Faces which degenerate to less than three distinct nodes after
+//! welding are dropped.
+
+use data::{attribute::AttributeMap, CellType, EntityBox, EntityKind};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use std::{collections::HashMap, io::Read, iter};
+use util::{geometry, sniff};
+
+/// The layer of entities without layer code.
+const DEFAULT_LAYER: &str = "0";
+
+/// Flag of `POLYLINE` entities which are polyface meshes.
+const POLYFACE_MESH: i32 = 64;
+/// Flag of `VERTEX` entities which belong to a polyface mesh.
+const POLYFACE_VERTEX: i32 = 128;
+/// Flag of `VERTEX` entities which carry coordinates, as opposed to face records.
+const COORDINATES_VERTEX: i32 = 64;
+
+/// An entity of the `ENTITIES` section: its type and its group code/value pairs.
+struct DxfEntity<'a> {
+    kind: &'a str,
+    pairs: Vec<(i32, &'a str)>,
+}
+
+impl<'a> DxfEntity<'a> {
+    fn value(&self, code: i32) -> Option<&'a str> {
+        self.pairs
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, value)| *value)
+    }
+
+    fn float(&self, code: i32) -> Result<f64, Error> {
+        match self.value(code) {
+            Some(value) => Ok(value.parse()?),
+            None => Ok(0.0),
+        }
+    }
+
+    fn int(&self, code: i32) -> Result<i32, Error> {
+        match self.value(code) {
+            Some(value) => Ok(value.parse()?),
+            None => Ok(0),
+        }
+    }
+
+    /// The point given by the codes `10 + n`, `20 + n` and `30 + n`.
+    fn point(&self, n: i32) -> Result<[f64; 3], Error> {
+        Ok([
+            self.float(10 + n)?,
+            self.float(20 + n)?,
+            self.float(30 + n)?,
+        ])
+    }
+
+    fn layer(&self) -> Option<&'a str> {
+        self.value(8)
+    }
+}
+
+/// Split the file into the entities of its `ENTITIES` section.
+fn entities(data: &str) -> Result<Vec<DxfEntity<'_>>, Error> {
+    let mut lines = data.lines();
+    let mut pairs = Vec::new();
+    while let Some(code) = lines.next() {
+        if code.trim().is_empty() && lines.clone().all(|line| line.trim().is_empty()) {
+            break;
+        }
+        let code: i32 = code
+            .trim()
+            .parse()
+            .map_err(|_| Error::Syntax(format!("Bad group code: {}", code.trim())))?;
+        let value = lines
+            .next()
+            .ok_or_else(|| Error::Syntax(format!("Group code {} without value.", code)))?;
+        pairs.push((code, value.trim()));
+    }
+
+    let mut entities: Vec<DxfEntity> = Vec::new();
+    let mut in_entities = false;
+    let mut iter = pairs.into_iter().peekable();
+    while let Some((code, value)) = iter.next() {
+        if code != 0 {
+            if in_entities {
+                if let Some(entity) = entities.last_mut() {
+                    entity.pairs.push((code, value));
+                }
+            }
+            continue;
+        }
+        match value {
+            "SECTION" => {
+                in_entities = iter.peek() == Some(&(2, "ENTITIES"));
+            }
+            "ENDSEC" | "EOF" => in_entities = false,
+            kind if in_entities => entities.push(DxfEntity {
+                kind,
+                pairs: Vec::new(),
+            }),
+            _ => (),
+        }
+    }
+    Ok(entities)
+}
+
+/// Collects welded nodes and faces grouped by layer.
+#[derive(Default)]
+struct Builder {
+    points: Vec<[f64; 3]>,
+    welded: HashMap<[u64; 3], usize>,
+    layers: Vec<(String, Vec<Vec<usize>>)>,
+    layer_indices: HashMap<String, usize>,
+}
+
+impl Builder {
+    fn weld(&mut self, point: [f64; 3]) -> usize {
+        // Adding zero turns -0.0 into 0.0, so both weld together.
+        let key = [
+            (point[0] + 0.0).to_bits(),
+            (point[1] + 0.0).to_bits(),
+            (point[2] + 0.0).to_bits(),
+        ];
+        let points = &mut self.points;
+        *self.welded.entry(key).or_insert_with(|| {
+            points.push(point);
+            points.len() - 1
+        })
+    }
+
+    fn add_face(&mut self, layer: &str, mut nodes: Vec<usize>) {
+        nodes.dedup();
+        while nodes.len() > 1 && nodes.first() == nodes.last() {
+            nodes.pop();
+        }
+        if nodes.len() < 3 {
+            return;
+        }
+
+        let layers = &mut self.layers;
+        let index = *self
+            .layer_indices
+            .entry(layer.to_string())
+            .or_insert_with(|| {
+                layers.push((layer.to_string(), Vec::new()));
+                layers.len() - 1
+            });
+        self.layers[index].1.push(nodes);
+    }
+}
+
+/// Weld the points of all faces and group the faces by layer.
+fn parse<S: Read>(mut source: S) -> Result<Builder, Error> {
+    // Read the file into memory, older files may use a legacy code page.
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
+    let data = String::from_utf8_lossy(&data);
+    let entities = entities(&data)?;
+
+    let mut builder = Builder::default();
+    let mut iter = entities.iter();
+    while let Some(entity) = iter.next() {
+        match entity.kind {
+            "3DFACE" => {
+                let layer = entity.layer().unwrap_or(DEFAULT_LAYER);
+                let mut corners = Vec::with_capacity(4);
+                for n in 0..4 {
+                    corners.push(entity.point(n)?);
+                }
+                if corners[3] == corners[2] {
+                    corners.pop();
+                }
+                let nodes = corners.into_iter().map(|p| builder.weld(p)).collect();
+                builder.add_face(layer, nodes);
+            }
+            "POLYLINE" => {
+                let polyface = entity.int(70)? & POLYFACE_MESH != 0;
+                let layer = entity.layer().unwrap_or(DEFAULT_LAYER);
+                // Polyface vertices are numbered from 1 in order of appearance.
+                let mut vertices = Vec::new();
+                for vertex in iter.by_ref() {
+                    match vertex.kind {
+                        "VERTEX" if polyface => (),
+                        "VERTEX" => continue,
+                        "SEQEND" => break,
+                        kind => {
+                            return Err(Error::Syntax(format!("Unexpected {} in POLYLINE.", kind)))
+                        }
+                    }
+
+                    let flags = vertex.int(70)?;
+                    if flags & POLYFACE_VERTEX == 0 {
+                        return Err(Error::Syntax("VERTEX is not part of polyface.".into()));
+                    }
+                    if flags & COORDINATES_VERTEX != 0 {
+                        vertices.push(builder.weld(vertex.point(0)?));
+                        continue;
+                    }
+
+                    // A face record, negative indices mark invisible edges.
+                    let mut nodes = Vec::with_capacity(4);
+                    for code in 71..75 {
+                        let index = vertex.int(code)?.unsigned_abs() as usize;
+                        if index == 0 {
+                            continue;
+                        }
+                        let node = vertices.get(index - 1).ok_or_else(|| {
+                            Error::Syntax(format!("Undefined polyface vertex {}.", index))
+                        })?;
+                        nodes.push(*node);
+                    }
+                    builder.add_face(vertex.layer().unwrap_or(layer), nodes);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(builder)
+}
+
+fn face(nodes: &[usize]) -> EntityBox {
+    let cell = if nodes.len() == 3 {
+        CellType::Triangle
+    } else {
+        CellType::Quadrilateral
+    };
+    geometry::element_with_cell(cell, nodes, AttributeMap::new())
+}
+
+pub struct DxfDeserializer {}
+
+impl Deserializer for DxfDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(parse(source), |builder| {
+            let name = Name::parse("vertices".into(), Format::Dxf, EntityKind::Node).unwrap();
+            let points = builder.points.into_iter().map(|point| {
+                Ok(geometry::entity_with_position(
+                    EntityKind::Node,
+                    &point,
+                    AttributeMap::new(),
+                ))
+            });
+            let nodes = group_events(name, EntityKind::Node, Vec::new(), points);
+            let layers = builder.layers.into_iter().flat_map(|(layer, faces)| {
+                let name = Name::parse(layer, Format::Dxf, EntityKind::Element).unwrap();
+                let faces = faces.into_iter().map(|nodes| Ok(face(&nodes)));
+                group_events(name, EntityKind::Element, Vec::new(), faces)
+            });
+            iter::once(Ok(Event::Dimension(3)))
+                .chain(nodes)
+                .chain(layers)
+        })
+    }
+
+    /// Recognizes the group code 0 with the value `SECTION`, after optional comments.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let mut lines = text.lines().map(|line| line.trim());
+        while let (Some(code), Some(value)) = (lines.next(), lines.next()) {
+            match (code, value) {
+                ("999", _) => continue,
+                ("0", "SECTION") => return Confidence::High,
+                _ => break,
+            }
+        }
+        Confidence::No
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{attribute::AttributeContainer, face_vertex::Mesh, Entity};
+
+    fn dxf(pairs: &[(i32, &str)]) -> String {
+        pairs
+            .iter()
+            .map(|(code, value)| format!("{:>3}\n{}\n", code, value))
+            .collect()
+    }
+
+    #[test]
+    fn polyface_with_invisible_edges() {
+        let mut pairs = vec![(0, "SECTION"), (2, "ENTITIES")];
+        pairs.extend_from_slice(&[(0, "POLYLINE"), (8, "walls"), (66, "1"), (70, "64")]);
+        for (x, y) in &[("0", "0"), ("1", "0"), ("1", "1"), ("0", "1")] {
+            pairs.extend_from_slice(&[(0, "VERTEX"), (10, x), (20, y), (30, "0"), (70, "192")]);
+        }
+        // A quad split into two triangles with the diagonal hidden, the second on a layer
+        // of its own.
+        pairs.extend_from_slice(&[(0, "VERTEX"), (70, "128"), (71, "1"), (72, "2")]);
+        pairs.extend_from_slice(&[(73, "-3")]);
+        pairs.extend_from_slice(&[(0, "VERTEX"), (8, "roof"), (70, "128"), (71, "-1")]);
+        pairs.extend_from_slice(&[(72, "3"), (73, "4")]);
+        // An ordinary polyline, whose vertices are ignored.
+        pairs.extend_from_slice(&[(0, "SEQEND"), (0, "POLYLINE"), (70, "1")]);
+        pairs.extend_from_slice(&[(0, "VERTEX"), (10, "5"), (0, "SEQEND")]);
+        pairs.extend_from_slice(&[(0, "ENDSEC"), (0, "EOF")]);
+
+        let mut mesh = Mesh::default();
+        DxfDeserializer::deserialize_into(dxf(&pairs).as_bytes(), &mut mesh).unwrap();
+
+        assert_eq!(mesh.node_groups()[0].entities().len(), 4);
+        let groups = mesh.element_groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name().get_original().0, "walls");
+        assert_eq!(groups[1].name().get_original().0, "roof");
+        let nodes = |group: usize| {
+            groups[group].entities()[0]
+                .attributes()
+                .get(&"nodes".into())
+                .unwrap()
+                .to_usize_list()
+                .unwrap()
+        };
+        assert_eq!(nodes(0), vec![0, 1, 2]);
+        assert_eq!(nodes(1), vec![0, 2, 3]);
+    }
+}
@@ -0,0 +1,965 @@
+//! Implementation of ANSYS Fluent mesh format support (ASCII `.msh` files).
+//!
+//! Definition: ANSYS Fluent User's Guide, appendix "Fluent Case File Format".
+//!
+//! A Fluent mesh is a list of Scheme-like sections `(index ...)`, of which the following are
+//! understood, all others are skipped:
+//!
+//! - `(0 ...)` comments and `(1 ...)` headers,
+//! - `(2 ...)` the dimension of the mesh,
+//! - `(10 ...)` node zones,
+//! - `(12 ...)` cell zones, including zones with mixed element types,
+//! - `(13 ...)` face zones, including zones with mixed face types,
+//! - `(39 ...)` and `(45 ...)` zone names.
+//!
+//! Zone ids and indices in sections 10, 12 and 13 are hexadecimal. Every zone becomes a group
+//! named after the zone, falling back to `zone-<id>` for zones without name.
+//!
+//! Fluent does not store the nodes of a cell, only the faces enclosing it. The connectivity of
+//! triangles, quadrilaterals, tetrahedra, pyramids, wedges and hexahedra is reconstructed from
+//! their faces and oriented so that it has a positive volume. Polyhedral cells and the binary
+//! sections are not supported.
+
+use data::{
+    attribute::{keys, AttributeContainerMut, AttributeMap, AttributeName},
+    CellType, EntityKind,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use std::{collections::HashMap, io::Read, iter, mem};
+use util::{geometry, sniff};
+
+pub struct FluentDeserializer {}
+
+impl Deserializer for FluentDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(read(source), Groups::events)
+    }
+
+    /// Recognizes files made of s-expressions with section indices, like `(0 "comment")`.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let mut confidence = Confidence::No;
+        let lines = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty());
+        for line in lines.take(8) {
+            let index: String = match line.strip_prefix('(') {
+                Some(rest) => rest
+                    .trim_start()
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect(),
+                None => return Confidence::No,
+            };
+            match index.as_str() {
+                "" => return Confidence::No,
+                // Node, cell and face sections.
+                "10" | "12" | "13" => return Confidence::High,
+                _ => confidence = Confidence::Medium,
+            }
+        }
+        confidence
+    }
+}
+
+/// The cell type and nodes of an element, the cell type is `None` for polygons and polyhedra.
+type Element = (Option<CellType>, Vec<usize>);
+
+/// The groups of a Fluent mesh, with cells reconstructed from their faces.
+struct Groups {
+    dimension: usize,
+    /// Node zones with the number of coordinates per node and their coordinates.
+    nodes: Vec<(Name, usize, Vec<f64>)>,
+    /// Cell and face zones with their zone id and elements.
+    elements: Vec<(Name, usize, Vec<Element>)>,
+}
+
+impl Groups {
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let nodes = self.nodes.into_iter().flat_map(|(name, nd, coords)| {
+            let len = coords.len().checked_div(nd).unwrap_or(0);
+            let nodes = (0..len).map(move |i| {
+                Ok(geometry::entity_with_position(
+                    EntityKind::Node,
+                    &coords[i * nd..(i + 1) * nd],
+                    AttributeMap::new(),
+                ))
+            });
+            group_events(name, EntityKind::Node, Vec::new(), nodes)
+        });
+        let elements = self
+            .elements
+            .into_iter()
+            .flat_map(|(name, zone_id, elements)| {
+                let elements = elements.into_iter().map(move |(cell, nodes)| {
+                    let attr = zone_attr(zone_id);
+                    Ok(match cell {
+                        Some(cell) => geometry::element_with_cell(cell, &nodes, attr),
+                        None => geometry::entity_with_nodes(EntityKind::Element, &nodes, attr),
+                    })
+                });
+                group_events(name, EntityKind::Element, Vec::new(), elements)
+            });
+        iter::once(Ok(Event::Dimension(self.dimension as u8)))
+            .chain(nodes)
+            .chain(elements)
+    }
+}
+
+/// Reads a Fluent mesh and reconstructs its cells.
+fn read<S: Read>(mut source: S) -> Result<Groups, Error> {
+    // Read the file into memory.
+    let mut data = String::new();
+    source.read_to_string(&mut data)?;
+
+    let mut mesh = parse(&data)?;
+    let dimension = mesh.dimension()?;
+    mesh.node_zones.sort_by_key(|zone| zone.first);
+    mesh.cell_zones.sort_by_key(|zone| zone.first);
+    mesh.face_zones.sort_by_key(|zone| zone.first);
+
+    // Nodes, which have to cover the range of node indices without gaps.
+    let mut next_index = 1;
+    for zone in &mesh.node_zones {
+        if zone.first != next_index {
+            return Err(Error::Syntax(format!(
+                "Node zone {} does not start at node {:x}.",
+                zone.id, next_index
+            )));
+        }
+        next_index = zone.last + 1;
+    }
+    let coords: Vec<&[f64]> = mesh
+        .node_zones
+        .iter()
+        .flat_map(|zone| zone.coords.chunks(zone.nd))
+        .collect();
+    for face in &mesh.faces {
+        if let Some(node) = face.nodes.iter().find(|&&node| node >= coords.len()) {
+            return Err(Error::Syntax(format!(
+                "Face refers to undefined node {:x}.",
+                node + 1
+            )));
+        }
+    }
+
+    // Cells, reconstructed from the faces referring to them.
+    let num_cells = mesh
+        .cell_zones
+        .iter()
+        .map(|zone| zone.last)
+        .max()
+        .unwrap_or(0);
+    // Every cell is bounded by at least three faces, each of which has two sides.
+    if num_cells > 2 * mesh.faces.len() {
+        return Err(Error::Syntax(format!(
+            "Cell zones cover {:x} cells, but there are only {:x} faces.",
+            num_cells,
+            mesh.faces.len()
+        )));
+    }
+    let mut cell_faces: Vec<Vec<usize>> = vec![Vec::new(); num_cells];
+    for (i, face) in mesh.faces.iter().enumerate() {
+        for &cell in &[face.c0, face.c1] {
+            if cell > num_cells {
+                return Err(Error::Syntax(format!(
+                    "Face refers to undefined cell {:x}.",
+                    cell
+                )));
+            } else if cell > 0 {
+                cell_faces[cell - 1].push(i);
+            }
+        }
+    }
+
+    let mut elements = Vec::new();
+    for zone in &mesh.cell_zones {
+        let mut cells = Vec::with_capacity(zone.len());
+        for (i, cell) in (zone.first..=zone.last).enumerate() {
+            let element_type = if zone.element_type == MIXED {
+                zone.element_types[i]
+            } else {
+                zone.element_type
+            };
+            let faces: Vec<&[usize]> = cell_faces[cell - 1]
+                .iter()
+                .map(|&f| mesh.faces[f].nodes.as_ref())
+                .collect();
+            let nodes = reconstruct_cell(element_type, &faces, &coords)
+                .map_err(|e| Error::Syntax(format!("Cell {:x}: {}", cell, e)))?;
+            cells.push((cell_type(element_type), nodes));
+        }
+        let name = mesh.zone_name(zone.id, EntityKind::Element);
+        elements.push((name, zone.id, cells));
+    }
+
+    // Faces, as they are given.
+    let face_names: Vec<Name> = mesh
+        .face_zones
+        .iter()
+        .map(|zone| mesh.zone_name(zone.id, EntityKind::Element))
+        .collect();
+    let node_names: Vec<Name> = mesh
+        .node_zones
+        .iter()
+        .map(|zone| mesh.zone_name(zone.id, EntityKind::Node))
+        .collect();
+    let FluentMesh {
+        node_zones,
+        face_zones,
+        faces,
+        ..
+    } = mesh;
+    let mut faces: Vec<Vec<usize>> = faces.into_iter().map(|face| face.nodes).collect();
+    for (zone, name) in face_zones.iter().zip(face_names) {
+        let faces = faces[zone.faces.clone()]
+            .iter_mut()
+            .map(|nodes| {
+                let nodes = mem::take(nodes);
+                (face_cell_type(nodes.len()), nodes)
+            })
+            .collect();
+        elements.push((name, zone.id, faces));
+    }
+
+    let nodes = node_zones
+        .into_iter()
+        .zip(node_names)
+        .map(|(zone, name)| (name, zone.nd, zone.coords))
+        .collect();
+    Ok(Groups {
+        dimension,
+        nodes,
+        elements,
+    })
+}
+
+fn zone_attr(zone_id: usize) -> AttributeMap {
+    let mut attr = AttributeMap::new();
+    attr.set(AttributeName::key(keys::REF), zone_id.into());
+    attr
+}
+
+/// Element type of cell and face zones with more than one element type.
+const MIXED: usize = 0;
+
+// Cell element types.
+const TRIANGLE: usize = 1;
+const TETRAHEDRON: usize = 2;
+const QUADRILATERAL: usize = 3;
+const HEXAHEDRON: usize = 4;
+const PYRAMID: usize = 5;
+const WEDGE: usize = 6;
+const POLYHEDRON: usize = 7;
+
+// Face types with a fixed number of nodes.
+const FACE_LINE: usize = 2;
+const FACE_TRIANGLE: usize = 3;
+const FACE_QUADRILATERAL: usize = 4;
+
+/// The cell type of a cell element type, `None` for polyhedra.
+fn cell_type(element_type: usize) -> Option<CellType> {
+    match element_type {
+        TRIANGLE => Some(CellType::Triangle),
+        TETRAHEDRON => Some(CellType::Tetrahedron),
+        QUADRILATERAL => Some(CellType::Quadrilateral),
+        HEXAHEDRON => Some(CellType::Hexahedron),
+        PYRAMID => Some(CellType::Pyramid),
+        WEDGE => Some(CellType::Prism),
+        _ => None,
+    }
+}
+
+/// The cell type of a face with `num_nodes` nodes, `None` for polygons.
+fn face_cell_type(num_nodes: usize) -> Option<CellType> {
+    match num_nodes {
+        FACE_LINE => Some(CellType::Line),
+        FACE_TRIANGLE => Some(CellType::Triangle),
+        FACE_QUADRILATERAL => Some(CellType::Quadrilateral),
+        _ => None,
+    }
+}
+
+struct NodeZone {
+    id: usize,
+    first: usize,
+    last: usize,
+    /// Number of coordinates per node.
+    nd: usize,
+    coords: Vec<f64>,
+}
+
+struct CellZone {
+    id: usize,
+    first: usize,
+    last: usize,
+    element_type: usize,
+    /// Element type of each cell, if the zone is mixed.
+    element_types: Vec<usize>,
+}
+
+struct FaceZone {
+    id: usize,
+    first: usize,
+    last: usize,
+    /// Range of the faces of this zone in [FluentMesh::faces].
+    faces: ::std::ops::Range<usize>,
+}
+
+struct Face {
+    /// Node indices, starting at zero.
+    nodes: Vec<usize>,
+    /// Cell indices on both sides of the face, starting at one, zero if there is none.
+    c0: usize,
+    c1: usize,
+}
+
+trait Zone {
+    fn first(&self) -> usize;
+    fn last(&self) -> usize;
+
+    fn len(&self) -> usize {
+        self.last() + 1 - self.first()
+    }
+}
+
+macro_rules! impl_zone {
+    ($($t:ty),*) => {
+        $(impl Zone for $t {
+            fn first(&self) -> usize {
+                self.first
+            }
+
+            fn last(&self) -> usize {
+                self.last
+            }
+        })*
+    };
+}
+impl_zone!(NodeZone, CellZone, FaceZone);
+
+#[derive(Default)]
+struct FluentMesh {
+    dimension: Option<usize>,
+    /// Total numbers of nodes, cells and faces, if declared by a section with zone id 0.
+    num_nodes: Option<usize>,
+    num_cells: Option<usize>,
+    num_faces: Option<usize>,
+    node_zones: Vec<NodeZone>,
+    cell_zones: Vec<CellZone>,
+    face_zones: Vec<FaceZone>,
+    faces: Vec<Face>,
+    zone_names: HashMap<usize, String>,
+}
+
+impl FluentMesh {
+    fn dimension(&self) -> Result<usize, Error> {
+        match self
+            .dimension
+            .or_else(|| self.node_zones.first().map(|z| z.nd))
+        {
+            Some(dim) if dim == 2 || dim == 3 => Ok(dim),
+            Some(dim) => Err(Error::Syntax(format!("Bad dimension: {}", dim))),
+            None => Err(Error::Syntax("Mesh without dimension.".into())),
+        }
+    }
+
+    fn zone_name(&self, id: usize, kind: EntityKind) -> Name {
+        let name = self
+            .zone_names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| format!("zone-{}", id));
+        // Note: Should never fail, since there is no validation of Fluent names.
+        Name::parse(name, Format::Fluent, kind).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'s> {
+    Open,
+    Close,
+    Atom(&'s str),
+    Str(&'s str),
+}
+
+/// Splits the Scheme-like syntax into parentheses, atoms and strings.
+struct Lexer<'s> {
+    data: &'s str,
+    pos: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn new(data: &'s str) -> Self {
+        Lexer { data, pos: 0 }
+    }
+
+    fn next_result(&mut self) -> Result<Token<'s>, Error> {
+        self.next()
+            .ok_or_else(|| Error::Syntax("Unexpected EOF.".into()))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let token = self.next_result()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(Error::Syntax(format!(
+                "Expected {:?}, found {:?}.",
+                expected, token
+            )))
+        }
+    }
+
+    fn next_atom(&mut self) -> Result<&'s str, Error> {
+        match self.next_result()? {
+            Token::Atom(atom) => Ok(atom),
+            token => Err(Error::Syntax(format!("Expected atom, found {:?}.", token))),
+        }
+    }
+
+    fn next_hex(&mut self) -> Result<usize, Error> {
+        parse_hex(self.next_atom()?)
+    }
+
+    /// An upper bound of the number of atoms which are left.
+    fn max_atoms(&self) -> usize {
+        (self.data.len() - self.pos).div_ceil(2)
+    }
+
+    /// Skip everything until the list which is currently open is closed.
+    fn skip_list(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next_result()? {
+                Token::Open => depth += 1,
+                Token::Close => depth -= 1,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the items of a list whose opening parenthesis has already been consumed.
+    fn read_list(&mut self) -> Result<Vec<&'s str>, Error> {
+        let mut items = Vec::new();
+        loop {
+            match self.next_result()? {
+                Token::Close => return Ok(items),
+                Token::Atom(s) | Token::Str(s) => items.push(s),
+                Token::Open => {
+                    return Err(Error::Syntax("Unexpected nested list.".into()));
+                }
+            }
+        }
+    }
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Token<'s>;
+
+    fn next(&mut self) -> Option<Token<'s>> {
+        let bytes = self.data.as_bytes();
+        while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if self.pos >= bytes.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        match bytes[start] {
+            b'(' => {
+                self.pos += 1;
+                Some(Token::Open)
+            }
+            b')' => {
+                self.pos += 1;
+                Some(Token::Close)
+            }
+            b'"' => {
+                let len = self.data[start + 1..]
+                    .find('"')
+                    .unwrap_or(bytes.len() - start - 1);
+                self.pos = (start + len + 2).min(bytes.len());
+                Some(Token::Str(&self.data[start + 1..start + 1 + len]))
+            }
+            _ => {
+                while self.pos < bytes.len()
+                    && !bytes[self.pos].is_ascii_whitespace()
+                    && !b"()\"".contains(&bytes[self.pos])
+                {
+                    self.pos += 1;
+                }
+                Some(Token::Atom(&self.data[start..self.pos]))
+            }
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Result<usize, Error> {
+    usize::from_str_radix(s, 16).map_err(|e| Error::Syntax(format!("Bad hex number {}: {}", s, e)))
+}
+
+fn parse(data: &str) -> Result<FluentMesh, Error> {
+    let mut lexer = Lexer::new(data);
+    let mut mesh = FluentMesh::default();
+
+    while let Some(token) = lexer.next() {
+        if token != Token::Open {
+            return Err(Error::Syntax(format!(
+                "Expected section, found {:?}.",
+                token
+            )));
+        }
+
+        let index = lexer.next_atom()?;
+        match index {
+            "2" => {
+                mesh.dimension = Some(lexer.next_atom()?.parse()?);
+                lexer.expect(Token::Close)?;
+            }
+            "10" => parse_nodes(&mut lexer, &mut mesh)?,
+            "12" => parse_cells(&mut lexer, &mut mesh)?,
+            "13" => parse_faces(&mut lexer, &mut mesh)?,
+            "39" | "45" => {
+                lexer.expect(Token::Open)?;
+                let header = lexer.read_list()?;
+                if header.len() < 3 {
+                    return Err(Error::Syntax("Zone name section too short.".into()));
+                }
+                mesh.zone_names
+                    .insert(header[0].parse()?, header[2].to_string());
+                lexer.skip_list()?;
+            }
+            _ if index.len() == 4 && (index.starts_with("20") || index.starts_with("30")) => {
+                return Err(Error::Syntax(format!(
+                    "Binary section {} not supported.",
+                    index
+                )));
+            }
+            _ => lexer.skip_list()?,
+        }
+    }
+
+    Ok(mesh)
+}
+
+/// Read a section header `(zone-id first-index last-index type ...)` of sections 10, 12, 13.
+///
+/// Returns the header values and whether the section contains data.
+fn parse_header(lexer: &mut Lexer) -> Result<(Vec<usize>, bool), Error> {
+    lexer.expect(Token::Open)?;
+    let header = lexer
+        .read_list()?
+        .into_iter()
+        .map(parse_hex)
+        .collect::<Result<Vec<_>, _>>()?;
+    if header.len() < 4 {
+        return Err(Error::Syntax("Section header too short.".into()));
+    }
+
+    match lexer.next_result()? {
+        Token::Close => Ok((header, false)),
+        Token::Open => Ok((header, true)),
+        token => Err(Error::Syntax(format!("Unexpected {:?}.", token))),
+    }
+}
+
+/// Validate the index range `first..=last` of a zone header against the declared count.
+fn index_range(header: &[usize], what: &str, declared: Option<usize>) -> Result<(), Error> {
+    let (id, first, last) = (header[0], header[1], header[2]);
+    if first == 0 || last < first {
+        return Err(Error::Syntax(format!(
+            "Bad {} range {:x} to {:x} of zone {}.",
+            what, first, last, id
+        )));
+    }
+    match declared {
+        Some(count) if last > count => Err(Error::Syntax(format!(
+            "Zone {} ends at {} {:x}, but only {:x} are declared.",
+            id, what, last, count
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Consume the end of the data list and the section.
+fn end_data(lexer: &mut Lexer) -> Result<(), Error> {
+    lexer.expect(Token::Close)?;
+    lexer.expect(Token::Close)
+}
+
+fn parse_nodes(lexer: &mut Lexer, mesh: &mut FluentMesh) -> Result<(), Error> {
+    let (header, has_data) = parse_header(lexer)?;
+    if header[0] == 0 {
+        // Declaration of the total number of nodes.
+        mesh.num_nodes = Some(header[2]);
+    }
+    if !has_data {
+        // A declaration, or a zone of virtual nodes.
+        return Ok(());
+    }
+
+    index_range(&header, "node", mesh.num_nodes)?;
+    let nd = header
+        .get(4)
+        .cloned()
+        .or(mesh.dimension)
+        .ok_or_else(|| Error::Syntax("Node section without dimension.".into()))?;
+    if nd != 2 && nd != 3 {
+        return Err(Error::Syntax(format!("Bad dimension: {}", nd)));
+    }
+    let mut zone = NodeZone {
+        id: header[0],
+        first: header[1],
+        last: header[2],
+        nd,
+        coords: Vec::new(),
+    };
+    let num_coords = zone.len() * nd;
+    zone.coords.reserve_exact(num_coords.min(lexer.max_atoms()));
+    for _ in 0..num_coords {
+        zone.coords.push(lexer.next_atom()?.parse()?);
+    }
+    end_data(lexer)?;
+
+    mesh.node_zones.push(zone);
+    Ok(())
+}
+
+fn parse_cells(lexer: &mut Lexer, mesh: &mut FluentMesh) -> Result<(), Error> {
+    let (header, has_data) = parse_header(lexer)?;
+    if header[0] == 0 {
+        // Declaration of the total number of cells.
+        mesh.num_cells = Some(header[2]);
+        if has_data {
+            lexer.skip_list()?;
+            lexer.expect(Token::Close)?;
+        }
+        return Ok(());
+    }
+
+    index_range(&header, "cell", mesh.num_cells)?;
+    let mut zone = CellZone {
+        id: header[0],
+        first: header[1],
+        last: header[2],
+        element_type: header.get(4).cloned().unwrap_or(MIXED),
+        element_types: Vec::new(),
+    };
+    if zone.element_type == MIXED {
+        if !has_data {
+            return Err(Error::Syntax(format!(
+                "Mixed cell zone {} without element types.",
+                zone.id
+            )));
+        }
+        zone.element_types
+            .reserve_exact(zone.len().min(lexer.max_atoms()));
+        for _ in 0..zone.len() {
+            zone.element_types.push(lexer.next_hex()?);
+        }
+        end_data(lexer)?;
+    } else if has_data {
+        lexer.skip_list()?;
+        lexer.expect(Token::Close)?;
+    }
+
+    mesh.cell_zones.push(zone);
+    Ok(())
+}
+
+fn parse_faces(lexer: &mut Lexer, mesh: &mut FluentMesh) -> Result<(), Error> {
+    let (header, has_data) = parse_header(lexer)?;
+    if header[0] == 0 {
+        // Declaration of the total number of faces.
+        mesh.num_faces = Some(header[2]);
+    }
+    if !has_data {
+        return Ok(());
+    }
+
+    index_range(&header, "face", mesh.num_faces)?;
+    let face_type = header.get(4).cloned().unwrap_or(MIXED);
+    let start = mesh.faces.len();
+    let mut zone = FaceZone {
+        id: header[0],
+        first: header[1],
+        last: header[2],
+        faces: start..start,
+    };
+
+    mesh.faces.reserve(zone.len().min(lexer.max_atoms()));
+    for _ in 0..zone.len() {
+        let num_nodes = match face_type {
+            FACE_LINE | FACE_TRIANGLE | FACE_QUADRILATERAL => face_type,
+            _ => lexer.next_hex()?,
+        };
+        let mut nodes = Vec::with_capacity(num_nodes);
+        for _ in 0..num_nodes {
+            let node = lexer.next_hex()?;
+            if node == 0 {
+                return Err(Error::Syntax("Node index 0 in face.".into()));
+            }
+            nodes.push(node - 1);
+        }
+        let c0 = lexer.next_hex()?;
+        let c1 = lexer.next_hex()?;
+        mesh.faces.push(Face { nodes, c0, c1 });
+    }
+    end_data(lexer)?;
+
+    zone.faces.end = mesh.faces.len();
+    mesh.face_zones.push(zone);
+    Ok(())
+}
+
+/// Reconstruct the nodes of a cell of type `element_type` from its faces.
+fn reconstruct_cell(
+    element_type: usize,
+    faces: &[&[usize]],
+    coords: &[&[f64]],
+) -> Result<Vec<usize>, String> {
+    match element_type {
+        TRIANGLE | QUADRILATERAL => {
+            let n = if element_type == TRIANGLE { 3 } else { 4 };
+            let mut nodes = chain_edges(faces, n)?;
+            if normal(&points(&nodes, coords)?)[2] < 0. {
+                nodes.reverse();
+            }
+            Ok(nodes)
+        }
+        TETRAHEDRON | PYRAMID | WEDGE | HEXAHEDRON => {
+            let (base_len, num_nodes) = match element_type {
+                TETRAHEDRON => (3, 4),
+                PYRAMID => (4, 5),
+                WEDGE => (3, 6),
+                _ => (4, 8),
+            };
+            let mut base: Vec<usize> = faces
+                .iter()
+                .find(|face| face.len() == base_len)
+                .ok_or_else(|| format!("No face with {} nodes.", base_len))?
+                .to_vec();
+
+            // Orient the base so that its normal points into the cell.
+            let mut all: Vec<usize> = faces.iter().flat_map(|face| face.iter().cloned()).collect();
+            all.sort();
+            all.dedup();
+            if all.len() != num_nodes {
+                return Err("Inconsistent faces.".into());
+            }
+            let base_points = points(&base, coords)?;
+            let inward = sub(centroid(&points(&all, coords)?), base_points[0]);
+            if dot(inward, normal(&base_points)) < 0. {
+                base.reverse();
+            }
+
+            let mut nodes = base.clone();
+            if num_nodes == base_len + 1 {
+                // Apex of tetrahedra and pyramids.
+                let apex = all
+                    .iter()
+                    .find(|i| !base.contains(i))
+                    .ok_or("No apex found.")?;
+                nodes.push(*apex);
+            } else {
+                // The top node of every base node is its neighbour outside of the base.
+                for &b in &base {
+                    let top = faces
+                        .iter()
+                        .flat_map(|face| neighbours(face, b))
+                        .find(|i| !base.contains(i))
+                        .ok_or_else(|| format!("No top node for node {:x}.", b + 1))?;
+                    nodes.push(top);
+                }
+
+                let mut unique = nodes.clone();
+                unique.sort();
+                unique.dedup();
+                if unique.len() != num_nodes {
+                    return Err("Inconsistent faces.".into());
+                }
+            }
+            Ok(nodes)
+        }
+        POLYHEDRON => Err("Polyhedral cells are not supported.".into()),
+        other => Err(format!("Unknown element type {}.", other)),
+    }
+}
+
+/// Chain the edges of a polygon with `n` nodes into a loop.
+fn chain_edges(faces: &[&[usize]], n: usize) -> Result<Vec<usize>, String> {
+    let bad_edges = || "Edges do not form a loop.".to_string();
+    if faces.len() != n || faces.iter().any(|face| face.len() != 2) {
+        return Err(bad_edges());
+    }
+
+    let mut nodes = vec![faces[0][0], faces[0][1]];
+    let mut used = vec![false; n];
+    used[0] = true;
+    while nodes.len() < n {
+        let last = nodes[nodes.len() - 1];
+        let (i, next) = faces
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !used[i])
+            .filter_map(|(i, face)| {
+                if face[0] == last {
+                    Some((i, face[1]))
+                } else if face[1] == last {
+                    Some((i, face[0]))
+                } else {
+                    None
+                }
+            })
+            .next()
+            .ok_or_else(bad_edges)?;
+        used[i] = true;
+        nodes.push(next);
+    }
+    Ok(nodes)
+}
+
+/// The nodes adjacent to `node` along the boundary of a face.
+fn neighbours(face: &[usize], node: usize) -> Vec<usize> {
+    let n = face.len();
+    match face.iter().position(|&i| i == node) {
+        Some(pos) => vec![face[(pos + 1) % n], face[(pos + n - 1) % n]],
+        None => Vec::new(),
+    }
+}
+
+type Point = [f64; 3];
+
+/// Look up the coordinates of nodes, padded with zeros to three dimensions.
+fn points(nodes: &[usize], coords: &[&[f64]]) -> Result<Vec<Point>, String> {
+    nodes
+        .iter()
+        .map(|&i| {
+            let c = coords
+                .get(i)
+                .ok_or_else(|| format!("Undefined node {:x}.", i + 1))?;
+            let mut p = [0.; 3];
+            p[..c.len()].copy_from_slice(c);
+            Ok(p)
+        })
+        .collect()
+}
+
+fn centroid(points: &[Point]) -> Point {
+    let mut c = [0.; 3];
+    for p in points {
+        for k in 0..3 {
+            c[k] += p[k] / points.len() as f64;
+        }
+    }
+    c
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Normal of a polygon by Newell's method.
+fn normal(polygon: &[Point]) -> Point {
+    let mut n = [0.; 3];
+    for (i, p) in polygon.iter().enumerate() {
+        let q = polygon[(i + 1) % polygon.len()];
+        n[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        n[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        n[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{face_vertex::Mesh, Entity, GetMesh};
+
+    #[test]
+    fn two_dimensional_mixed_zone() {
+        // A unit square split into a quadrilateral (cell 1) and a triangle (cell 2), with
+        // the edges given in arbitrary order and orientation.
+        let source = r#"(0 "2d mesh")
+(2 2)
+(10 (1 1 5 1 2)(
+0 0
+1 0
+1 1
+0 1
+2 0))
+(12 (2 1 2 1 0)(3 1))
+(13 (3 1 6 3 2)(
+1 2 1 0
+4 3 0 1
+3 2 2 1
+1 4 0 1
+2 5 2 0
+5 3 0 2))
+(45 (2 fluid surface)())"#;
+        let mut mesh = Mesh::default();
+        FluentDeserializer::deserialize_into(source.as_bytes(), &mut mesh).unwrap();
+        assert_eq!(mesh.metadata().dimension(), 2);
+
+        let cells = &mesh.element_groups()[0];
+        assert_eq!(cells.name().get_original().0, "surface");
+        let nodes: Vec<_> = cells
+            .entities()
+            .iter()
+            .map(|e| e.node_indices().unwrap())
+            .collect();
+        assert_eq!(nodes, vec![vec![0, 1, 2, 3], vec![2, 1, 4]]);
+
+        let faces = &mesh.element_groups()[1];
+        assert_eq!(faces.name().get_original().0, "zone-3");
+        assert_eq!(faces.entities().len(), 6);
+    }
+
+    #[test]
+    fn bad_ranges() {
+        let nodes = "(2 2)(10 (1 1 3 1 2)(0 0 1 0 0 1))";
+        let cases = [
+            // Zones starting at index 0 or ending before their start.
+            ("(10 (1 0 2 1 2)(0 0 1 0 0 1))", "Bad node range 0 to 2"),
+            ("(12 (2 3 1 1 1))", "Bad cell range 3 to 1"),
+            ("(13 (3 1 0 2 2)())", "Bad face range 1 to 0"),
+            // Indices beyond the declared counts.
+            ("(12 (0 1 1 0))(12 (2 1 2 1 1))", "only 1 are declared"),
+            // Faces referring to cells and nodes which don't exist.
+            (
+                "(12 (2 1 1 1 1))(13 (3 1 3 2 2)(1 2 2 0 2 3 1 0 3 1 1 0))",
+                "undefined cell 2",
+            ),
+            (
+                "(12 (2 1 1 1 1))(13 (3 1 3 2 2)(1 2 1 0 2 3 1 0 3 4 1 0))",
+                "undefined node 4",
+            ),
+            // More cells than faces can bound.
+            (
+                "(12 (2 1 ffffffff 1 1))(13 (3 1 1 2 2)(1 2 1 0))",
+                "only 1 faces",
+            ),
+        ];
+        for &(sections, message) in &cases {
+            let source = format!("{}{}", nodes, sections);
+            let mut mesh = Mesh::default();
+            match FluentDeserializer::deserialize_into(source.as_bytes(), &mut mesh) {
+                Err(e @ Error::Syntax(_)) => assert!(e.to_string().contains(message), "{}", e),
+                result => panic!("{}: {:?}", sections, result),
+            }
+        }
+    }
+}
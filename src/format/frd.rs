@@ -0,0 +1,511 @@
+//! Implementation of CalculiX result file (`.frd`) support.
+//!
+//! Definition: CalculiX GraphiX manual, section "Result Format".
+//!
+//! The blocks `2C` (nodes), `3C` (elements) and `100C` (nodal results) are read, in ASCII
+//! (short and long) as well as in binary format. All other records are skipped.
+//!
+//! Nodes become the node group `nodes`, elements are grouped by their material number into
+//! groups `material-<n>` with the material number as reference tag and their
+//! [cell type](::data::CellType), in canonical node ordering. Every result block
+//! becomes an entity of the group `results` (of kind `Other`) with the attributes `name`,
+//! `step`, `time` and `components`, and the values of a result are stored as attribute
+//! `<name>@<step>` of the nodes, like `DISP@1`, with the components separated by spaces.
+
+use data::{
+    attribute::{keys, AttributeContainerMut, AttributeMap, AttributeName},
+    CellType, EntityBox, EntityKind,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::{
+    naming::{Format, Name},
+    ordering,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    iter, str,
+};
+use util::geometry;
+
+/// Cell types of the element types, indexed by type number minus one.
+const ELEMENT_TYPES: &[CellType] = &[
+    CellType::Hexahedron,
+    CellType::Prism,
+    CellType::Tetrahedron,
+    CellType::Hexahedron20,
+    CellType::Prism15,
+    CellType::Tetrahedron10,
+    CellType::Triangle,
+    CellType::Triangle6,
+    CellType::Quadrilateral,
+    CellType::Quadrilateral8,
+    CellType::Line,
+    CellType::Line3,
+];
+
+/// How the records of a block are stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BlockFormat {
+    Short,
+    Long,
+    BinaryFloat,
+    BinaryDouble,
+}
+
+impl BlockFormat {
+    fn parse(field: &str) -> Result<Self, Error> {
+        match field {
+            "" | "0" => Ok(BlockFormat::Short),
+            "1" => Ok(BlockFormat::Long),
+            "2" => Ok(BlockFormat::BinaryFloat),
+            "3" => Ok(BlockFormat::BinaryDouble),
+            _ => Err(Error::Syntax(format!(
+                "Unsupported block format: {}",
+                field
+            ))),
+        }
+    }
+
+    fn is_binary(self) -> bool {
+        self == BlockFormat::BinaryFloat || self == BlockFormat::BinaryDouble
+    }
+
+    /// Width of the entity number in ASCII records.
+    fn number_width(self) -> usize {
+        if self == BlockFormat::Short {
+            5
+        } else {
+            10
+        }
+    }
+}
+
+/// Get the trimmed fixed width field of a record, empty if the record is too short.
+fn field(line: &str, start: usize, width: usize) -> &str {
+    let end = (start + width).min(line.len());
+    line.get(start.min(end)..end).unwrap_or("").trim()
+}
+
+fn parse_field<T: str::FromStr>(line: &str, start: usize, width: usize) -> Result<T, Error> {
+    let value = field(line, start, width);
+    value
+        .parse()
+        .map_err(|_| Error::Syntax(format!("Bad value `{}` in record: {}", value, line)))
+}
+
+/// A cursor over the mixed text and binary content of a file.
+struct Cursor<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> Cursor<'d> {
+    fn line(&mut self) -> Result<Option<&'d str>, Error> {
+        if self.pos >= self.data.len() {
+            return Ok(None);
+        }
+        let rest = &self.data[self.pos..];
+        let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        self.pos += (len + 1).min(rest.len());
+        let line = str::from_utf8(&rest[..len])
+            .map_err(|_| Error::Syntax("Invalid text record.".into()))?;
+        Ok(Some(line.trim_end_matches('\r')))
+    }
+
+    fn next_line(&mut self) -> Result<&'d str, Error> {
+        self.line()?
+            .ok_or_else(|| Error::Syntax("Unexpected EOF.".into()))
+    }
+
+    /// Whether the next line is a record with the key `key`.
+    fn at_key(&self, key: &str) -> bool {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        rest.len() >= 3 && str::from_utf8(&rest[1..3]).ok() == Some(key)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| Error::Syntax("Unexpected EOF in binary block.".into()))?;
+        self.pos += N;
+        let mut array = [0; N];
+        array.copy_from_slice(bytes);
+        Ok(array)
+    }
+
+    fn int(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_le_bytes(self.bytes()?))
+    }
+
+    fn real(&mut self, format: BlockFormat) -> Result<f64, Error> {
+        if format == BlockFormat::BinaryDouble {
+            Ok(f64::from_le_bytes(self.bytes()?))
+        } else {
+            Ok(f64::from(f32::from_le_bytes(self.bytes()?)))
+        }
+    }
+
+    /// Skip the end record of a binary block, which some writers add.
+    fn skip_end(&mut self) -> Result<(), Error> {
+        if self.at_key("-3") {
+            self.next_line()?;
+        }
+        Ok(())
+    }
+}
+
+/// A result block, read into memory.
+struct Result100 {
+    name: String,
+    step: usize,
+    time: f64,
+    components: Vec<String>,
+    values: Vec<(usize, Vec<f64>)>,
+}
+
+/// The contents of a file, read into memory.
+#[derive(Default)]
+struct FrdFile {
+    node_numbers: HashMap<usize, usize>,
+    points: Vec<[f64; 3]>,
+    elements: BTreeMap<usize, Vec<(CellType, Vec<usize>)>>,
+    results: Vec<Result100>,
+}
+
+impl FrdFile {
+    fn node_index(&self, number: usize) -> Result<usize, Error> {
+        self.node_numbers
+            .get(&number)
+            .cloned()
+            .ok_or_else(|| Error::Syntax(format!("Undefined node {}.", number)))
+    }
+
+    fn read_nodes(&mut self, cursor: &mut Cursor, header: &str) -> Result<(), Error> {
+        let num_nodes: usize = parse_field(header, 24, 12)?;
+        let format = BlockFormat::parse(field(header, 73, 2))?;
+        self.points.reserve(num_nodes);
+
+        for _ in 0..num_nodes {
+            let (number, point) = if format.is_binary() {
+                let number = cursor.int()? as usize;
+                let mut point = [0.; 3];
+                for x in point.iter_mut() {
+                    *x = cursor.real(format)?;
+                }
+                (number, point)
+            } else {
+                let line = cursor.next_line()?;
+                let width = format.number_width();
+                let number = parse_field(line, 3, width)?;
+                let mut point = [0.; 3];
+                for (k, x) in point.iter_mut().enumerate() {
+                    *x = parse_field(line, 3 + width + 12 * k, 12)?;
+                }
+                (number, point)
+            };
+            self.node_numbers.insert(number, self.points.len());
+            self.points.push(point);
+        }
+        if format.is_binary() {
+            cursor.skip_end()?;
+        }
+        Ok(())
+    }
+
+    fn read_elements(&mut self, cursor: &mut Cursor, header: &str) -> Result<(), Error> {
+        let num_elements: usize = parse_field(header, 24, 12)?;
+        let format = BlockFormat::parse(field(header, 73, 2))?;
+        let cell_type = |element_type: usize| {
+            ELEMENT_TYPES
+                .get(element_type.wrapping_sub(1))
+                .cloned()
+                .ok_or_else(|| Error::Syntax(format!("Unsupported element type {}.", element_type)))
+        };
+
+        for _ in 0..num_elements {
+            let (cell, material, numbers) = if format.is_binary() {
+                let _number = cursor.int()?;
+                let cell = cell_type(cursor.int()? as usize)?;
+                let _group = cursor.int()?;
+                let material = cursor.int()? as usize;
+                let mut numbers = Vec::new();
+                for _ in 0..cell.num_nodes() {
+                    numbers.push(cursor.int()? as usize);
+                }
+                (cell, material, numbers)
+            } else {
+                let line = cursor.next_line()?;
+                let width = format.number_width();
+                let cell = cell_type(parse_field(line, 3 + width, 5)?)?;
+                let material = parse_field(line, 3 + width + 10, 5)?;
+                let nary = cell.num_nodes();
+                let mut numbers = Vec::with_capacity(nary);
+                while numbers.len() < nary {
+                    let line = cursor.next_line()?;
+                    if field(line, 1, 2) != "-2" {
+                        return Err(Error::Syntax(format!("Expected node record: {}", line)));
+                    }
+                    for k in 0..(nary - numbers.len()).min(10) {
+                        numbers.push(parse_field(line, 3 + width * k, width)?);
+                    }
+                }
+                (cell, material, numbers)
+            };
+            let nodes = numbers
+                .iter()
+                .map(|&n| self.node_index(n))
+                .collect::<Result<Vec<_>, _>>()?;
+            let nodes = ordering::to_canonical(Format::Frd, cell, &nodes);
+            self.elements
+                .entry(material)
+                .or_default()
+                .push((cell, nodes));
+        }
+        if format.is_binary() {
+            cursor.skip_end()?;
+        }
+        Ok(())
+    }
+
+    fn read_results(&mut self, cursor: &mut Cursor, header: &str) -> Result<(), Error> {
+        let time = parse_field(header, 12, 12)?;
+        let num_nodes: usize = parse_field(header, 24, 12)?;
+        let step: usize = parse_field(header, 58, 5)?;
+        let format = BlockFormat::parse(field(header, 73, 2))?;
+
+        let line = cursor.next_line()?;
+        if field(line, 1, 2) != "-4" {
+            return Err(Error::Syntax(format!("Expected dataset record: {}", line)));
+        }
+        let name = field(line, 5, 8).to_string();
+
+        // Components which are calculated by predefined functions (like `ALL`) have no values.
+        let mut components = Vec::new();
+        while cursor.at_key("-5") {
+            let line = cursor.next_line()?;
+            if field(line, 33, 5) != "1" {
+                components.push(field(line, 5, 8).to_string());
+            }
+        }
+
+        let mut values = Vec::with_capacity(num_nodes);
+        for _ in 0..num_nodes {
+            if format.is_binary() {
+                let number = cursor.int()? as usize;
+                let mut node_values = Vec::with_capacity(components.len());
+                for _ in 0..components.len() {
+                    node_values.push(cursor.real(format)?);
+                }
+                values.push((self.node_index(number)?, node_values));
+            } else {
+                let mut line = cursor.next_line()?;
+                let width = format.number_width();
+                let number = parse_field(line, 3, width)?;
+                let mut node_values = Vec::with_capacity(components.len());
+                loop {
+                    for k in 0..(components.len() - node_values.len()).min(6) {
+                        let value = field(line, 3 + width + 12 * k, 12);
+                        node_values.push(value.parse::<f64>().map_err(|_| {
+                            Error::Syntax(format!("Bad value `{}` in record: {}", value, line))
+                        })?);
+                    }
+                    if node_values.len() == components.len() {
+                        break;
+                    }
+                    line = cursor.next_line()?;
+                    if field(line, 1, 2) != "-2" {
+                        return Err(Error::Syntax(format!("Expected continuation: {}", line)));
+                    }
+                }
+                values.push((self.node_index(number)?, node_values));
+            }
+        }
+        if format.is_binary() {
+            cursor.skip_end()?;
+        }
+
+        self.results.push(Result100 {
+            name,
+            step,
+            time,
+            components,
+            values,
+        });
+        Ok(())
+    }
+}
+
+impl FrdFile {
+    fn read<S: Read>(mut source: S) -> Result<FrdFile, Error> {
+        // Read the file into memory.
+        let mut data = Vec::new();
+        source.read_to_end(&mut data)?;
+        let mut cursor = Cursor {
+            data: &data,
+            pos: 0,
+        };
+
+        let mut file = FrdFile::default();
+        while let Some(line) = cursor.line()? {
+            let head = field(line, 0, 6);
+            if head == "9999" {
+                break;
+            }
+            match head {
+                "2C" => file.read_nodes(&mut cursor, line)?,
+                "3C" => file.read_elements(&mut cursor, line)?,
+                "100C" => file.read_results(&mut cursor, line)?,
+                // Headers (1C, 1U, 1P) and the end of ASCII blocks.
+                _ => (),
+            }
+        }
+        Ok(file)
+    }
+
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        // The values of every result, indexed by node.
+        let num_nodes = self.points.len();
+        let mut node_values = Vec::with_capacity(self.results.len());
+        let mut results = Vec::with_capacity(self.results.len());
+        for result in self.results {
+            let mut values = vec![None; num_nodes];
+            for (i, value) in result.values {
+                values[i] = Some(value);
+            }
+            let key = format!("{}@{}", result.name, result.step);
+            node_values.push((AttributeName::from(key), values));
+
+            let mut attr = AttributeMap::new();
+            attr.set("name".into(), result.name.into());
+            attr.set("step".into(), result.step.into());
+            attr.set("time".into(), result.time.into());
+            attr.set("components".into(), result.components.join(" ").into());
+            results.push(Ok(EntityBox::new(EntityKind::Other, attr)));
+        }
+
+        let name = Name::parse("nodes".into(), Format::Frd, EntityKind::Node).unwrap();
+        let nodes = self.points.into_iter().enumerate().map(move |(i, point)| {
+            let mut attr = AttributeMap::new();
+            for (key, values) in &mut node_values {
+                let value = match values[i].take() {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let value = match *value.as_slice() {
+                    [value] => value.into(),
+                    _ => value.into(),
+                };
+                attr.set(key.clone(), value);
+            }
+            Ok(geometry::entity_with_position(
+                EntityKind::Node,
+                &point,
+                attr,
+            ))
+        });
+        let nodes = group_events(name, EntityKind::Node, Vec::new(), nodes);
+
+        let elements = self.elements.into_iter().flat_map(|(material, elements)| {
+            let name = format!("material-{}", material);
+            let name = Name::parse(name, Format::Frd, EntityKind::Element).unwrap();
+            let elements = elements.into_iter().map(move |(cell, nodes)| {
+                let mut attr = AttributeMap::new();
+                attr.set(AttributeName::key(keys::REF), material.into());
+                Ok(geometry::element_with_cell(cell, &nodes, attr))
+            });
+            group_events(name, EntityKind::Element, Vec::new(), elements)
+        });
+
+        let results = if results.is_empty() {
+            None
+        } else {
+            let name = Name::parse("results".into(), Format::Frd, EntityKind::Other).unwrap();
+            Some(group_events(
+                name,
+                EntityKind::Other,
+                Vec::new(),
+                results.into_iter(),
+            ))
+        };
+
+        iter::once(Ok(Event::Dimension(3)))
+            .chain(nodes)
+            .chain(elements)
+            .chain(results.into_iter().flatten())
+    }
+}
+
+pub struct FrdDeserializer {}
+
+impl Deserializer for FrdDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(FrdFile::read(source), FrdFile::events)
+    }
+
+    /// Recognizes the model header block `1C` which starts every file.
+    fn sniff(head: &[u8]) -> Confidence {
+        if head.starts_with(b"    1C") {
+            Confidence::High
+        } else {
+            Confidence::No
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{attribute::AttributeContainer, face_vertex::Mesh, Entity, GetMesh};
+    use nalgebra::Point3;
+
+    fn header(key: &str, count: usize, format: u8) -> String {
+        format!(" {:>4}C{:18}{:12}{:37}{}\n", key, "", count, "", format)
+    }
+
+    #[test]
+    fn binary_blocks() {
+        let mut data = Vec::new();
+        data.extend_from_slice(header("2", 3, 3).as_bytes());
+        for (n, point) in [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]]
+            .iter()
+            .enumerate()
+        {
+            data.extend_from_slice(&(10 * n as i32 + 10).to_le_bytes());
+            for x in point {
+                data.extend_from_slice(&f64::to_le_bytes(*x));
+            }
+        }
+        data.extend_from_slice(header("3", 1, 2).as_bytes());
+        for value in &[1, 7, 0, 5, 10, 20, 30] {
+            data.extend_from_slice(&i32::to_le_bytes(*value));
+        }
+        data.extend_from_slice(
+            b"  100CL  101 2.00000E+00           3                     0    4           2\n",
+        );
+        data.extend_from_slice(b" -4  TEMP        1    1\n");
+        data.extend_from_slice(b" -5  T           1    1    0    0\n");
+        for (n, t) in [(30, 1.5f32), (10, 2.5), (20, 3.5)].iter() {
+            data.extend_from_slice(&i32::to_le_bytes(*n));
+            data.extend_from_slice(&f32::to_le_bytes(*t));
+        }
+        data.extend_from_slice(b" -3\n 9999\n");
+
+        let mut mesh = Mesh::default();
+        FrdDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+        let element = &mesh.element_groups()[0].entities()[0];
+        assert_eq!(
+            mesh.element_groups()[0].name().get_original().0,
+            "material-5"
+        );
+        assert_eq!(element.node_indices().unwrap(), vec![0, 1, 2]);
+        let node = &mesh.node_groups()[0].entities()[2];
+        assert_eq!(node.position(3).unwrap(), Point3::new(0., 1., 0.));
+        assert_eq!(node.attributes().get_f64(&"TEMP@4".into()), Some(1.5));
+        let result = &mesh.other_groups()[0].entities()[0];
+        assert_eq!(result.attributes().get_f64(&"time".into()), Some(2.));
+    }
+}
@@ -0,0 +1,508 @@
+//! Implementation of LS-DYNA keyword file (`.k`) mesh support.
+//!
+//! Definition: LS-DYNA Keyword User's Manual, Volume I.
+//!
+//! The keywords `*NODE`, `*ELEMENT_SHELL`, `*ELEMENT_SOLID`, `*ELEMENT_SOLID_TET10`, `*PART`
+//! and `*SET_NODE_LIST` are understood, with cards in fixed column or free (comma separated)
+//! format. All other keywords are skipped.
+//!
+//! Nodes become the node group `nodes`. Elements are grouped by their part, named after the
+//! heading of the part (or `part-<pid>` if it has none), and carry the part id as attribute
+//! `pid`. Degenerate elements are stored with their distinct nodes, like triangular shells
+//! with three nodes and tetrahedral solids with four. Node sets become groups of kind
+//! `Other`, named after their title (or `set-<sid>`), with one entity per node.
+
+use data::{
+    attribute::{keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName},
+    Entity, EntityKind, GetMesh, GetMeshGroup, SetMesh, SetMeshGroup,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use ser::Serializer;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::{Read, Write},
+    iter,
+};
+use util::{geometry, sniff};
+
+/// Column widths of the cards of `*NODE`.
+const NODE_WIDTHS: &[usize] = &[8, 16, 16, 16, 8, 8];
+/// Column width of element cards.
+const ELEMENT_WIDTH: usize = 8;
+/// Column width of part and set cards.
+const WIDE_WIDTH: usize = 10;
+
+/// Split a card into its fields, either comma separated or in fixed columns of `widths`.
+///
+/// The last width is repeated for the rest of the card.
+fn fields<'c>(card: &'c str, widths: &[usize]) -> Vec<&'c str> {
+    if card.contains(',') {
+        return card.split(',').map(|f| f.trim()).collect();
+    }
+
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut widths = widths
+        .iter()
+        .chain(::std::iter::repeat(&widths[widths.len() - 1]));
+    while start < card.len() {
+        let end = (start + widths.next().unwrap()).min(card.len());
+        fields.push(card.get(start..end).unwrap_or("").trim());
+        start = end;
+    }
+    fields
+}
+
+/// Parse an integer field, blank meaning zero.
+fn int_field(fields: &[&str], i: usize) -> Result<usize, Error> {
+    match fields.get(i).cloned().unwrap_or("") {
+        "" => Ok(0),
+        field => field
+            .parse()
+            .map_err(|_| Error::Syntax(format!("Bad integer field: {}", field))),
+    }
+}
+
+/// Parse a real field, blank meaning zero.
+fn real_field(fields: &[&str], i: usize) -> Result<f64, Error> {
+    match fields.get(i).cloned().unwrap_or("") {
+        "" => Ok(0.),
+        field => field
+            .parse()
+            .map_err(|_| Error::Syntax(format!("Bad real field: {}", field))),
+    }
+}
+
+/// The distinct nodes of a shell or solid given with the nodes `n`, in the usual order.
+fn collapse(n: &[usize]) -> Vec<usize> {
+    match n.len() {
+        4 if n[2] == n[3] => n[..3].to_vec(),
+        8 if n[3..].iter().all(|&i| i == n[3]) => n[..4].to_vec(),
+        8 if n[4..].iter().all(|&i| i == n[4]) => n[..5].to_vec(),
+        // Pentahedra have the triangles (n1, n2, n5) and (n4, n3, n7).
+        8 if n[4] == n[5] && n[6] == n[7] => vec![n[0], n[1], n[4], n[3], n[2], n[6]],
+        _ => n.to_vec(),
+    }
+}
+
+/// The nodes of an eight node solid for an element with the nodes `n`, degenerate if needed.
+fn expand_solid(n: &[usize]) -> Vec<usize> {
+    match n.len() {
+        4 => vec![n[0], n[1], n[2], n[3], n[3], n[3], n[3], n[3]],
+        5 => vec![n[0], n[1], n[2], n[3], n[4], n[4], n[4], n[4]],
+        6 => vec![n[0], n[1], n[4], n[3], n[2], n[2], n[5], n[5]],
+        _ => n.to_vec(),
+    }
+}
+
+/// A part, read into memory.
+#[derive(Default)]
+struct Part {
+    heading: Option<String>,
+    elements: Vec<Vec<usize>>,
+}
+
+/// The contents of a file, read into memory, with node indices instead of node numbers.
+struct LsDynaMesh {
+    points: Vec<[f64; 3]>,
+    parts: BTreeMap<usize, Part>,
+    sets: Vec<(String, Vec<usize>)>,
+}
+
+impl LsDynaMesh {
+    fn read<S: Read>(mut source: S) -> Result<LsDynaMesh, Error> {
+        // Read the file into memory.
+        let mut data = String::new();
+        source.read_to_string(&mut data)?;
+
+        // Split into keywords with their cards, without comments.
+        let mut blocks: Vec<(String, Vec<&str>)> = Vec::new();
+        for line in data.lines() {
+            let line = line.trim_end();
+            if line.starts_with('$') {
+                continue;
+            } else if let Some(keyword) = line.strip_prefix('*') {
+                let keyword = keyword.split_whitespace().next().unwrap_or("");
+                blocks.push((keyword.to_ascii_uppercase(), Vec::new()));
+            } else if let Some(block) = blocks.last_mut() {
+                // Blank cards are significant, like empty part headings.
+                block.1.push(line);
+            } else if !line.is_empty() {
+                return Err(Error::Syntax(format!("Card before keyword: {}", line)));
+            }
+        }
+
+        for block in &mut blocks {
+            while block.1.last().is_some_and(|card| card.trim().is_empty()) {
+                block.1.pop();
+            }
+        }
+
+        let mut node_numbers: HashMap<usize, usize> = HashMap::new();
+        let mut points: Vec<[f64; 3]> = Vec::new();
+        // Elements with node numbers, by part id.
+        let mut parts: BTreeMap<usize, Part> = BTreeMap::new();
+        let mut sets: Vec<(String, Vec<usize>)> = Vec::new();
+
+        for (keyword, cards) in &blocks {
+            let data_cards = cards.iter().filter(|card| !card.trim().is_empty());
+            match keyword.as_str() {
+                "NODE" => {
+                    for card in data_cards {
+                        let f = fields(card, NODE_WIDTHS);
+                        let number = int_field(&f, 0)?;
+                        let point = [real_field(&f, 1)?, real_field(&f, 2)?, real_field(&f, 3)?];
+                        node_numbers.insert(number, points.len());
+                        points.push(point);
+                    }
+                }
+                "ELEMENT_SHELL" | "ELEMENT_SHELL_THICKNESS" | "ELEMENT_SHELL_BETA" => {
+                    // Thickness and angle options have a second card per element.
+                    let step = if keyword == "ELEMENT_SHELL" { 1 } else { 2 };
+                    for card in data_cards.step_by(step) {
+                        let f = fields(card, &[ELEMENT_WIDTH]);
+                        let pid = int_field(&f, 1)?;
+                        let nodes = (2..6).map(|i| int_field(&f, i)).collect::<Result<_, _>>()?;
+                        parts.entry(pid).or_default().elements.push(nodes);
+                    }
+                }
+                "ELEMENT_SOLID" | "ELEMENT_SOLID_TET10" => {
+                    let nary = if keyword == "ELEMENT_SOLID" { 8 } else { 10 };
+                    let mut cards = data_cards;
+                    while let Some(card) = cards.next() {
+                        let f = fields(card, &[ELEMENT_WIDTH]);
+                        let pid = int_field(&f, 1)?;
+                        // Nodes follow on the next card in the two card layout.
+                        let (f, first) = if f.iter().skip(2).all(|f| f.is_empty() || *f == "0") {
+                            let card = cards
+                                .next()
+                                .ok_or_else(|| Error::Syntax("Missing node card.".into()))?;
+                            (fields(card, &[ELEMENT_WIDTH]), 0)
+                        } else {
+                            (f, 2)
+                        };
+                        let nodes = (first..first + nary)
+                            .map(|i| int_field(&f, i))
+                            .collect::<Result<_, _>>()?;
+                        parts.entry(pid).or_default().elements.push(nodes);
+                    }
+                }
+                "PART" => {
+                    for pair in cards.chunks(2) {
+                        if pair.len() < 2 {
+                            return Err(Error::Syntax("Incomplete *PART.".into()));
+                        }
+                        let f = fields(pair[1], &[WIDE_WIDTH]);
+                        let heading = pair[0].trim();
+                        let part = parts.entry(int_field(&f, 0)?).or_default();
+                        if !heading.is_empty() {
+                            part.heading = Some(heading.to_string());
+                        }
+                    }
+                }
+                "SET_NODE_LIST" | "SET_NODE_LIST_TITLE" => {
+                    let mut cards = cards.iter();
+                    let title = if keyword == "SET_NODE_LIST_TITLE" {
+                        cards.next().map(|title| title.trim().to_string())
+                    } else {
+                        None
+                    };
+                    let sid = match cards.next() {
+                        Some(card) => int_field(&fields(card, &[WIDE_WIDTH]), 0)?,
+                        None => return Err(Error::Syntax("Incomplete *SET_NODE_LIST.".into())),
+                    };
+                    let mut numbers = Vec::new();
+                    for card in cards.filter(|card| !card.trim().is_empty()) {
+                        for i in 0..8 {
+                            match int_field(&fields(card, &[WIDE_WIDTH]), i)? {
+                                0 => (),
+                                number => numbers.push(number),
+                            }
+                        }
+                    }
+                    let name = title
+                        .filter(|title| !title.is_empty())
+                        .unwrap_or_else(|| format!("set-{}", sid));
+                    sets.push((name, numbers));
+                }
+                "END" => break,
+                // Like *KEYWORD, *TITLE, materials and sections.
+                _ => (),
+            }
+        }
+
+        let node_index = |number: &usize| {
+            node_numbers
+                .get(number)
+                .cloned()
+                .ok_or_else(|| Error::Syntax(format!("Undefined node {}.", number)))
+        };
+
+        for part in parts.values_mut() {
+            for element in &mut part.elements {
+                *element = collapse(element)
+                    .iter()
+                    .map(node_index)
+                    .collect::<Result<_, _>>()?;
+            }
+        }
+        for (_, set) in &mut sets {
+            for node in set.iter_mut() {
+                *node = node_index(node)?;
+            }
+        }
+
+        Ok(LsDynaMesh {
+            points,
+            parts,
+            sets,
+        })
+    }
+
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let name = Name::parse("nodes".into(), Format::LsDyna, EntityKind::Node).unwrap();
+        let nodes = self.points.into_iter().map(|point| {
+            Ok(geometry::entity_with_position(
+                EntityKind::Node,
+                &point,
+                AttributeMap::new(),
+            ))
+        });
+        let nodes = group_events(name, EntityKind::Node, Vec::new(), nodes);
+
+        let parts = self.parts.into_iter().flat_map(|(pid, part)| {
+            let name = part.heading.unwrap_or_else(|| format!("part-{}", pid));
+            let name = Name::parse(name, Format::LsDyna, EntityKind::Element).unwrap();
+            let elements = part.elements.into_iter().map(move |nodes| {
+                let mut attr = AttributeMap::new();
+                attr.set("pid".into(), pid.into());
+                Ok(geometry::entity_with_nodes(
+                    EntityKind::Element,
+                    &nodes,
+                    attr,
+                ))
+            });
+            group_events(name, EntityKind::Element, Vec::new(), elements)
+        });
+
+        let sets = self.sets.into_iter().flat_map(|(name, nodes)| {
+            let name = Name::parse(name, Format::LsDyna, EntityKind::Other).unwrap();
+            let nodes = nodes.into_iter().map(|node| {
+                Ok(geometry::entity_with_nodes(
+                    EntityKind::Other,
+                    &[node],
+                    AttributeMap::new(),
+                ))
+            });
+            group_events(name, EntityKind::Other, Vec::new(), nodes)
+        });
+
+        iter::once(Ok(Event::Dimension(3)))
+            .chain(nodes)
+            .chain(parts)
+            .chain(sets)
+    }
+}
+
+pub struct LsDynaDeserializer {}
+
+impl Deserializer for LsDynaDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(LsDynaMesh::read(source), LsDynaMesh::events)
+    }
+
+    /// Recognizes the `*KEYWORD` card which starts most input decks, or any other keyword.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let first = sniff::lines(&text, "$").next();
+        match first {
+            Some(line) if line.to_ascii_uppercase().starts_with("*KEYWORD") => Confidence::High,
+            Some(line) if line.starts_with('*') => Confidence::Low,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// Format a real number to fit into `width` columns.
+fn real(x: f64, width: usize) -> String {
+    let s = x.to_string();
+    if s.len() <= width {
+        s
+    } else {
+        format!("{:.*e}", width.saturating_sub(8).max(1), x)
+    }
+}
+
+/// A part to be written.
+struct OutputPart {
+    name: String,
+    pid: usize,
+    /// Shells, solids and ten node tetrahedra.
+    elements: [Vec<Vec<usize>>; 3],
+}
+
+#[derive(Default)]
+pub struct LsDynaSerializer {}
+
+impl LsDynaSerializer {
+    pub fn new() -> Self {
+        LsDynaSerializer {}
+    }
+}
+
+impl Serializer for LsDynaSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "LS-DYNA does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        let mut parts: Vec<OutputPart> = Vec::new();
+        let mut used_pids = BTreeSet::new();
+        let mut sets = Vec::new();
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            let name = metadata.name().get_original().0.to_string();
+            match metadata.kind() {
+                EntityKind::Element => {
+                    let mut pid = None;
+                    let mut elements: [Vec<Vec<usize>>; 3] = Default::default();
+                    for element in group {
+                        let nodes = element.node_indices()?;
+                        if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                            return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                        }
+                        if pid.is_none() {
+                            pid = element
+                                .attributes()
+                                .get_usize(&AttributeName::from("pid"))
+                                .filter(|pid| !used_pids.contains(pid));
+                        }
+                        let shell = match nodes.len() {
+                            3 => true,
+                            4 => dimension == 2 || geometry::is_flat(&nodes, &points),
+                            5 | 6 | 8 | 10 => false,
+                            n => {
+                                return Err(Error::Unsupported(format!(
+                                    "LS-DYNA writer does not support elements with {} nodes.",
+                                    n
+                                )))
+                            }
+                        };
+                        match (shell, nodes.len()) {
+                            (true, _) => elements[0].push(nodes),
+                            (false, 10) => elements[2].push(nodes),
+                            (false, _) => elements[1].push(nodes),
+                        }
+                    }
+                    let pid =
+                        pid.unwrap_or_else(|| (1..).find(|pid| !used_pids.contains(pid)).unwrap());
+                    used_pids.insert(pid);
+                    parts.push(OutputPart {
+                        name,
+                        pid,
+                        elements,
+                    });
+                }
+                EntityKind::Other => {
+                    let mut nodes = Vec::new();
+                    for entity in group {
+                        match entity.node_indices() {
+                            Ok(ref indices) if indices.len() == 1 => nodes.push(indices[0]),
+                            // Not a node set.
+                            _ => {
+                                nodes.clear();
+                                break;
+                            }
+                        }
+                    }
+                    if !nodes.is_empty() {
+                        sets.push((name, nodes));
+                    }
+                }
+                EntityKind::Node | EntityKind::Vector => (),
+            }
+        }
+
+        writeln!(target, "*KEYWORD")?;
+        writeln!(target, "$ LS-DYNA keyword file, generated by multimesh")?;
+
+        writeln!(target, "*NODE")?;
+        writeln!(
+            target,
+            "$#   nid               x               y               z"
+        )?;
+        for (i, point) in points.iter().enumerate() {
+            writeln!(
+                target,
+                "{:>8}{:>16}{:>16}{:>16}",
+                i + 1,
+                real(point[0], 16),
+                real(point[1], 16),
+                real(point[2], 16)
+            )?;
+        }
+
+        for part in &parts {
+            writeln!(target, "*PART")?;
+            writeln!(target, "{}", part.name)?;
+            writeln!(target, "{:>10}{:>10}{:>10}", part.pid, 1, 1)?;
+        }
+
+        let mut eid = 0;
+        let keywords = ["*ELEMENT_SHELL", "*ELEMENT_SOLID", "*ELEMENT_SOLID_TET10"];
+        for (k, keyword) in keywords.iter().enumerate() {
+            if parts.iter().all(|part| part.elements[k].is_empty()) {
+                continue;
+            }
+            writeln!(target, "{}", keyword)?;
+            for part in &parts {
+                for nodes in &part.elements[k] {
+                    eid += 1;
+                    let nodes = match k {
+                        0 if nodes.len() == 3 => vec![nodes[0], nodes[1], nodes[2], nodes[2]],
+                        1 => expand_solid(nodes),
+                        _ => nodes.clone(),
+                    };
+                    write!(target, "{:>8}{:>8}", eid, part.pid)?;
+                    if k == 2 {
+                        writeln!(target)?;
+                    }
+                    for node in nodes {
+                        write!(target, "{:>8}", node + 1)?;
+                    }
+                    writeln!(target)?;
+                }
+            }
+        }
+
+        for (sid, (name, nodes)) in sets.iter().enumerate() {
+            writeln!(target, "*SET_NODE_LIST_TITLE")?;
+            writeln!(target, "{}", name)?;
+            writeln!(target, "{:>10}", sid + 1)?;
+            for chunk in nodes.chunks(8) {
+                for node in chunk {
+                    write!(target, "{:>10}", node + 1)?;
+                }
+                writeln!(target)?;
+            }
+        }
+
+        writeln!(target, "*END")?;
+
+        Ok(())
+    }
+}
@@ -1,33 +1,88 @@
 //! Implementation of MEDIT mesh format support.
 //!
 //! Defined in [ISSN 0249-0803](https://www.ljll.math.upmc.fr/frey/publications/RT-0253.pdf) (PDF).
+//! `Pyramids` and `Prisms` are understood as well, like by libMeshb.
+//!
+//! Medit names element sections by the type of their elements, so the serializer writes the
+//! elements of all groups into one section per cell type, keeping their reference tags.
+//! Quadratic elements are written as linear ones of their corners, references which are no
+//! integers as `0`, and groups of vectors and other entities without an equivalent section are
+//! skipped, all with a warning.
+//!
+//! Binary files (`.meshb`) as written by libMeshb are read by [MeditBinaryDeserializer], or
+//! viewed in place by [MeditView], for example from a [memory mapped file](::de::MappedFile).
 
 use data::{
-    attribute::{AttributeName},
-    face_vertex,
-    mesh::{ReadElement, ReadNode},
-    GroupData, GroupKind,
+    attribute::{keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName},
+    view::Strided,
+    CellType, Entity, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata,
+    SetMesh, SetMeshGroup,
 };
+use de::{Confidence, Deserializer, Event, Events};
 use error::Error;
-use nalgebra::DVector;
-use naming::{Format, Name};
+use format::naming::{Format, Name};
+use ser::Serializer;
 use std::{
     borrow::Cow,
-    io::{Read, Write},
+    collections::VecDeque,
+    convert::TryFrom,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+use util::{
+    geometry,
+    item_reader::{ItemReader, ItemReaderError, ParseItem},
+    parallel, sniff,
 };
-use util::item_reader::ItemReader;
-
-fn element_nary(element_name: &str) -> Option<usize> {
-    match element_name {
-        "Edges" => Some(2),
-        "Triangles" => Some(3),
-        "Quadrilaterals" => Some(4),
-        "Tetrahedra" => Some(4),
-        "Hexahedra" => Some(8),
+
+/// The element sections, in the order they are written, with the cell type of their elements.
+const ELEMENT_SECTIONS: &[(&str, CellType)] = &[
+    ("Edges", CellType::Line),
+    ("Triangles", CellType::Triangle),
+    ("Quadrilaterals", CellType::Quadrilateral),
+    ("Tetrahedra", CellType::Tetrahedron),
+    ("Pyramids", CellType::Pyramid),
+    ("Prisms", CellType::Prism),
+    ("Hexahedra", CellType::Hexahedron),
+];
+
+/// The reference tag of an entity as integer, counting those which are no integers in
+/// `skipped` and writing them as `0`.
+fn reference<E: Entity>(entity: &E, skipped: &mut usize) -> i64 {
+    match geometry::reference(entity) {
+        Some(reference) => reference.to_i64().unwrap_or_else(|| {
+            *skipped += 1;
+            0
+        }),
+        None => 0,
+    }
+}
+
+fn warn_skipped_references(skipped: usize) {
+    if skipped > 0 {
+        warn!(
+            "Medit references are integers, wrote 0 instead of {} other references.",
+            skipped
+        );
+    }
+}
+
+fn element_cell(element_name: &str) -> Option<CellType> {
+    ELEMENT_SECTIONS
+        .iter()
+        .find(|&&(name, _)| name == element_name)
+        .map(|&(_, cell)| cell)
+}
+
+fn other_nary(other_name: &str) -> Option<usize> {
+    match other_name {
+        "Ridges" | "RequiredEdges" | "Corners" | "RequiredVertices" => Some(1),
+        "NormalAtVertices" => Some(2),
+        "NormalAtTriangleVertices" | "NormalAtQuadrilateralVertices" | "TangentAtEdges" => Some(3),
         _ => None,
     }
 }
 
+#[derive(Default)]
 pub struct MeditSerializer {}
 
 impl MeditSerializer {
@@ -35,89 +90,194 @@ impl MeditSerializer {
         MeditSerializer {}
     }
 
-    fn serialize_group<G, GS, W, F, FR>(
-        &self,
-        groups: GS,
+    /// Write all node groups of the mesh into one `Vertices` section.
+    fn serialize_nodes<M, W>(mesh: &M, mut target: W, mesh_dim: u8) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let num_nodes: usize = mesh
+            .groups()
+            .map(|group| group.metadata())
+            .filter(|metadata| metadata.kind() == EntityKind::Node)
+            .map(|metadata| metadata.len())
+            .sum();
+        if num_nodes == 0 {
+            return Ok(());
+        }
+
+        writeln!(target, "Vertices\n{}", num_nodes)?;
+        let mut skipped = 0;
+        for group in mesh.groups() {
+            if group.metadata().kind() != EntityKind::Node {
+                continue;
+            }
+
+            for node in group {
+                let p = node.position(mesh_dim as usize)?;
+                for x in p.iter().take(mesh_dim as usize) {
+                    write!(target, "{} ", x)?;
+                }
+                writeln!(target, "{}", reference(&node, &mut skipped))?;
+            }
+        }
+        writeln!(target)?;
+        warn_skipped_references(skipped);
+
+        Ok(())
+    }
+
+    fn serialize_groups<M, W, F>(
+        mesh: &M,
+        kind: EntityKind,
         mut target: W,
         serialize_item: F,
-    ) -> <Self as Serializer>::Result
+    ) -> Result<(), Error>
     where
-        G: SerializableGroup,
-        GS: Iterator<Item = G>,
+        M: GetMesh,
         W: Write,
-        F: Fn(&mut W, <G as SerializableGroup>::Item, &str) -> FR,
-        FR: Into<<Self as Serializer>::Result>,
+        F: Fn(&mut W, M::Entity, &str) -> Result<(), Error>,
     {
-        for group in groups {
+        for group in mesh.groups() {
             let group_metadata = group.metadata();
-            // TODO: Rename error to "InvalidGroup".
-            let group_name: Cow<str> = group_metadata
-                .name()
-                .get_as(Format::Medit)
-                .ok_or_else(|| format_err!("Element group without name."))?;
+            if group_metadata.kind() != kind {
+                continue;
+            }
+
+            let group_name: Cow<str> = match group_metadata.name().get_as(Format::Medit) {
+                Ok(name) => name,
+                Err(Error::NoEquivalentName(reason)) => {
+                    warn!("Skipped group: {}", reason);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
 
-            writeln!(target, "{}\n{}", group_name, group.len())?;
+            writeln!(target, "{}\n{}", group_name, group_metadata.len())?;
 
-            for i in 0..group_metadata.len() {
-                // TODO: Remove unwrap, this is an invariant violation error.
-                let item = group.item_at(i).unwrap();
-                serialize_item(&mut target, item, &group_name).into()?;
+            for item in group {
+                serialize_item(&mut target, item, &group_name)?;
             }
 
-            writeln!(target, "")?;
+            writeln!(target)?;
         }
 
         Ok(())
     }
 
-    fn serialize_node<N, W>(node: N, mut target: W, mesh_dim: u8) -> <Self as Serializer>::Result
+    fn serialize_vector<E: Entity, W: Write>(
+        vector: E,
+        mut target: W,
+        mesh_dim: u8,
+    ) -> Result<(), Error> {
+        let p = vector.components(mesh_dim as usize)?;
+        let strs: Vec<String> = p
+            .iter()
+            .take(mesh_dim as usize)
+            .map(|x| x.to_string())
+            .collect();
+        writeln!(target, "{}", strs.join(" "))?;
+        Ok(())
+    }
+
+    /// Write the elements of all groups into one section per cell type.
+    fn serialize_elements<M, W>(mesh: &M, mut target: W, mesh_dim: u8) -> Result<(), Error>
     where
-        N: ReadNode,
+        M: GetMesh,
         W: Write,
     {
-        let p = node.position()?;
-        let attr = node
-            .attr(&AttrName::Index(0))?
-            .unwrap_or(Cow::Borrowed("0"));
-        if mesh_dim == 2 {
-            writeln!(target, "{} {} {}", p[0], p[1], attr)?;
-        } else if mesh_dim == 3 {
-            writeln!(target, "{} {} {} {}", p[0], p[1], p[2], attr)?;
-        } else {
-            // TODO
-            panic!("unsupported");
+        let dimension = mesh_dim as usize;
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        // The section of every element, so that each section can be written in a pass of its
+        // own without buffering the elements.
+        let mut sections: Vec<u8> = Vec::new();
+        let mut counts = vec![0; ELEMENT_SECTIONS.len()];
+        let mut linearized = 0;
+        for group in mesh.groups() {
+            if group.metadata().kind() != EntityKind::Element {
+                continue;
+            }
+
+            for element in group {
+                let nodes = element.node_indices()?;
+                if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                    return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                }
+                let mut cell = geometry::cell_type(&element, &nodes, &points, dimension)?;
+                if cell.is_quadratic() {
+                    cell = cell.linear();
+                    linearized += 1;
+                }
+                let section = ELEMENT_SECTIONS
+                    .iter()
+                    .position(|&(_, c)| c == cell)
+                    .ok_or_else(|| {
+                        Error::Unsupported(format!(
+                            "Medit does not support cells of type {}.",
+                            cell.name()
+                        ))
+                    })?;
+                counts[section] += 1;
+                sections.push(section as u8);
+            }
+        }
+        if linearized > 0 {
+            warn!(
+                "Medit has no quadratic cells, wrote the corners of {} of them.",
+                linearized
+            );
         }
+
+        let mut skipped = 0;
+        for (section, &(name, cell)) in ELEMENT_SECTIONS.iter().enumerate() {
+            if counts[section] == 0 {
+                continue;
+            }
+
+            writeln!(target, "{}\n{}", name, counts[section])?;
+            let elements = mesh
+                .groups()
+                .filter(|group| group.metadata().kind() == EntityKind::Element)
+                .flatten();
+            for (element, _) in elements
+                .zip(&sections)
+                .filter(|&(_, &s)| s as usize == section)
+            {
+                for i in &element.node_indices()?[..cell.num_nodes()] {
+                    write!(target, "{} ", i + 1)?;
+                }
+                writeln!(target, "{}", reference(&element, &mut skipped))?;
+            }
+            writeln!(target)?;
+        }
+        warn_skipped_references(skipped);
+
         Ok(())
     }
 
-    fn serialize_element<E, W>(
-        element: E,
+    fn serialize_other<E: Entity, W: Write>(
+        other: E,
         mut target: W,
         nary: usize,
-    ) -> <Self as Serializer>::Result
-    where
-        E: ReadElement,
-        W: Write,
-    {
-        let is = element.node_indices()?.unwrap();
-        let attr = element
-            .attr(&AttrName::Index(0))?
-            .unwrap_or(Cow::Borrowed("0"));
-
-        for j in 0..nary {
-            write!(target, "{} ", is[j])?;
+    ) -> Result<(), Error> {
+        let mut values = Vec::with_capacity(nary);
+        for i in 0..nary {
+            let value = other
+                .attributes()
+                .get(&AttributeName::Index(i))
+                .ok_or_else(|| Error::BrokenInvariant(format!("Missing attribute {}.", i)))?;
+            values.push(value.to_string());
         }
-        writeln!(target, "{}", attr)?;
+        writeln!(target, "{}", values.join(" "))?;
         Ok(())
     }
 }
 
 impl Serializer for MeditSerializer {
-    type Result = Result<(), ::failure::Error>;
-
-    fn serialize<M, W>(&self, mesh: M, mut target: W) -> Self::Result
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
     where
-        M: SerializableMesh,
+        M: GetMesh,
         W: Write,
     {
         // TODO: include version information of crate
@@ -126,16 +286,23 @@ impl Serializer for MeditSerializer {
 
         // Get dimensionality.
         let mesh_dim = mesh.metadata().dimension();
-        // TODO: handle other dimensions, remove assert
-        assert!(mesh_dim == 2 || mesh_dim == 3);
+        if mesh_dim != 2 && mesh_dim != 3 {
+            return Err(Error::Unsupported(format!(
+                "Medit does not support meshes of dimension {}.",
+                mesh_dim
+            )));
+        }
         writeln!(target, "Dimension {}\n", mesh_dim)?;
 
-        self.serialize_group(mesh.node_groups(), &mut target, |tgt, node, _| {
-            Self::serialize_node(node, tgt, mesh_dim)
+        Self::serialize_nodes(mesh, &mut target, mesh_dim)?;
+        Self::serialize_elements(mesh, &mut target, mesh_dim)?;
+        Self::serialize_groups(mesh, EntityKind::Vector, &mut target, |tgt, vector, _| {
+            Self::serialize_vector(vector, tgt, mesh_dim)
         })?;
-        self.serialize_group(mesh.element_groups(), &mut target, |tgt, element, name| {
-            let nary = element_nary(name).unwrap();
-            Self::serialize_element(element, tgt, nary)
+        Self::serialize_groups(mesh, EntityKind::Other, &mut target, |tgt, other, name| {
+            let nary = other_nary(name)
+                .ok_or_else(|| Error::BrokenInvariant(format!("Unknown entity: {}", name)))?;
+            Self::serialize_other(other, tgt, nary)
         })?;
 
         writeln!(target, "End")?;
@@ -146,171 +313,212 @@ impl Serializer for MeditSerializer {
 
 pub struct MeditDeserializer {}
 
-impl Deserializer for MeditDeserializer {
-    fn deserialize_into<S, T>(mut source: S, mut target: T) -> Result<(), Error>
-    where
-        S: Read,
-        T: DeserializeMesh,
-    {
-        // Read the file into memory.
-        let mut data = String::new();
-        source.read_to_string(&mut data)?;
-        let mut reader = ItemReader::new(data.as_ref());
+/// Reads Medit files like [MeditDeserializer], but parses the vertices on the thread pool of
+/// rayon.
+///
+/// Worth it for large files, where parsing the coordinates dominates. The entities and errors
+/// are the same as with [MeditDeserializer].
+pub struct ParallelMeditDeserializer {}
+
+/// The kind of a section, which determines how its entities are read.
+#[derive(Clone, Copy)]
+enum Section {
+    /// Nodes (with reference) or vectors, with one component per dimension.
+    Points(EntityKind),
+    Elements(CellType),
+    /// Other entities with the given number of attributes.
+    Others(usize),
+}
+
+/// The events of a Medit file, read entity by entity.
+struct MeditEvents<R> {
+    reader: ItemReader<R>,
+    dimension: usize,
+    /// The open section with its number of remaining entities.
+    section: Option<(Section, usize)>,
+    done: bool,
+    /// Whether vertices are parsed in parallel, see [ParallelMeditDeserializer].
+    parallel: bool,
+    /// Entities which were parsed in parallel, followed by the error which stopped parsing.
+    parsed: VecDeque<EntityBox>,
+    error: Option<Error>,
+}
+
+fn next_parse<R: BufRead, T: ParseItem>(reader: &mut ItemReader<R>) -> Result<T, Error> {
+    Ok(reader.next_parse()?)
+}
+
+/// Parse the next item, which has to exist.
+fn next_attribute<R: BufRead, T: ParseItem>(
+    reader: &mut ItemReader<R>,
+    missing: &str,
+) -> Result<T, Error> {
+    match reader.next_parse() {
+        Err(ItemReaderError::UnexpectedEof) => Err(Error::Syntax(missing.into())),
+        result => Ok(result?),
+    }
+}
+
+/// The section started by `keyword`, with the kind of its entities.
+fn keyword_section(keyword: &str) -> Option<(Section, EntityKind)> {
+    match keyword {
+        "Vertices" => Some((Section::Points(EntityKind::Node), EntityKind::Node)),
+        "Normals" | "Tangents" => Some((Section::Points(EntityKind::Vector), EntityKind::Vector)),
+        _ => element_cell(keyword)
+            .map(|cell| (Section::Elements(cell), EntityKind::Element))
+            .or_else(|| other_nary(keyword).map(|n| (Section::Others(n), EntityKind::Other))),
+    }
+}
+
+/// The number of items of every entity of a section.
+fn section_width(section: Section, dimension: usize) -> usize {
+    match section {
+        Section::Points(EntityKind::Node) => dimension + 1,
+        Section::Points(_) => dimension,
+        Section::Elements(cell) => cell.num_nodes() + 1,
+        Section::Others(n_attrs) => n_attrs,
+    }
+}
+
+fn read_entity<R: BufRead>(
+    reader: &mut ItemReader<R>,
+    dimension: usize,
+    section: Section,
+) -> Result<EntityBox, Error> {
+    let mut attr = AttributeMap::new();
+    match section {
+        Section::Points(kind) => {
+            let mut position = [0.; 3];
+            for x in position[..dimension].iter_mut() {
+                *x = next_parse(reader)?;
+            }
+            if kind == EntityKind::Node {
+                let reference: i64 =
+                    next_attribute(reader, "Missing expected attribute for vertex.")?;
+                attr.set(AttributeName::key(keys::REF), reference.into());
+            }
+            Ok(geometry::entity_with_position(
+                kind,
+                &position[..dimension],
+                attr,
+            ))
+        }
+        Section::Elements(cell) => {
+            let mut indices = vec![0; cell.num_nodes()];
+            for index in indices.iter_mut() {
+                let i: usize = next_parse(reader)?;
+                if i == 0 {
+                    return Err(Error::Syntax("Vertex index 0 in element.".into()));
+                }
+                *index = i - 1;
+            }
+            let reference: i64 = next_attribute(reader, "Missing expected attribute for Element.")?;
+            attr.set(AttributeName::key(keys::REF), reference.into());
+            Ok(geometry::element_with_cell(cell, &indices, attr))
+        }
+        Section::Others(n_attrs) => {
+            for i in 0..n_attrs {
+                let value: u64 =
+                    next_attribute(reader, "Missing expected attribute for Other entity.")?;
+                attr.set(AttributeName::Index(i), value.into());
+            }
+            Ok(EntityBox::new(EntityKind::Other, attr))
+        }
+    }
+}
+
+impl<R: BufRead> MeditEvents<R> {
+    fn new(reader: ItemReader<R>, parallel: bool) -> Self {
+        MeditEvents {
+            reader,
+            dimension: 0,
+            section: None,
+            done: false,
+            parallel,
+            parsed: VecDeque::new(),
+            error: None,
+        }
+    }
 
-        // Read data.
-        let mut dimension: usize = 0;
-        let mut parsing_uid: u64 = 0;
+    fn next_parse<T: ParseItem>(&mut self) -> Result<T, Error> {
+        next_parse(&mut self.reader)
+    }
 
-        while let Some(keyword) = reader.next() {
-            match keyword {
+    /// Read the next entity of a section with `remaining` entities left.
+    fn next_entity(&mut self, section: Section, remaining: usize) -> Result<EntityBox, Error> {
+        if self.parallel && self.parsed.is_empty() && self.error.is_none() {
+            if let Section::Points(EntityKind::Node) = section {
+                let dimension = self.dimension;
+                let parsed = parallel::read_parallel(
+                    &mut self.reader,
+                    remaining,
+                    section_width(section, dimension),
+                    |reader| read_entity(reader, dimension, section),
+                );
+                self.parsed.extend(parsed.entities);
+                self.error = parsed.error;
+            }
+        }
+        match self.parsed.pop_front() {
+            Some(entity) => Ok(entity),
+            None => match self.error.take() {
+                Some(e) => Err(e),
+                None => read_entity(&mut self.reader, self.dimension, section),
+            },
+        }
+    }
+
+    /// Open a section, whose number of entities follows.
+    fn start_section(
+        &mut self,
+        keyword: &str,
+        section: Section,
+        kind: EntityKind,
+    ) -> Result<Event, Error> {
+        let len: usize = self.next_parse()?;
+        self.section = Some((section, len));
+        Ok(Event::GroupStart {
+            name: Name::parse(keyword.into(), Format::Medit, kind).unwrap(),
+            kind,
+            len: Some(len),
+        })
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some((section, remaining)) = self.section {
+            if remaining == 0 {
+                self.section = None;
+                return Ok(Some(Event::GroupEnd));
+            }
+            self.section = Some((section, remaining - 1));
+            return self
+                .next_entity(section, remaining)
+                .map(|e| Some(Event::Entity(e)));
+        }
+
+        loop {
+            let keyword = match self.reader.next()? {
+                Some(keyword) => keyword.to_string(),
+                None => return Ok(None),
+            };
+            match keyword.as_str() {
                 "MeshVersionFormatted" => {
-                    let version: &str = reader.next_result()?;
+                    let version: String = self.next_parse()?;
                     if version != "1" {
                         return Err(Error::Syntax(format!("Unsupported version: {}", version)));
                     }
                 }
                 "Dimension" => {
-                    dimension = reader
-                        .next_result()?
-                        .parse()
+                    self.dimension = self
+                        .next_parse()
                         .map_err(|_| Error::Syntax("Unexpected EOF after 'Dimension'.".into()))?;
-                    target.de_dimension(dimension as u8);
-                }
-                "Vertices" | "Normals" | "Tangents" => {
-                    if dimension != 3 {
-                        return Err(Error::Syntax("Bad dimension (must be 3).".into()));
-                    }
-
-                    let num_nodes: usize = reader.next_parse()?;
-
-                    parsing_uid += 1;
-                    let group_kind = if keyword == "Vertices" {
-                        GroupKind::Node
-                    } else {
-                        GroupKind::Vector
-                    };
-                    let group_name =
-                        Name::parse(keyword.into(), Format::Medit, group_kind).unwrap();
-                    let group =
-                        GroupData::new(parsing_uid, group_name, Some(num_nodes), group_kind);
-                    target.de_group_begin(&group)?;
-
-                    for _ in 0..num_nodes {
-                        let mut position = DVector::<f64>::zeros(dimension);
-                        for i in 0..dimension {
-                            position[i] = reader.next_parse()?;
-                        }
-                        let mut attr = Attr::new();
-                        if keyword == "Vertices" {
-                            attr.insert(
-                                AttrName::Index(0),
-                                reader
-                                    .next()
-                                    .ok_or_else(|| {
-                                        Error::Syntax(
-                                            "Missing expected attribute for vertex.".into(),
-                                        )
-                                    })?
-                                    .into(),
-                            );
-                            // TODO: the double reference is not that optimal
-                            target.de_node(&&face_vertex::Node { position, attr }, &group)?;
-                        } else {
-                            target.de_vector(
-                                &&face_vertex::Vector {
-                                    components: position,
-                                    attr,
-                                },
-                                &group,
-                            )?;
-                        }
+                    if self.dimension != 2 && self.dimension != 3 {
+                        return Err(Error::Syntax(format!("Bad dimension: {}", self.dimension)));
                     }
-
-                    target.de_group_end(&group)?;
+                    return Ok(Some(Event::Dimension(self.dimension as u8)));
                 }
-                "Edges" | "Triangles" | "Quadrilaterals" | "Tetrahedra" | "Hexahedra" => {
-                    let num_elements: usize = reader.next_parse()?;
-                    // Note: Should never fail by definition of `element_nary`.
-                    let nary = element_nary(keyword).unwrap();
-
-                    parsing_uid += 1;
-                    let group_name =
-                        Name::parse(keyword.into(), Format::Medit, GroupKind::Element).unwrap();
-                    let group = GroupData::new(
-                        parsing_uid,
-                        group_name,
-                        Some(num_elements),
-                        GroupKind::Element,
-                    );
-                    target.de_group_begin(&group)?;
-
-                    for _ in 0..num_elements {
-                        let mut indices = DVector::<usize>::from_element(nary, 0);
-                        for i_no in 0..nary {
-                            indices[i_no] = reader.next_parse()?;
-                        }
-                        let mut attr = Attr::new();
-                        attr.insert(
-                            AttrName::Index(0),
-                            reader
-                                .next()
-                                .ok_or_else(|| {
-                                    Error::Syntax("Missing expected attribute for Element.".into())
-                                })?
-                                .into(),
-                        );
-                        target.de_element(&(indices, attr), &group)?;
-                    }
-
-                    target.de_group_end(&group)?;
-                }
-                "Ridges"
-                | "RequiredEdges"
-                | "Corners"
-                | "RequiredVertices"
-                | "NormalAtVertices"
-                | "NormalAtTriangleVertices"
-                | "NormalAtQuadrilateralVertices"
-                | "TangentAtEdges" => {
-                    let num_elements: usize = reader.next_parse()?;
-                    let n_attrs = match keyword {
-                        "Ridges" | "RequiredEdges" | "Corners" | "RequiredVertices" => 1,
-                        "NormalAtVertices" => 2,
-                        _ => 3,
-                    };
-
-                    parsing_uid += 1;
-                    // TODO
-                    let group_name =
-                        Name::parse(keyword.into(), Format::Medit, GroupKind::Other).unwrap();
-                    let group = GroupData::new(
-                        parsing_uid,
-                        group_name,
-                        Some(num_elements),
-                        GroupKind::Other,
-                    );
-
-                    target.de_group_begin(&group)?;
-
-                    for _ in 0..num_elements {
-                        let mut attr = Attr::new();
-                        for i in 0..n_attrs {
-                            attr.insert(
-                                AttrName::Index(i),
-                                reader
-                                    .next()
-                                    .ok_or_else(|| {
-                                        Error::Syntax(
-                                            "Missing expected attribute for Other entity.".into(),
-                                        )
-                                    })?
-                                    .into(),
-                            );
-                        }
-                        target.de_entity(&&face_vertex::Entity { attr }, &group)?;
-                    }
-
-                    target.de_group_end(&group)?;
+                "Vertices" | "Normals" | "Tangents" if self.dimension == 0 => {
+                    return Err(Error::Syntax(format!("{} before Dimension.", keyword)));
                 }
                 "End" => {
                     // TODO: Maybe it would be better to set a flag and check
@@ -318,10 +526,12 @@ impl Deserializer for MeditDeserializer {
                     // is that there might be an obscure convention where someone
                     // puts different data after the end keyword, or if reading
                     // from a stream of multiple medit meshes.
-                    return Ok(());
+                    return Ok(None);
                 }
                 other => {
-                    if other.trim().is_empty() || other.starts_with("#") {
+                    if let Some((section, kind)) = keyword_section(other) {
+                        return self.start_section(other, section, kind).map(Some);
+                    } else if other.trim().is_empty() || other.starts_with('#') {
                         // Ignore.
                     } else {
                         return Err(Error::Syntax(format!("Unsupported keyword: {}", other)));
@@ -329,7 +539,901 @@ impl Deserializer for MeditDeserializer {
                 }
             }
         }
+    }
+}
+
+impl<R: BufRead> Iterator for MeditEvents<R> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event();
+        self.done = !matches!(event, Ok(Some(_)));
+        match event {
+            Ok(event) => event.map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl Deserializer for MeditDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        let reader = ItemReader::new(BufReader::new(source));
+        Box::new(MeditEvents::new(reader, false))
+    }
+
+    /// Recognizes the `MeshVersionFormatted` keyword which starts every file.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let first = sniff::lines(&text, "#").next();
+        match first {
+            Some(line) if line.starts_with("MeshVersionFormatted") => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+impl Deserializer for ParallelMeditDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        let reader = ItemReader::new(BufReader::new(source));
+        Box::new(MeditEvents::new(reader, true))
+    }
+
+    fn sniff(head: &[u8]) -> Confidence {
+        MeditDeserializer::sniff(head)
+    }
+}
+
+/// Keyword codes of binary files, as defined by libMeshb, with the keywords of text files.
+const KEYWORD_CODES: &[(i32, &str)] = &[
+    (3, "Dimension"),
+    (4, "Vertices"),
+    (5, "Edges"),
+    (6, "Triangles"),
+    (7, "Quadrilaterals"),
+    (8, "Tetrahedra"),
+    (9, "Prisms"),
+    (10, "Hexahedra"),
+    (13, "Corners"),
+    (14, "Ridges"),
+    (15, "RequiredVertices"),
+    (16, "RequiredEdges"),
+    (19, "TangentAtEdges"),
+    (20, "NormalAtVertices"),
+    (21, "NormalAtTriangleVertices"),
+    (22, "NormalAtQuadrilateralVertices"),
+    (49, "Pyramids"),
+    (59, "Tangents"),
+    (60, "Normals"),
+];
+
+/// The code of the keyword which ends binary files.
+const END_CODE: i32 = 54;
+
+fn code_keyword(code: i32) -> Option<&'static str> {
+    KEYWORD_CODES
+        .iter()
+        .find(|&&(c, _)| c == code)
+        .map(|&(_, keyword)| keyword)
+}
+
+/// The sizes of the values of a binary file, which depend on its version.
+///
+/// Reals are `float` in version 1 and `double` since version 2, positions of keywords have 64
+/// bits since version 3, and so do integers since version 4. The dimension and the codes of
+/// keywords are always 32 bit integers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Layout {
+    version: i32,
+    big_endian: bool,
+}
+
+impl Layout {
+    /// Recognize the byte order by the code 1 and read the version, which follows.
+    fn parse(head: &[u8]) -> Result<Layout, Error> {
+        let word = |i: usize| <[u8; 4]>::try_from(head.get(i..i + 4)?).ok();
+        let (code, version) = match (word(0), word(4)) {
+            (Some(code), Some(version)) => (code, version),
+            _ => return Err(Error::Syntax("Unexpected end of binary data.".into())),
+        };
+        let big_endian = match (i32::from_le_bytes(code), i32::from_be_bytes(code)) {
+            (1, _) => false,
+            (_, 1) => true,
+            _ => return Err(Error::Syntax("Not a binary Medit file.".into())),
+        };
+        let layout = Layout {
+            version: 0,
+            big_endian,
+        };
+        let version = layout.i32(version);
+        if !(1..=4).contains(&version) {
+            return Err(Error::Syntax(format!("Unsupported version: {}", version)));
+        }
+        Ok(Layout { version, ..layout })
+    }
+
+    fn i32(self, bytes: [u8; 4]) -> i32 {
+        if self.big_endian {
+            i32::from_be_bytes(bytes)
+        } else {
+            i32::from_le_bytes(bytes)
+        }
+    }
+
+    fn real_size(self) -> usize {
+        if self.version == 1 {
+            4
+        } else {
+            8
+        }
+    }
+
+    fn int_size(self) -> usize {
+        if self.version >= 4 {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn position_size(self) -> usize {
+        if self.version >= 3 {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// The number of bytes of every entity of a section.
+    fn record_size(self, section: Section, dimension: usize) -> usize {
+        match section {
+            Section::Points(EntityKind::Node) => dimension * self.real_size() + self.int_size(),
+            Section::Points(_) => dimension * self.real_size(),
+            section => section_width(section, dimension) * self.int_size(),
+        }
+    }
+}
+
+/// An error of reading binary data, whose end is a syntax error.
+fn read_error(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => Error::Syntax("Unexpected end of binary data.".into()),
+        _ => Error::Io(e),
+    }
+}
+
+/// The values of a binary file, with the number of bytes read so far.
+struct Words<R> {
+    source: R,
+    layout: Layout,
+    offset: u64,
+}
+
+impl<R: Read> Words<R> {
+    fn take<A: Default + AsMut<[u8]>>(&mut self) -> Result<A, Error> {
+        let mut bytes = A::default();
+        self.source.read_exact(bytes.as_mut()).map_err(read_error)?;
+        self.offset += bytes.as_mut().len() as u64;
+        Ok(bytes)
+    }
+
+    fn word(&mut self) -> Result<i32, Error> {
+        let bytes = self.take()?;
+        Ok(self.layout.i32(bytes))
+    }
+
+    /// The code of the next keyword, `None` at the end of the data.
+    fn keyword(&mut self) -> Result<Option<i32>, Error> {
+        let mut bytes = [0; 4];
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match self.source.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+        self.offset += filled as u64;
+        match filled {
+            0 => Ok(None),
+            4 => Ok(Some(self.layout.i32(bytes))),
+            _ => Err(Error::Syntax("Unexpected end of binary data.".into())),
+        }
+    }
+
+    fn long(&mut self) -> Result<i64, Error> {
+        let bytes = self.take()?;
+        Ok(if self.layout.big_endian {
+            i64::from_be_bytes(bytes)
+        } else {
+            i64::from_le_bytes(bytes)
+        })
+    }
+
+    fn int(&mut self) -> Result<i64, Error> {
+        match self.layout.int_size() {
+            4 => self.word().map(i64::from),
+            _ => self.long(),
+        }
+    }
+
+    /// A non-negative integer, like the number of entities of a section.
+    fn count(&mut self) -> Result<usize, Error> {
+        let value = self.int()?;
+        usize::try_from(value).map_err(|_| Error::Syntax(format!("Negative integer: {}", value)))
+    }
+
+    /// The position of the next keyword, zero after the last one.
+    fn position(&mut self) -> Result<u64, Error> {
+        match self.layout.position_size() {
+            4 => self.word().map(|p| p as u32 as u64),
+            _ => self.long().map(|p| p as u64),
+        }
+    }
+
+    fn real(&mut self) -> Result<f64, Error> {
+        Ok(match (self.layout.real_size(), self.layout.big_endian) {
+            (4, true) => f64::from(f32::from_be_bytes(self.take()?)),
+            (4, false) => f64::from(f32::from_le_bytes(self.take()?)),
+            (_, true) => f64::from_be_bytes(self.take()?),
+            (_, false) => f64::from_le_bytes(self.take()?),
+        })
+    }
 
+    /// Skip the data up to the keyword at `position`.
+    fn skip_to(&mut self, position: u64) -> Result<(), Error> {
+        let len = position.checked_sub(self.offset).ok_or_else(|| {
+            Error::Syntax(format!("Bad position of the next keyword: {}", position))
+        })?;
+        let skipped = io::copy(&mut (&mut self.source).take(len), &mut io::sink())?;
+        self.offset += skipped;
+        if skipped < len {
+            return Err(Error::Syntax("Unexpected end of binary data.".into()));
+        }
         Ok(())
     }
 }
+
+/// Read the next entity of a section of a binary file.
+fn read_binary_entity<R: Read>(
+    words: &mut Words<R>,
+    dimension: usize,
+    section: Section,
+) -> Result<EntityBox, Error> {
+    let mut attr = AttributeMap::new();
+    match section {
+        Section::Points(kind) => {
+            let mut position = [0.; 3];
+            for x in position[..dimension].iter_mut() {
+                *x = words.real()?;
+            }
+            if kind == EntityKind::Node {
+                attr.set(AttributeName::key(keys::REF), words.int()?.into());
+            }
+            Ok(geometry::entity_with_position(
+                kind,
+                &position[..dimension],
+                attr,
+            ))
+        }
+        Section::Elements(cell) => {
+            let mut indices = vec![0; cell.num_nodes()];
+            for index in indices.iter_mut() {
+                match words.count()? {
+                    0 => return Err(Error::Syntax("Vertex index 0 in element.".into())),
+                    i => *index = i - 1,
+                }
+            }
+            attr.set(AttributeName::key(keys::REF), words.int()?.into());
+            Ok(geometry::element_with_cell(cell, &indices, attr))
+        }
+        Section::Others(n_attrs) => {
+            for i in 0..n_attrs {
+                attr.set(AttributeName::Index(i), (words.count()? as u64).into());
+            }
+            Ok(EntityBox::new(EntityKind::Other, attr))
+        }
+    }
+}
+
+/// The events of a binary Medit file, read entity by entity.
+struct MeditBinaryEvents<R> {
+    source: Option<R>,
+    words: Option<Words<R>>,
+    dimension: usize,
+    /// The open section with its number of remaining entities.
+    section: Option<(Section, usize)>,
+    done: bool,
+}
+
+impl<R: Read> MeditBinaryEvents<R> {
+    /// Read the code and the version at the start of the file.
+    fn words(&mut self) -> Result<&mut Words<R>, Error> {
+        if let Some(mut source) = self.source.take() {
+            let mut head = [0; 8];
+            source.read_exact(&mut head).map_err(read_error)?;
+            self.words = Some(Words {
+                source,
+                layout: Layout::parse(&head)?,
+                offset: head.len() as u64,
+            });
+        }
+        // Note: The source was taken above, or the words were created by an earlier call.
+        Ok(self.words.as_mut().expect("words created from the source"))
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        let dimension = self.dimension;
+        if let Some((section, remaining)) = self.section {
+            if remaining == 0 {
+                self.section = None;
+                return Ok(Some(Event::GroupEnd));
+            }
+            self.section = Some((section, remaining - 1));
+            let entity = read_binary_entity(self.words()?, dimension, section)?;
+            return Ok(Some(Event::Entity(entity)));
+        }
+
+        loop {
+            let words = self.words()?;
+            let code = match words.keyword()? {
+                Some(END_CODE) | None => return Ok(None),
+                Some(code) => code,
+            };
+            let next = words.position()?;
+            match code_keyword(code) {
+                Some("Dimension") => {
+                    let dimension = words.word()?;
+                    if dimension != 2 && dimension != 3 {
+                        return Err(Error::Syntax(format!("Bad dimension: {}", dimension)));
+                    }
+                    self.dimension = dimension as usize;
+                    return Ok(Some(Event::Dimension(dimension as u8)));
+                }
+                Some(keyword) => {
+                    // Note: Every keyword with a code but `Dimension` starts a section.
+                    let (section, kind) = keyword_section(keyword).unwrap();
+                    if let (Section::Points(_), 0) = (section, dimension) {
+                        return Err(Error::Syntax(format!("{} before Dimension.", keyword)));
+                    }
+                    let len = words.count()?;
+                    self.section = Some((section, len));
+                    return Ok(Some(Event::GroupStart {
+                        name: Name::parse(keyword.into(), Format::Medit, kind).unwrap(),
+                        kind,
+                        len: Some(len),
+                    }));
+                }
+                None if next == 0 => {
+                    warn!("Skipped keyword {} at the end of the file.", code);
+                    return Ok(None);
+                }
+                None => {
+                    warn!("Skipped unknown keyword {}.", code);
+                    words.skip_to(next)?;
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for MeditBinaryEvents<R> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event();
+        self.done = !matches!(event, Ok(Some(_)));
+        match event {
+            Ok(event) => event.map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Reader of binary Medit files (`.meshb`), in any version and byte order.
+///
+/// Sections are the same as in text files, keywords unknown to the reader are skipped with a
+/// warning. See [MeditView] to borrow the values of a file in place.
+pub struct MeditBinaryDeserializer {}
+
+impl Deserializer for MeditBinaryDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        Box::new(MeditBinaryEvents {
+            source: Some(BufReader::new(source)),
+            words: None,
+            dimension: 0,
+            section: None,
+            done: false,
+        })
+    }
+
+    /// Recognizes the code 1 and the version, followed by the `Dimension` keyword which
+    /// libMeshb writes first.
+    fn sniff(head: &[u8]) -> Confidence {
+        match Layout::parse(head) {
+            Ok(layout) => match head.get(8..12) {
+                Some(&[a, b, c, d]) if layout.i32([a, b, c, d]) == 3 => Confidence::High,
+                _ => Confidence::Low,
+            },
+            Err(_) => Confidence::No,
+        }
+    }
+}
+
+/// Check the node indices of elements and the attributes of other entities, which have to be
+/// positive, while skipping over `len` records.
+fn check_binary_records<R: Read>(
+    words: &mut Words<R>,
+    section: Section,
+    len: usize,
+) -> Result<(), Error> {
+    match section {
+        Section::Points(_) => {}
+        Section::Elements(cell) => {
+            for _ in 0..len {
+                for _ in 0..cell.num_nodes() {
+                    if words.count()? == 0 {
+                        return Err(Error::Syntax("Vertex index 0 in element.".into()));
+                    }
+                }
+                words.int()?;
+            }
+        }
+        Section::Others(n_attrs) => {
+            for _ in 0..len * n_attrs {
+                words.count()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A binary Medit file, whose values are borrowed without copying where possible.
+///
+/// Groups borrow the positions of vertices from files of version 4, if they are stored in the
+/// byte order of this machine and aligned, see [GetMeshGroup::positions]. Whether they are
+/// depends on the keywords before the vertices. Older versions store `float` coordinates, or
+/// references of 32 bits which leave the coordinates of the next vertex unaligned. Node indices start at one in the file, so elements have no borrowed
+/// connectivity. Entities are decoded one by one, so the view can be read like any other
+/// [GetMesh] implementation.
+///
+/// ```no_run
+/// # use multimesh::{data::{GetMesh, GetMeshGroup}, de::MappedFile, format::medit::MeditView};
+/// let file = MappedFile::open("cube.meshb")?;
+/// let view = MeditView::new(&file)?;
+/// for group in view.groups() {
+///     if let Some(positions) = group.positions() {
+///         println!("{} vertices, the first one is {:?}", positions.len(), positions.get(0));
+///     }
+/// }
+/// # Ok::<(), multimesh::error::Error>(())
+/// ```
+pub struct MeditView<'a> {
+    layout: Layout,
+    /// The dimension of the mesh, zero without `Dimension` keyword.
+    dimension: u8,
+    sections: Vec<ViewSection<'a>>,
+}
+
+/// A section of a viewed file with the bytes of its entities.
+struct ViewSection<'a> {
+    keyword: &'static str,
+    section: Section,
+    kind: EntityKind,
+    len: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> MeditView<'a> {
+    /// Find the data of every section.
+    ///
+    /// The node indices of elements are checked while skipping over them, all other values are
+    /// only decoded when they are read.
+    pub fn new(data: &'a [u8]) -> Result<MeditView<'a>, Error> {
+        let layout = Layout::parse(data)?;
+        let mut words = Words {
+            source: &data[8..],
+            layout,
+            offset: 8,
+        };
+        let mut dimension = 0;
+        let mut sections = Vec::new();
+        loop {
+            let code = match words.keyword()? {
+                Some(END_CODE) | None => break,
+                Some(code) => code,
+            };
+            let next = words.position()?;
+            match code_keyword(code) {
+                Some("Dimension") => {
+                    dimension = words.word()?;
+                    if dimension != 2 && dimension != 3 {
+                        return Err(Error::Syntax(format!("Bad dimension: {}", dimension)));
+                    }
+                }
+                Some(keyword) => {
+                    // Note: Every keyword with a code but `Dimension` starts a section.
+                    let (section, kind) = keyword_section(keyword).unwrap();
+                    if let (Section::Points(_), 0) = (section, dimension) {
+                        return Err(Error::Syntax(format!("{} before Dimension.", keyword)));
+                    }
+                    let len = words.count()?;
+                    let start = words.offset as usize;
+                    let bytes = len
+                        .checked_mul(layout.record_size(section, dimension as usize))
+                        .and_then(|size| data.get(start..start.checked_add(size)?))
+                        .ok_or_else(|| Error::Syntax("Unexpected end of binary data.".into()))?;
+                    let mut records = Words {
+                        source: bytes,
+                        layout,
+                        offset: 0,
+                    };
+                    check_binary_records(&mut records, section, len)?;
+                    words.source = &data[start + bytes.len()..];
+                    words.offset += bytes.len() as u64;
+                    sections.push(ViewSection {
+                        keyword,
+                        section,
+                        kind,
+                        len,
+                        bytes,
+                    });
+                }
+                None if next == 0 => {
+                    warn!("Skipped keyword {} at the end of the file.", code);
+                    break;
+                }
+                None => {
+                    warn!("Skipped unknown keyword {}.", code);
+                    words.skip_to(next)?;
+                }
+            }
+        }
+        Ok(MeditView {
+            layout,
+            dimension: dimension as u8,
+            sections,
+        })
+    }
+}
+
+impl<'a> GetMesh for MeditView<'a> {
+    type Entity = EntityBox;
+    type GroupReader<'m>
+        = MeditGroupReader<'m>
+    where
+        Self: 'm;
+    type GroupReaders<'m>
+        = Box<dyn Iterator<Item = MeditGroupReader<'m>> + 'm>
+    where
+        Self: 'm;
+
+    fn metadata(&self) -> MeshMetadata {
+        MeshMetadata::new(self.dimension)
+    }
+
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        Box::new(self.sections.iter().map(move |section| MeditGroupReader {
+            view: self,
+            section,
+            rest: section.bytes,
+            remaining: section.len,
+        }))
+    }
+}
+
+pub struct MeditGroupReader<'m> {
+    view: &'m MeditView<'m>,
+    section: &'m ViewSection<'m>,
+    /// The bytes of the entities which haven't been read yet.
+    rest: &'m [u8],
+    remaining: usize,
+}
+
+impl<'m> GetMeshGroup for MeditGroupReader<'m> {
+    fn metadata(&self) -> GroupMetadata {
+        let section = self.section;
+        let name = Name::parse(section.keyword.into(), Format::Medit, section.kind).unwrap();
+        GroupMetadata::new(name, section.kind, section.len)
+    }
+
+    /// Available for files of version 4 in the byte order of this machine, if the vertices are
+    /// aligned for `f64`.
+    fn positions(&self) -> Option<Strided<'_, f64>> {
+        let layout = self.view.layout;
+        let dimension = self.view.dimension as usize;
+        let section = self.section.section;
+        if !matches!(section, Section::Points(EntityKind::Node))
+            || layout.real_size() != 8
+            || layout.big_endian != cfg!(target_endian = "big")
+        {
+            return None;
+        }
+        let record_size = layout.record_size(section, dimension);
+        if !record_size.is_multiple_of(8) {
+            return None;
+        }
+        let values: &[f64] = bytemuck::try_cast_slice(self.section.bytes).ok()?;
+        Strided::new(values, self.section.len, dimension, record_size / 8)
+    }
+}
+
+impl<'m> Iterator for MeditGroupReader<'m> {
+    type Item = EntityBox;
+
+    fn next(&mut self) -> Option<EntityBox> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let mut words = Words {
+            source: &mut self.rest,
+            layout: self.view.layout,
+            offset: 0,
+        };
+        let entity = read_binary_entity(
+            &mut words,
+            self.view.dimension as usize,
+            self.section.section,
+        );
+        // Note: The length of the data and the node indices were checked by `MeditView::new`.
+        Some(entity.expect("medit data checked by MeditView::new"))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{attribute::AttributeValue, face_vertex::Mesh};
+
+    const SQUARE: &str = "MeshVersionFormatted 1
+Dimension 3
+Vertices
+4
+0 0 0 1
+1 0 0 1
+1 1 0.5 2
+0 1 0.5 2
+Triangles
+2
+1 2 3 7
+1 3 4 -7
+End
+";
+
+    /// Writes the values of a binary file.
+    struct Encoder {
+        bytes: Vec<u8>,
+        layout: Layout,
+    }
+
+    impl Encoder {
+        fn put(&mut self, le: &[u8], be: &[u8]) {
+            let bytes = if self.layout.big_endian { be } else { le };
+            self.bytes.extend_from_slice(bytes);
+        }
+
+        fn word(&mut self, value: i32) {
+            self.put(&value.to_le_bytes(), &value.to_be_bytes());
+        }
+
+        fn int(&mut self, value: i64) {
+            match self.layout.int_size() {
+                4 => self.word(value as i32),
+                _ => self.put(&value.to_le_bytes(), &value.to_be_bytes()),
+            }
+        }
+
+        fn real(&mut self, value: f64) {
+            match self.layout.real_size() {
+                4 => self.put(&(value as f32).to_le_bytes(), &(value as f32).to_be_bytes()),
+                _ => self.put(&value.to_le_bytes(), &value.to_be_bytes()),
+            }
+        }
+
+        /// Write a keyword, followed by the position of the next one and `body`.
+        fn keyword<F: FnOnce(&mut Encoder)>(&mut self, code: i32, body: F) {
+            self.word(code);
+            let start = self.bytes.len();
+            self.int_position(0);
+            body(self);
+            let next = self.bytes.len() as i64;
+            let mut position = Encoder {
+                bytes: Vec::new(),
+                layout: self.layout,
+            };
+            position.int_position(next);
+            let len = position.bytes.len();
+            self.bytes[start..start + len].copy_from_slice(&position.bytes);
+        }
+
+        fn int_position(&mut self, value: i64) {
+            match self.layout.position_size() {
+                4 => self.word(value as i32),
+                _ => self.put(&value.to_le_bytes(), &value.to_be_bytes()),
+            }
+        }
+    }
+
+    /// [SQUARE] in binary, with an unknown keyword between the sections.
+    fn meshb(version: i32, big_endian: bool, first_index: i64) -> Vec<u8> {
+        let mut encoder = Encoder {
+            bytes: Vec::new(),
+            layout: Layout {
+                version,
+                big_endian,
+            },
+        };
+        encoder.word(1);
+        encoder.word(version);
+        encoder.keyword(3, |e| e.word(3));
+        encoder.keyword(4, |e| {
+            e.int(4);
+            for &(x, y, z, r) in &[
+                (0., 0., 0., 1),
+                (1., 0., 0., 1),
+                (1., 1., 0.5, 2),
+                (0., 1., 0.5, 2),
+            ] {
+                e.real(x);
+                e.real(y);
+                e.real(z);
+                e.int(r);
+            }
+        });
+        encoder.keyword(50, |e| {
+            for _ in 0..6 {
+                e.real(1.);
+            }
+        });
+        encoder.keyword(6, |e| {
+            e.int(2);
+            for &nodes in &[[first_index, 2, 3, 7], [1, 3, 4, -7]] {
+                for &i in &nodes {
+                    e.int(i);
+                }
+            }
+        });
+        encoder.word(END_CODE);
+        encoder.bytes
+    }
+
+    /// The attributes of all entities, group by group.
+    fn attributes<M: GetMesh>(mesh: &M) -> Vec<Vec<AttributeMap>> {
+        mesh.groups()
+            .map(|group| {
+                group
+                    .map(|entity| EntityBox::from_entity(&entity).attributes().clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_sections() {
+        let with_ref = |value: AttributeValue| {
+            let mut attr = AttributeMap::new();
+            attr.set(AttributeName::key(keys::REF), value);
+            attr
+        };
+        let mut mesh = Mesh::default();
+        mesh.set_dimension(2);
+        let name = Name::parse("nodes".into(), Format::Fluent, EntityKind::Node).unwrap();
+        let mut group = mesh.add_group(name, EntityKind::Node).unwrap();
+        let refs = [1.0.into(), "2".into(), "a".into(), 2.5.into()];
+        for (p, r) in [[0., 0.], [1., 0.], [1., 1.], [0., 1.]].iter().zip(refs) {
+            let node = geometry::entity_with_position(EntityKind::Node, p, with_ref(r));
+            group.add_entity(node).unwrap();
+        }
+        group.end().unwrap();
+        for (name, elements) in [
+            (
+                "a",
+                vec![
+                    (CellType::Triangle, vec![0, 1, 2], 2.5.into()),
+                    (CellType::Line, vec![0, 1], 3.into()),
+                ],
+            ),
+            ("b", vec![(CellType::Triangle, vec![0, 2, 3], (-7).into())]),
+        ] {
+            let name = Name::parse(name.into(), Format::Fluent, EntityKind::Element).unwrap();
+            let mut group = mesh.add_group(name, EntityKind::Element).unwrap();
+            for (cell, nodes, r) in elements {
+                let element = geometry::element_with_cell(cell, &nodes, with_ref(r));
+                group.add_entity(element).unwrap();
+            }
+            group.end().unwrap();
+        }
+
+        let mut output = Vec::new();
+        MeditSerializer::new()
+            .serialize(&mesh, &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Vertices\n4\n0 0 1\n1 0 2\n1 1 0\n0 1 0\n\n"));
+        assert!(output.contains("Edges\n1\n1 2 3\n\nTriangles\n2\n1 2 3 0\n1 3 4 -7\n\n"));
+    }
+
+    #[test]
+    fn binary_versions() {
+        let mut text = Mesh::default();
+        MeditDeserializer::deserialize_into(SQUARE.as_bytes(), &mut text).unwrap();
+        let expected = attributes(&text);
+
+        for version in 1..=4 {
+            for &big_endian in &[false, true] {
+                let data = meshb(version, big_endian, 1);
+                assert_eq!(MeditBinaryDeserializer::sniff(&data), Confidence::High);
+                let mut mesh = Mesh::default();
+                MeditBinaryDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+                assert_eq!(mesh.dimension(), 3);
+                assert_eq!(attributes(&mesh), expected, "version {}", version);
+
+                let view = MeditView::new(&data).unwrap();
+                assert_eq!(attributes(&view), expected, "version {}", version);
+            }
+        }
+        assert_eq!(
+            MeditBinaryDeserializer::sniff(SQUARE.as_bytes()),
+            Confidence::No
+        );
+    }
+
+    #[test]
+    fn view_positions() {
+        let data = meshb(4, cfg!(target_endian = "big"), 1);
+        // Note: The vertices start 44 bytes into the file, so the data is placed four bytes
+        // after an aligned address to align them.
+        let mut aligned = vec![0f64; data.len() / 8 + 2];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut aligned);
+        bytes[4..4 + data.len()].copy_from_slice(&data);
+        let view = MeditView::new(&bytes[4..4 + data.len()]).unwrap();
+
+        let groups: Vec<_> = view.groups().collect();
+        let positions = groups[0].positions().unwrap();
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions.get(2), &[1., 1., 0.5]);
+        assert!(groups[1].positions().is_none());
+        assert!(groups[1].connectivity().is_none());
+
+        let data = meshb(2, cfg!(target_endian = "big"), 1);
+        let view = MeditView::new(&data).unwrap();
+        assert!(view.groups().next().unwrap().positions().is_none());
+    }
+
+    #[test]
+    fn reject_malformed_binary() {
+        let data = meshb(2, false, 0);
+        assert!(MeditView::new(&data).is_err());
+        let mut mesh = Mesh::default();
+        assert!(MeditBinaryDeserializer::deserialize_into(&data[..], &mut mesh).is_err());
+
+        let data = meshb(2, false, 1);
+        for len in &[4, 22, data.len() - 12] {
+            assert!(MeditView::new(&data[..*len]).is_err(), "length {}", len);
+            let mut mesh = Mesh::default();
+            let result = MeditBinaryDeserializer::deserialize_into(&data[..*len], &mut mesh);
+            assert!(result.is_err(), "length {}", len);
+        }
+
+        // A count of vertices which exceeds the data.
+        let mut data = meshb(4, false, 1);
+        data[36..44].copy_from_slice(&i64::MAX.to_le_bytes());
+        assert!(MeditView::new(&data).is_err());
+        let mut mesh = Mesh::default();
+        assert!(MeditBinaryDeserializer::deserialize_into(&data[..], &mut mesh).is_err());
+    }
+}
@@ -1,5 +1,18 @@
-//pub mod medit;
-//pub mod ply;
+pub mod amf;
+pub mod dolfin;
+pub mod dxf;
+pub mod fluent;
+pub mod frd;
+pub mod lsdyna;
+pub mod medit;
+pub mod netgen;
+pub mod ply;
+pub mod stl;
 
+pub mod compression;
 pub mod naming;
+pub mod ordering;
 pub mod registry;
+pub mod tecplot;
+pub mod ugrid;
+pub mod xdmf;
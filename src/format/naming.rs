@@ -2,27 +2,40 @@
 //! between formats.
 //!
 //! For example what is called `line` in one format could be called `Edge` in another.
+//!
+//! Names are translated through a [Canonical] vocabulary of entity types: Formats which name
+//! groups by entity type, like Medit or UGRID, have a table of spellings. Formats which name
+//! groups freely, like after zones or materials, keep the original name. Their names never
+//! stand for an entity type, even if they spell one like `tetrahedron`, since a user chose
+//! them; writers take the type of elements from their cells instead.
 
 // TODO: This should be merged to support the registry.
 
-use data::EntityKind;
-use std::borrow::Cow;
+use data::{CellType, EntityKind};
+use error::Error;
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
 
-pub(crate) const NODES_MEDIT: &'static [&'static str] = &[
+pub(crate) const NODES_MEDIT: &[&str] = &[
     "Vertices", // x_i y_i z_i ref_i
 ];
-pub(crate) const VECTORS_MEDIT: &'static [&'static str] = &[
+pub(crate) const VECTORS_MEDIT: &[&str] = &[
     "Normals",  // x_i y_i z_i
     "Tangents", // x_i y_i z_i
 ];
-pub(crate) const ELEMENTS_MEDIT: &'static [&'static str] = &[
+pub(crate) const ELEMENTS_MEDIT: &[&str] = &[
     "Edges",          // e1_i e2_i ref_i
     "Triangles",      // v1_i v2_i v3_i ref_i
     "Quadrilaterals", // v1_i v2_i v3_i v4_i ref_i
     "Tetrahedra",     // v1_i v2_i v3_i v4_i ref_i
+    "Pyramids",       // v1_i v2_i v3_i v4_i v5_i ref_i
+    "Prisms",         // v1_i v2_i v3_i v4_i v5_i v6_i ref_i
     "Hexahedra",      // v1_i v2_i v3_i v4_i v5_i v6_i v7_i v8_i ref_i
 ];
-pub(crate) const OTHER_MEDIT: &'static [&'static str] = &[
+pub(crate) const OTHER_MEDIT: &[&str] = &[
     "Ridges",                        // e_i
     "RequiredEdges",                 // e_i
     "Corners",                       // v_i
@@ -33,6 +46,145 @@ pub(crate) const OTHER_MEDIT: &'static [&'static str] = &[
     "TangentAtEdges",                // e_i v_j t_i
 ];
 
+pub(crate) const NODES_UGRID: &[&str] = &["nodes"];
+pub(crate) const ELEMENTS_UGRID: &[&str] = &[
+    "triangles",      // boundary faces
+    "quadrilaterals", // boundary faces
+    "tetrahedra",
+    "pyramids",
+    "prisms",
+    "hexahedra",
+];
+
+/// Spellings of entity types in Medit, see [NODES_MEDIT] etc.
+const SPELLINGS_MEDIT: &[(Canonical, &str)] = &[
+    (Canonical::Vertex, "Vertices"),
+    (Canonical::Edge, "Edges"),
+    (Canonical::Triangle, "Triangles"),
+    (Canonical::Quadrilateral, "Quadrilaterals"),
+    (Canonical::Tetrahedron, "Tetrahedra"),
+    (Canonical::Pyramid, "Pyramids"),
+    (Canonical::Prism, "Prisms"),
+    (Canonical::Hexahedron, "Hexahedra"),
+    (Canonical::Normal, "Normals"),
+    (Canonical::Tangent, "Tangents"),
+    (Canonical::Corner, "Corners"),
+    (Canonical::Ridge, "Ridges"),
+    (Canonical::RequiredVertex, "RequiredVertices"),
+    (Canonical::RequiredEdge, "RequiredEdges"),
+];
+
+/// Spellings of entity types in UGRID, see [NODES_UGRID] and [ELEMENTS_UGRID].
+const SPELLINGS_UGRID: &[(Canonical, &str)] = &[
+    (Canonical::Vertex, "nodes"),
+    (Canonical::Triangle, "triangles"),
+    (Canonical::Quadrilateral, "quadrilaterals"),
+    (Canonical::Tetrahedron, "tetrahedra"),
+    (Canonical::Pyramid, "pyramids"),
+    (Canonical::Prism, "prisms"),
+    (Canonical::Hexahedron, "hexahedra"),
+];
+
+/// Spellings of entity types by PLY convention. Faces can be any polygon, so a `face` read from
+/// PLY is a [Canonical::Polygon], while triangles and quadrilaterals are written as `face`.
+const SPELLINGS_PLY: &[(Canonical, &str)] = &[
+    (Canonical::Vertex, "vertex"),
+    (Canonical::Edge, "edge"),
+    (Canonical::Polygon, "face"),
+    (Canonical::Triangle, "face"),
+    (Canonical::Quadrilateral, "face"),
+];
+
+/// Format independent names of the types of entities groups consist of.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Canonical {
+    Vertex,
+    Edge,
+    Triangle,
+    Quadrilateral,
+    /// A face with any number of nodes.
+    Polygon,
+    Tetrahedron,
+    Pyramid,
+    Prism,
+    Hexahedron,
+    Normal,
+    Tangent,
+    /// A vertex marked as corner of the geometry.
+    Corner,
+    /// An edge marked as ridge of the geometry.
+    Ridge,
+    /// A vertex which must be kept by remeshing.
+    RequiredVertex,
+    /// An edge which must be kept by remeshing.
+    RequiredEdge,
+}
+
+impl Canonical {
+    /// The kind of entities of this type.
+    pub fn kind(self) -> EntityKind {
+        match self {
+            Canonical::Vertex => EntityKind::Node,
+            Canonical::Edge
+            | Canonical::Triangle
+            | Canonical::Quadrilateral
+            | Canonical::Polygon
+            | Canonical::Tetrahedron
+            | Canonical::Pyramid
+            | Canonical::Prism
+            | Canonical::Hexahedron => EntityKind::Element,
+            Canonical::Normal | Canonical::Tangent => EntityKind::Vector,
+            Canonical::Corner
+            | Canonical::Ridge
+            | Canonical::RequiredVertex
+            | Canonical::RequiredEdge => EntityKind::Other,
+        }
+    }
+
+    /// The plain English name, like `required vertex`.
+    pub fn singular(self) -> &'static str {
+        match self {
+            Canonical::Vertex => "vertex",
+            Canonical::Edge => "edge",
+            Canonical::Triangle => "triangle",
+            Canonical::Quadrilateral => "quadrilateral",
+            Canonical::Polygon => "polygon",
+            Canonical::Tetrahedron => "tetrahedron",
+            Canonical::Pyramid => "pyramid",
+            Canonical::Prism => "prism",
+            Canonical::Hexahedron => "hexahedron",
+            Canonical::Normal => "normal",
+            Canonical::Tangent => "tangent",
+            Canonical::Corner => "corner",
+            Canonical::Ridge => "ridge",
+            Canonical::RequiredVertex => "required vertex",
+            Canonical::RequiredEdge => "required edge",
+        }
+    }
+
+    /// The plural of [singular](Canonical::singular), like `required vertices`.
+    pub fn plural(self) -> Cow<'static, str> {
+        let singular = self.singular();
+        if let Some(stem) = singular.strip_suffix("ex") {
+            Cow::Owned(format!("{}ices", stem))
+        } else if let Some(stem) = singular.strip_suffix("hedron") {
+            Cow::Owned(format!("{}hedra", stem))
+        } else {
+            Cow::Owned(format!("{}s", singular))
+        }
+    }
+}
+
+/// The spellings of entity types of a built-in format, `None` if it names groups freely.
+fn spellings(format: Format) -> Option<&'static [(Canonical, &'static str)]> {
+    match format {
+        Format::Medit => Some(SPELLINGS_MEDIT),
+        Format::Ugrid => Some(SPELLINGS_UGRID),
+        Format::Ply => Some(SPELLINGS_PLY),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Name {
     name: String,
@@ -44,7 +196,7 @@ impl Name {
     pub fn parse(s: String, format: Format, kind: EntityKind) -> Option<Self> {
         match format {
             Format::Medit => {
-                let ref whitelist = match kind {
+                let whitelist = match kind {
                     EntityKind::Node => NODES_MEDIT,
                     EntityKind::Element => ELEMENTS_MEDIT,
                     EntityKind::Vector => VECTORS_MEDIT,
@@ -59,12 +211,58 @@ impl Name {
                 // No validation.
                 // TODO Are there naming conventions for "ply elements"?
             }
+            Format::Fluent => {
+                // No validation, groups are named after the zones of the mesh.
+            }
+            Format::Netgen => {
+                // No validation, groups are named after materials and boundary conditions.
+            }
+            Format::Ugrid => {
+                let whitelist = match kind {
+                    EntityKind::Node => NODES_UGRID,
+                    EntityKind::Element => ELEMENTS_UGRID,
+                    EntityKind::Vector | EntityKind::Other => &[],
+                };
+
+                if !whitelist.contains(&s.as_str()) {
+                    return None;
+                }
+            }
+            Format::Tecplot => {
+                // No validation, groups are named after the zones of the mesh.
+            }
+            Format::Dolfin => {
+                // No validation, groups are named after cell types and marker collections.
+            }
+            Format::Xdmf => {
+                // No validation, groups are named after the grids of the mesh.
+            }
+            Format::Frd => {
+                // No validation, groups are named after materials.
+            }
+            Format::LsDyna => {
+                // No validation, groups are named after parts and node sets.
+            }
+            Format::Amf => {
+                // No validation, groups are named after objects and volumes.
+            }
+            Format::Dxf => {
+                // No validation, groups are named after layers.
+            }
+            Format::Stl => {
+                // No validation, groups are named after solids.
+            }
+            Format::Custom(custom) => {
+                if !custom.accepts(&s, kind) {
+                    return None;
+                }
+            }
         }
 
         Some(Name {
             name: s,
             format,
-            kind: kind.clone(),
+            kind,
         })
     }
 
@@ -72,22 +270,172 @@ impl Name {
         (self.name.as_ref(), self.format, self.kind)
     }
 
-    pub fn get_as(&self, f: Format) -> Option<Cow<str>> {
+    /// The entity type this name stands for, if its format declares it in its vocabulary.
+    pub fn canonical(&self) -> Option<Canonical> {
+        let canonical = match self.format {
+            Format::Custom(custom) => custom.canonical(&self.name, self.kind),
+            format => spellings(format)?
+                .iter()
+                .find(|(c, spelling)| *spelling == self.name && c.kind() == self.kind)
+                .map(|(c, _)| *c),
+        };
+        canonical.filter(|c| c.kind() == self.kind)
+    }
+
+    /// The name as it is spelled in format `f`.
+    ///
+    /// Names of entity types are translated to the spelling of `f`. Other names, and names of
+    /// entity types `f` doesn't spell, are kept if they are valid in `f`. Fails with an
+    /// explanation otherwise.
+    pub fn get_as(&self, f: Format) -> Result<Cow<'_, str>, Error> {
         if f == self.format {
-            Some(Cow::Borrowed(&self.name))
-        } else {
-            // TODO
-            unimplemented!()
+            return Ok(Cow::Borrowed(&self.name));
         }
+
+        let canonical = self.canonical();
+        if let Some(c) = canonical {
+            let spelling = match f {
+                Format::Custom(custom) => custom.spelling(c),
+                format => spellings(format)
+                    .and_then(|spellings| spellings.iter().find(|(s, _)| *s == c))
+                    .map(|(_, spelling)| *spelling),
+            };
+            if let Some(spelling) = spelling {
+                return Ok(Cow::Borrowed(spelling));
+            }
+        }
+
+        if Name::parse(self.name.clone(), f, self.kind).is_some() {
+            return Ok(Cow::Borrowed(&self.name));
+        }
+
+        Err(Error::NoEquivalentName(match canonical {
+            Some(c) => format!(
+                "{} has no equivalent of {} `{}` of {}.",
+                f.id(),
+                c.plural(),
+                self.name,
+                self.format.id()
+            ),
+            None => format!(
+                "`{}` of {} doesn't name a type of entities, but {} names its groups by type.",
+                self.name,
+                self.format.id(),
+                f.id()
+            ),
+        }))
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Copy)]
+/// Identifies the format a [Name] belongs to.
+///
+/// Formats implemented outside of this crate are identified by a [CustomFormat].
+#[derive(Clone, Debug, Copy)]
 pub enum Format {
     Medit,
     Ply,
-    // TODO: Allow formats other than the ones implemented together with this crate.
-    //Other(String),
+    Fluent,
+    Netgen,
+    Ugrid,
+    Tecplot,
+    Dolfin,
+    Xdmf,
+    Frd,
+    LsDyna,
+    Amf,
+    Dxf,
+    Stl,
+    Custom(&'static dyn CustomFormat),
+}
+
+impl Format {
+    /// A short lowercase identifier of the format, like `medit`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Format::Medit => "medit",
+            Format::Ply => "ply",
+            Format::Fluent => "fluent",
+            Format::Netgen => "netgen",
+            Format::Ugrid => "ugrid",
+            Format::Tecplot => "tecplot",
+            Format::Dolfin => "dolfin",
+            Format::Xdmf => "xdmf",
+            Format::Frd => "frd",
+            Format::LsDyna => "lsdyna",
+            Format::Amf => "amf",
+            Format::Dxf => "dxf",
+            Format::Stl => "stl",
+            Format::Custom(custom) => custom.id(),
+        }
+    }
+}
+
+/// Formats are equal if their identifiers are, so custom formats must use unique ones.
+impl PartialEq for Format {
+    fn eq(&self, other: &Format) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for Format {}
+
+impl Hash for Format {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
+    }
+}
+
+/// The naming conventions of a format implemented outside of this crate.
+///
+/// Implementors are usually unit structs referenced by a static:
+///
+/// ```
+/// use multimesh::data::EntityKind;
+/// use multimesh::format::naming::{CustomFormat, Format, Name};
+///
+/// #[derive(Debug)]
+/// struct Stl;
+///
+/// impl CustomFormat for Stl {
+///     fn id(&self) -> &'static str {
+///         "stl"
+///     }
+///
+///     fn accepts(&self, name: &str, kind: EntityKind) -> bool {
+///         kind == EntityKind::Element || name == "vertices"
+///     }
+/// }
+///
+/// static STL: Stl = Stl;
+///
+/// let name = Name::parse("solid".into(), Format::Custom(&STL), EntityKind::Element);
+/// assert_eq!(name.unwrap().get_original().1, Format::Custom(&STL));
+/// ```
+pub trait CustomFormat: Debug + Sync {
+    /// A short lowercase identifier which doesn't clash with other formats.
+    fn id(&self) -> &'static str;
+
+    /// Whether `name` is valid for a group of entities of `kind`, accepts all names by default.
+    fn accepts(&self, _name: &str, _kind: EntityKind) -> bool {
+        true
+    }
+
+    /// The entity type a name stands for, `None` by default for formats which name groups
+    /// freely.
+    fn canonical(&self, _name: &str, _kind: EntityKind) -> Option<Canonical> {
+        None
+    }
+
+    /// The spelling of an entity type, `None` by default to keep the original names.
+    fn spelling(&self, _canonical: Canonical) -> Option<&'static str> {
+        None
+    }
+
+    /// The node ordering of a cell type, if it differs from the canonical one; see
+    /// [ordering::permutation](::format::ordering::permutation).
+    fn permutation(&self, _cell: CellType) -> Option<&'static [usize]> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -104,4 +452,92 @@ mod tests {
         assert_eq!(name.get_original().0, "Triangles");
         assert_eq!(name.get_original().1, Format::Medit);
     }
+
+    #[derive(Debug)]
+    struct Upper;
+
+    impl CustomFormat for Upper {
+        fn id(&self) -> &'static str {
+            "upper"
+        }
+
+        fn accepts(&self, name: &str, _kind: EntityKind) -> bool {
+            name.chars().all(|c| c.is_ascii_uppercase())
+        }
+    }
+
+    static UPPER: Upper = Upper;
+
+    #[test]
+    fn parse_custom_name() {
+        let format = Format::Custom(&UPPER);
+        assert!(Name::parse("wall".into(), format, EntityKind::Element).is_none());
+        let name = Name::parse("WALL".into(), format, EntityKind::Element).unwrap();
+        assert_eq!(name.get_original().1, format);
+        assert_ne!(format, Format::Medit);
+        assert_eq!(name.get_as(format).unwrap(), "WALL");
+        // Names of other formats are only kept if the custom format accepts them.
+        let name = Name::parse("TRIANGLES".into(), Format::Tecplot, EntityKind::Element).unwrap();
+        assert_eq!(name.get_as(format).unwrap(), "TRIANGLES");
+        let name = Name::parse("Triangles".into(), Format::Medit, EntityKind::Element).unwrap();
+        assert!(name.get_as(format).is_err());
+    }
+
+    fn translate(name: &str, from: Format, kind: EntityKind, to: Format) -> Result<String, Error> {
+        let name = Name::parse(name.into(), from, kind).unwrap();
+        name.get_as(to).map(|name| name.into_owned())
+    }
+
+    #[test]
+    fn translate_entity_types() {
+        let element = EntityKind::Element;
+        assert_eq!(
+            translate("Tetrahedra", Format::Medit, element, Format::Ugrid).unwrap(),
+            "tetrahedra"
+        );
+        assert_eq!(
+            translate("nodes", Format::Ugrid, EntityKind::Node, Format::Medit).unwrap(),
+            "Vertices"
+        );
+        assert_eq!(
+            translate("Triangles", Format::Medit, element, Format::Ply).unwrap(),
+            "face"
+        );
+        // Formats with free names keep the original.
+        assert_eq!(
+            translate("Triangles", Format::Medit, element, Format::Netgen).unwrap(),
+            "Triangles"
+        );
+        assert_eq!(Canonical::RequiredVertex.plural(), "required vertices");
+        assert_eq!(Canonical::Polygon.plural(), "polygons");
+    }
+
+    #[test]
+    fn explain_missing_equivalents() {
+        let error = translate("Normals", Format::Medit, EntityKind::Vector, Format::Ugrid);
+        match error {
+            Err(Error::NoEquivalentName(msg)) => {
+                assert_eq!(
+                    msg,
+                    "ugrid has no equivalent of normals `Normals` of medit."
+                )
+            }
+            _ => panic!("UGRID has no normals."),
+        }
+        // PLY faces may have any number of nodes.
+        assert!(translate("face", Format::Ply, EntityKind::Element, Format::Medit).is_err());
+        let error = translate("steel", Format::Netgen, EntityKind::Element, Format::Ugrid);
+        match error {
+            Err(Error::NoEquivalentName(msg)) => assert_eq!(
+                msg,
+                "`steel` of netgen doesn't name a type of entities, but ugrid names its groups \
+                 by type."
+            ),
+            _ => panic!("Materials are no entity types."),
+        }
+        // Free names are never taken for entity types, even if they spell one.
+        let name = Name::parse("tetrahedron".into(), Format::Amf, EntityKind::Element).unwrap();
+        assert_eq!(name.canonical(), None);
+        assert!(name.get_as(Format::Medit).is_err());
+    }
 }
@@ -0,0 +1,620 @@
+//! Implementation of Netgen/NGSolve mesh format support (ASCII `.vol` files).
+//!
+//! Definition: the `Mesh::Load` and `Mesh::Save` methods of Netgen's `meshclass.cpp`.
+//!
+//! The sections `points`, `volumeelements`, `surfaceelements` (also with the `gi` and `uv`
+//! suffixes), `edgesegmentsgp` (or `edgesegments`), `materials` and `bcnames` are read, all
+//! other sections are skipped up to the next known section keyword.
+//!
+//! Elements are grouped by the index which Netgen uses to name them, group names are taken
+//! from `materials` for elements of the full mesh dimension and from `bcnames` for elements of
+//! the boundary. Groups without name are called `material-<n>`, `bc-<n>` and `edges-<n>`.
+//! The index is stored as the reference tag of each element, the domain indices `domin` and
+//! `domout` of surface elements and segments as attributes with the same names.
+//!
+//! Elements get their [cell type](::data::CellType) by class and number of nodes, and are
+//! converted to the canonical node ordering, see [ordering](::format::ordering). The serializer
+//! determines the class by the cell type of an element, which is inferred for elements
+//! without type, see [CellType::infer](::data::CellType::infer).
+
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    CellType, Entity, EntityBox, EntityKind, GetMesh, GetMeshGroup,
+};
+use de::{Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::{
+    naming::{Format, Name},
+    ordering,
+};
+use ser::Serializer;
+use std::{
+    collections::{btree_map, BTreeMap},
+    io::{BufRead, BufReader, Read, Write},
+    iter, vec,
+};
+use util::{geometry, item_reader::ItemReader, sniff};
+
+const KEYWORDS: &[&str] = &[
+    "mesh3d",
+    "dimension",
+    "geomtype",
+    "points",
+    "volumeelements",
+    "surfaceelements",
+    "surfaceelementsgi",
+    "surfaceelementsuv",
+    "edgesegments",
+    "edgesegmentsgp",
+    "materials",
+    "bcnames",
+    "endmesh",
+];
+
+const DOMIN: &str = "domin";
+const DOMOUT: &str = "domout";
+
+/// The classes of elements distinguished by Netgen.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum Class {
+    Volume,
+    Surface,
+    Segment,
+}
+
+struct Element {
+    class: Class,
+    /// Index the element is grouped and named by.
+    index: usize,
+    nodes: Vec<usize>,
+    domains: Option<(usize, usize)>,
+}
+
+/// The sections of a Netgen file which are read.
+///
+/// Elements are grouped by their index and named by the sections `materials` and `bcnames`,
+/// which follow them, so the whole file is parsed before any events are produced.
+struct NetgenMesh {
+    dimension: usize,
+    points: Vec<f64>,
+    groups: BTreeMap<(Class, usize), Vec<Element>>,
+    materials: BTreeMap<usize, String>,
+    bcnames: BTreeMap<usize, String>,
+}
+
+fn parse<S: Read>(source: S) -> Result<NetgenMesh, Error> {
+    let mut reader = ItemReader::new(BufReader::new(source));
+
+    let mut dimension: usize = 3;
+    let mut points: Vec<f64> = Vec::new();
+    let mut elements: Vec<Element> = Vec::new();
+    let mut materials: BTreeMap<usize, String> = BTreeMap::new();
+    let mut bcnames: BTreeMap<usize, String> = BTreeMap::new();
+
+    let mut next_keyword = reader.next()?.map(str::to_string);
+    while let Some(keyword) = next_keyword.take() {
+        let keyword = keyword.as_str();
+        match keyword {
+            "mesh3d" => (),
+            "dimension" => {
+                dimension = reader.next_parse()?;
+                if dimension != 2 && dimension != 3 {
+                    return Err(Error::Syntax(format!("Bad dimension: {}", dimension)));
+                }
+            }
+            "points" => {
+                let num_points: usize = reader.next_parse()?;
+                points.reserve_exact(num_points * 3);
+                for _ in 0..num_points * 3 {
+                    points.push(reader.next_parse()?);
+                }
+            }
+            "volumeelements" => {
+                let num_elements: usize = reader.next_parse()?;
+                for _ in 0..num_elements {
+                    let index = reader.next_parse()?;
+                    let nodes = read_nodes(&mut reader)?;
+                    elements.push(Element {
+                        class: Class::Volume,
+                        index,
+                        nodes,
+                        domains: None,
+                    });
+                }
+            }
+            "surfaceelements" | "surfaceelementsgi" | "surfaceelementsuv" => {
+                let num_elements: usize = reader.next_parse()?;
+                for _ in 0..num_elements {
+                    let surfnr: usize = reader.next_parse()?;
+                    let bcnr: usize = reader.next_parse()?;
+                    let domin = reader.next_parse()?;
+                    let domout = reader.next_parse()?;
+                    let nodes = read_nodes(&mut reader)?;
+                    // Skip the geometry information of the nodes.
+                    let skip = match keyword {
+                        "surfaceelementsgi" => nodes.len(),
+                        "surfaceelementsuv" => 2 * nodes.len(),
+                        _ => 0,
+                    };
+                    for _ in 0..skip {
+                        reader.next_result()?;
+                    }
+                    elements.push(Element {
+                        class: Class::Surface,
+                        index: if dimension == 2 { surfnr } else { bcnr },
+                        nodes,
+                        domains: Some((domin, domout)),
+                    });
+                }
+            }
+            "edgesegments" | "edgesegmentsgp" => {
+                let num_segments: usize = reader.next_parse()?;
+                for _ in 0..num_segments {
+                    let index = reader.next_parse()?;
+                    reader.next_result()?;
+                    let nodes = vec![read_index(&mut reader)?, read_index(&mut reader)?];
+                    let domains = if keyword == "edgesegmentsgp" {
+                        // Skip the geometry information of the end points.
+                        reader.next_result()?;
+                        reader.next_result()?;
+                        let domains = (reader.next_parse()?, reader.next_parse()?);
+                        for _ in 0..4 {
+                            reader.next_result()?;
+                        }
+                        Some(domains)
+                    } else {
+                        None
+                    };
+                    elements.push(Element {
+                        class: Class::Segment,
+                        index,
+                        nodes,
+                        domains,
+                    });
+                }
+            }
+            "materials" | "bcnames" => {
+                let num_names: usize = reader.next_parse()?;
+                let names = if keyword == "materials" {
+                    &mut materials
+                } else {
+                    &mut bcnames
+                };
+                for _ in 0..num_names {
+                    let index = reader.next_parse()?;
+                    let name = reader.next_result()?;
+                    names.insert(index, name.to_string());
+                }
+            }
+            "endmesh" => break,
+            _ => {
+                // Unsupported section, skip it.
+                next_keyword = loop {
+                    match reader.next()? {
+                        Some(item) if KEYWORDS.contains(&item) => break Some(item.to_string()),
+                        Some(_) => (),
+                        None => break None,
+                    }
+                };
+                continue;
+            }
+        }
+        next_keyword = reader.next()?.map(str::to_string);
+    }
+
+    // Group the elements by their class and index.
+    let mut groups: BTreeMap<(Class, usize), Vec<Element>> = BTreeMap::new();
+    for element in elements {
+        if let Some(&i) = element.nodes.iter().find(|&&i| i * 3 >= points.len()) {
+            return Err(Error::Syntax(format!("Undefined point {}.", i + 1)));
+        }
+        groups
+            .entry((element.class, element.index))
+            .or_default()
+            .push(element);
+    }
+
+    Ok(NetgenMesh {
+        dimension,
+        points,
+        groups,
+        materials,
+        bcnames,
+    })
+}
+
+/// The part of a [NetgenMesh] which [NetgenEvents] produces next.
+enum Stage {
+    Dimension,
+    /// The next point, or `None` before the group of points started.
+    Points(Option<usize>),
+    Elements,
+}
+
+/// The events of a parsed Netgen file, whose entities are created one at a time.
+struct NetgenEvents {
+    dimension: usize,
+    points: Vec<f64>,
+    groups: btree_map::IntoIter<(Class, usize), Vec<Element>>,
+    /// The remaining elements of the open group of elements.
+    elements: Option<vec::IntoIter<Element>>,
+    materials: BTreeMap<usize, String>,
+    bcnames: BTreeMap<usize, String>,
+    stage: Stage,
+}
+
+impl NetgenEvents {
+    fn new(mesh: NetgenMesh) -> Self {
+        NetgenEvents {
+            dimension: mesh.dimension,
+            points: mesh.points,
+            groups: mesh.groups.into_iter(),
+            elements: None,
+            materials: mesh.materials,
+            bcnames: mesh.bcnames,
+            stage: Stage::Dimension,
+        }
+    }
+
+    fn group_name(&self, class: Class, index: usize) -> Name {
+        let name = match codimension(class, self.dimension) {
+            0 => self
+                .materials
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("material-{}", index)),
+            1 => self
+                .bcnames
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("bc-{}", index)),
+            _ => format!("edges-{}", index),
+        };
+        // Note: Should never fail, since there is no validation of Netgen names.
+        Name::parse(name, Format::Netgen, EntityKind::Element).unwrap()
+    }
+}
+
+fn element_entity(element: Element) -> EntityBox {
+    let mut attr = AttributeMap::new();
+    attr.set(AttributeName::key(keys::REF), element.index.into());
+    if let Some((domin, domout)) = element.domains {
+        attr.set(DOMIN.into(), domin.into());
+        attr.set(DOMOUT.into(), domout.into());
+    }
+    match cell_type(element.class, element.nodes.len()) {
+        Some(cell) => geometry::element_with_cell(
+            cell,
+            &ordering::to_canonical(Format::Netgen, cell, &element.nodes),
+            attr,
+        ),
+        None => geometry::entity_with_nodes(EntityKind::Element, &element.nodes, attr),
+    }
+}
+
+impl Iterator for NetgenEvents {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        let num_points = self.points.len() / 3;
+        match self.stage {
+            Stage::Dimension => {
+                self.stage = Stage::Points(None);
+                Some(Event::Dimension(self.dimension as u8))
+            }
+            Stage::Points(None) => {
+                self.stage = Stage::Points(Some(0));
+                let name = Name::parse("points".into(), Format::Netgen, EntityKind::Node).unwrap();
+                Some(Event::GroupStart {
+                    name,
+                    kind: EntityKind::Node,
+                    len: Some(num_points),
+                })
+            }
+            Stage::Points(Some(i)) if i < num_points => {
+                self.stage = Stage::Points(Some(i + 1));
+                let position = &self.points[i * 3..i * 3 + self.dimension];
+                Some(Event::Entity(geometry::entity_with_position(
+                    EntityKind::Node,
+                    position,
+                    AttributeMap::new(),
+                )))
+            }
+            Stage::Points(Some(_)) => {
+                self.stage = Stage::Elements;
+                Some(Event::GroupEnd)
+            }
+            Stage::Elements => match self.elements {
+                Some(ref mut elements) => match elements.next() {
+                    Some(element) => Some(Event::Entity(element_entity(element))),
+                    None => {
+                        self.elements = None;
+                        Some(Event::GroupEnd)
+                    }
+                },
+                None => {
+                    let ((class, index), elements) = self.groups.next()?;
+                    let name = self.group_name(class, index);
+                    let len = Some(elements.len());
+                    self.elements = Some(elements.into_iter());
+                    Some(Event::GroupStart {
+                        name,
+                        kind: EntityKind::Element,
+                        len,
+                    })
+                }
+            },
+        }
+    }
+}
+
+pub struct NetgenDeserializer {}
+
+impl Deserializer for NetgenDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        match parse(source) {
+            Ok(mesh) => Box::new(NetgenEvents::new(mesh).map(Ok)),
+            Err(e) => Box::new(iter::once(Err(e))),
+        }
+    }
+
+    /// Recognizes the `mesh3d` keyword which starts every file.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let first = sniff::lines(&text, "#").next();
+        match first {
+            Some("mesh3d") => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// Read a one based point index and convert it to a zero based one.
+fn read_index<R: BufRead>(reader: &mut ItemReader<R>) -> Result<usize, Error> {
+    let index: usize = reader.next_parse()?;
+    if index == 0 {
+        return Err(Error::Syntax("Point index 0 in element.".into()));
+    }
+    Ok(index - 1)
+}
+
+/// Read the number of nodes followed by the node indices.
+fn read_nodes<R: BufRead>(reader: &mut ItemReader<R>) -> Result<Vec<usize>, Error> {
+    let num_nodes: usize = reader.next_parse()?;
+    (0..num_nodes).map(|_| read_index(reader)).collect()
+}
+
+/// The codimension of an element class in a mesh of dimension `dimension`.
+fn codimension(class: Class, dimension: usize) -> usize {
+    let class_dimension = match class {
+        Class::Volume => 3,
+        Class::Surface => 2,
+        Class::Segment => 1,
+    };
+    dimension.saturating_sub(class_dimension)
+}
+
+#[derive(Default)]
+pub struct NetgenSerializer {}
+
+impl NetgenSerializer {
+    pub fn new() -> Self {
+        NetgenSerializer {}
+    }
+}
+
+impl Serializer for NetgenSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "Netgen does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        // Sort the elements into classes, and name the indices after their groups.
+        let mut elements: Vec<Element> = Vec::new();
+        let mut materials: BTreeMap<usize, String> = BTreeMap::new();
+        let mut bcnames: BTreeMap<usize, String> = BTreeMap::new();
+        let mut num_groups = BTreeMap::new();
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            if metadata.kind() != EntityKind::Element {
+                continue;
+            }
+            let group_name: String = metadata
+                .name()
+                .get_original()
+                .0
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("_");
+
+            let mut group_indices = BTreeMap::new();
+            for element in group {
+                let nodes = element.node_indices()?;
+                if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                    return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                }
+                let cell = geometry::cell_type(&element, &nodes, &points, dimension)?;
+                let class = classify(cell)?;
+                let nodes = ordering::from_canonical(Format::Netgen, cell, &nodes);
+                let index = match geometry::reference(&element).and_then(AttributeValue::to_usize) {
+                    Some(index) => index,
+                    None => *group_indices.entry(class).or_insert_with(|| {
+                        let n = num_groups.entry(class).or_insert(0);
+                        *n += 1;
+                        *n
+                    }),
+                };
+                let domains = match (attribute(&element, DOMIN), attribute(&element, DOMOUT)) {
+                    (Some(domin), Some(domout)) => Some((domin, domout)),
+                    _ => None,
+                };
+
+                let names = match codimension(class, dimension) {
+                    0 => Some(&mut materials),
+                    1 => Some(&mut bcnames),
+                    _ => None,
+                };
+                if let Some(names) = names {
+                    names.entry(index).or_insert_with(|| group_name.clone());
+                }
+
+                elements.push(Element {
+                    class,
+                    index,
+                    nodes,
+                    domains,
+                });
+            }
+        }
+
+        writeln!(target, "mesh3d")?;
+        writeln!(target, "dimension\n{}", dimension)?;
+        writeln!(target, "geomtype\n0\n")?;
+
+        let of_class = |class| elements.iter().filter(move |e| e.class == class);
+
+        writeln!(
+            target,
+            "# surfnr    bcnr   domin  domout      np      p1      p2      p3"
+        )?;
+        writeln!(
+            target,
+            "surfaceelements\n{}",
+            of_class(Class::Surface).count()
+        )?;
+        for element in of_class(Class::Surface) {
+            let (domin, domout) = element.domains.unwrap_or((1, 0));
+            write!(
+                target,
+                "{} {} {} {} ",
+                element.index, element.index, domin, domout
+            )?;
+            write_nodes(&mut target, &element.nodes)?;
+        }
+        writeln!(target)?;
+
+        writeln!(target, "#  matnr      np      p1      p2      p3      p4")?;
+        writeln!(
+            target,
+            "volumeelements\n{}",
+            of_class(Class::Volume).count()
+        )?;
+        for element in of_class(Class::Volume) {
+            write!(target, "{} ", element.index)?;
+            write_nodes(&mut target, &element.nodes)?;
+        }
+        writeln!(target)?;
+
+        writeln!(
+            target,
+            "# surfid  0   p1   p2   trignum1    trignum2   domin/surfnr1    domout/surfnr2   ednr1   dist1   ednr2   dist2"
+        )?;
+        writeln!(
+            target,
+            "edgesegmentsgp\n{}",
+            of_class(Class::Segment).count()
+        )?;
+        for element in of_class(Class::Segment) {
+            let (domin, domout) = element.domains.unwrap_or((1, 0));
+            writeln!(
+                target,
+                "{} 0 {} {} 0 0 {} {} {} 0 {} 1",
+                element.index,
+                element.nodes[0] + 1,
+                element.nodes[1] + 1,
+                domin,
+                domout,
+                element.index,
+                element.index
+            )?;
+        }
+        writeln!(target)?;
+
+        writeln!(target, "#          X             Y             Z")?;
+        writeln!(target, "points\n{}", points.len())?;
+        for p in &points {
+            writeln!(target, "{} {} {}", p[0], p[1], p[2])?;
+        }
+        writeln!(target)?;
+
+        for (keyword, names) in &[("materials", &materials), ("bcnames", &bcnames)] {
+            if !names.is_empty() {
+                writeln!(target, "{}\n{}", keyword, names.len())?;
+                for (index, name) in names.iter() {
+                    writeln!(target, "{} {}", index, name)?;
+                }
+                writeln!(target)?;
+            }
+        }
+
+        writeln!(target, "endmesh")?;
+
+        Ok(())
+    }
+}
+
+fn attribute<E: Entity>(entity: &E, name: &str) -> Option<usize> {
+    entity.attributes().get_usize(&AttributeName::from(name))
+}
+
+fn write_nodes<W: Write>(mut target: W, nodes: &[usize]) -> Result<(), Error> {
+    write!(target, "{}", nodes.len())?;
+    for i in nodes {
+        write!(target, " {}", i + 1)?;
+    }
+    writeln!(target)?;
+    Ok(())
+}
+
+/// Determine the class of an element by its cell type.
+fn classify(cell: CellType) -> Result<Class, Error> {
+    match cell {
+        CellType::Line => Ok(Class::Segment),
+        CellType::Triangle
+        | CellType::Triangle6
+        | CellType::Quadrilateral
+        | CellType::Quadrilateral8 => Ok(Class::Surface),
+        CellType::Tetrahedron
+        | CellType::Tetrahedron10
+        | CellType::Pyramid
+        | CellType::Prism
+        | CellType::Hexahedron => Ok(Class::Volume),
+        _ => Err(Error::Unsupported(format!(
+            "Netgen does not support cells of type {}.",
+            cell.name()
+        ))),
+    }
+}
+
+/// The cell type of an element of `class` with `num_nodes` nodes, the inverse of [classify].
+fn cell_type(class: Class, num_nodes: usize) -> Option<CellType> {
+    match (class, num_nodes) {
+        (Class::Segment, 2) => Some(CellType::Line),
+        (Class::Surface, 3) => Some(CellType::Triangle),
+        (Class::Surface, 4) => Some(CellType::Quadrilateral),
+        (Class::Surface, 6) => Some(CellType::Triangle6),
+        (Class::Surface, 8) => Some(CellType::Quadrilateral8),
+        (Class::Volume, 4) => Some(CellType::Tetrahedron),
+        (Class::Volume, 5) => Some(CellType::Pyramid),
+        (Class::Volume, 6) => Some(CellType::Prism),
+        (Class::Volume, 8) => Some(CellType::Hexahedron),
+        (Class::Volume, 10) => Some(CellType::Tetrahedron10),
+        _ => None,
+    }
+}
@@ -0,0 +1,144 @@
+//! Conversion between the node ordering of formats and the canonical ordering of
+//! [CellType].
+//!
+//! A permutation `perm` of a cell type lists for each canonical node the position of the
+//! same node in the format, so the canonical nodes are `format_nodes[perm[i]]`. Formats and
+//! cell types without a permutation use the canonical ordering.
+//!
+//! Readers convert elements with [to_canonical] and writers with [from_canonical], so a
+//! converted mesh keeps the orientation of its cells.
+
+use data::CellType;
+use format::naming::Format;
+
+/// Netgen numbers tetrahedra with the base clockwise when seen from inside, and the edges of
+/// quadratic tetrahedra lexicographically by their corners.
+const NETGEN: &[(CellType, &[usize])] = &[
+    (CellType::Tetrahedron, &[0, 2, 1, 3]),
+    (CellType::Tetrahedron10, &[0, 2, 1, 3, 5, 7, 4, 6, 9, 8]),
+];
+
+/// CalculiX writes the edges between base and top of quadratic hexahedra and prisms before the
+/// edges of the top.
+const FRD: &[(CellType, &[usize])] = &[
+    (
+        CellType::Prism15,
+        &[0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 13, 14, 9, 10, 11],
+    ),
+    (
+        CellType::Hexahedron20,
+        &[
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 16, 17, 18, 19, 12, 13, 14, 15,
+        ],
+    ),
+];
+
+/// DOLFIN numbers the vertices of quadrilaterals and hexahedra like a tensor product, with the
+/// last vertex of each face opposite to the first one.
+const DOLFIN: &[(CellType, &[usize])] = &[
+    (CellType::Quadrilateral, &[0, 1, 3, 2]),
+    (CellType::Hexahedron, &[0, 1, 3, 2, 4, 5, 7, 6]),
+];
+
+/// XDMF follows VTK, which numbers the base of wedges clockwise when seen from inside.
+const XDMF: &[(CellType, &[usize])] = &[
+    (CellType::Prism, &[0, 2, 1, 3, 5, 4]),
+    (
+        CellType::Prism15,
+        &[0, 2, 1, 3, 5, 4, 8, 7, 6, 11, 10, 9, 12, 14, 13],
+    ),
+];
+
+/// UGRID numbers pyramids like prisms whose third and sixth node coincide, so the base is
+/// `1 4 5 2` and the apex is node 3.
+const UGRID: &[(CellType, &[usize])] = &[(CellType::Pyramid, &[0, 3, 4, 1, 2])];
+
+fn lookup(table: &[(CellType, &'static [usize])], cell: CellType) -> Option<&'static [usize]> {
+    table
+        .iter()
+        .find(|&&(c, _)| c == cell)
+        .map(|&(_, perm)| perm)
+}
+
+/// The permutation from the node ordering of `format` to the canonical ordering of `cell`,
+/// `None` if they are the same.
+pub fn permutation(format: Format, cell: CellType) -> Option<&'static [usize]> {
+    match format {
+        Format::Netgen => lookup(NETGEN, cell),
+        Format::Frd => lookup(FRD, cell),
+        Format::Dolfin => lookup(DOLFIN, cell),
+        Format::Xdmf => lookup(XDMF, cell),
+        Format::Ugrid => lookup(UGRID, cell),
+        Format::Custom(custom) => custom.permutation(cell),
+        _ => None,
+    }
+}
+
+/// Reorder the nodes of a cell as stored by `format` into canonical order.
+///
+/// Nodes which don't match the number of nodes of the permutation are returned unchanged.
+pub fn to_canonical(format: Format, cell: CellType, nodes: &[usize]) -> Vec<usize> {
+    match permutation(format, cell) {
+        Some(perm) if perm.len() == nodes.len() => perm.iter().map(|&i| nodes[i]).collect(),
+        _ => nodes.to_vec(),
+    }
+}
+
+/// Reorder the nodes of a cell in canonical order into the order of `format`, the inverse of
+/// [to_canonical].
+pub fn from_canonical(format: Format, cell: CellType, nodes: &[usize]) -> Vec<usize> {
+    match permutation(format, cell) {
+        Some(perm) if perm.len() == nodes.len() => {
+            let mut reordered = vec![0; nodes.len()];
+            for (&i, &node) in perm.iter().zip(nodes) {
+                reordered[i] = node;
+            }
+            reordered
+        }
+        _ => nodes.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutations_are_bijective() {
+        let tables = [
+            (Format::Netgen, NETGEN),
+            (Format::Frd, FRD),
+            (Format::Dolfin, DOLFIN),
+            (Format::Xdmf, XDMF),
+            (Format::Ugrid, UGRID),
+        ];
+        for &(format, table) in &tables {
+            for &(cell, perm) in table {
+                assert_eq!(perm.len(), cell.num_nodes(), "{:?}", cell);
+                let nodes: Vec<usize> = (10..10 + perm.len()).collect();
+                let canonical = to_canonical(format, cell, &nodes);
+                let mut sorted = canonical.clone();
+                sorted.sort();
+                assert_eq!(sorted, nodes);
+                assert_eq!(from_canonical(format, cell, &canonical), nodes);
+            }
+        }
+        assert_eq!(
+            to_canonical(Format::Medit, CellType::Tetrahedron, &[3, 2, 1, 0]),
+            vec![3, 2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn netgen_tetrahedron10() {
+        // Netgen's quadratic tetrahedron: corners, then the edges (0,1), (0,2), (0,3), (1,2),
+        // (1,3) and (2,3) with the midpoint numbered 10 * a + b.
+        let netgen = [0, 1, 2, 3, 1, 2, 3, 12, 13, 23];
+        let canonical = to_canonical(Format::Netgen, CellType::Tetrahedron10, &netgen);
+        let corners = &canonical[..4];
+        for (k, &[a, b]) in CellType::Tetrahedron10.edges().iter().enumerate() {
+            let (a, b) = (corners[a].min(corners[b]), corners[a].max(corners[b]));
+            assert_eq!(canonical[4 + k], 10 * a + b);
+        }
+    }
+}
@@ -1,43 +1,44 @@
-//! Implementation of serializer and deserializer for the Polygon File Format (PLY format).
+//! Implementation of the Polygon File Format (PLY format) reader.
 //!
 //! Definition: http://paulbourke.net/dataformats/ply/
 //!
-//! TODO: Binary ply support.
-//! TODO: Ply does not differentiate between different kinds of entities on the format level.
-//!       Type ascription as such has to be done by the user. There seem to be certain conventions
-//!       however so far I have not been able to find an authority on "the right ones".
-//! TODO: Another possibility would be to do the least surprising thing by default (deserialize
-//!       everything as "other entity" and provide a way to configure lists of entities to be
-//!       parsed in a specific way). This is rather cumbersome, but if the alternative is having
-//!       the user implement everything on their own (which they still could with the default
-//!       arguments) then this would be clearly beneficial since there would have to be only one
-//!       implementation of this boring stuff.
-
-use data::{attribute::Attr, face_vertex::Entity, AttrName, GroupData, GroupKind};
-use de::{DeserializeMesh, Deserializer};
-use error::Error;
-use naming::{Format, Name};
-use std::{io::Read, str::FromStr};
-use util::item_reader::ItemReader;
+//! Properties keep the types declared in the header. The element `vertex` becomes a node group
+//! with the coordinates `x`, `y` and (if present) `z`, the element `face` becomes an element
+//! group with the node indices of its property `vertex_indices` (or `vertex_index`). All other
+//! elements become groups of other entities. Every property is stored as attribute under its
+//! name.
+//!
+//! Binary files, in either byte order, are read like ASCII files. A [PlyView] borrows the
+//! values of a binary file in place instead, for example from a [MappedFile], including the
+//! node indices of faces if all faces have the same number of them.
 
-// TODO: Find the conventions
-/*
-pub struct PlyConfig {
-    pub autodetect_elements: bool,
-}
+use bytemuck::Pod;
+use data::{
+    attribute::{keys, AttributeContainerMut, AttributeMap, AttributeValue},
+    view::Strided,
+    CellType, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata, MeshMetadata, SetMesh,
+    SetMeshGroup,
+};
+use de::{Confidence, Deserializer, Event, Events, MappedFile};
+use error::Error;
+use format::naming::{Format, Name};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+    iter, mem,
+    str::FromStr,
+};
+use util::{
+    geometry,
+    item_reader::{parse_item, ItemReader},
+    parallel::{self, Parsed},
+    sniff,
+};
 
-impl Default for PlyConfig {
-    fn default() -> Self {
-        PlyConfig {
-            autodetect_elements: false
-        }
-    }
-}
-*/
-
-pub struct PlySerializer {}
-pub struct PlyDeserializer {}
+/// The names of the property with the node indices of faces.
+const INDEX_PROPERTIES: &[&str] = &["vertex_indices", "vertex_index"];
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum DataType {
     Char,
     Uchar,
@@ -54,126 +55,1103 @@ impl FromStr for DataType {
 
     fn from_str(s: &str) -> Result<Self, Error> {
         match s {
-            "char" => Ok(DataType::Char),
-            "uchar" => Ok(DataType::Uchar),
-            "short" => Ok(DataType::Short),
-            "ushort" => Ok(DataType::Ushort),
-            "int" => Ok(DataType::Int),
-            "uint" => Ok(DataType::Uint),
-            "float" => Ok(DataType::Float),
-            "double" => Ok(DataType::Double),
+            "char" | "int8" => Ok(DataType::Char),
+            "uchar" | "uint8" => Ok(DataType::Uchar),
+            "short" | "int16" => Ok(DataType::Short),
+            "ushort" | "uint16" => Ok(DataType::Ushort),
+            "int" | "int32" => Ok(DataType::Int),
+            "uint" | "uint32" => Ok(DataType::Uint),
+            "float" | "float32" => Ok(DataType::Float),
+            "double" | "float64" => Ok(DataType::Double),
             _ => Err(Error::Syntax(format!("Unknown data type: {}", s))),
         }
     }
 }
 
-struct Group {
-    data: GroupData,
-    attrs: Vec<(AttrName, DataType)>,
+impl DataType {
+    /// The number of bytes of a value in binary files.
+    fn size(self) -> usize {
+        match self {
+            DataType::Char | DataType::Uchar => 1,
+            DataType::Short | DataType::Ushort => 2,
+            DataType::Int | DataType::Uint | DataType::Float => 4,
+            DataType::Double => 8,
+        }
+    }
+
+    /// Decode a value from the first [size](DataType::size) bytes of `bytes`.
+    fn decode(self, bytes: &[u8], big_endian: bool) -> AttributeValue {
+        macro_rules! decode {
+            ($t:ty) => {{
+                let mut raw = [0; mem::size_of::<$t>()];
+                raw.copy_from_slice(&bytes[..mem::size_of::<$t>()]);
+                if big_endian {
+                    <$t>::from_be_bytes(raw).into()
+                } else {
+                    <$t>::from_le_bytes(raw).into()
+                }
+            }};
+        }
+        match self {
+            DataType::Char => decode!(i8),
+            DataType::Uchar => decode!(u8),
+            DataType::Short => decode!(i16),
+            DataType::Ushort => decode!(u16),
+            DataType::Int => decode!(i32),
+            DataType::Uint => decode!(u32),
+            DataType::Float => decode!(f32),
+            DataType::Double => decode!(f64),
+        }
+    }
+
+    fn parse(self, word: &str) -> Result<AttributeValue, Error> {
+        let bad_value = |_| Error::Syntax(format!("Bad value for type {:?}: {}", self, word));
+        Ok(match self {
+            DataType::Char => parse_item::<i8>(word).map_err(bad_value)?.into(),
+            DataType::Uchar => parse_item::<u8>(word).map_err(bad_value)?.into(),
+            DataType::Short => parse_item::<i16>(word).map_err(bad_value)?.into(),
+            DataType::Ushort => parse_item::<u16>(word).map_err(bad_value)?.into(),
+            DataType::Int => parse_item::<i32>(word).map_err(bad_value)?.into(),
+            DataType::Uint => parse_item::<u32>(word).map_err(bad_value)?.into(),
+            DataType::Float => parse_item::<f32>(word).map_err(bad_value)?.into(),
+            DataType::Double => parse_item::<f64>(word).map_err(bad_value)?.into(),
+        })
+    }
 }
 
-impl Deserializer for PlyDeserializer {
-    fn deserialize_into<S, T>(mut source: S, mut target: T) -> Result<(), Error>
-    where
-        S: Read,
-        T: DeserializeMesh,
-    {
-        // Read the file into memory.
-        let mut data = String::new();
-        source.read_to_string(&mut data)?;
-        let mut reader = ItemReader::new(data.as_ref());
-
-        if reader.next().unwrap_or("") != "ply" {
-            return Err(Error::Syntax(
-                "Ply document does not begin with keyword ply.".into(),
-            ));
-        }
-
-        // Read the header.
-        let mut groups: Vec<Group> = Vec::new();
-        let mut parsing_uid: u64 = 0;
-        let mut header_ended = false;
-
-        while let Some(keyword) = reader.next() {
-            match keyword {
-                "format" => {
-                    // TODO: support binary format
-                    if reader.next() != Some("ascii") || reader.next() != Some("1.0") {
-                        return Err(Error::Syntax(
-                            "Only 'ascii 1.0' ply supported as of now.".into(),
-                        ));
-                    }
+enum PropertyType {
+    Scalar(DataType),
+    /// A list with the type of its length and the type of its items.
+    List(DataType, DataType),
+}
+
+struct Property {
+    name: String,
+    property_type: PropertyType,
+}
+
+struct Element {
+    name: String,
+    len: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn kind(&self) -> EntityKind {
+        match self.name.as_str() {
+            "vertex" => EntityKind::Node,
+            "face" => EntityKind::Element,
+            _ => EntityKind::Other,
+        }
+    }
+
+    fn has_property(&self, name: &str) -> bool {
+        self.properties.iter().any(|p| p.name == name)
+    }
+
+    /// The number of bytes of every entity in binary files, `None` if it has lists.
+    fn record_size(&self) -> Option<usize> {
+        self.properties
+            .iter()
+            .map(|p| match p.property_type {
+                PropertyType::Scalar(data_type) => Some(data_type.size()),
+                PropertyType::List(..) => None,
+            })
+            .sum()
+    }
+
+    /// The byte offset of property `name` within records, and its type, if all properties are
+    /// scalars.
+    fn scalar_offset(&self, name: &str) -> Option<(usize, DataType)> {
+        let mut offset = 0;
+        for property in &self.properties {
+            let data_type = match property.property_type {
+                PropertyType::Scalar(data_type) => data_type,
+                PropertyType::List(..) => return None,
+            };
+            if property.name == name {
+                return Some((offset, data_type));
+            }
+            offset += data_type.size();
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+    /// The number of lines of the header, up to and including `end_header`.
+    lines: usize,
+}
+
+impl Header {
+    fn vertex(&self) -> Option<&Element> {
+        self.elements.iter().find(|e| e.kind() == EntityKind::Node)
+    }
+
+    /// The dimension of the mesh, checking that vertices have coordinates.
+    fn dimension(&self) -> Result<Option<u8>, Error> {
+        match self.vertex() {
+            Some(vertex) => {
+                if !vertex.has_property(keys::X) || !vertex.has_property(keys::Y) {
+                    return Err(Error::Syntax("Vertex without coordinates.".into()));
                 }
-                "comment" => {
-                    // Ignore comments. (TODO: might be made more efficient.)
-                    while let Some(_) = reader.next_until_eol() {}
+                Ok(Some(if vertex.has_property(keys::Z) { 3 } else { 2 }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn num_vertices(&self) -> usize {
+        self.vertex().map_or(0, |vertex| vertex.len)
+    }
+}
+
+/// Parse the header, up to and including `end_header`.
+fn parse_header<R: BufRead>(source: &mut R) -> Result<Header, Error> {
+    let mut lines = source.lines();
+    if lines.next().transpose()?.as_ref().map(|l| l.trim()) != Some("ply") {
+        return Err(Error::Syntax(
+            "Ply document does not begin with keyword ply.".into(),
+        ));
+    }
+
+    let mut encoding = Encoding::Ascii;
+    let mut elements: Vec<Element> = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let mut next = || {
+            words
+                .next()
+                .ok_or_else(|| Error::Syntax(format!("Incomplete header line: {}", line.trim())))
+        };
+        match next() {
+            Err(_) => (),
+            Ok("format") => {
+                encoding = match next()? {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    format => return Err(Error::Syntax(format!("Unknown ply format: {}", format))),
+                };
+                if next()? != "1.0" {
+                    return Err(Error::Syntax("Only ply version 1.0 supported.".into()));
                 }
-                "element" => {
-                    let group_name_str: String = reader.next_parse_until_eol()?;
-                    let group_name = Name::parse(group_name_str, Format::Ply, GroupKind::Other)
-                        .ok_or_else(|| Error::OtherInternal("Should never happen".into()))?;
-                    let group_len: usize = reader.next_parse_until_eol()?;
-
-                    groups.push(Group {
-                        data: GroupData::new(
-                            parsing_uid,
-                            group_name,
-                            Some(group_len),
-                            GroupKind::Other,
-                        ),
-                        attrs: Vec::new(),
+            }
+            Ok("comment") | Ok("obj_info") => (),
+            Ok("element") => {
+                let name = next()?.to_string();
+                let len = next()?.parse()?;
+                elements.push(Element {
+                    name,
+                    len,
+                    properties: Vec::new(),
+                });
+            }
+            Ok("property") => {
+                let property_type = match next()? {
+                    "list" => PropertyType::List(next()?.parse()?, next()?.parse()?),
+                    data_type => PropertyType::Scalar(data_type.parse()?),
+                };
+                let name = next()?.to_string();
+                elements
+                    .last_mut()
+                    .ok_or_else(|| Error::Syntax("property encountered before element.".into()))?
+                    .properties
+                    .push(Property {
+                        name,
+                        property_type,
                     });
+            }
+            Ok("end_header") => {
+                return Ok(Header {
+                    encoding,
+                    elements,
+                    lines: i + 2,
+                })
+            }
+            Ok(kwd) => return Err(Error::Syntax(format!("Unknown keyword: {}", kwd))),
+        }
+    }
+    Err(Error::Syntax(
+        "Header not terminated by `end_header` keyword.".into(),
+    ))
+}
+
+/// Reads the values of properties, in the encoding of the file.
+trait Values {
+    fn next_value(&mut self, data_type: DataType) -> Result<AttributeValue, Error>;
+
+    /// Read some of the next `count` entities of `element` in parallel, if supported.
+    fn read_parallel(
+        &mut self,
+        _element: &Element,
+        _count: usize,
+        _num_vertices: usize,
+    ) -> Parsed<EntityBox> {
+        Parsed {
+            entities: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl<R: BufRead> Values for ItemReader<R> {
+    fn next_value(&mut self, data_type: DataType) -> Result<AttributeValue, Error> {
+        let value = data_type.parse(self.next_result()?);
+        value.map_err(|e| match e {
+            Error::Syntax(message) => {
+                Error::Syntax(format!("{} in line {}", message, self.line_number()))
+            }
+            e => e,
+        })
+    }
+
+    /// Supported for elements without lists, whose entities have one item per property.
+    fn read_parallel(
+        &mut self,
+        element: &Element,
+        count: usize,
+        num_vertices: usize,
+    ) -> Parsed<EntityBox> {
+        if element.record_size().is_none() {
+            return Parsed {
+                entities: Vec::new(),
+                error: None,
+            };
+        }
+        parallel::read_parallel(self, count, element.properties.len(), |reader| {
+            read_entity(element, reader, num_vertices)
+        })
+    }
+}
 
-                    parsing_uid += 1;
+/// Values of a binary file.
+struct Binary<R> {
+    source: R,
+    big_endian: bool,
+}
+
+impl<R: Read> Values for Binary<R> {
+    fn next_value(&mut self, data_type: DataType) -> Result<AttributeValue, Error> {
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..data_type.size()];
+        self.source.read_exact(bytes).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Syntax("Unexpected end of binary data.".into()),
+            _ => Error::Io(e),
+        })?;
+        Ok(data_type.decode(bytes, self.big_endian))
+    }
+}
+
+/// Read the next entity of `element`.
+fn read_entity<V: Values>(
+    element: &Element,
+    values: &mut V,
+    num_vertices: usize,
+) -> Result<EntityBox, Error> {
+    let kind = element.kind();
+    let mut attr = AttributeMap::new();
+    let mut nodes = None;
+    for property in &element.properties {
+        let value = match property.property_type {
+            PropertyType::Scalar(data_type) => values.next_value(data_type)?,
+            PropertyType::List(len_type, item_type) => {
+                let len = list_len(values.next_value(len_type)?)?;
+                // Note: The length is read from the file, so the list grows as items are read.
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(values.next_value(item_type)?);
                 }
-                "property" => {
-                    let prop_type: DataType = reader.next_parse()?;
-                    let prop_name = AttrName::Key(reader.next_result()?.into());
-
-                    if let Some(ref mut group) = groups.last_mut() {
-                        group.attrs.push((prop_name, prop_type));
-                    } else {
-                        return Err(Error::Syntax("property encountered before element.".into()));
-                    }
+                AttributeValue::List(items)
+            }
+        };
+        if kind == EntityKind::Element && INDEX_PROPERTIES.contains(&property.name.as_str()) {
+            nodes = Some(face_nodes(&value, num_vertices)?);
+        } else {
+            attr.set(property.name.clone().into(), value);
+        }
+    }
+
+    Ok(match nodes {
+        Some(nodes) => match nodes.len() {
+            3 => geometry::element_with_cell(CellType::Triangle, &nodes, attr),
+            4 => geometry::element_with_cell(CellType::Quadrilateral, &nodes, attr),
+            _ => geometry::entity_with_nodes(kind, &nodes, attr),
+        },
+        None => EntityBox::new(kind, attr),
+    })
+}
+
+fn list_len(len: AttributeValue) -> Result<usize, Error> {
+    len.to_usize()
+        .ok_or_else(|| Error::Syntax(format!("Bad list length: {}", len)))
+}
+
+/// The events of the elements of a PLY file, read one entity at a time.
+struct PlyEvents<V> {
+    header: Header,
+    values: V,
+    /// Whether vertices are read in parallel, see [ParallelPlyDeserializer].
+    parallel: bool,
+    /// The dimension, until its event is produced.
+    dimension: Option<u8>,
+    /// The index of the element whose entities are read.
+    element: usize,
+    /// The number of entities of the element still to read, `None` before its group started.
+    remaining: Option<usize>,
+    /// Entities read in parallel, followed by the error which ended parallel reading.
+    parsed: VecDeque<EntityBox>,
+    error: Option<Error>,
+    done: bool,
+}
+
+impl<V: Values> PlyEvents<V> {
+    fn new(header: Header, values: V, parallel: bool) -> Result<Self, Error> {
+        Ok(PlyEvents {
+            dimension: header.dimension()?,
+            header,
+            values,
+            parallel,
+            element: 0,
+            remaining: None,
+            parsed: VecDeque::new(),
+            error: None,
+            done: false,
+        })
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        if let Some(dimension) = self.dimension.take() {
+            return Ok(Some(Event::Dimension(dimension)));
+        }
+        let num_vertices = self.header.num_vertices();
+        let element = match self.header.elements.get(self.element) {
+            Some(element) => element,
+            None => return Ok(None),
+        };
+        let kind = element.kind();
+        let remaining = match self.remaining {
+            None => {
+                self.remaining = Some(element.len);
+                // Note: Should never fail, since there is no validation of PLY names.
+                let name = Name::parse(element.name.clone(), Format::Ply, kind).unwrap();
+                return Ok(Some(Event::GroupStart {
+                    name,
+                    kind,
+                    len: Some(element.len),
+                }));
+            }
+            Some(0) => {
+                self.remaining = None;
+                self.element += 1;
+                return Ok(Some(Event::GroupEnd));
+            }
+            Some(remaining) => remaining,
+        };
+        if self.parsed.is_empty() {
+            if let Some(e) = self.error.take() {
+                return Err(e);
+            }
+            if self.parallel && kind == EntityKind::Node {
+                let parsed = self.values.read_parallel(element, remaining, num_vertices);
+                self.parsed.extend(parsed.entities);
+                self.error = parsed.error;
+            }
+        }
+        let entity = match self.parsed.pop_front() {
+            Some(entity) => entity,
+            None => match self.error.take() {
+                Some(e) => return Err(e),
+                None => read_entity(element, &mut self.values, num_vertices)?,
+            },
+        };
+        self.remaining = Some(remaining - 1);
+        Ok(Some(Event::Entity(entity)))
+    }
+}
+
+impl<V: Values> Iterator for PlyEvents<V> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event();
+        self.done = !matches!(event, Ok(Some(_)));
+        match event {
+            Ok(event) => event.map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// The events of a PLY file, the vertices of ASCII files read in parallel if `parallel` is set.
+fn events<'s, S: Read + 's>(source: S, parallel: bool) -> Events<'s> {
+    start_events(source, parallel).unwrap_or_else(|e| Box::new(iter::once(Err(e))))
+}
+
+/// Parse the header of a PLY file, and return the events of its elements.
+fn start_events<'s, S: Read + 's>(source: S, parallel: bool) -> Result<Events<'s>, Error> {
+    let mut source = BufReader::new(source);
+    let header = parse_header(&mut source)?;
+    match header.encoding {
+        Encoding::Ascii => {
+            let reader = ItemReader::starting_at_line(source, header.lines + 1);
+            Ok(Box::new(PlyEvents::new(header, reader, parallel)?))
+        }
+        encoding => {
+            let big_endian = encoding == Encoding::BinaryBigEndian;
+            let values = Binary { source, big_endian };
+            Ok(Box::new(PlyEvents::new(header, values, false)?))
+        }
+    }
+}
+
+pub struct PlyDeserializer {}
+
+impl Deserializer for PlyDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        events(source, false)
+    }
+
+    /// Recognizes the keyword `ply` which starts every file, binary files included.
+    fn sniff(head: &[u8]) -> Confidence {
+        let first_line = head.split(|&b| b == b'\n').next().unwrap_or_default();
+        match String::from_utf8_lossy(first_line).trim() {
+            "ply" => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// Reads PLY files like [PlyDeserializer], but parses the vertices of ASCII files on the thread
+/// pool of rayon.
+///
+/// Vertices with list properties are read one by one. The entities and errors are the same as
+/// with [PlyDeserializer].
+pub struct ParallelPlyDeserializer {}
+
+impl Deserializer for ParallelPlyDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        events(source, true)
+    }
+
+    fn sniff(head: &[u8]) -> Confidence {
+        PlyDeserializer::sniff(head)
+    }
+}
+
+/// Types of PLY properties, which a [PlyView] can borrow.
+pub trait PlyScalar: Pod {
+    /// The name of the type in PLY headers.
+    const NAME: &'static str;
+}
+
+macro_rules! ply_scalar {
+    ($($t:ty => $name:expr),*) => {
+        $(
+            impl PlyScalar for $t {
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
+
+ply_scalar!(
+    i8 => "char", u8 => "uchar", i16 => "short", u16 => "ushort",
+    i32 => "int", u32 => "uint", f32 => "float", f64 => "double"
+);
+
+/// A binary PLY file, whose values are borrowed without copying where possible.
+///
+/// Typed views of properties are only available if the values are stored in the byte order
+/// of this machine and aligned for their type, which depends on the length of the header if
+/// the file is [memory mapped](MappedFile). Groups borrow the positions of vertices with
+/// `double` coordinates and the node indices of faces with the same number of `int` or `uint`
+/// indices, see [GetMeshGroup]. Entities are still decoded one by one, so the view can be read
+/// like any other [GetMesh] implementation.
+///
+/// ```no_run
+/// # use multimesh::{de::MappedFile, format::ply::PlyView};
+/// let file = MappedFile::open("bunny.ply")?;
+/// let view = PlyView::new(&file)?;
+/// if let Some(red) = view.property::<u8>("vertex", "red") {
+///     println!("{} vertices, the first one is {:?}", red.len(), red.get(0));
+/// }
+/// # Ok::<(), multimesh::error::Error>(())
+/// ```
+pub struct PlyView<'a> {
+    header: Header,
+    /// The dimension of the mesh, zero without vertices.
+    dimension: u8,
+    /// The bytes of every element of the header.
+    bytes: Vec<&'a [u8]>,
+    /// The number of node indices of the faces of every element, if all have the same number.
+    face_sizes: Vec<Option<usize>>,
+}
+
+impl<'a> PlyView<'a> {
+    /// Parse the header and find the data of every element.
+    ///
+    /// The node indices of faces are checked while skipping over them, all other values are
+    /// only decoded when they are read.
+    pub fn new(data: &'a [u8]) -> Result<PlyView<'a>, Error> {
+        let mut rest = data;
+        let header = parse_header(&mut rest)?;
+        let dimension = header.dimension()?.unwrap_or(0);
+        let big_endian = match header.encoding {
+            Encoding::Ascii => {
+                return Err(Error::Unsupported(
+                    "Only binary ply files can be viewed.".into(),
+                ))
+            }
+            encoding => encoding == Encoding::BinaryBigEndian,
+        };
+
+        let mut bytes = Vec::with_capacity(header.elements.len());
+        let mut face_sizes = Vec::with_capacity(header.elements.len());
+        for element in &header.elements {
+            let start = rest;
+            let has_indices = element.kind() == EntityKind::Element
+                && INDEX_PROPERTIES
+                    .iter()
+                    .any(|&name| element.has_property(name));
+            let len = match element.record_size() {
+                Some(size) if !has_indices => {
+                    face_sizes.push(None);
+                    size.saturating_mul(element.len)
                 }
-                "end_header" => {
-                    header_ended = true;
-                    break;
+                _ => {
+                    let mut values = Binary {
+                        source: &mut rest,
+                        big_endian,
+                    };
+                    face_sizes.push(check_records(element, &mut values, header.num_vertices())?);
+                    start.len() - rest.len()
                 }
-                kwd => return Err(Error::Syntax(format!("Unknown keyword: {}", kwd))),
+            };
+            if len > start.len() {
+                return Err(Error::Syntax("Unexpected end of binary data.".into()));
             }
+            bytes.push(&start[..len]);
+            rest = &start[len..];
         }
+        Ok(PlyView {
+            header,
+            dimension,
+            bytes,
+            face_sizes,
+        })
+    }
 
-        if !header_ended {
-            return Err(Error::Syntax(
-                "Header not terminated by `end_header` keyword.".into(),
-            ));
-        }
+    /// The values of property `name` of all entities of element `element`.
+    ///
+    /// Returns `None` if there is no such property, it has another type than `T`, or it can't
+    /// be borrowed as explained in the [type documentation](PlyView).
+    pub fn property<T: PlyScalar>(&self, element: &str, name: &str) -> Option<Strided<'a, T>> {
+        let i = self
+            .header
+            .elements
+            .iter()
+            .position(|e| e.name == element)?;
+        self.properties(i, &[name])
+    }
 
-        // Parse body.
-        for ref group_info in groups.iter() {
-            target.de_group_begin(&group_info.data)?;
+    /// The values of the consecutive properties `names` of all entities of element `i`.
+    fn properties<T: PlyScalar>(&self, i: usize, names: &[&str]) -> Option<Strided<'a, T>> {
+        let element = &self.header.elements[i];
+        let size = mem::size_of::<T>();
+        let record_size = element.record_size()?;
+        let (offset, _) = element.scalar_offset(names[0])?;
+        for (j, name) in names.iter().enumerate() {
+            match element.scalar_offset(name) {
+                Some((o, data_type))
+                    if o == offset + j * size && T::NAME.parse().ok() == Some(data_type) => {}
+                _ => return None,
+            }
+        }
+        self.strided(i, offset, names.len(), record_size)
+    }
 
-            for _ in 0..group_info.data.size().unwrap() {
-                let mut attr = Attr::new();
-                for (ref attr_name, ref _attr_type) in &group_info.attrs {
-                    let val = reader.next_result()?;
-                    attr.insert(attr_name.clone(), val.into());
+    /// `width` values of type `T` at byte `offset` of the records of element `i`, if they are
+    /// in the byte order of this machine and aligned.
+    fn strided<T: PlyScalar>(
+        &self,
+        i: usize,
+        offset: usize,
+        width: usize,
+        record_size: usize,
+    ) -> Option<Strided<'a, T>> {
+        let size = mem::size_of::<T>();
+        let big_endian = self.header.encoding == Encoding::BinaryBigEndian;
+        if big_endian != cfg!(target_endian = "big")
+            || !offset.is_multiple_of(size)
+            || !record_size.is_multiple_of(size)
+        {
+            return None;
+        }
+        let values: &'a [T] = bytemuck::try_cast_slice(self.bytes[i]).ok()?;
+        Strided::new(
+            values.get(offset / size..).unwrap_or_default(),
+            self.header.elements[i].len,
+            width,
+            record_size / size,
+        )
+    }
+}
 
-                    // TODO: respect the attr types?
-                    /*
-                    match attr_type {
-                        DataType::Char | DataType::Short | DataType::Int => {
+/// Skip over the records of an element, checking the node indices of faces.
+///
+/// Returns the number of node indices of the faces, if all of them have the same number.
+fn check_records<V: Values>(
+    element: &Element,
+    values: &mut V,
+    num_vertices: usize,
+) -> Result<Option<usize>, Error> {
+    let faces = element.kind() == EntityKind::Element;
+    let mut face_size = None;
+    let mut uniform = faces;
+    for _ in 0..element.len {
+        for property in &element.properties {
+            match property.property_type {
+                PropertyType::Scalar(data_type) => {
+                    let value = values.next_value(data_type)?;
+                    if faces && INDEX_PROPERTIES.contains(&property.name.as_str()) {
+                        check_node(&value, num_vertices)?;
+                    }
+                }
+                PropertyType::List(len_type, item_type) => {
+                    let len = list_len(values.next_value(len_type)?)?;
+                    let indices = faces && INDEX_PROPERTIES.contains(&property.name.as_str());
+                    if indices && *face_size.get_or_insert(len) != len {
+                        uniform = false;
+                    }
+                    for _ in 0..len {
+                        let value = values.next_value(item_type)?;
+                        if indices {
+                            check_node(&value, num_vertices)?;
                         }
                     }
-                    */                }
-                // TODO: handle the different types of entities.
-                target.de_entity(&&Entity { attr }, &group_info.data)?;
+                }
+            }
+        }
+    }
+    Ok(face_size.filter(|_| uniform))
+}
+
+impl<'a> GetMesh for PlyView<'a> {
+    type Entity = EntityBox;
+    type GroupReader<'m>
+        = PlyGroupReader<'m>
+    where
+        Self: 'm;
+    type GroupReaders<'m>
+        = Box<dyn Iterator<Item = PlyGroupReader<'m>> + 'm>
+    where
+        Self: 'm;
+
+    fn metadata(&self) -> MeshMetadata {
+        MeshMetadata::new(self.dimension)
+    }
+
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        Box::new(
+            (0..self.header.elements.len()).map(move |i| PlyGroupReader {
+                view: self,
+                element: i,
+                rest: self.bytes[i],
+                remaining: self.header.elements[i].len,
+            }),
+        )
+    }
+}
+
+pub struct PlyGroupReader<'m> {
+    view: &'m PlyView<'m>,
+    element: usize,
+    /// The bytes of the entities which haven't been read yet.
+    rest: &'m [u8],
+    remaining: usize,
+}
+
+impl<'m> GetMeshGroup for PlyGroupReader<'m> {
+    fn metadata(&self) -> GroupMetadata {
+        let element = &self.view.header.elements[self.element];
+        let kind = element.kind();
+        let name = Name::parse(element.name.clone(), Format::Ply, kind).unwrap();
+        GroupMetadata::new(name, kind, element.len)
+    }
+
+    /// Available if the coordinates of vertices are doubles, followed by each other.
+    fn positions(&self) -> Option<Strided<'_, f64>> {
+        let element = &self.view.header.elements[self.element];
+        if element.kind() != EntityKind::Node {
+            return None;
+        }
+        let dimension = self.view.dimension as usize;
+        self.view
+            .properties(self.element, &keys::POSITION[..dimension])
+    }
+
+    /// Available if all faces have the same number of node indices of type `int` or `uint`,
+    /// which are aligned, and the faces have no other lists.
+    fn connectivity(&self) -> Option<Strided<'_, u32>> {
+        let element = &self.view.header.elements[self.element];
+        let num_nodes = self.view.face_sizes[self.element]?;
+        let mut offset = None;
+        let mut record_size = 0;
+        for property in &element.properties {
+            match property.property_type {
+                PropertyType::Scalar(data_type) => record_size += data_type.size(),
+                PropertyType::List(len_type, item_type)
+                    if offset.is_none()
+                        && INDEX_PROPERTIES.contains(&property.name.as_str())
+                        && (item_type == DataType::Int || item_type == DataType::Uint) =>
+                {
+                    // Note: Negative indices were rejected by `PlyView::new`, so `int` indices
+                    // have the same bytes as `uint` ones.
+                    offset = Some(record_size + len_type.size());
+                    record_size += len_type.size() + num_nodes * item_type.size();
+                }
+                PropertyType::List(..) => return None,
             }
+        }
+        self.view
+            .strided(self.element, offset?, num_nodes, record_size)
+    }
+}
+
+impl<'m> Iterator for PlyGroupReader<'m> {
+    type Item = EntityBox;
 
-            target.de_group_end(&group_info.data)?;
+    fn next(&mut self) -> Option<EntityBox> {
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
+        let mut values = Binary {
+            source: &mut self.rest,
+            big_endian: self.view.header.encoding == Encoding::BinaryBigEndian,
+        };
+        let element = &self.view.header.elements[self.element];
+        let entity = read_entity(element, &mut values, self.view.header.num_vertices());
+        // Note: The length of the data and the node indices were checked by `PlyView::new`.
+        Some(entity.expect("ply data checked by PlyView::new"))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// The node indices of a face, checked against the number of vertices.
+fn face_nodes(value: &AttributeValue, num_vertices: usize) -> Result<Vec<usize>, Error> {
+    let nodes = value
+        .to_usize_list()
+        .ok_or_else(|| Error::Syntax(format!("Bad vertex indices: {}", value)))?;
+    if let Some(&i) = nodes.iter().find(|&&i| i >= num_vertices) {
+        return Err(Error::Syntax(format!("Undefined vertex {}.", i)));
+    }
+    Ok(nodes)
+}
+
+/// Check a single node index of a face against the number of vertices.
+fn check_node(value: &AttributeValue, num_vertices: usize) -> Result<(), Error> {
+    match value.to_usize() {
+        Some(i) if i < num_vertices => Ok(()),
+        Some(i) => Err(Error::Syntax(format!("Undefined vertex {}.", i))),
+        None => Err(Error::Syntax(format!("Bad vertex index: {}", value))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{attribute::AttributeContainer, face_vertex::Mesh, Entity, GetMesh};
+    use de::read_events;
+    use nalgebra::Point3;
+    use std::{env, fs};
+
+    const SQUARE: &str = "ply
+format ascii 1.0
+comment two triangles and a quadrilateral
+element vertex 5
+property float x
+property float y
+property float z
+property uchar red
+element face 2
+property list uchar int vertex_indices
+property short ref
+end_header
+0 0 0 255
+1 0 0 0
+1 1 0 0
+0 1 0 0
+0.5 0.5 1 128
+4 0 1 2 3 -2
+3 0 1 4 7
+";
+
+    #[test]
+    fn keep_declared_types() {
+        let mut mesh = Mesh::default();
+        PlyDeserializer::deserialize_into(SQUARE.as_bytes(), &mut mesh).unwrap();
+        assert_eq!(mesh.metadata().dimension(), 3);
+
+        let apex = &mesh.node_groups()[0].entities()[4];
+        assert_eq!(apex.position(3).unwrap(), Point3::new(0.5, 0.5, 1.));
+        let attr = apex.attributes();
+        assert_eq!(attr.get(&"x".into()), Some(&AttributeValue::F32(0.5)));
+        assert_eq!(attr.get(&"red".into()), Some(&AttributeValue::U8(128)));
+
+        let faces = mesh.element_groups()[0].entities();
+        assert_eq!(
+            faces[0].attributes().get(&"cell".into()).unwrap(),
+            "quadrilateral"
+        );
+        assert_eq!(faces[1].node_indices().unwrap(), vec![0, 1, 4]);
+        assert_eq!(
+            faces[0].attributes().get(&"ref".into()),
+            Some(&AttributeValue::I16(-2))
+        );
+
+        assert_eq!(PlyDeserializer::sniff(SQUARE.as_bytes()), Confidence::High);
+    }
+
+    #[test]
+    fn parallel_vertices() {
+        let read = |source: &str, parallel: bool| {
+            let mut mesh = Mesh::default();
+            let result = read_events(events(source.as_bytes(), parallel), &mut mesh);
+            (format!("{:?}", mesh), result.err().map(|e| e.to_string()))
+        };
+        let broken = SQUARE.replace("1 1 0 0", "1 1 x 0");
+        for source in &[SQUARE, &broken, &SQUARE[..SQUARE.find("0 1 0 0").unwrap()]] {
+            assert_eq!(read(source, true), read(source, false));
+        }
+        let error = read(&broken, true).1.unwrap();
+        assert!(error.ends_with("Bad value for type Float: x in line 15"));
+    }
+
+    #[test]
+    fn reject_undefined_vertex() {
+        let source = SQUARE.replace("3 0 1 4 7", "3 0 1 5 7");
+        assert!(
+            PlyDeserializer::deserialize_into(source.as_bytes(), &mut Mesh::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn reject_malformed_binary() {
+        let header = |face: &str| {
+            format!(
+                "ply\nformat binary_little_endian 1.0\nelement vertex 1\nproperty float x\n\
+                 property float y\nelement face 1\n{}\nend_header\n",
+                face
+            )
+            .into_bytes()
+        };
+        let vertex = [0f32.to_le_bytes(), 0f32.to_le_bytes()].concat();
+
+        // A face with a scalar node index, which is checked like the items of lists.
+        let scalar = |index: u32| {
+            [
+                header("property uint vertex_index"),
+                vertex.clone(),
+                index.to_le_bytes().to_vec(),
+            ]
+            .concat()
+        };
+        let valid = scalar(0);
+        let view = PlyView::new(&valid).unwrap();
+        let faces: Vec<EntityBox> = view.groups().nth(1).unwrap().collect();
+        assert_eq!(faces[0].node_indices().unwrap(), vec![0]);
+        assert!(PlyView::new(&scalar(7)).is_err());
+        assert!(PlyDeserializer::deserialize_into(&scalar(7)[..], &mut Mesh::default()).is_err());
+
+        // A list whose length is far beyond the data.
+        let long = [
+            header("property list uint uint vertex_indices"),
+            vertex,
+            4_000_000_000u32.to_le_bytes().to_vec(),
+            0u32.to_le_bytes().to_vec(),
+        ]
+        .concat();
+        assert!(PlyView::new(&long).is_err());
+        assert!(PlyDeserializer::deserialize_into(&long[..], &mut Mesh::default()).is_err());
+        let ascii = SQUARE.replace("3 0 1 4 7", "4000000000 0 1 4 7");
+        assert!(PlyDeserializer::deserialize_into(ascii.as_bytes(), &mut Mesh::default()).is_err());
+    }
+
+    /// SQUARE in a binary encoding.
+    fn binary_square(big_endian: bool) -> Vec<u8> {
+        let encoding = if big_endian { "big" } else { "little" };
+        let header = SQUARE[..SQUARE.find("end_header\n").unwrap() + 11]
+            .replace("ascii", &format!("binary_{}_endian", encoding));
+        let mut data = header.into_bytes();
+        let values = SQUARE.lines().skip_while(|l| *l != "end_header").skip(1);
+        // The types of the values of the five vertices and the two faces.
+        let types = vec!["fffB"; 5].into_iter().chain(vec!["Biiiih", "Biiih"]);
+        for (line, types) in values.zip(types) {
+            for (word, t) in line.split_whitespace().zip(types.chars()) {
+                let mut bytes = match t {
+                    'f' => word.parse::<f32>().unwrap().to_le_bytes().to_vec(),
+                    'B' => word.parse::<u8>().unwrap().to_le_bytes().to_vec(),
+                    'i' => word.parse::<i32>().unwrap().to_le_bytes().to_vec(),
+                    _ => word.parse::<i16>().unwrap().to_le_bytes().to_vec(),
+                };
+                if big_endian {
+                    bytes.reverse();
+                }
+                data.extend(bytes);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn binary_encodings() {
+        let mut expected = Mesh::default();
+        PlyDeserializer::deserialize_into(SQUARE.as_bytes(), &mut expected).unwrap();
+        for &big_endian in &[false, true] {
+            let data = binary_square(big_endian);
+            assert_eq!(PlyDeserializer::sniff(&data), Confidence::High);
+            let mut mesh = Mesh::default();
+            PlyDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+            assert_eq!(format!("{:?}", mesh), format!("{:?}", expected));
+
+            let view = PlyView::new(&data).unwrap();
+            let entities: Vec<Vec<EntityBox>> = view.groups().map(|g| g.collect()).collect();
+            let expected_entities: Vec<Vec<EntityBox>> =
+                expected.groups().map(|g| g.collect()).collect();
+            assert_eq!(
+                format!("{:?}", entities),
+                format!("{:?}", expected_entities)
+            );
+
+            let truncated = &data[..data.len() - 1];
+            assert!(PlyDeserializer::deserialize_into(truncated, &mut Mesh::default()).is_err());
+            assert!(PlyView::new(truncated).is_err());
+        }
+
+        // The last face has the node indices 0, 1 and 4, followed by a short.
+        let mut undefined = binary_square(false);
+        let last_index = undefined.len() - 6;
+        undefined[last_index] = 5;
+        assert!(PlyView::new(&undefined).is_err());
+        assert!(PlyView::new(SQUARE.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn borrow_mapped_values() {
+        let mut data = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\n\
+            property double x\nproperty double y\nproperty double z\nproperty uint id\n\
+            property uint ref\nend_header\n"
+            .to_vec();
+        for i in 0..3 {
+            for &c in &[i as f64, 1., 0.5] {
+                data.extend(c.to_le_bytes().iter());
+            }
+            data.extend((10 * i as u32).to_le_bytes().iter());
+            data.extend(7u32.to_le_bytes().iter());
+        }
+
+        // The header is 153 bytes long, a comment moves the values to a multiple of eight.
+        let path = env::temp_dir().join(format!("multimesh-{}.ply", ::std::process::id()));
+        let comment = format!("comment {}\n", "-".repeat(6));
+        let padded = [&data[..4], comment.as_bytes(), &data[4..]].concat();
+        for &(bytes, aligned) in &[(&padded, true), (&data, false)] {
+            fs::write(&path, bytes).unwrap();
+            let file = MappedFile::open(&path).unwrap();
+            let view = PlyView::new(&file).unwrap();
+            assert_eq!(view.metadata().dimension(), 3);
+
+            let vertices = view.groups().next().unwrap();
+            let points = geometry::collect_points(&view, 3).unwrap();
+            assert_eq!(points, vec![[0., 1., 0.5], [1., 1., 0.5], [2., 1., 0.5]]);
+            match vertices.positions() {
+                Some(positions) => {
+                    assert!(aligned);
+                    assert_eq!(positions.len(), 3);
+                    assert_eq!(positions.get(2), &[2., 1., 0.5]);
+                    assert_eq!(positions.as_slice(), None);
+                }
+                None => assert!(!aligned),
+            }
+            let ids = view.property::<u32>("vertex", "id");
+            assert_eq!(ids.is_some(), aligned);
+            if let Some(ids) = ids {
+                assert_eq!(ids.iter().collect::<Vec<_>>(), vec![&[0], &[10], &[20]]);
+            }
+            assert!(view.property::<i32>("vertex", "id").is_none());
+            assert!(view.property::<u32>("vertex", "red").is_none());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn borrow_connectivity() {
+        // A little endian file with four vertices and the given faces, whose node indices are
+        // aligned if the lengths of lists have four bytes.
+        let file = |len_type: &str, faces: &[&[i32]]| -> Vec<u32> {
+            let header = format!(
+                "ply\nformat binary_little_endian 1.0\nelement vertex 4\nproperty float x\n\
+                 property float y\nelement face {}\nproperty list {} int vertex_indices\n\
+                 property uint ref\nend_header\n",
+                faces.len(),
+                len_type
+            );
+            let padding = "-".repeat(3 - (header.len() + 8) % 4);
+            let mut data = format!("ply\ncomment {}\n{}", padding, &header[4..]).into_bytes();
+            for i in 0..4 {
+                for &c in &[(i % 2) as f32, (i / 2) as f32] {
+                    data.extend(c.to_le_bytes().iter());
+                }
+            }
+            for (i, face) in faces.iter().enumerate() {
+                match len_type {
+                    "uchar" => data.push(face.len() as u8),
+                    _ => data.extend((face.len() as u32).to_le_bytes().iter()),
+                }
+                for &node in face.iter() {
+                    data.extend(node.to_le_bytes().iter());
+                }
+                data.extend((i as u32).to_le_bytes().iter());
+            }
+            // Note: The values are copied into words, so their alignment is the one in the file.
+            let mut words = vec![0u32; data.len().div_ceil(4)];
+            bytemuck::cast_slice_mut::<u32, u8>(&mut words)[..data.len()].copy_from_slice(&data);
+            words
+        };
+        let connectivity = |words: &[u32]| {
+            let view = PlyView::new(bytemuck::cast_slice(words)).unwrap();
+            let faces = view.groups().nth(1).unwrap();
+            let nodes = faces.connectivity();
+            nodes.map(|c| format!("{:?}", c))
+        };
 
-        Ok(())
+        let triangles = file("uint", &[&[0, 1, 2], &[2, 1, 3]]);
+        let expected = Some("[[0, 1, 2], [2, 1, 3]]".to_string());
+        assert_eq!(connectivity(&triangles), expected);
+        assert_eq!(
+            connectivity(&file("uint", &[&[0, 1, 2], &[0, 1, 3, 2]])),
+            None
+        );
+        assert_eq!(
+            connectivity(&file("uchar", &[&[0, 1, 2], &[2, 1, 3]])),
+            None
+        );
     }
 }
@@ -2,12 +2,128 @@
 //!
 //! This module makes it possible to integrate readers and writers from foreign crates
 //! with multimesh.
+//!
+//! A format is registered as [FormatEntry] with its name, its file extensions and handlers
+//! for reading and writing. Handlers work on the object safe mesh traits of
+//! [dynamic](::data::dynamic), [FormatEntry::read] and [FormatEntry::write] accept any
+//! [SetMesh] and [GetMesh] implementation.
+//!
+//! Files with unknown format can be read with [FormatRegistry::read_auto], which asks every
+//! read handler how confident it is that the first bytes belong to its format.
+//!
+//! Sources compressed with gzip, zstd or xz are decompressed on the fly, and formats are
+//! recognized by the decompressed data, see [compression](::format::compression). Files are
+//! read and written with [FormatRegistry::read_path] and [FormatRegistry::write_path], which
+//! compress files whose name ends with the extension of a compression.
+
+use data::{
+    dynamic::{DynGetMesh, DynSetMesh},
+    GetMesh, SetMesh,
+};
+use de::{Confidence, Deserializer, SNIFF_LEN};
+use error::Error;
+use format::{
+    amf::{AmfDeserializer, AmfSerializer, Compression as AmfCompression},
+    compression::{decompress, CompressedWriter, Compression},
+    dolfin::{DolfinDeserializer, DolfinSerializer},
+    dxf::DxfDeserializer,
+    fluent::FluentDeserializer,
+    frd::FrdDeserializer,
+    lsdyna::{LsDynaDeserializer, LsDynaSerializer},
+    medit::{MeditBinaryDeserializer, MeditDeserializer, MeditSerializer},
+    naming::Format,
+    netgen::{NetgenDeserializer, NetgenSerializer},
+    ply::PlyDeserializer,
+    stl::StlDeserializer,
+    tecplot::{DataPacking, TecplotDeserializer, TecplotSerializer},
+    ugrid::{ByteOrder, Encoding, Precision, UgridDeserializer, UgridSerializer},
+    xdmf::{XdmfDeserializer, XdmfSerializer},
+};
+use ser::Serializer;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Cursor, Read, Write},
+    marker::PhantomData,
+    path::Path,
+    process,
+};
 
 /// Returns a [FormatRegistry] containing all the available within crate formats.
 ///
 /// If you want additional formats, you have to add them manually.
+///
+/// The binary UGRID encodings are registered as formats of their own (`ugrid-b8` etc.), since
+/// they are told apart by extension when writing. All of them detect the encoding when reading.
 pub fn default_registry() -> FormatRegistry {
-    unimplemented!()
+    let mut registry = FormatRegistry::empty();
+    registry.register(
+        FormatEntry::new(Format::Medit, &["mesh"])
+            .with_deserializer::<MeditDeserializer>()
+            .with_writer(MeditSerializer::new()),
+    );
+    registry.register(
+        FormatEntry::new(Format::Medit, &["meshb"])
+            .with_name("medit-binary")
+            .with_deserializer::<MeditBinaryDeserializer>(),
+    );
+    registry.register(
+        FormatEntry::new(Format::Fluent, &["msh"]).with_deserializer::<FluentDeserializer>(),
+    );
+    registry.register(
+        FormatEntry::new(Format::Netgen, &["vol"])
+            .with_deserializer::<NetgenDeserializer>()
+            .with_writer(NetgenSerializer::new()),
+    );
+    registry.register(
+        FormatEntry::new(Format::Ugrid, &["ugrid"])
+            .with_deserializer::<UgridDeserializer>()
+            .with_writer(UgridSerializer::new(Encoding::Ascii)),
+    );
+    for &(suffix, byte_order) in &[("b", ByteOrder::BigEndian), ("lb", ByteOrder::LittleEndian)] {
+        for &(bytes, precision) in &[(4, Precision::Single), (8, Precision::Double)] {
+            let encoding = Encoding::Binary(byte_order, precision);
+            registry.register(
+                FormatEntry::new(Format::Ugrid, &[&format!("{}{}.ugrid", suffix, bytes)])
+                    .with_name(&format!("ugrid-{}{}", suffix, bytes))
+                    .with_deserializer::<UgridDeserializer>()
+                    .with_writer(UgridSerializer::new(encoding)),
+            );
+        }
+    }
+    registry.register(
+        FormatEntry::new(Format::Tecplot, &["dat"])
+            .with_deserializer::<TecplotDeserializer>()
+            .with_writer(TecplotSerializer::new(DataPacking::Point)),
+    );
+    registry.register(
+        FormatEntry::new(Format::Dolfin, &["xml"])
+            .with_deserializer::<DolfinDeserializer>()
+            .with_writer(DolfinSerializer::new()),
+    );
+    registry.register(
+        FormatEntry::new(Format::Xdmf, &["xdmf", "xmf"])
+            .with_deserializer::<XdmfDeserializer>()
+            .with_writer(XdmfSerializer::new()),
+    );
+    registry
+        .register(FormatEntry::new(Format::Frd, &["frd"]).with_deserializer::<FrdDeserializer>());
+    registry.register(
+        FormatEntry::new(Format::LsDyna, &["k", "key", "dyn"])
+            .with_deserializer::<LsDynaDeserializer>()
+            .with_writer(LsDynaSerializer::new()),
+    );
+    registry.register(
+        FormatEntry::new(Format::Amf, &["amf"])
+            .with_deserializer::<AmfDeserializer>()
+            .with_writer(AmfSerializer::new(AmfCompression::None)),
+    );
+    registry
+        .register(FormatEntry::new(Format::Dxf, &["dxf"]).with_deserializer::<DxfDeserializer>());
+    registry
+        .register(FormatEntry::new(Format::Ply, &["ply"]).with_deserializer::<PlyDeserializer>());
+    registry
+        .register(FormatEntry::new(Format::Stl, &["stl"]).with_deserializer::<StlDeserializer>());
+    registry
 }
 
 /// Manages a set of available formats.
@@ -16,16 +132,186 @@ pub struct FormatRegistry {
 }
 
 pub struct FormatEntry {
+    /// The format, which also identifies the names of its groups.
+    format: Format,
     /// Name of the format.
     name: String,
+    /// File extensions without leading dot, like `mesh` or `b8.ugrid`.
+    extensions: Vec<String>,
 
     read_handler: Option<Box<dyn ReadHandler>>,
     write_handler: Option<Box<dyn WriteHandler>>,
 }
 
-pub trait ReadHandler {}
+/// What a registered format is able to do.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+    pub read: bool,
+    pub write: bool,
+}
+
+/// Reads a format into any mesh.
+pub trait ReadHandler {
+    fn read(&self, source: &mut dyn Read, target: &mut dyn DynSetMesh) -> Result<(), Error>;
+
+    /// Guess whether data is in this format by its first (at most [SNIFF_LEN]) bytes.
+    fn sniff(&self, _head: &[u8]) -> Confidence {
+        Confidence::No
+    }
+}
+
+/// Writes any mesh into a format.
+///
+/// Implemented for every [Serializer].
+pub trait WriteHandler {
+    fn write(&self, mesh: &dyn DynGetMesh, target: &mut dyn Write) -> Result<(), Error>;
+}
+
+/// The [ReadHandler] of a [Deserializer], see [FormatEntry::with_deserializer].
+struct DeserializerHandler<D>(PhantomData<D>);
+
+impl<D: Deserializer> ReadHandler for DeserializerHandler<D> {
+    fn read(&self, source: &mut dyn Read, mut target: &mut dyn DynSetMesh) -> Result<(), Error> {
+        D::deserialize_into(source, &mut target)
+    }
+
+    fn sniff(&self, head: &[u8]) -> Confidence {
+        D::sniff(head)
+    }
+}
 
-pub trait WriteHandler {}
+impl<S: Serializer> WriteHandler for S {
+    fn write(&self, mesh: &dyn DynGetMesh, target: &mut dyn Write) -> Result<(), Error> {
+        self.serialize(&mesh, target)
+    }
+}
+
+impl FormatEntry {
+    /// Create an entry without handlers, named after the identifier of the format.
+    pub fn new(format: Format, extensions: &[&str]) -> Self {
+        FormatEntry {
+            format,
+            name: format.id().to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            read_handler: None,
+            write_handler: None,
+        }
+    }
+
+    /// Use another name, to register variants of a format, like its encodings.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn with_reader<R: ReadHandler + 'static>(mut self, reader: R) -> Self {
+        self.read_handler = Some(Box::new(reader));
+        self
+    }
+
+    /// Use a [Deserializer] as read handler.
+    pub fn with_deserializer<D: Deserializer + 'static>(self) -> Self {
+        self.with_reader(DeserializerHandler::<D>(PhantomData))
+    }
+
+    pub fn with_writer<W: WriteHandler + 'static>(mut self, writer: W) -> Self {
+        self.write_handler = Some(Box::new(writer));
+        self
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            read: self.read_handler.is_some(),
+            write: self.write_handler.is_some(),
+        }
+    }
+
+    pub fn reader(&self) -> Option<&dyn ReadHandler> {
+        self.read_handler.as_deref()
+    }
+
+    pub fn writer(&self) -> Option<&dyn WriteHandler> {
+        self.write_handler.as_deref()
+    }
+
+    /// Read a mesh of this format, fails if the format can't be read.
+    ///
+    /// Compressed sources are decompressed first.
+    pub fn read<S: Read, T: SetMesh>(&self, source: S, target: &mut T) -> Result<(), Error> {
+        match self.reader() {
+            Some(reader) => reader.read(&mut decompress(source)?.0, target),
+            None => Err(Error::Unsupported(format!(
+                "Format {} can't be read.",
+                self.name
+            ))),
+        }
+    }
+
+    /// Write a mesh in this format, fails if the format can't be written.
+    pub fn write<M: GetMesh, W: Write>(&self, mesh: &M, mut target: W) -> Result<(), Error> {
+        match self.writer() {
+            Some(writer) => writer.write(mesh, &mut target),
+            None => Err(Error::Unsupported(format!(
+                "Format {} can't be written.",
+                self.name
+            ))),
+        }
+    }
+
+    /// Read a file of this format, which is decompressed if it is compressed.
+    pub fn read_path<P: AsRef<Path>, T: SetMesh>(
+        &self,
+        path: P,
+        target: &mut T,
+    ) -> Result<(), Error> {
+        self.read(BufReader::new(File::open(path)?), target)
+    }
+
+    /// Write a mesh in this format into a file, compressed if the file name ends with the
+    /// extension of a [Compression].
+    ///
+    /// The mesh is written into a temporary file next to `path`, which only replaces `path` if
+    /// writing succeeds, so a failed write leaves no truncated file behind.
+    pub fn write_path<M: GetMesh, P: AsRef<Path>>(&self, mesh: &M, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        if self.writer().is_none() {
+            // Note: Fails before a file is created.
+            return self.write(mesh, io::sink());
+        }
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.tmp", process::id()));
+        let temporary = path.with_file_name(name);
+
+        let write = || -> Result<(), Error> {
+            let file = File::create(&temporary)?;
+            let mut target =
+                CompressedWriter::new(BufWriter::new(file), Compression::from_path(path))?;
+            self.write(mesh, &mut target)?;
+            let file = target.finish()?.into_inner().map_err(|e| e.into_error())?;
+            file.sync_all()?;
+            fs::rename(&temporary, path)?;
+            Ok(())
+        };
+        let result = write();
+        if result.is_err() {
+            // Note: The error of writing is more useful than one of removing.
+            let _ = fs::remove_file(&temporary);
+        }
+        result
+    }
+}
 
 impl FormatRegistry {
     /// Create an empty registry.
@@ -39,7 +325,339 @@ impl FormatRegistry {
         &self.formats
     }
 
+    /// Add a format. Formats registered later take precedence in lookups.
     pub fn register(&mut self, entry: FormatEntry) {
         self.formats.push(entry);
     }
+
+    /// Find a format by its name, ignoring case.
+    pub fn by_name(&self, name: &str) -> Option<&FormatEntry> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find the entry of a format, the first variant registered if there are several.
+    pub fn by_format(&self, format: Format) -> Option<&FormatEntry> {
+        self.formats.iter().find(|entry| entry.format == format)
+    }
+
+    /// Find a format by a file extension without leading dot, ignoring case.
+    pub fn by_extension(&self, extension: &str) -> Option<&FormatEntry> {
+        self.formats.iter().rev().find(|entry| {
+            entry
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+
+    /// Find a format by the file name of a path, ignoring case.
+    ///
+    /// The longest matching extension wins, so `grid.b8.ugrid` is found by `b8.ugrid` even if
+    /// `ugrid` is registered, too. The extension of a [Compression] is skipped, so
+    /// `cube.mesh.gz` is found by `mesh`.
+    pub fn by_path<P: AsRef<Path>>(&self, path: P) -> Option<&FormatEntry> {
+        let mut name = path.as_ref().file_name()?.to_str()?.to_ascii_lowercase();
+        if let Some((stem, extension)) = name.rsplit_once('.') {
+            if Compression::from_extension(extension).is_some() {
+                name = stem.to_string();
+            }
+        }
+        let mut best: Option<(&FormatEntry, usize)> = None;
+        for entry in self.formats.iter().rev() {
+            for extension in &entry.extensions {
+                let suffix = format!(".{}", extension.to_ascii_lowercase());
+                if name.ends_with(&suffix) && best.is_none_or(|(_, len)| suffix.len() > len) {
+                    best = Some((entry, suffix.len()));
+                }
+            }
+        }
+        best.map(|(entry, _)| entry)
+    }
+
+    /// Find the readable format which is most confident that `head` belongs to it.
+    ///
+    /// Among equally confident formats the one registered last wins.
+    pub fn sniff(&self, head: &[u8]) -> Option<(&FormatEntry, Confidence)> {
+        let head = &head[..head.len().min(SNIFF_LEN)];
+        let mut best: Option<(&FormatEntry, Confidence)> = None;
+        for entry in self.formats.iter().rev() {
+            let confidence = match entry.reader() {
+                Some(reader) => reader.sniff(head),
+                None => continue,
+            };
+            if confidence > Confidence::No && best.is_none_or(|(_, c)| confidence > c) {
+                best = Some((entry, confidence));
+            }
+        }
+        best
+    }
+
+    /// Read a mesh of unknown format, which is recognized by its first bytes.
+    ///
+    /// Compressed sources are recognized by the first bytes of the decompressed data. Returns
+    /// the format which was chosen.
+    pub fn read_auto<S: Read, T: SetMesh>(
+        &self,
+        source: S,
+        target: &mut T,
+    ) -> Result<&FormatEntry, Error> {
+        let (mut source, _) = decompress(source)?;
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        source
+            .by_ref()
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)?;
+        let entry = match self.sniff(&head) {
+            Some((entry, _)) => entry,
+            None => return Err(Error::Unsupported("Format of data not recognized.".into())),
+        };
+        // Note: Sniffed formats have a reader. `FormatEntry::read` is skipped, since the data
+        // is decompressed already.
+        let reader = entry.reader().unwrap();
+        reader.read(&mut Cursor::new(head).chain(source), target)?;
+        Ok(entry)
+    }
+
+    /// Read a file in the format of its extension, or of unknown format if no readable format
+    /// has its extension, see [FormatRegistry::read_auto]. Returns the format which was chosen.
+    pub fn read_path<P: AsRef<Path>, T: SetMesh>(
+        &self,
+        path: P,
+        target: &mut T,
+    ) -> Result<&FormatEntry, Error> {
+        let path = path.as_ref();
+        match self.by_path(path) {
+            Some(entry) if entry.capabilities().read => {
+                entry.read_path(path, target)?;
+                Ok(entry)
+            }
+            _ => self.read_auto(BufReader::new(File::open(path)?), target),
+        }
+    }
+
+    /// Write a mesh into a file in the format of its extension, see [FormatEntry::write_path].
+    /// Returns the format which was chosen.
+    pub fn write_path<M: GetMesh, P: AsRef<Path>>(
+        &self,
+        mesh: &M,
+        path: P,
+    ) -> Result<&FormatEntry, Error> {
+        let path = path.as_ref();
+        let entry = self
+            .by_path(path)
+            .ok_or_else(|| Error::Unsupported(format!("No format for `{}`.", path.display())))?;
+        entry.write_path(mesh, path)?;
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{face_vertex::Mesh, GetMesh};
+    use format::naming::CustomFormat;
+    use std::env;
+
+    #[test]
+    fn lookup() {
+        let registry = default_registry();
+        assert_eq!(registry.by_name("Medit").unwrap().name(), "medit");
+        assert!(registry.by_name("vtk").is_none());
+        assert_eq!(registry.by_name("stl").unwrap().name(), "stl");
+        assert_eq!(
+            registry.by_path("cube.meshb").unwrap().name(),
+            "medit-binary"
+        );
+        assert_eq!(registry.by_extension("XMF").unwrap().name(), "xdmf");
+        assert_eq!(
+            registry.by_path("a/grid.lb8.ugrid").unwrap().name(),
+            "ugrid-lb8"
+        );
+        assert_eq!(registry.by_path("grid.ugrid").unwrap().name(), "ugrid");
+        assert!(registry.by_path("mesh").is_none());
+
+        let fluent = registry.by_name("fluent").unwrap();
+        assert_eq!(
+            fluent.capabilities(),
+            Capabilities {
+                read: true,
+                write: false
+            }
+        );
+        let mesh = Mesh::default();
+        match fluent.write(&mesh, Vec::new()) {
+            Err(Error::Unsupported(_)) => (),
+            _ => panic!("Fluent can't be written."),
+        }
+    }
+
+    #[test]
+    fn read_and_write_through_handlers() {
+        let registry = default_registry();
+        let data = include_bytes!("../../tests/files/two-tets.xml");
+        let mut mesh = Mesh::default();
+        registry
+            .by_path("two-tets.xml")
+            .unwrap()
+            .read(&data[..], &mut mesh)
+            .unwrap();
+
+        let mut output = Vec::new();
+        let netgen = registry.by_name("netgen").unwrap();
+        netgen.write(&mesh, &mut output).unwrap();
+        let mut mesh2 = Mesh::default();
+        netgen.read(&output[..], &mut mesh2).unwrap();
+
+        assert_eq!(mesh2.metadata().dimension(), 3);
+        assert_eq!(mesh2.element_groups()[0].entities().len(), 2);
+    }
+
+    #[derive(Debug)]
+    struct Counter;
+
+    impl CustomFormat for Counter {
+        fn id(&self) -> &'static str {
+            "counter"
+        }
+    }
+
+    static COUNTER: Counter = Counter;
+
+    impl ReadHandler for Counter {
+        fn read(&self, source: &mut dyn Read, target: &mut dyn DynSetMesh) -> Result<(), Error> {
+            let mut data = String::new();
+            source.read_to_string(&mut data)?;
+            target.dyn_set_dimension(data.trim().parse()?);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_format_takes_precedence() {
+        let mut registry = default_registry();
+        registry
+            .register(FormatEntry::new(Format::Custom(&COUNTER), &["mesh"]).with_reader(Counter));
+
+        let mut mesh = Mesh::default();
+        registry
+            .by_extension("mesh")
+            .unwrap()
+            .read(&b"2"[..], &mut mesh)
+            .unwrap();
+        assert_eq!(mesh.metadata().dimension(), 2);
+        let entry = registry.by_format(Format::Custom(&COUNTER)).unwrap();
+        assert_eq!(entry.name(), "counter");
+        assert_eq!(registry.by_format(Format::Ugrid).unwrap().name(), "ugrid");
+    }
+
+    #[test]
+    fn sniff_fixtures() {
+        let registry = default_registry();
+        let fixtures: &[(&[u8], &str)] = &[
+            (
+                include_bytes!("../../tests/files/blender-monkey.mesh"),
+                "medit",
+            ),
+            (
+                include_bytes!("../../tests/files/hex-pyramid.msh"),
+                "fluent",
+            ),
+            (include_bytes!("../../tests/files/two-tets.vol"), "netgen"),
+            (
+                include_bytes!("../../tests/files/cube-wedge.dat"),
+                "tecplot",
+            ),
+            (include_bytes!("../../tests/files/two-tets.xml"), "dolfin"),
+            (include_bytes!("../../tests/files/two-tets.xdmf"), "xdmf"),
+            (include_bytes!("../../tests/files/hex20-tet.frd"), "frd"),
+            (include_bytes!("../../tests/files/parts.k"), "lsdyna"),
+            (include_bytes!("../../tests/files/two-objects.amf"), "amf"),
+            (include_bytes!("../../tests/files/room.dxf"), "dxf"),
+        ];
+        for (data, name) in fixtures {
+            let (entry, confidence) = registry.sniff(data).unwrap();
+            assert_eq!((entry.name(), confidence), (*name, Confidence::High));
+        }
+        assert!(registry.sniff(b"OFF\n3 1 0\n").is_none());
+
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        let mut mesh = Mesh::default();
+        let entry = registry.read_auto(&data[..], &mut mesh).unwrap();
+        assert_eq!(entry.name(), "netgen");
+        assert_eq!(mesh.element_groups()[0].name().get_original().0, "steel");
+    }
+
+    #[test]
+    fn compressed_sources() {
+        let registry = default_registry();
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        assert_eq!(
+            registry.by_path("two-tets.vol.GZ").unwrap().name(),
+            "netgen"
+        );
+        assert!(registry.by_path("two-tets.gz").is_none());
+
+        for &compression in Compression::ALL {
+            let mut writer = CompressedWriter::new(Vec::new(), Some(compression)).unwrap();
+            writer.write_all(data).unwrap();
+            let compressed = writer.finish().unwrap();
+
+            let mut mesh = Mesh::default();
+            let entry = registry.read_auto(&compressed[..], &mut mesh).unwrap();
+            assert_eq!(entry.name(), "netgen");
+            assert_eq!(mesh.element_groups()[0].name().get_original().0, "steel");
+
+            let mut mesh = Mesh::default();
+            entry.read(&compressed[..], &mut mesh).unwrap();
+            assert_eq!(mesh.element_groups()[0].name().get_original().0, "steel");
+        }
+    }
+
+    #[test]
+    fn compressed_files() {
+        let registry = default_registry();
+        let data = include_bytes!("../../tests/files/two-tets.vol");
+        let mut mesh = Mesh::default();
+        registry
+            .by_name("netgen")
+            .unwrap()
+            .read(&data[..], &mut mesh)
+            .unwrap();
+        let medit = registry.by_name("medit").unwrap();
+        let mut plain = Vec::new();
+        medit.write(&mesh, &mut plain).unwrap();
+
+        let dir = env::temp_dir();
+        let id = process::id();
+        for &compression in Compression::ALL {
+            let path = dir.join(format!("multimesh-{}.mesh.{}", id, compression.extension()));
+            assert_eq!(registry.write_path(&mesh, &path).unwrap().name(), "medit");
+            let (mut source, detected) = decompress(File::open(&path).unwrap()).unwrap();
+            assert_eq!(detected, Some(compression));
+            let mut written = Vec::new();
+            source.read_to_end(&mut written).unwrap();
+            assert_eq!(written, plain);
+
+            let mut read = Mesh::default();
+            assert_eq!(
+                registry.read_path(&path, &mut read).unwrap().name(),
+                "medit"
+            );
+            let mut rewritten = Vec::new();
+            medit.write(&read, &mut rewritten).unwrap();
+            assert_eq!(rewritten, plain);
+            fs::remove_file(&path).unwrap();
+        }
+
+        // Netgen can't write meshes without dimension, so no file is left behind.
+        let path = dir.join(format!("multimesh-{}.vol.gz", id));
+        assert!(registry.write_path(&Mesh::default(), &path).is_err());
+        assert!(!path.exists());
+        assert!(registry
+            .write_path(&mesh, dir.join("mesh.unknown"))
+            .is_err());
+    }
 }
@@ -0,0 +1,554 @@
+//! Implementation of STL (stereolithography) import, ASCII and binary files.
+//!
+//! Definition: https://www.fabbers.com/tech/STL_Format
+//!
+//! STL stores the corners of every triangle separately, so points with exactly the same
+//! coordinates are welded into a single node, like by the [DXF reader](::format::dxf). All
+//! points end up in one node group called `vertices`, the triangles of every solid in an
+//! element group named after it. Solids without name, and the single solid of binary files,
+//! are called `solid`. Normals and the attribute bytes of binary files are dropped, since
+//! they can be computed from the triangles or have no agreed meaning.
+//!
+//! A [StlView] reads binary files in place instead, without welding.
+
+use data::{
+    attribute::AttributeMap, CellType, EntityBox, EntityKind, GetMesh, GetMeshGroup, GroupMetadata,
+    MeshMetadata,
+};
+use de::{Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use std::{collections::HashMap, io::Read, iter, vec};
+use util::{geometry, sniff};
+
+/// The length of the header of binary files, including the number of triangles.
+const HEADER_SIZE: usize = 84;
+/// The length of every triangle of binary files: normal, corners and attribute bytes.
+const RECORD_SIZE: usize = 50;
+/// The name of solids without name.
+const DEFAULT_SOLID: &str = "solid";
+
+/// The number of triangles of binary data, if its length matches the count in the header.
+fn binary_len(data: &[u8]) -> Option<usize> {
+    let count = u32::from_le_bytes([
+        *data.get(80)?,
+        *data.get(81)?,
+        *data.get(82)?,
+        *data.get(83)?,
+    ]) as usize;
+    let len = count.checked_mul(RECORD_SIZE)?.checked_add(HEADER_SIZE)?;
+    if len == data.len() {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+/// The corners of the binary triangle `record`, after its normal.
+fn corners(record: &[u8]) -> [[f64; 3]; 3] {
+    let value = |i: usize| {
+        let start = 12 + 4 * i;
+        let bytes = [
+            record[start],
+            record[start + 1],
+            record[start + 2],
+            record[start + 3],
+        ];
+        f64::from(f32::from_le_bytes(bytes))
+    };
+    let mut corners = [[0.; 3]; 3];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        for (j, x) in corner.iter_mut().enumerate() {
+            *x = value(3 * i + j);
+        }
+    }
+    corners
+}
+
+/// Welded points and the triangles of every solid.
+#[derive(Default)]
+struct StlMesh {
+    points: Vec<[f64; 3]>,
+    welded: HashMap<[u64; 3], usize>,
+    solids: Vec<(String, Vec<[usize; 3]>)>,
+    solid_indices: HashMap<String, usize>,
+}
+
+impl StlMesh {
+    fn weld(&mut self, point: [f64; 3]) -> usize {
+        // Adding zero turns -0.0 into 0.0, so both weld together.
+        let key = [
+            (point[0] + 0.0).to_bits(),
+            (point[1] + 0.0).to_bits(),
+            (point[2] + 0.0).to_bits(),
+        ];
+        let points = &mut self.points;
+        *self.welded.entry(key).or_insert_with(|| {
+            points.push(point);
+            points.len() - 1
+        })
+    }
+
+    /// Start adding triangles to solid `name`, returns its index.
+    fn solid(&mut self, name: &str) -> usize {
+        let solids = &mut self.solids;
+        *self
+            .solid_indices
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                solids.push((name.to_string(), Vec::new()));
+                solids.len() - 1
+            })
+    }
+
+    fn add_triangle(&mut self, solid: usize, corners: [[f64; 3]; 3]) {
+        let nodes = [
+            self.weld(corners[0]),
+            self.weld(corners[1]),
+            self.weld(corners[2]),
+        ];
+        self.solids[solid].1.push(nodes);
+    }
+}
+
+fn parse_binary(data: &[u8]) -> StlMesh {
+    let mut mesh = StlMesh::default();
+    let solid = mesh.solid(DEFAULT_SOLID);
+    for record in data[HEADER_SIZE..].chunks_exact(RECORD_SIZE) {
+        mesh.add_triangle(solid, corners(record));
+    }
+    mesh
+}
+
+fn parse_ascii(text: &str) -> Result<StlMesh, Error> {
+    let mut mesh = StlMesh::default();
+    let mut solid = None;
+    // The corners of the open facet.
+    let mut facet: Option<Vec<[f64; 3]>> = None;
+    for (number, line) in text.lines().enumerate() {
+        let syntax = |message: &str| Error::Syntax(format!("{} in line {}", message, number + 1));
+        let mut words = line.split_whitespace();
+        match (words.next(), solid, facet.as_mut()) {
+            (None, _, _) => {}
+            (Some("solid"), None, _) => {
+                let name = line.trim()["solid".len()..].trim();
+                let name = if name.is_empty() { DEFAULT_SOLID } else { name };
+                solid = Some(mesh.solid(name));
+            }
+            (Some("facet"), Some(_), None) => facet = Some(Vec::with_capacity(3)),
+            (Some("outer"), Some(_), Some(_)) | (Some("endloop"), Some(_), Some(_)) => {}
+            (Some("vertex"), Some(_), Some(corners)) => {
+                let mut point = [0.; 3];
+                for x in &mut point {
+                    *x = words
+                        .next()
+                        .ok_or_else(|| syntax("Missing coordinate"))?
+                        .parse()
+                        .map_err(|_| syntax("Bad coordinate"))?;
+                }
+                corners.push(point);
+            }
+            (Some("endfacet"), Some(index), Some(corners)) => {
+                if corners.len() != 3 {
+                    return Err(syntax(&format!("Facet with {} vertices", corners.len())));
+                }
+                mesh.add_triangle(index, [corners[0], corners[1], corners[2]]);
+                facet = None;
+            }
+            (Some("endsolid"), Some(_), None) => solid = None,
+            (Some(keyword), _, _) => {
+                return Err(syntax(&format!("Unexpected keyword '{}'", keyword)));
+            }
+        }
+    }
+    if solid.is_some() {
+        return Err(Error::Syntax("Missing 'endsolid'.".into()));
+    }
+    Ok(mesh)
+}
+
+/// Parse a binary file if its length matches the number of triangles, an ASCII file
+/// otherwise.
+fn parse<S: Read>(mut source: S) -> Result<StlMesh, Error> {
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
+    if binary_len(&data).is_some() {
+        return Ok(parse_binary(&data));
+    }
+    match std::str::from_utf8(&data) {
+        Ok(text) if text.trim_start().starts_with("solid") => parse_ascii(text),
+        _ => Err(Error::Syntax(
+            "Neither ASCII STL nor binary STL of the length given by its header.".into(),
+        )),
+    }
+}
+
+/// The part of a [StlMesh] which [StlEvents] produces next.
+enum Stage {
+    Dimension,
+    /// The next point, or `None` before the group of points started.
+    Points(Option<usize>),
+    Solids,
+}
+
+/// The events of a parsed STL file, whose entities are created one at a time.
+struct StlEvents {
+    points: Vec<[f64; 3]>,
+    solids: vec::IntoIter<(String, Vec<[usize; 3]>)>,
+    /// The remaining triangles of the open solid.
+    triangles: Option<vec::IntoIter<[usize; 3]>>,
+    stage: Stage,
+}
+
+impl StlEvents {
+    fn new(mesh: StlMesh) -> Self {
+        StlEvents {
+            points: mesh.points,
+            solids: mesh.solids.into_iter(),
+            triangles: None,
+            stage: Stage::Dimension,
+        }
+    }
+}
+
+impl Iterator for StlEvents {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        match self.stage {
+            Stage::Dimension => {
+                self.stage = Stage::Points(None);
+                Some(Event::Dimension(3))
+            }
+            Stage::Points(None) => {
+                self.stage = Stage::Points(Some(0));
+                Some(Event::GroupStart {
+                    name: Name::parse("vertices".into(), Format::Stl, EntityKind::Node).unwrap(),
+                    kind: EntityKind::Node,
+                    len: Some(self.points.len()),
+                })
+            }
+            Stage::Points(Some(i)) if i < self.points.len() => {
+                self.stage = Stage::Points(Some(i + 1));
+                Some(Event::Entity(geometry::entity_with_position(
+                    EntityKind::Node,
+                    &self.points[i],
+                    AttributeMap::new(),
+                )))
+            }
+            Stage::Points(Some(_)) => {
+                self.stage = Stage::Solids;
+                Some(Event::GroupEnd)
+            }
+            Stage::Solids => match self.triangles {
+                Some(ref mut triangles) => match triangles.next() {
+                    Some(nodes) => Some(Event::Entity(triangle(&nodes))),
+                    None => {
+                        self.triangles = None;
+                        Some(Event::GroupEnd)
+                    }
+                },
+                None => {
+                    let (name, triangles) = self.solids.next()?;
+                    let len = Some(triangles.len());
+                    self.triangles = Some(triangles.into_iter());
+                    Some(Event::GroupStart {
+                        // Note: Should never fail, since there is no validation of STL names.
+                        name: Name::parse(name, Format::Stl, EntityKind::Element).unwrap(),
+                        kind: EntityKind::Element,
+                        len,
+                    })
+                }
+            },
+        }
+    }
+}
+
+fn triangle(nodes: &[usize]) -> EntityBox {
+    geometry::element_with_cell(CellType::Triangle, nodes, AttributeMap::new())
+}
+
+pub struct StlDeserializer {}
+
+impl Deserializer for StlDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        match parse(source) {
+            Ok(mesh) => Box::new(StlEvents::new(mesh).map(Ok)),
+            Err(e) => Box::new(iter::once(Err(e))),
+        }
+    }
+
+    /// Recognizes ASCII files by the keyword `solid` followed by a facet. Binary files are
+    /// only recognized by their length, if the head is the whole file.
+    fn sniff(head: &[u8]) -> Confidence {
+        if binary_len(head).is_some() {
+            return Confidence::Medium;
+        }
+        match sniff::text(head) {
+            Some(text) => {
+                let mut lines = sniff::lines(&text, "#");
+                match (lines.next(), lines.next()) {
+                    (Some(solid), Some(facet))
+                        if solid.starts_with("solid") && facet.starts_with("facet") =>
+                    {
+                        Confidence::High
+                    }
+                    (Some(solid), _) if solid.starts_with("solid") => Confidence::Low,
+                    _ => Confidence::No,
+                }
+            }
+            None => Confidence::No,
+        }
+    }
+}
+
+/// A binary STL file, whose triangles are decoded in place.
+///
+/// Unlike [StlDeserializer], the view doesn't weld points, which takes a map of all of them:
+/// the node group `vertices` has the three corners of every triangle, and triangle `i` of the
+/// element group `solid` has the nodes `3 * i` to `3 * i + 2`. Neither positions nor
+/// connectivity can be borrowed, since the corners are `float` values in records of 50 bytes,
+/// which aren't aligned for any type but bytes. Entities are decoded one by one instead.
+///
+/// ```no_run
+/// # use multimesh::{de::MappedFile, format::stl::StlView};
+/// let file = MappedFile::open("part.stl")?;
+/// let view = StlView::new(&file)?;
+/// println!("{} triangles", view.len());
+/// # Ok::<(), multimesh::error::Error>(())
+/// ```
+pub struct StlView<'a> {
+    /// The records of all triangles.
+    records: &'a [u8],
+}
+
+impl<'a> StlView<'a> {
+    /// Check that the length of the data matches the number of triangles in the header.
+    pub fn new(data: &'a [u8]) -> Result<StlView<'a>, Error> {
+        match binary_len(data) {
+            Some(_) => Ok(StlView {
+                records: &data[HEADER_SIZE..],
+            }),
+            None if data.starts_with(b"solid") => Err(Error::Unsupported(
+                "Only binary STL files can be viewed.".into(),
+            )),
+            None => Err(Error::Syntax(
+                "The length of the binary STL data doesn't match its header.".into(),
+            )),
+        }
+    }
+
+    /// The number of triangles.
+    pub fn len(&self) -> usize {
+        self.records.len() / RECORD_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl<'a> GetMesh for StlView<'a> {
+    type Entity = EntityBox;
+    type GroupReader<'m>
+        = StlGroupReader<'m>
+    where
+        Self: 'm;
+    type GroupReaders<'m>
+        = Box<dyn Iterator<Item = StlGroupReader<'m>> + 'm>
+    where
+        Self: 'm;
+
+    fn metadata(&self) -> MeshMetadata {
+        MeshMetadata::new(3)
+    }
+
+    fn groups(&self) -> Self::GroupReaders<'_> {
+        let kinds = vec![EntityKind::Node, EntityKind::Element];
+        Box::new(kinds.into_iter().map(move |kind| StlGroupReader {
+            view: self,
+            kind,
+            next: 0,
+        }))
+    }
+}
+
+pub struct StlGroupReader<'m> {
+    view: &'m StlView<'m>,
+    kind: EntityKind,
+    next: usize,
+}
+
+impl<'m> StlGroupReader<'m> {
+    fn len(&self) -> usize {
+        match self.kind {
+            EntityKind::Node => 3 * self.view.len(),
+            _ => self.view.len(),
+        }
+    }
+}
+
+impl<'m> GetMeshGroup for StlGroupReader<'m> {
+    fn metadata(&self) -> GroupMetadata {
+        let name = match self.kind {
+            EntityKind::Node => "vertices",
+            _ => DEFAULT_SOLID,
+        };
+        let name = Name::parse(name.into(), Format::Stl, self.kind).unwrap();
+        GroupMetadata::new(name, self.kind, self.len())
+    }
+}
+
+impl<'m> Iterator for StlGroupReader<'m> {
+    type Item = EntityBox;
+
+    fn next(&mut self) -> Option<EntityBox> {
+        if self.next == self.len() {
+            return None;
+        }
+        let i = self.next;
+        self.next += 1;
+        Some(match self.kind {
+            EntityKind::Node => {
+                let start = i / 3 * RECORD_SIZE;
+                let record = &self.view.records[start..start + RECORD_SIZE];
+                geometry::entity_with_position(
+                    EntityKind::Node,
+                    &corners(record)[i % 3],
+                    AttributeMap::new(),
+                )
+            }
+            _ => triangle(&[3 * i, 3 * i + 1, 3 * i + 2]),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len() - self.next;
+        (n, Some(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{face_vertex::Mesh, Entity};
+
+    const TETRAHEDRON: &str = "solid tet
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 0 0
+    endloop
+  endfacet
+  facet normal 0 -1 0
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 0 1
+    endloop
+  endfacet
+endsolid tet
+solid
+  facet normal -1 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 0 1
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid
+";
+
+    /// The triangles of [TETRAHEDRON] as binary file.
+    fn binary() -> Vec<u8> {
+        let triangles = [
+            [[0., 0., 0.], [0., 1., 0.], [1., 0., 0.]],
+            [[0., 0., 0.], [1., 0., 0.], [0., 0., 1.]],
+            [[0., 0., 0.], [0., 0., 1.], [0., 1., 0.]],
+        ];
+        let mut data = vec![0; 80];
+        data.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+        for triangle in &triangles {
+            data.extend_from_slice(&[0; 12]);
+            for x in triangle.iter().flat_map(|corner| corner.iter()) {
+                data.extend_from_slice(&(*x as f32).to_le_bytes());
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+        data
+    }
+
+    fn nodes(mesh: &Mesh, group: usize) -> Vec<Vec<usize>> {
+        mesh.element_groups()[group]
+            .entities()
+            .iter()
+            .map(|e| e.node_indices().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn weld_solids() {
+        let mut mesh = Mesh::default();
+        StlDeserializer::deserialize_into(TETRAHEDRON.as_bytes(), &mut mesh).unwrap();
+        assert_eq!(mesh.node_groups()[0].entities().len(), 4);
+        let groups = mesh.element_groups();
+        assert_eq!(groups[0].name().get_original().0, "tet");
+        assert_eq!(groups[1].name().get_original().0, "solid");
+        assert_eq!(nodes(&mesh, 0), vec![vec![0, 1, 2], vec![0, 2, 3]]);
+        assert_eq!(nodes(&mesh, 1), vec![vec![0, 3, 1]]);
+
+        let data = binary();
+        assert_eq!(StlDeserializer::sniff(&data), Confidence::Medium);
+        assert_eq!(
+            StlDeserializer::sniff(TETRAHEDRON.as_bytes()),
+            Confidence::High
+        );
+        let mut mesh = Mesh::default();
+        StlDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+        assert_eq!(mesh.node_groups()[0].entities().len(), 4);
+        assert_eq!(
+            nodes(&mesh, 0),
+            vec![vec![0, 1, 2], vec![0, 2, 3], vec![0, 3, 1]]
+        );
+    }
+
+    #[test]
+    fn view_corners() {
+        let data = binary();
+        let view = StlView::new(&data).unwrap();
+        assert_eq!(view.len(), 3);
+        let groups: Vec<_> = view.groups().collect();
+        assert_eq!(groups[0].metadata().len(), 9);
+        assert!(groups[0].positions().is_none());
+        let positions: Vec<_> = view
+            .groups()
+            .next()
+            .unwrap()
+            .map(|node| node.position(3).unwrap())
+            .collect();
+        assert_eq!(positions[5], [0., 0., 1.].into());
+        let triangles: Vec<_> = view
+            .groups()
+            .nth(1)
+            .unwrap()
+            .map(|e| e.node_indices().unwrap())
+            .collect();
+        assert_eq!(triangles[2], vec![6, 7, 8]);
+
+        assert!(StlView::new(&data[..data.len() - 1]).is_err());
+        assert!(StlView::new(TETRAHEDRON.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn reject_malformed() {
+        let mut mesh = Mesh::default();
+        let quad = TETRAHEDRON.replacen("endloop", "vertex 1 1 1\n endloop", 1);
+        assert!(StlDeserializer::deserialize_into(quad.as_bytes(), &mut mesh).is_err());
+        let unclosed = TETRAHEDRON.trim_end().trim_end_matches("endsolid");
+        assert!(StlDeserializer::deserialize_into(unclosed.as_bytes(), &mut mesh).is_err());
+        let data = binary();
+        let truncated = &data[..data.len() - 1];
+        assert!(StlDeserializer::deserialize_into(truncated, &mut mesh).is_err());
+    }
+}
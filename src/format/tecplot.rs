@@ -0,0 +1,955 @@
+//! Implementation of Tecplot ASCII mesh format support (`.dat` files with finite element zones).
+//!
+//! Definition: Tecplot 360 Data Format Guide, chapter "ASCII Data".
+//!
+//! The records `TITLE`, `VARIABLES` and `ZONE` are understood. Zones have to be finite element
+//! zones of type `FELINESEG`, `FETRIANGLE`, `FEQUADRILATERAL`, `FETETRAHEDRON` or `FEBRICK`,
+//! given by `ZONETYPE=...` or the older `ET=...`, with data packed as `POINT` or `BLOCK`
+//! (`DATAPACKING=...` or the older `F=FEPOINT`/`F=FEBLOCK`). Cell centered variables of block
+//! packed zones are supported through `VARLOCATION`.
+//!
+//! Every zone becomes a node group and an element group, both named after the title of the
+//! zone (or `zone-<n>` if it has none). The variables `X`, `Y` and `Z` are the node
+//! coordinates, all other variables are stored as attributes of the nodes (or of the elements
+//! for cell centered variables) named after the variable.
+//!
+//! The serializer writes one zone per element group, with the nodes used by it. Groups with
+//! elements of different kinds are written with degenerate elements, like tetrahedra as
+//! bricks with repeated nodes. Pyramids and prisms are always written as bricks, since there
+//! are no zone types for them. The deserializer collapses degenerate elements again.
+
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::naming::{Format, Name};
+use ser::Serializer;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
+};
+use util::{geometry, sniff};
+
+/// How the values of the variables are arranged in a zone.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataPacking {
+    /// All variables of one node after another.
+    Point,
+    /// All values of one variable after another.
+    Block,
+}
+
+/// The zone types, with the cell type of their elements.
+const ZONE_TYPES: &[(&str, CellType)] = &[
+    ("FELINESEG", CellType::Line),
+    ("FETRIANGLE", CellType::Triangle),
+    ("FEQUADRILATERAL", CellType::Quadrilateral),
+    ("FETETRAHEDRON", CellType::Tetrahedron),
+    ("FEBRICK", CellType::Hexahedron),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'s> {
+    Word(&'s str),
+    Str(&'s str),
+    /// Raw content of parentheses, like the value of `VARLOCATION=([3]=CELLCENTERED)`.
+    Paren(&'s str),
+    Eq,
+}
+
+#[derive(Clone)]
+struct Lexer<'s> {
+    data: &'s str,
+    pos: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn new(data: &'s str) -> Self {
+        Lexer { data, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<Token<'s>> {
+        self.clone().next()
+    }
+
+    fn next_result(&mut self) -> Result<Token<'s>, Error> {
+        self.next()
+            .ok_or_else(|| Error::Syntax("Unexpected EOF.".into()))
+    }
+
+    fn next_number<T: ::std::str::FromStr>(&mut self) -> Result<T, Error> {
+        match self.next_result()? {
+            Token::Word(word) => word
+                .parse()
+                .map_err(|_| Error::Syntax(format!("Expected number, found {}.", word))),
+            token => Err(Error::Syntax(format!(
+                "Expected number, found {:?}.",
+                token
+            ))),
+        }
+    }
+
+    /// An upper bound of the number of tokens which are left.
+    fn max_tokens(&self) -> usize {
+        (self.data.len() - self.pos).div_ceil(2)
+    }
+
+    /// Whether the next token starts a `KEY=value` pair.
+    fn at_key(&self) -> bool {
+        let mut lexer = self.clone();
+        match (lexer.next(), lexer.next()) {
+            (Some(Token::Word(word)), Some(Token::Eq)) => !is_number(word),
+            _ => false,
+        }
+    }
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Token<'s>;
+
+    fn next(&mut self) -> Option<Token<'s>> {
+        let bytes = self.data.as_bytes();
+        loop {
+            while self.pos < bytes.len()
+                && (bytes[self.pos].is_ascii_whitespace() || bytes[self.pos] == b',')
+            {
+                self.pos += 1;
+            }
+            if self.pos < bytes.len() && bytes[self.pos] == b'#' {
+                // Comment until the end of the line.
+                while self.pos < bytes.len() && bytes[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        if self.pos >= bytes.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        let (end, token) = match bytes[start] {
+            b'=' => (start + 1, Token::Eq),
+            b'"' => {
+                let len = self.data[start + 1..]
+                    .find('"')
+                    .unwrap_or(bytes.len() - start - 1);
+                (
+                    start + len + 2,
+                    Token::Str(&self.data[start + 1..start + 1 + len]),
+                )
+            }
+            b'(' => {
+                let len = self.data[start + 1..]
+                    .find(')')
+                    .unwrap_or(bytes.len() - start - 1);
+                (
+                    start + len + 2,
+                    Token::Paren(&self.data[start + 1..start + 1 + len]),
+                )
+            }
+            _ => {
+                let mut end = start;
+                while end < bytes.len()
+                    && !bytes[end].is_ascii_whitespace()
+                    && !b",=\"(".contains(&bytes[end])
+                {
+                    end += 1;
+                }
+                (end, Token::Word(&self.data[start..end]))
+            }
+        };
+        self.pos = end.min(bytes.len());
+        Some(token)
+    }
+}
+
+fn is_number(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.')
+}
+
+/// The index of the coordinate a variable name stands for, if any.
+fn coordinate(variable: &str) -> Option<usize> {
+    let variable = variable.to_ascii_uppercase();
+    let variable = variable.trim_start_matches("COORDINATE");
+    ["X", "Y", "Z"].iter().position(|&c| c == variable)
+}
+
+/// The declared type of the values of a variable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DataType {
+    Single,
+    Double,
+    LongInt,
+    ShortInt,
+    Byte,
+    Bit,
+}
+
+impl DataType {
+    fn from_name(name: &str) -> Option<DataType> {
+        match name.to_ascii_uppercase().as_str() {
+            "SINGLE" => Some(DataType::Single),
+            "DOUBLE" => Some(DataType::Double),
+            "LONGINT" => Some(DataType::LongInt),
+            "SHORTINT" => Some(DataType::ShortInt),
+            "BYTE" => Some(DataType::Byte),
+            "BIT" => Some(DataType::Bit),
+            _ => None,
+        }
+    }
+
+    fn parse(self, word: &str) -> Result<AttributeValue, Error> {
+        let bad_value = || Error::Syntax(format!("Bad value for type {:?}: {}", self, word));
+        Ok(match self {
+            DataType::Single => word.parse::<f32>().map_err(|_| bad_value())?.into(),
+            DataType::Double => word.parse::<f64>().map_err(|_| bad_value())?.into(),
+            DataType::LongInt => word.parse::<i32>().map_err(|_| bad_value())?.into(),
+            DataType::ShortInt => word.parse::<i16>().map_err(|_| bad_value())?.into(),
+            DataType::Byte | DataType::Bit => word.parse::<u8>().map_err(|_| bad_value())?.into(),
+        })
+    }
+}
+
+struct Zone {
+    title: Option<String>,
+    num_nodes: usize,
+    num_elements: usize,
+    /// The cell type of the elements, `None` for ordered zones.
+    cell_type: Option<CellType>,
+    packing: DataPacking,
+    /// Whether the variables are cell centered.
+    cell_centered: Vec<bool>,
+    /// The declared types of the variables, values without type are read as `f64`.
+    data_types: Vec<Option<DataType>>,
+}
+
+impl Zone {
+    fn parse(lexer: &mut Lexer, num_variables: usize) -> Result<Zone, Error> {
+        let mut zone = Zone {
+            title: None,
+            num_nodes: 0,
+            num_elements: 0,
+            cell_type: None,
+            packing: DataPacking::Point,
+            cell_centered: vec![false; num_variables],
+            data_types: vec![None; num_variables],
+        };
+
+        while lexer.at_key() {
+            let key = match lexer.next() {
+                Some(Token::Word(key)) => key.to_ascii_uppercase(),
+                _ => unreachable!(),
+            };
+            lexer.next_result()?;
+            let value = match lexer.next_result()? {
+                Token::Word(s) | Token::Str(s) | Token::Paren(s) => s,
+                Token::Eq => return Err(Error::Syntax(format!("No value for {}.", key))),
+            };
+            let upper = value.to_ascii_uppercase();
+            match key.as_str() {
+                "T" => zone.title = Some(value.to_string()),
+                "N" | "NODES" => zone.num_nodes = value.parse()?,
+                "E" | "ELEMENTS" => zone.num_elements = value.parse()?,
+                "ZONETYPE" | "ET" => {
+                    let zone_type = if upper.starts_with("FE") {
+                        upper
+                    } else {
+                        format!("FE{}", upper)
+                    };
+                    let cell_type = ZONE_TYPES
+                        .iter()
+                        .find(|&&(name, _)| name == zone_type)
+                        .map(|&(_, cell)| cell)
+                        .ok_or_else(|| {
+                            Error::Syntax(format!("Unsupported zone type: {}", value))
+                        })?;
+                    zone.cell_type = Some(cell_type);
+                }
+                "DATAPACKING" | "F" => {
+                    zone.packing = match upper.as_str() {
+                        "POINT" | "FEPOINT" => DataPacking::Point,
+                        "BLOCK" | "FEBLOCK" => DataPacking::Block,
+                        _ => {
+                            return Err(Error::Syntax(format!(
+                                "Unsupported data packing: {}",
+                                value
+                            )))
+                        }
+                    }
+                }
+                "VARLOCATION" => zone.parse_varlocation(value)?,
+                "DT" => {
+                    let types: Vec<&str> = value.split_whitespace().collect();
+                    if types.len() != num_variables {
+                        return Err(Error::Syntax(format!(
+                            "Expected {} data types, found {}.",
+                            num_variables,
+                            types.len()
+                        )));
+                    }
+                    for (data_type, name) in zone.data_types.iter_mut().zip(types) {
+                        *data_type = Some(DataType::from_name(name).ok_or_else(|| {
+                            Error::Syntax(format!("Unknown data type: {}", name))
+                        })?);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if zone.cell_type.is_none() {
+            return Err(Error::Syntax(
+                "Only finite element zones are supported.".into(),
+            ));
+        }
+        if zone.packing == DataPacking::Point && zone.cell_centered.contains(&true) {
+            return Err(Error::Syntax(
+                "Cell centered variables require block packing.".into(),
+            ));
+        }
+        Ok(zone)
+    }
+
+    /// Parse a list like `[1-3,5]=CELLCENTERED [4]=NODAL`.
+    fn parse_varlocation(&mut self, value: &str) -> Result<(), Error> {
+        let mut rest = value;
+        while let Some(open) = rest.find('[') {
+            let close = rest[open..]
+                .find(']')
+                .map(|i| i + open)
+                .ok_or_else(|| Error::Syntax(format!("Bad VARLOCATION: {}", value)))?;
+            let ranges = &rest[open + 1..close];
+            rest = &rest[close + 1..];
+            let location = rest
+                .trim_start_matches(|c: char| c == '=' || c.is_whitespace())
+                .to_ascii_uppercase();
+            let cell_centered = location.starts_with("CELLCENTERED");
+
+            for range in ranges.split(',') {
+                let mut bounds = range.splitn(2, '-');
+                let first: usize = bounds.next().unwrap_or("").trim().parse()?;
+                let last: usize = match bounds.next() {
+                    Some(last) => last.trim().parse()?,
+                    None => first,
+                };
+                if first == 0 || last > self.cell_centered.len() {
+                    return Err(Error::Syntax(format!("Bad VARLOCATION: {}", value)));
+                }
+                for c in &mut self.cell_centered[first - 1..last] {
+                    *c = cell_centered;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The contents of a file, read into memory.
+struct TecplotMesh {
+    /// The dimension given by the coordinate variables, `None` without `VARIABLES` record.
+    dimension: Option<u8>,
+    zones: Vec<ZoneData>,
+}
+
+/// The values of a zone, with all variables except the coordinates as attributes.
+struct ZoneData {
+    title: String,
+    dimension: usize,
+    num_nodes: usize,
+    /// The coordinates of the nodes, empty if all coordinates are cell centered.
+    positions: Vec<f64>,
+    node_attrs: Vec<(AttributeName, Vec<AttributeValue>)>,
+    cell: CellType,
+    /// The node indices of the elements, `cell.num_nodes()` per element.
+    connectivity: Vec<usize>,
+    element_attrs: Vec<(AttributeName, Vec<AttributeValue>)>,
+}
+
+impl TecplotMesh {
+    fn read<S: Read>(mut source: S) -> Result<TecplotMesh, Error> {
+        // Read the file into memory.
+        let mut data = String::new();
+        source.read_to_string(&mut data)?;
+        let mut lexer = Lexer::new(&data);
+
+        let mut variables: Vec<String> = Vec::new();
+        let mut mesh = TecplotMesh {
+            dimension: None,
+            zones: Vec::new(),
+        };
+        let mut node_offset = 0;
+
+        while let Some(token) = lexer.next() {
+            let record = match token {
+                Token::Word(word) => word.to_ascii_uppercase(),
+                token => return Err(Error::Syntax(format!("Unexpected {:?}.", token))),
+            };
+            match record.as_str() {
+                "TITLE" => {
+                    lexer.next_result()?;
+                    lexer.next_result()?;
+                }
+                "VARIABLES" => {
+                    if lexer.next_result()? != Token::Eq {
+                        return Err(Error::Syntax("Expected = after VARIABLES.".into()));
+                    }
+                    while !lexer.at_key() {
+                        match lexer.peek() {
+                            Some(Token::Str(s)) => variables.push(s.to_string()),
+                            Some(Token::Word(s))
+                                if !is_number(s) && !s.eq_ignore_ascii_case("ZONE") =>
+                            {
+                                variables.push(s.to_string())
+                            }
+                            _ => break,
+                        }
+                        lexer.next();
+                    }
+
+                    let coordinates: Vec<usize> =
+                        variables.iter().filter_map(|v| coordinate(v)).collect();
+                    if !coordinates.contains(&0) || !coordinates.contains(&1) {
+                        return Err(Error::Syntax("Variables X and Y are required.".into()));
+                    }
+                    mesh.dimension = Some(if coordinates.contains(&2) { 3 } else { 2 });
+                }
+                "ZONE" => {
+                    if variables.is_empty() {
+                        return Err(Error::Syntax("ZONE before VARIABLES.".into()));
+                    }
+                    let zone = Zone::parse(&mut lexer, variables.len())?;
+                    let zone_number = mesh.zones.len() + 1;
+                    mesh.zones.push(read_zone(
+                        &mut lexer,
+                        &zone,
+                        zone_number,
+                        node_offset,
+                        &variables,
+                    )?);
+                    node_offset += zone.num_nodes;
+                }
+                _ => {
+                    // Unsupported record like TEXT or GEOMETRY, skip its key value pairs.
+                    while lexer.at_key() {
+                        lexer.next();
+                        lexer.next();
+                        lexer.next();
+                    }
+                }
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let zones = self.zones.into_iter().flat_map(ZoneData::events);
+        self.dimension
+            .map(|dimension| Ok(Event::Dimension(dimension)))
+            .into_iter()
+            .chain(zones)
+    }
+}
+
+impl ZoneData {
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let name = Name::parse(self.title.clone(), Format::Tecplot, EntityKind::Node).unwrap();
+        let dimension = self.dimension;
+        let positions = self.positions;
+        let mut node_columns = columns(self.node_attrs);
+        let origin = vec![0.; dimension];
+        let nodes = (0..self.num_nodes).map(move |i| {
+            let position = positions.get(i * dimension..(i + 1) * dimension);
+            Ok(geometry::entity_with_position(
+                EntityKind::Node,
+                position.unwrap_or(&origin),
+                next_attributes(&mut node_columns),
+            ))
+        });
+        let nodes = group_events(name, EntityKind::Node, Vec::new(), nodes);
+
+        let name = Name::parse(self.title, Format::Tecplot, EntityKind::Element).unwrap();
+        let cell = self.cell;
+        let connectivity = self.connectivity;
+        let mut element_columns = columns(self.element_attrs);
+        let elements = (0..connectivity.len() / cell.num_nodes()).map(move |i| {
+            let n = cell.num_nodes();
+            let (cell, nodes) = collapse(cell, &connectivity[i * n..(i + 1) * n]);
+            Ok(geometry::element_with_cell(
+                cell,
+                &nodes,
+                next_attributes(&mut element_columns),
+            ))
+        });
+        let elements = group_events(name, EntityKind::Element, Vec::new(), elements);
+
+        nodes.chain(elements)
+    }
+}
+
+type Columns = Vec<(AttributeName, ::std::vec::IntoIter<AttributeValue>)>;
+
+fn columns(columns: Vec<(AttributeName, Vec<AttributeValue>)>) -> Columns {
+    columns
+        .into_iter()
+        .map(|(name, values)| (name, values.into_iter()))
+        .collect()
+}
+
+/// The attributes of the next entity, with one value of each column.
+fn next_attributes(columns: &mut Columns) -> AttributeMap {
+    let mut attr = AttributeMap::new();
+    for (name, values) in columns.iter_mut() {
+        if let Some(value) = values.next() {
+            attr.set(name.clone(), value);
+        }
+    }
+    attr
+}
+
+pub struct TecplotDeserializer {}
+
+impl Deserializer for TecplotDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(TecplotMesh::read(source), TecplotMesh::events)
+    }
+
+    /// Recognizes the `ZONE` record, and the `VARIABLES` record which usually precedes it.
+    fn sniff(head: &[u8]) -> Confidence {
+        let text = match sniff::text(head) {
+            Some(text) => text,
+            None => return Confidence::No,
+        };
+        let mut variables = false;
+        for line in sniff::lines(&text, "#") {
+            let line = line.to_ascii_uppercase();
+            if line.starts_with("VARIABLES") {
+                variables = true;
+            } else if line.starts_with("ZONE") {
+                return if variables {
+                    Confidence::High
+                } else {
+                    Confidence::Medium
+                };
+            }
+        }
+        Confidence::No
+    }
+}
+
+fn read_zone(
+    lexer: &mut Lexer,
+    zone: &Zone,
+    zone_number: usize,
+    node_offset: usize,
+    variables: &[String],
+) -> Result<ZoneData, Error> {
+    let dimension = if variables.iter().any(|v| coordinate(v) == Some(2)) {
+        3
+    } else {
+        2
+    };
+
+    // Note: Counts are taken from the zone header, so reservations are bounded by the data.
+    let max_tokens = lexer.max_tokens();
+
+    // Read the values of all variables, as given in the file.
+    let num_values = |v: usize| {
+        if zone.cell_centered[v] {
+            zone.num_elements
+        } else {
+            zone.num_nodes
+        }
+    };
+    let mut values: Vec<Vec<&str>> = variables
+        .iter()
+        .enumerate()
+        .map(|(v, _)| Vec::with_capacity(num_values(v).min(max_tokens)))
+        .collect();
+    let mut next_value = || match lexer.next_result()? {
+        Token::Word(word) if is_number(word) => Ok(word),
+        token => Err(Error::Syntax(format!(
+            "Expected number, found {:?}.",
+            token
+        ))),
+    };
+    match zone.packing {
+        DataPacking::Point => {
+            for _ in 0..zone.num_nodes {
+                for v in values.iter_mut() {
+                    v.push(next_value()?);
+                }
+            }
+        }
+        DataPacking::Block => {
+            for (i, v) in values.iter_mut().enumerate() {
+                for _ in 0..num_values(i) {
+                    v.push(next_value()?);
+                }
+            }
+        }
+    }
+
+    // Coordinates of the nodes, and all other variables as attributes.
+    // Note: Only sized once a node located coordinate proves the number of nodes.
+    let mut positions = Vec::new();
+    let mut node_attrs = Vec::new();
+    let mut element_attrs = Vec::new();
+    let columns = variables.iter().zip(&values).zip(&zone.cell_centered);
+    for (v, ((variable, column), &cell_centered)) in columns.enumerate() {
+        match coordinate(variable) {
+            Some(c) if c < dimension && !cell_centered => {
+                positions.resize(zone.num_nodes * dimension, 0.);
+                for (i, word) in column.iter().enumerate() {
+                    positions[i * dimension + c] = word.parse()?;
+                }
+            }
+            _ => {
+                let column = column
+                    .iter()
+                    .map(|word| match zone.data_types[v] {
+                        Some(data_type) => data_type.parse(word),
+                        None => Ok(word.parse::<f64>()?.into()),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let attrs = if cell_centered {
+                    &mut element_attrs
+                } else {
+                    &mut node_attrs
+                };
+                attrs.push((AttributeName::from(variable.clone()), column));
+            }
+        }
+    }
+
+    // Note: Should never fail, since `Zone::parse` rejects zones without type.
+    let cell = zone.cell_type.unwrap();
+    let mut connectivity =
+        Vec::with_capacity((zone.num_elements * cell.num_nodes()).min(max_tokens));
+    for _ in 0..zone.num_elements * cell.num_nodes() {
+        let index: usize = lexer.next_number()?;
+        if index == 0 || index > zone.num_nodes {
+            return Err(Error::Syntax(format!(
+                "Node {} not in zone {}.",
+                index, zone_number
+            )));
+        }
+        connectivity.push(node_offset + index - 1);
+    }
+
+    Ok(ZoneData {
+        title: zone
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("zone-{}", zone_number)),
+        dimension,
+        num_nodes: zone.num_nodes,
+        positions,
+        node_attrs,
+        cell,
+        connectivity,
+        element_attrs,
+    })
+}
+
+/// The cell type and the distinct nodes of an element with the nodes `n` in a zone of cells of
+/// type `cell`, the inverse of [ElementKind::degenerate].
+fn collapse(cell: CellType, n: &[usize]) -> (CellType, Vec<usize>) {
+    match cell {
+        CellType::Quadrilateral | CellType::Tetrahedron if n[2] == n[3] => {
+            (CellType::Triangle, n[..3].to_vec())
+        }
+        CellType::Hexahedron if n[4..].iter().all(|&i| i == n[4]) => {
+            if n[2] == n[3] {
+                (CellType::Tetrahedron, vec![n[0], n[1], n[2], n[4]])
+            } else {
+                (CellType::Pyramid, n[..5].to_vec())
+            }
+        }
+        CellType::Hexahedron if n[2] == n[3] && n[6] == n[7] => {
+            (CellType::Prism, vec![n[0], n[1], n[2], n[4], n[5], n[6]])
+        }
+        _ => (cell, n.to_vec()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum ElementKind {
+    Line,
+    Triangle,
+    Quadrilateral,
+    Tetrahedron,
+    Pyramid,
+    Prism,
+    Brick,
+}
+
+impl ElementKind {
+    fn zone_type(self) -> &'static str {
+        match self {
+            ElementKind::Line => "FELINESEG",
+            ElementKind::Triangle => "FETRIANGLE",
+            ElementKind::Quadrilateral => "FEQUADRILATERAL",
+            ElementKind::Tetrahedron => "FETETRAHEDRON",
+            ElementKind::Pyramid | ElementKind::Prism | ElementKind::Brick => "FEBRICK",
+        }
+    }
+
+    /// The nodes of an element of this kind in a zone of kind `zone`, degenerate if needed.
+    fn degenerate(self, zone: ElementKind, n: &[usize]) -> Vec<usize> {
+        match (self, zone) {
+            (ElementKind::Triangle, ElementKind::Quadrilateral) => vec![n[0], n[1], n[2], n[2]],
+            (ElementKind::Tetrahedron, ElementKind::Brick) => {
+                vec![n[0], n[1], n[2], n[2], n[3], n[3], n[3], n[3]]
+            }
+            (ElementKind::Pyramid, ElementKind::Brick) => {
+                vec![n[0], n[1], n[2], n[3], n[4], n[4], n[4], n[4]]
+            }
+            (ElementKind::Prism, ElementKind::Brick) => {
+                vec![n[0], n[1], n[2], n[2], n[3], n[4], n[5], n[5]]
+            }
+            _ => n.to_vec(),
+        }
+    }
+}
+
+pub struct TecplotSerializer {
+    packing: DataPacking,
+}
+
+impl TecplotSerializer {
+    pub fn new(packing: DataPacking) -> Self {
+        TecplotSerializer { packing }
+    }
+}
+
+impl Serializer for TecplotSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "Tecplot does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        // Node attributes with numeric values become variables.
+        let mut node_attrs: Vec<AttributeMap> = Vec::with_capacity(points.len());
+        let mut variables: BTreeSet<String> = BTreeSet::new();
+        for group in mesh.groups() {
+            if group.metadata().kind() == EntityKind::Node {
+                for node in group {
+                    let mut attr = AttributeMap::new();
+                    for (name, value) in node.attributes().iter() {
+                        let is_coordinate = keys::POSITION.iter().any(|k| name.to_string() == *k);
+                        if name.is_key() && !is_coordinate && value.to_f64().is_some() {
+                            variables.insert(name.to_string());
+                            attr.set(name.clone(), value.clone());
+                        }
+                    }
+                    node_attrs.push(attr);
+                }
+            }
+        }
+        let variables: Vec<String> = variables.into_iter().collect();
+
+        writeln!(target, "TITLE = \"Generated by multimesh\"")?;
+        let mut names: Vec<String> = ["X", "Y", "Z"][..dimension]
+            .iter()
+            .map(|c| format!("\"{}\"", c))
+            .collect();
+        names.extend(variables.iter().map(|v| format!("\"{}\"", v)));
+        writeln!(target, "VARIABLES = {}", names.join(", "))?;
+
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            if metadata.kind() != EntityKind::Element {
+                continue;
+            }
+
+            let mut elements = Vec::with_capacity(metadata.len());
+            for element in group {
+                let nodes = element.node_indices()?;
+                if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                    return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                }
+                let kind = match geometry::cell_type(&element, &nodes, &points, dimension)? {
+                    CellType::Line => ElementKind::Line,
+                    CellType::Triangle => ElementKind::Triangle,
+                    CellType::Quadrilateral => ElementKind::Quadrilateral,
+                    CellType::Tetrahedron => ElementKind::Tetrahedron,
+                    CellType::Pyramid => ElementKind::Pyramid,
+                    CellType::Prism => ElementKind::Prism,
+                    CellType::Hexahedron => ElementKind::Brick,
+                    cell => {
+                        return Err(Error::Unsupported(format!(
+                            "Tecplot does not support cells of type {}.",
+                            cell.name()
+                        )))
+                    }
+                };
+                elements.push((kind, nodes));
+            }
+            if elements.is_empty() {
+                continue;
+            }
+
+            let zone_kind = match elements.iter().map(|&(kind, _)| kind).max().unwrap() {
+                ElementKind::Triangle if elements.iter().any(|e| e.0 != ElementKind::Triangle) => {
+                    ElementKind::Quadrilateral
+                }
+                ElementKind::Tetrahedron if elements.iter().any(|e| e.0 != elements[0].0) => {
+                    ElementKind::Brick
+                }
+                // Note: Tecplot has no zone type for them.
+                ElementKind::Pyramid | ElementKind::Prism => ElementKind::Brick,
+                kind => kind,
+            };
+            if elements
+                .iter()
+                .any(|e| e.0 != zone_kind && e.0.degenerate(zone_kind, &e.1).len() == e.1.len())
+            {
+                return Err(Error::Unsupported(format!(
+                    "Elements of group {} can't be combined in one zone.",
+                    metadata.name().get_original().0
+                )));
+            }
+
+            // Number the nodes used by the zone.
+            let mut zone_nodes: BTreeMap<usize, usize> = BTreeMap::new();
+            for (_, nodes) in &elements {
+                for &i in nodes {
+                    zone_nodes.insert(i, 0);
+                }
+            }
+            for (n, local) in zone_nodes.values_mut().enumerate() {
+                *local = n + 1;
+            }
+
+            writeln!(
+                target,
+                "ZONE T=\"{}\", N={}, E={}, DATAPACKING={}, ZONETYPE={}",
+                metadata.name().get_original().0,
+                zone_nodes.len(),
+                elements.len(),
+                match self.packing {
+                    DataPacking::Point => "POINT",
+                    DataPacking::Block => "BLOCK",
+                },
+                zone_kind.zone_type()
+            )?;
+
+            let value = |i: usize, v: usize| -> String {
+                if v < dimension {
+                    points[i][v].to_string()
+                } else {
+                    node_attrs[i]
+                        .get(&AttributeName::from(variables[v - dimension].as_str()))
+                        .map_or_else(|| "0".into(), |value| value.to_string())
+                }
+            };
+            let num_variables = dimension + variables.len();
+            match self.packing {
+                DataPacking::Point => {
+                    for &i in zone_nodes.keys() {
+                        let line: Vec<String> = (0..num_variables).map(|v| value(i, v)).collect();
+                        writeln!(target, "{}", line.join(" "))?;
+                    }
+                }
+                DataPacking::Block => {
+                    for v in 0..num_variables {
+                        let line: Vec<String> = zone_nodes.keys().map(|&i| value(i, v)).collect();
+                        writeln!(target, "{}", line.join(" "))?;
+                    }
+                }
+            }
+
+            for (kind, nodes) in &elements {
+                let line: Vec<String> = kind
+                    .degenerate(zone_kind, nodes)
+                    .iter()
+                    .map(|i| zone_nodes[i].to_string())
+                    .collect();
+                writeln!(target, "{}", line.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::face_vertex::Mesh;
+    use nalgebra::Point3;
+
+    #[test]
+    fn block_zone_with_cell_centered_variable() {
+        let source = r#"TITLE = "Two triangles"
+VARIABLES = "X" "Y"
+"P", "Q"
+ZONE T="plate", N=4, E=2, DATAPACKING=BLOCK, ZONETYPE=FETRIANGLE,
+ VARLOCATION=([4]=CELLCENTERED)
+# x, y, p and q
+0 1 1 0
+0 0 1 1
+10 11 12 13
+0.5 1.5
+1 2 3
+1 3 4
+"#;
+        let mut mesh = Mesh::default();
+        TecplotDeserializer::deserialize_into(source.as_bytes(), &mut mesh).unwrap();
+        assert_eq!(mesh.metadata().dimension(), 2);
+
+        let nodes = &mesh.node_groups()[0];
+        assert_eq!(nodes.name().get_original().0, "plate");
+        let node = &nodes.entities()[2];
+        assert_eq!(node.position(2).unwrap(), Point3::new(1., 1., 0.));
+        assert_eq!(
+            node.attributes().get(&"P".into()),
+            Some(&AttributeValue::F64(12.))
+        );
+
+        let elements = &mesh.element_groups()[0];
+        let element = &elements.entities()[1];
+        assert_eq!(element.node_indices().unwrap(), vec![0, 2, 3]);
+        assert_eq!(element.attributes().get_f64(&"Q".into()), Some(1.5));
+    }
+
+    #[test]
+    fn declared_data_types() {
+        let source = r#"VARIABLES = "X" "Y" "P" "Q"
+ZONE N=3, E=1, DATAPACKING=POINT, ZONETYPE=FETRIANGLE, DT=(DOUBLE DOUBLE LONGINT SINGLE)
+0 0 -1 0.1
+1 0 2 0.2
+0 1 3 0.3
+1 2 3
+"#;
+        let mut mesh = Mesh::default();
+        TecplotDeserializer::deserialize_into(source.as_bytes(), &mut mesh).unwrap();
+
+        let node = &mesh.node_groups()[0].entities()[0];
+        let attr = node.attributes();
+        assert_eq!(attr.get(&"P".into()), Some(&AttributeValue::I32(-1)));
+        assert_eq!(attr.get(&"Q".into()), Some(&AttributeValue::F32(0.1)));
+
+        let bad = source.replace("-1", "-1.5");
+        assert!(
+            TecplotDeserializer::deserialize_into(bad.as_bytes(), &mut Mesh::default()).is_err()
+        );
+    }
+}
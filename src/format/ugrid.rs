@@ -0,0 +1,640 @@
+//! Implementation of UGRID mesh format support (NASA/AFLR3 `.ugrid` files).
+//!
+//! Definition: [UG_IO documentation](https://www.simcenter.msstate.edu/software/documentation/ug_io/3d_grid_file_type_ugrid.html).
+//!
+//! A UGRID file starts with the number of nodes, triangles, quadrilaterals, tetrahedra,
+//! pyramids, prisms and hexahedra, followed by the node coordinates, the boundary faces, the
+//! boundary tags of the faces and finally the volume elements. Every element class becomes
+//! its own group, the boundary tags are stored as reference tags of the faces.
+//!
+//! The encoding of the data is selected by the file suffix, see [Encoding::from_path]:
+//! ASCII (`.ugrid`) or binary with big (`.b8.ugrid`, `.b4.ugrid`) or little endian byte order
+//! (`.lb8.ugrid`, `.lb4.ugrid`) and double or single precision reals. The Fortran unformatted
+//! variants (`.r8.ugrid` etc.) are not supported.
+//!
+//! The serializer sorts elements into the classes by their cell type, which they carry or
+//! which is inferred from their nodes. Vertices and edges have no class in UGRID, they are
+//! skipped with a warning.
+//!
+//! Pyramids are numbered with the apex as third node, see [ordering](::format::ordering).
+
+use data::{
+    attribute::{keys, AttributeContainerMut, AttributeMap, AttributeName, AttributeValue},
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup, SetMesh, SetMeshGroup,
+};
+use de::{group_events, parsed_events, read_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::{
+    naming::{Format, Name},
+    ordering,
+};
+use ser::Serializer;
+use std::{
+    io::{BufRead, Read, Write},
+    iter,
+    path::Path,
+};
+use util::{geometry, item_reader::ItemReader, sniff};
+
+/// The names of the element groups, in the order of the header counts, with the number of
+/// nodes of each element.
+const ELEMENTS: &[(&str, usize)] = &[
+    ("triangles", 3),
+    ("quadrilaterals", 4),
+    ("tetrahedra", 4),
+    ("pyramids", 5),
+    ("prisms", 6),
+    ("hexahedra", 8),
+];
+
+/// Cell types of the element classes of [ELEMENTS].
+const CELL_TYPES: &[CellType] = &[
+    CellType::Triangle,
+    CellType::Quadrilateral,
+    CellType::Tetrahedron,
+    CellType::Pyramid,
+    CellType::Prism,
+    CellType::Hexahedron,
+];
+
+/// Number of boundary face classes at the start of [ELEMENTS].
+const NUM_FACE_CLASSES: usize = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Precision {
+    /// 4 byte reals.
+    Single,
+    /// 8 byte reals.
+    Double,
+}
+
+impl Precision {
+    fn size(self) -> usize {
+        match self {
+            Precision::Single => 4,
+            Precision::Double => 8,
+        }
+    }
+}
+
+/// The encodings of UGRID files. Integers of binary files always have 4 bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    Ascii,
+    Binary(ByteOrder, Precision),
+}
+
+impl Encoding {
+    /// Determine the encoding by the suffix of a file name, like `.lb8.ugrid`.
+    ///
+    /// Returns `None` for files not ending in `.ugrid` and for unsupported encodings.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Encoding> {
+        let name = path.as_ref().file_name()?.to_str()?;
+        let stem = name.strip_suffix(".ugrid")?;
+        let encoding = match stem.rsplit('.').next() {
+            Some("b4") => Encoding::Binary(ByteOrder::BigEndian, Precision::Single),
+            Some("b8") => Encoding::Binary(ByteOrder::BigEndian, Precision::Double),
+            Some("lb4") => Encoding::Binary(ByteOrder::LittleEndian, Precision::Single),
+            Some("lb8") => Encoding::Binary(ByteOrder::LittleEndian, Precision::Double),
+            Some("r4") | Some("r8") | Some("lr4") | Some("lr8") => return None,
+            _ => Encoding::Ascii,
+        };
+        Some(encoding)
+    }
+
+    /// Determine the encoding by the content of a file.
+    ///
+    /// Binary files are recognized by header counts which match the length of the data.
+    pub fn detect(data: &[u8]) -> Option<Encoding> {
+        let head = &data[..data.len().min(1024)];
+        if !head.is_empty()
+            && head
+                .iter()
+                .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        {
+            return Some(Encoding::Ascii);
+        }
+
+        let mut candidate = None;
+        for &byte_order in &[ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            for &precision in &[Precision::Double, Precision::Single] {
+                let encoding = Encoding::Binary(byte_order, precision);
+                let mut values = BinaryValues::new(data, byte_order, precision);
+                let header = match Header::read(&mut values) {
+                    Ok(header) => header,
+                    Err(_) => continue,
+                };
+                match header.binary_len(precision) {
+                    Some(len) if len == data.len() => return Some(encoding),
+                    Some(len) if len < data.len() && candidate.is_none() => {
+                        candidate = Some(encoding)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        candidate
+    }
+}
+
+/// Source of the integers and reals of a file.
+trait Values {
+    fn int(&mut self) -> Result<usize, Error>;
+    fn real(&mut self) -> Result<f64, Error>;
+
+    /// Read a one based node index and convert it to a zero based one.
+    fn index(&mut self) -> Result<usize, Error> {
+        match self.int()? {
+            0 => Err(Error::Syntax("Node index 0 in element.".into())),
+            i => Ok(i - 1),
+        }
+    }
+}
+
+struct AsciiValues<R>(ItemReader<R>);
+
+impl<R: BufRead> Values for AsciiValues<R> {
+    fn int(&mut self) -> Result<usize, Error> {
+        Ok(self.0.next_parse()?)
+    }
+
+    fn real(&mut self) -> Result<f64, Error> {
+        Ok(self.0.next_parse()?)
+    }
+}
+
+struct BinaryValues<'b> {
+    data: &'b [u8],
+    pos: usize,
+    byte_order: ByteOrder,
+    precision: Precision,
+}
+
+impl<'b> BinaryValues<'b> {
+    fn new(data: &'b [u8], byte_order: ByteOrder, precision: Precision) -> Self {
+        BinaryValues {
+            data,
+            pos: 0,
+            byte_order,
+            precision,
+        }
+    }
+
+    fn take<A: Default + AsMut<[u8]>>(&mut self) -> Result<A, Error> {
+        let mut bytes = A::default();
+        let len = bytes.as_mut().len();
+        let slice = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| Error::Syntax("Unexpected EOF.".into()))?;
+        bytes.as_mut().copy_from_slice(slice);
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+impl<'b> Values for BinaryValues<'b> {
+    fn int(&mut self) -> Result<usize, Error> {
+        let bytes: [u8; 4] = self.take()?;
+        let value = match self.byte_order {
+            ByteOrder::BigEndian => i32::from_be_bytes(bytes),
+            ByteOrder::LittleEndian => i32::from_le_bytes(bytes),
+        };
+        if value < 0 {
+            return Err(Error::Syntax(format!("Negative integer: {}", value)));
+        }
+        Ok(value as usize)
+    }
+
+    fn real(&mut self) -> Result<f64, Error> {
+        Ok(match (self.precision, self.byte_order) {
+            (Precision::Single, ByteOrder::BigEndian) => f32::from_be_bytes(self.take()?) as f64,
+            (Precision::Single, ByteOrder::LittleEndian) => f32::from_le_bytes(self.take()?) as f64,
+            (Precision::Double, ByteOrder::BigEndian) => f64::from_be_bytes(self.take()?),
+            (Precision::Double, ByteOrder::LittleEndian) => f64::from_le_bytes(self.take()?),
+        })
+    }
+}
+
+/// The counts at the start of every file.
+struct Header {
+    num_nodes: usize,
+    /// Number of elements of each class of [ELEMENTS].
+    num_elements: [usize; 6],
+}
+
+impl Header {
+    fn read<V: Values>(values: &mut V) -> Result<Header, Error> {
+        let num_nodes = values.int()?;
+        let mut num_elements = [0; 6];
+        for n in num_elements.iter_mut() {
+            *n = values.int()?;
+        }
+        Ok(Header {
+            num_nodes,
+            num_elements,
+        })
+    }
+
+    fn num_faces(&self) -> usize {
+        self.num_elements[..NUM_FACE_CLASSES].iter().sum()
+    }
+
+    /// The length of a binary file with this header, if it does not overflow.
+    fn binary_len(&self, precision: Precision) -> Option<usize> {
+        let mut num_ints = 7 + self.num_faces();
+        for (n, &(_, nary)) in self.num_elements.iter().zip(ELEMENTS) {
+            num_ints = num_ints.checked_add(n.checked_mul(nary)?)?;
+        }
+        let reals = self.num_nodes.checked_mul(3 * precision.size())?;
+        num_ints.checked_mul(4)?.checked_add(reals)
+    }
+}
+
+/// Reader of UGRID files in a specific encoding.
+pub struct UgridDeserializer {
+    encoding: Encoding,
+}
+
+impl UgridDeserializer {
+    pub fn new(encoding: Encoding) -> Self {
+        UgridDeserializer { encoding }
+    }
+
+    pub fn deserialize<S, T>(&self, source: S, target: &mut T) -> Result<(), Error>
+    where
+        S: Read,
+        T: SetMesh,
+    {
+        let events = parsed_events(read(source, Some(self.encoding)), UgridMesh::events);
+        read_events(events, target)
+    }
+}
+
+/// Detects the encoding of the data with [Encoding::detect].
+impl Deserializer for UgridDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(read(source, None), UgridMesh::events)
+    }
+
+    /// Recognizes ASCII files by their header line of seven counts, binary files by counts
+    /// which are consistent with the size of the data.
+    fn sniff(head: &[u8]) -> Confidence {
+        match sniff::text(head) {
+            Some(text) => {
+                let header = sniff::lines(&text, "#").next().unwrap_or("");
+                let counts: Vec<_> = header.split_whitespace().collect();
+                if counts.len() == 7 && counts.iter().all(|c| c.parse::<usize>().is_ok()) {
+                    Confidence::Medium
+                } else {
+                    Confidence::No
+                }
+            }
+            None => match Encoding::detect(head) {
+                Some(_) => Confidence::Low,
+                None => Confidence::No,
+            },
+        }
+    }
+}
+
+/// The contents of a file, read into memory.
+struct UgridMesh {
+    positions: Vec<[f64; 3]>,
+    /// Zero based node indices of the elements of each class of [ELEMENTS], with the boundary
+    /// tags of the faces.
+    elements: Vec<(Vec<usize>, Vec<usize>)>,
+}
+
+impl UgridMesh {
+    fn events(self) -> impl Iterator<Item = Result<Event, Error>> {
+        let name = Name::parse("nodes".into(), Format::Ugrid, EntityKind::Node).unwrap();
+        let nodes = self.positions.into_iter().map(|position| {
+            Ok(geometry::entity_with_position(
+                EntityKind::Node,
+                &position,
+                AttributeMap::new(),
+            ))
+        });
+        let nodes = group_events(name, EntityKind::Node, Vec::new(), nodes);
+
+        let elements = self
+            .elements
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (nodes, _))| !nodes.is_empty())
+            .flat_map(|(class, (nodes, tags))| {
+                let (name, nary) = ELEMENTS[class];
+                let name = Name::parse(name.into(), Format::Ugrid, EntityKind::Element).unwrap();
+                let cell = CELL_TYPES[class];
+                let elements = (0..nodes.len() / nary).map(move |i| {
+                    let mut attr = AttributeMap::new();
+                    if let Some(&tag) = tags.get(i) {
+                        attr.set(AttributeName::key(keys::REF), tag.into());
+                    }
+                    let nodes = &nodes[i * nary..(i + 1) * nary];
+                    let nodes = ordering::to_canonical(Format::Ugrid, cell, nodes);
+                    Ok(geometry::element_with_cell(cell, &nodes, attr))
+                });
+                group_events(name, EntityKind::Element, Vec::new(), elements)
+            });
+
+        iter::once(Ok(Event::Dimension(3)))
+            .chain(nodes)
+            .chain(elements)
+    }
+}
+
+fn read<S: Read>(mut source: S, encoding: Option<Encoding>) -> Result<UgridMesh, Error> {
+    // Read the file into memory.
+    let mut data = Vec::new();
+    source.read_to_end(&mut data)?;
+
+    let encoding = match encoding.or_else(|| Encoding::detect(&data)) {
+        Some(encoding) => encoding,
+        None => return Err(Error::Syntax("Unknown UGRID encoding.".into())),
+    };
+    match encoding {
+        Encoding::Ascii => {
+            let text = ::std::str::from_utf8(&data)
+                .map_err(|e| Error::Syntax(format!("Invalid ASCII UGRID file: {}", e)))?;
+            read_values(&mut AsciiValues(ItemReader::new(text.as_bytes())))
+        }
+        Encoding::Binary(byte_order, precision) => {
+            read_values(&mut BinaryValues::new(&data, byte_order, precision))
+        }
+    }
+}
+
+fn read_values<V: Values>(values: &mut V) -> Result<UgridMesh, Error> {
+    let header = Header::read(values)?;
+
+    // Note: Counts are taken from the header, so vectors grow as the values are read.
+    let positions = (0..header.num_nodes)
+        .map(|_| Ok([values.real()?, values.real()?, values.real()?]))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut elements: Vec<(Vec<usize>, Vec<usize>)> = Vec::with_capacity(ELEMENTS.len());
+    for (class, (&n, &(_, nary))) in header.num_elements.iter().zip(ELEMENTS).enumerate() {
+        // The boundary tags follow the faces, the volume elements follow the tags.
+        if class == NUM_FACE_CLASSES {
+            for (&n, (_, tags)) in header.num_elements.iter().zip(&mut elements) {
+                *tags = (0..n).map(|_| values.int()).collect::<Result<_, _>>()?;
+            }
+        }
+        let nodes: Vec<usize> = (0..n * nary)
+            .map(|_| values.index())
+            .collect::<Result<_, _>>()?;
+        if let Some(&i) = nodes.iter().find(|&&i| i >= header.num_nodes) {
+            return Err(Error::Syntax(format!("Undefined node {}.", i + 1)));
+        }
+        elements.push((nodes, Vec::new()));
+    }
+
+    Ok(UgridMesh {
+        positions,
+        elements,
+    })
+}
+
+/// Writer of UGRID files in a specific encoding.
+pub struct UgridSerializer {
+    encoding: Encoding,
+}
+
+impl UgridSerializer {
+    pub fn new(encoding: Encoding) -> Self {
+        UgridSerializer { encoding }
+    }
+}
+
+impl Serializer for UgridSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "UGRID does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        // Sort the elements into the classes of the format.
+        let mut elements: Vec<Vec<usize>> = vec![Vec::new(); ELEMENTS.len()];
+        let mut tags: Vec<Vec<usize>> = vec![Vec::new(); NUM_FACE_CLASSES];
+        let mut skipped = 0;
+        for group in mesh.groups() {
+            if group.metadata().kind() != EntityKind::Element {
+                continue;
+            }
+
+            for element in group {
+                let nodes = element.node_indices()?;
+                if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                    return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                }
+                let cell = geometry::cell_type(&element, &nodes, &points, dimension)?;
+                if cell.dimension() < 2 {
+                    skipped += 1;
+                    continue;
+                }
+                let class = CELL_TYPES.iter().position(|&c| c == cell).ok_or_else(|| {
+                    Error::Unsupported(format!(
+                        "UGRID does not support cells of type {}.",
+                        cell.name()
+                    ))
+                })?;
+                if class < NUM_FACE_CLASSES {
+                    let tag = geometry::reference(&element).and_then(AttributeValue::to_usize);
+                    tags[class].push(tag.unwrap_or(0));
+                }
+                let nodes = ordering::from_canonical(Format::Ugrid, cell, &nodes);
+                elements[class].extend(nodes.iter().map(|i| i + 1));
+            }
+        }
+        if skipped > 0 {
+            warn!(
+                "UGRID can't store vertices and edges, skipped {} of them.",
+                skipped
+            );
+        }
+
+        let mut header = vec![points.len()];
+        header.extend(
+            elements
+                .iter()
+                .zip(ELEMENTS)
+                .map(|(nodes, &(_, nary))| nodes.len() / nary),
+        );
+
+        match self.encoding {
+            Encoding::Ascii => {
+                let strs: Vec<String> = header.iter().map(|n| n.to_string()).collect();
+                writeln!(target, "{}", strs.join(" "))?;
+                for p in &points {
+                    writeln!(target, "{} {} {}", p[0], p[1], p[2])?;
+                }
+                let tags = tags.concat();
+                let lines = elements[..NUM_FACE_CLASSES]
+                    .iter()
+                    .zip(ELEMENTS)
+                    .map(|(nodes, &(_, nary))| (nodes, nary))
+                    .chain(Some((&tags, 1)))
+                    .chain(
+                        elements[NUM_FACE_CLASSES..]
+                            .iter()
+                            .zip(&ELEMENTS[NUM_FACE_CLASSES..])
+                            .map(|(nodes, &(_, nary))| (nodes, nary)),
+                    );
+                for (values, per_line) in lines {
+                    for line in values.chunks(per_line) {
+                        let strs: Vec<String> = line.iter().map(|n| n.to_string()).collect();
+                        writeln!(target, "{}", strs.join(" "))?;
+                    }
+                }
+            }
+            Encoding::Binary(byte_order, precision) => {
+                let mut ints: Vec<usize> = header;
+                for nodes in &elements[..NUM_FACE_CLASSES] {
+                    ints.extend(nodes);
+                }
+                for tags in &tags {
+                    ints.extend(tags);
+                }
+
+                let mut buf = Vec::new();
+                write_ints(&mut buf, &ints[..7], byte_order)?;
+                for p in &points {
+                    for &x in p {
+                        write_real(&mut buf, x, byte_order, precision);
+                    }
+                }
+                write_ints(&mut buf, &ints[7..], byte_order)?;
+                for nodes in &elements[NUM_FACE_CLASSES..] {
+                    write_ints(&mut buf, nodes, byte_order)?;
+                }
+                target.write_all(&buf)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_ints(buf: &mut Vec<u8>, ints: &[usize], byte_order: ByteOrder) -> Result<(), Error> {
+    for &i in ints {
+        if i > i32::MAX as usize {
+            return Err(Error::Unsupported(format!(
+                "Integer {} too large for UGRID.",
+                i
+            )));
+        }
+        let i = i as i32;
+        buf.extend_from_slice(&match byte_order {
+            ByteOrder::BigEndian => i.to_be_bytes(),
+            ByteOrder::LittleEndian => i.to_le_bytes(),
+        });
+    }
+    Ok(())
+}
+
+fn write_real(buf: &mut Vec<u8>, x: f64, byte_order: ByteOrder, precision: Precision) {
+    match (precision, byte_order) {
+        (Precision::Single, ByteOrder::BigEndian) => {
+            buf.extend_from_slice(&(x as f32).to_be_bytes())
+        }
+        (Precision::Single, ByteOrder::LittleEndian) => {
+            buf.extend_from_slice(&(x as f32).to_le_bytes())
+        }
+        (Precision::Double, ByteOrder::BigEndian) => buf.extend_from_slice(&x.to_be_bytes()),
+        (Precision::Double, ByteOrder::LittleEndian) => buf.extend_from_slice(&x.to_le_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::{face_vertex::Mesh, validation, Entity};
+
+    #[test]
+    fn encoding_from_path() {
+        let big = Encoding::Binary(ByteOrder::BigEndian, Precision::Double);
+        let little = Encoding::Binary(ByteOrder::LittleEndian, Precision::Single);
+        assert_eq!(Encoding::from_path("wing.ugrid"), Some(Encoding::Ascii));
+        assert_eq!(Encoding::from_path("/data/wing.b8.ugrid"), Some(big));
+        assert_eq!(Encoding::from_path("wing.lb4.ugrid"), Some(little));
+        assert_eq!(Encoding::from_path("wing.r8.ugrid"), None);
+        assert_eq!(Encoding::from_path("wing.mesh"), None);
+    }
+
+    #[test]
+    fn roundtrip_all_encodings() {
+        // A prism with a pyramid on one of its quadrilateral faces, whose base is `2 3 6 5` in
+        // canonical order.
+        let source = "7 2 0 0 1 1 0
+0 0 0
+1 0 0
+0 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 0.5
+1 2 3
+4 6 5
+1
+2
+2 5 7 3 6
+1 2 3 4 5 6
+";
+        let mut mesh = Mesh::default();
+        UgridDeserializer::new(Encoding::Ascii)
+            .deserialize(source.as_bytes(), &mut mesh)
+            .unwrap();
+        let names: Vec<_> = mesh
+            .element_groups()
+            .iter()
+            .map(|g| g.name().get_original().0)
+            .collect();
+        assert_eq!(names, vec!["triangles", "pyramids", "prisms"]);
+        let pyramid = &mesh.element_groups()[1].entities()[0];
+        assert_eq!(pyramid.node_indices().unwrap(), vec![1, 2, 5, 4, 6]);
+        assert!(validation::validate(&mesh).is_empty());
+
+        let mut ascii = Vec::new();
+        UgridSerializer::new(Encoding::Ascii)
+            .serialize(&mesh, &mut ascii)
+            .unwrap();
+        assert_eq!(String::from_utf8(ascii).unwrap(), source);
+
+        for &byte_order in &[ByteOrder::BigEndian, ByteOrder::LittleEndian] {
+            for &precision in &[Precision::Single, Precision::Double] {
+                let encoding = Encoding::Binary(byte_order, precision);
+                let mut data = Vec::new();
+                UgridSerializer::new(encoding)
+                    .serialize(&mesh, &mut data)
+                    .unwrap();
+                assert_eq!(Encoding::detect(&data), Some(encoding));
+
+                let mut mesh2 = Mesh::default();
+                UgridDeserializer::deserialize_into(&data[..], &mut mesh2).unwrap();
+                assert!(validation::validate(&mesh2).is_empty());
+                for (g1, g2) in mesh.all_groups().zip(mesh2.all_groups()) {
+                    assert_eq!(g1.entities().len(), g2.entities().len());
+                    for (e1, e2) in g1.entities().iter().zip(g2.entities()) {
+                        assert_eq!(e1.attributes(), e2.attributes());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,861 @@
+//! Implementation of XDMF3 mesh format support, with data inline in the XML file.
+//!
+//! Definition: [XDMF Model and Format](http://www.xdmf.org/index.php/XDMF_Model_and_Format).
+//!
+//! Every uniform `Grid` (also inside of collections) becomes a node group and an element group,
+//! both named after the grid (or `grid-<n>` if it has no name). `Attribute`s centered on nodes
+//! or cells become attributes of the nodes or elements named after the attribute, with the
+//! components of non-scalar values separated by spaces.
+//!
+//! Only `DataItem`s with `Format="XML"` are supported. References to other elements, given as
+//! `Reference="XML"` with a path as text or with `xi:include`, are resolved for simple paths
+//! like `/Xdmf/Domain/Grid[@Name='mesh'][1]/Geometry`.
+//!
+//! The serializer writes one grid per element group, with the nodes used by it, inside of a
+//! spatial collection if there is more than one.
+
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    CellType, Entity, EntityKind, GetMesh, GetMeshGroup,
+};
+use de::{group_events, parsed_events, Confidence, Deserializer, Event, Events};
+use error::Error;
+use format::{
+    naming::{Format, Name},
+    ordering,
+};
+use roxmltree::{Document, Node};
+use ser::Serializer;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Write},
+    iter,
+};
+use util::{
+    geometry, sniff,
+    xml::{self, attribute, child, children, parse_attribute, required_child},
+};
+
+/// The topology types with a fixed number of nodes, with their type id in mixed topologies.
+const TOPOLOGY_TYPES: &[(&str, usize, u32)] = &[
+    ("Triangle", 3, 4),
+    ("Quadrilateral", 4, 5),
+    ("Tetrahedron", 4, 6),
+    ("Pyramid", 5, 7),
+    ("Wedge", 6, 8),
+    ("Hexahedron", 8, 9),
+    ("Edge_3", 3, 34),
+    ("Quadrilateral_9", 9, 35),
+    ("Triangle_6", 6, 36),
+    ("Quadrilateral_8", 8, 37),
+    ("Tetrahedron_10", 10, 38),
+    ("Pyramid_13", 13, 39),
+    ("Wedge_15", 15, 40),
+    ("Wedge_18", 18, 41),
+    ("Hexahedron_20", 20, 48),
+    ("Hexahedron_27", 27, 50),
+];
+
+/// The topology types with canonical equivalents.
+const CELL_TYPES: &[(&str, CellType)] = &[
+    ("Polyvertex", CellType::Vertex),
+    ("Polyline", CellType::Line),
+    ("Triangle", CellType::Triangle),
+    ("Quadrilateral", CellType::Quadrilateral),
+    ("Tetrahedron", CellType::Tetrahedron),
+    ("Pyramid", CellType::Pyramid),
+    ("Wedge", CellType::Prism),
+    ("Hexahedron", CellType::Hexahedron),
+    ("Edge_3", CellType::Line3),
+    ("Triangle_6", CellType::Triangle6),
+    ("Quadrilateral_8", CellType::Quadrilateral8),
+    ("Tetrahedron_10", CellType::Tetrahedron10),
+    ("Wedge_15", CellType::Prism15),
+    ("Hexahedron_20", CellType::Hexahedron20),
+];
+
+/// Type ids of mixed topologies, which are followed by their number of nodes.
+const POLYVERTEX_ID: u32 = 1;
+const POLYLINE_ID: u32 = 2;
+const POLYGON_ID: u32 = 3;
+
+/// Find the element an XPath like `/Xdmf/Domain/Grid[@Name='mesh'][1]/Geometry` points to.
+///
+/// Only child steps with attribute and position predicates are supported.
+fn select<'a, 'i>(document: &'a Document<'i>, path: &str) -> Result<Node<'a, 'i>, Error> {
+    let bad_path = || Error::Syntax(format!("Unsupported or invalid reference: {}", path));
+    if !path.starts_with('/') {
+        return Err(bad_path());
+    }
+
+    let mut nodes = vec![document.root()];
+    for step in path[1..].split('/') {
+        let (name, mut predicates) = match step.find('[') {
+            Some(i) => (&step[..i], &step[i..]),
+            None => (step, ""),
+        };
+        let mut selected: Vec<Node> = nodes
+            .iter()
+            .flat_map(|node| node.children())
+            .filter(|node| node.is_element() && node.tag_name().name() == name)
+            .collect();
+        while predicates.starts_with('[') {
+            let end = predicates.find(']').ok_or_else(bad_path)?;
+            let predicate = predicates[1..end].trim();
+            predicates = &predicates[end + 1..];
+
+            if let Some(condition) = predicate.strip_prefix('@') {
+                let mut parts = condition.splitn(2, '=');
+                let key = parts.next().unwrap().trim();
+                let value = parts
+                    .next()
+                    .ok_or_else(bad_path)?
+                    .trim()
+                    .trim_matches(|c| c == '\'' || c == '"');
+                selected.retain(|node| node.attribute(key) == Some(value));
+            } else {
+                let position: usize = predicate.parse().map_err(|_| bad_path())?;
+                selected = selected
+                    .get(position.wrapping_sub(1))
+                    .cloned()
+                    .into_iter()
+                    .collect();
+            }
+        }
+        nodes = selected;
+    }
+
+    nodes.first().cloned().ok_or_else(bad_path)
+}
+
+/// Follow the reference of an element, if it has one.
+fn dereference<'a, 'i>(
+    document: &'a Document<'i>,
+    node: Node<'a, 'i>,
+) -> Result<Node<'a, 'i>, Error> {
+    if let Some(include) = child(node, "include") {
+        let pointer = attribute(include, "xpointer")?;
+        let path = pointer
+            .trim()
+            .trim_start_matches("xpointer(")
+            .trim_end_matches(')');
+        select(document, path)
+    } else if let Some(reference) = node.attribute("Reference") {
+        if reference == "XML" {
+            select(document, node.text().unwrap_or("").trim())
+        } else {
+            select(document, reference)
+        }
+    } else {
+        Ok(node)
+    }
+}
+
+/// Get the values of the data items of an element (or of the element itself, if it is one).
+fn data_items<'a, 'i>(
+    document: &'a Document<'i>,
+    node: Node<'a, 'i>,
+) -> Result<Vec<&'a str>, Error> {
+    Ok(data_item_nodes(document, node)?
+        .iter()
+        .map(|item| item.text().unwrap_or(""))
+        .collect())
+}
+
+/// Get the data items of an element (or the element itself, if it is one).
+fn data_item_nodes<'a, 'i>(
+    document: &'a Document<'i>,
+    node: Node<'a, 'i>,
+) -> Result<Vec<Node<'a, 'i>>, Error> {
+    let node = dereference(document, node)?;
+    let items: Vec<Node> = if node.has_tag_name("DataItem") {
+        vec![node]
+    } else {
+        children(node, "DataItem").collect()
+    };
+
+    let mut values = Vec::with_capacity(items.len());
+    for item in items {
+        let item = dereference(document, item)?;
+        let format = item.attribute("Format").unwrap_or("XML");
+        if format != "XML" {
+            return Err(Error::Syntax(format!(
+                "Only XML data items are supported, found {}.",
+                format
+            )));
+        }
+        values.push(item);
+    }
+    Ok(values)
+}
+
+/// Parse the values of a data item as its declared number type.
+fn typed_values(item: Node) -> Result<Vec<AttributeValue>, Error> {
+    let number_type = item
+        .attribute("NumberType")
+        .or_else(|| item.attribute("DataType"))
+        .unwrap_or("Float");
+    let precision = item.attribute("Precision").unwrap_or("4");
+    let text = item.text().unwrap_or("");
+    Ok(match (number_type, precision) {
+        ("Float", "8") => typed(parse_values::<f64>(text)?),
+        ("Float", _) => typed(parse_values::<f32>(text)?),
+        ("Int", "1") | ("Char", _) => typed(parse_values::<i8>(text)?),
+        ("Int", "2") => typed(parse_values::<i16>(text)?),
+        ("Int", "8") => typed(parse_values::<i64>(text)?),
+        ("Int", _) => typed(parse_values::<i32>(text)?),
+        ("UInt", "1") | ("UChar", _) => typed(parse_values::<u8>(text)?),
+        ("UInt", "2") => typed(parse_values::<u16>(text)?),
+        ("UInt", "8") => typed(parse_values::<u64>(text)?),
+        ("UInt", _) => typed(parse_values::<u32>(text)?),
+        (other, _) => return Err(Error::Syntax(format!("Unsupported number type: {}", other))),
+    })
+}
+
+fn typed<T: Into<AttributeValue>>(values: Vec<T>) -> Vec<AttributeValue> {
+    values.into_iter().map(Into::into).collect()
+}
+
+fn parse_values<T: ::std::str::FromStr>(text: &str) -> Result<Vec<T>, Error> {
+    text.split_whitespace()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| Error::Syntax(format!("Bad value in data item: {}", value)))
+        })
+        .collect()
+}
+
+/// A uniform grid, read into memory.
+struct Grid {
+    name: String,
+    dimension: usize,
+    points: Vec<Vec<f64>>,
+    node_attrs: Vec<AttributeMap>,
+    elements: Vec<(Option<CellType>, Vec<usize>)>,
+    element_attrs: Vec<AttributeMap>,
+}
+
+impl Grid {
+    fn parse(document: &Document, grid: Node, name: String) -> Result<Grid, Error> {
+        // Geometry.
+        let geometry = dereference(document, required_child(grid, "Geometry")?)?;
+        let geometry_type = geometry
+            .attribute("GeometryType")
+            .or_else(|| geometry.attribute("Type"))
+            .unwrap_or("XYZ");
+        let dimension = if geometry_type.contains('Z') { 3 } else { 2 };
+        let items = data_items(document, geometry)?;
+        let points: Vec<Vec<f64>> = match geometry_type {
+            "XYZ" | "XY" => {
+                let values: Vec<f64> = parse_values(items.first().cloned().unwrap_or(""))?;
+                if !values.len().is_multiple_of(dimension) {
+                    return Err(Error::Syntax(format!(
+                        "Number of coordinates not divisible by {}.",
+                        dimension
+                    )));
+                }
+                values.chunks(dimension).map(|p| p.to_vec()).collect()
+            }
+            "X_Y_Z" | "X_Y" => {
+                if items.len() != dimension {
+                    return Err(Error::Syntax(format!(
+                        "Geometry {} needs {} data items.",
+                        geometry_type, dimension
+                    )));
+                }
+                let columns: Vec<Vec<f64>> = items
+                    .iter()
+                    .map(|item| parse_values(item))
+                    .collect::<Result<_, _>>()?;
+                if columns.iter().any(|c| c.len() != columns[0].len()) {
+                    return Err(Error::Syntax(
+                        "Coordinate arrays of different length.".into(),
+                    ));
+                }
+                (0..columns[0].len())
+                    .map(|i| columns.iter().map(|c| c[i]).collect())
+                    .collect()
+            }
+            other => {
+                return Err(Error::Syntax(format!(
+                    "Unsupported geometry type: {}",
+                    other
+                )))
+            }
+        };
+
+        // Topology.
+        let topology = dereference(document, required_child(grid, "Topology")?)?;
+        let topology_type = topology
+            .attribute("TopologyType")
+            .or_else(|| topology.attribute("Type"))
+            .ok_or_else(|| Error::Syntax("Topology without type.".into()))?;
+        let connectivity: Vec<usize> = parse_values(
+            data_items(document, topology)?
+                .first()
+                .cloned()
+                .unwrap_or(""),
+        )?;
+        let mut elements = Vec::new();
+        if topology_type.eq_ignore_ascii_case("Mixed") {
+            let mut values = connectivity.iter().cloned();
+            while let Some(id) = values.next() {
+                let (name, nary) = match id as u32 {
+                    id @ (POLYVERTEX_ID | POLYLINE_ID | POLYGON_ID) => {
+                        let nary = values.next().ok_or_else(|| {
+                            Error::Syntax("Missing number of nodes in mixed topology.".into())
+                        })?;
+                        let name = match id {
+                            POLYVERTEX_ID => "Polyvertex",
+                            POLYLINE_ID => "Polyline",
+                            _ => "Polygon",
+                        };
+                        (name, nary)
+                    }
+                    id => TOPOLOGY_TYPES
+                        .iter()
+                        .find(|t| t.2 == id)
+                        .map(|t| (t.0, t.1))
+                        .ok_or_else(|| Error::Syntax(format!("Unsupported type id: {}", id)))?,
+                };
+                let nodes: Vec<usize> = values.by_ref().take(nary).collect();
+                if nodes.len() != nary {
+                    return Err(Error::Syntax("Unexpected end of mixed topology.".into()));
+                }
+                elements.push((cell_type(name, nary), nodes));
+            }
+        } else {
+            let nary = match topology_type.to_ascii_lowercase().as_str() {
+                "polyvertex" => topology
+                    .attribute("NodesPerElement")
+                    .map(|_| parse_attribute(topology, "NodesPerElement"))
+                    .unwrap_or(Ok(1))?,
+                "polyline" | "polygon" => parse_attribute(topology, "NodesPerElement")?,
+                lower => TOPOLOGY_TYPES
+                    .iter()
+                    .find(|t| t.0.to_ascii_lowercase() == lower)
+                    .map(|t| t.1)
+                    .ok_or_else(|| {
+                        Error::Syntax(format!("Unsupported topology type: {}", topology_type))
+                    })?,
+            };
+            if nary == 0 || !connectivity.len().is_multiple_of(nary) {
+                return Err(Error::Syntax(format!(
+                    "Number of node indices not divisible by {}.",
+                    nary
+                )));
+            }
+            let cell = cell_type(topology_type, nary);
+            elements.extend(
+                connectivity
+                    .chunks(nary)
+                    .map(|nodes| (cell, nodes.to_vec())),
+            );
+        }
+        let mut indices = elements.iter().flat_map(|(_, nodes)| nodes);
+        if let Some(&i) = indices.find(|&&i| i >= points.len()) {
+            return Err(Error::Syntax(format!("Undefined node {}.", i)));
+        }
+
+        // Attributes.
+        let mut node_attrs = vec![AttributeMap::new(); points.len()];
+        let mut element_attrs = vec![AttributeMap::new(); elements.len()];
+        for attr in children(grid, "Attribute") {
+            let attr = dereference(document, attr)?;
+            let name = attribute(attr, "Name")?;
+            let targets = match attr.attribute("Center").unwrap_or("Node") {
+                "Node" => &mut node_attrs,
+                "Cell" => &mut element_attrs,
+                // Attributes of faces, edges or the whole grid don't map to entities.
+                _ => continue,
+            };
+            let values = match data_item_nodes(document, attr)?.first() {
+                Some(&item) => typed_values(item)?,
+                None => Vec::new(),
+            };
+            if targets.is_empty() || !values.len().is_multiple_of(targets.len()) {
+                return Err(Error::Syntax(format!(
+                    "Number of values of attribute {} doesn't match.",
+                    name
+                )));
+            }
+            let components = values.len() / targets.len();
+            for (target, value) in targets.iter_mut().zip(values.chunks(components)) {
+                let value = match *value {
+                    [ref value] => value.clone(),
+                    ref value => AttributeValue::List(value.to_vec()),
+                };
+                target.set(name.into(), value);
+            }
+        }
+
+        Ok(Grid {
+            name,
+            dimension,
+            points,
+            node_attrs,
+            elements,
+            element_attrs,
+        })
+    }
+}
+
+/// Collect the uniform grids below `node`, in document order.
+fn collect_grids<'a, 'i>(
+    document: &'a Document<'i>,
+    node: Node<'a, 'i>,
+    grids: &mut Vec<Grid>,
+) -> Result<(), Error> {
+    for grid in children(node, "Grid") {
+        let grid = dereference(document, grid)?;
+        match grid.attribute("GridType").unwrap_or("Uniform") {
+            "Uniform" => {
+                let name = grid
+                    .attribute("Name")
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("grid-{}", grids.len() + 1));
+                grids.push(Grid::parse(document, grid, name)?);
+            }
+            "Collection" | "Tree" => collect_grids(document, grid, grids)?,
+            other => return Err(Error::Syntax(format!("Unsupported grid type: {}", other))),
+        }
+    }
+    Ok(())
+}
+
+/// Read the uniform grids of a file.
+fn read<S: Read>(mut source: S) -> Result<Vec<Grid>, Error> {
+    // Read the file into memory.
+    let mut data = String::new();
+    source.read_to_string(&mut data)?;
+    let document = Document::parse(&data)?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "Xdmf" {
+        return Err(Error::Syntax("Root element is not <Xdmf>.".into()));
+    }
+    let mut grids = Vec::new();
+    for domain in children(root, "Domain") {
+        collect_grids(&document, domain, &mut grids)?;
+    }
+    Ok(grids)
+}
+
+fn grid_events(grids: Vec<Grid>) -> impl Iterator<Item = Result<Event, Error>> {
+    let dimension = grids.iter().map(|grid| grid.dimension).max().unwrap_or(3);
+
+    let mut node_offset = 0;
+    let grids =
+        grids.into_iter().flat_map(move |grid| {
+            let name = Name::parse(grid.name.clone(), Format::Xdmf, EntityKind::Node).unwrap();
+            let offset = node_offset;
+            node_offset += grid.points.len();
+            let nodes = grid
+                .points
+                .into_iter()
+                .zip(grid.node_attrs)
+                .map(|(position, attr)| {
+                    Ok(geometry::entity_with_position(
+                        EntityKind::Node,
+                        &position,
+                        attr,
+                    ))
+                });
+            let nodes = group_events(name, EntityKind::Node, Vec::new(), nodes);
+
+            let name = Name::parse(grid.name, Format::Xdmf, EntityKind::Element).unwrap();
+            let elements = grid.elements.into_iter().zip(grid.element_attrs).map(
+                move |((cell, nodes), attr)| {
+                    let nodes: Vec<usize> = nodes.iter().map(|i| i + offset).collect();
+                    Ok(match cell {
+                        Some(cell) => geometry::element_with_cell(
+                            cell,
+                            &ordering::to_canonical(Format::Xdmf, cell, &nodes),
+                            attr,
+                        ),
+                        None => geometry::entity_with_nodes(EntityKind::Element, &nodes, attr),
+                    })
+                },
+            );
+            let elements = group_events(name, EntityKind::Element, Vec::new(), elements);
+
+            nodes.chain(elements)
+        });
+
+    iter::once(Ok(Event::Dimension(dimension as u8))).chain(grids)
+}
+
+pub struct XdmfDeserializer {}
+
+impl Deserializer for XdmfDeserializer {
+    fn events<'s, S: Read + 's>(source: S) -> Events<'s> {
+        parsed_events(read(source), grid_events)
+    }
+
+    /// Recognizes the root element `<Xdmf>`.
+    fn sniff(head: &[u8]) -> Confidence {
+        match sniff::text(head)
+            .as_ref()
+            .and_then(|text| sniff::xml_root(text))
+        {
+            Some("Xdmf") => Confidence::High,
+            _ => Confidence::No,
+        }
+    }
+}
+
+/// The cell type of a topology type with `nary` nodes, if it has a canonical equivalent.
+fn cell_type(topology_type: &str, nary: usize) -> Option<CellType> {
+    CELL_TYPES
+        .iter()
+        .find(|t| t.0.eq_ignore_ascii_case(topology_type))
+        .map(|t| t.1)
+        .filter(|cell| cell.num_nodes() == nary)
+}
+
+/// The topology type name and mixed type id of an element, with its nodes in XDMF order.
+///
+/// Elements with 9 or 27 nodes are taken as quadrilaterals and hexahedra with center nodes,
+/// which have no canonical equivalent.
+fn topology_type<E: Entity>(
+    element: &E,
+    nodes: Vec<usize>,
+    points: &[[f64; 3]],
+    dimension: usize,
+) -> Result<((&'static str, u32), Vec<usize>), Error> {
+    let (name, nodes) = match nodes.len() {
+        9 => ("Quadrilateral_9", nodes),
+        27 => ("Hexahedron_27", nodes),
+        _ => {
+            let cell = geometry::cell_type(element, &nodes, points, dimension)?;
+            let name = CELL_TYPES
+                .iter()
+                .find(|t| t.1 == cell)
+                .map(|t| t.0)
+                .ok_or_else(|| {
+                    Error::Unsupported(format!(
+                        "XDMF does not support cells of type {}.",
+                        cell.name()
+                    ))
+                })?;
+            (name, ordering::from_canonical(Format::Xdmf, cell, &nodes))
+        }
+    };
+    let id = match name {
+        "Polyvertex" => POLYVERTEX_ID,
+        "Polyline" => POLYLINE_ID,
+        // Note: Should never fail, since all other names are topology types.
+        _ => TOPOLOGY_TYPES.iter().find(|t| t.0 == name).unwrap().2,
+    };
+    Ok(((name, id), nodes))
+}
+
+/// The numeric attributes of some entities with their number of components, except the ones
+/// with geometry or connectivity.
+fn numeric_attributes<'a, I>(attrs: I) -> Vec<(String, usize)>
+where
+    I: Iterator<Item = &'a AttributeMap>,
+{
+    let mut names: BTreeMap<String, Option<usize>> = BTreeMap::new();
+    for attr in attrs {
+        for (name, value) in attr.iter() {
+            if let AttributeName::Key(ref key) = *name {
+                if key == keys::NODES || keys::POSITION.contains(&key.as_ref()) {
+                    continue;
+                }
+                let values = value.to_f64_list().unwrap_or_default();
+                let numeric = !values.is_empty();
+                let components = names.entry(key.to_string()).or_insert(Some(values.len()));
+                if !numeric || *components != Some(values.len()) {
+                    *components = None;
+                }
+            }
+        }
+    }
+    names
+        .into_iter()
+        .filter_map(|(name, components)| components.map(|c| (name, c)))
+        .collect()
+}
+
+/// The number type and precision of a data item with the values of an attribute, `f64` unless
+/// all values have the same type.
+fn number_type<'a, I>(values: I) -> (&'static str, u8)
+where
+    I: Iterator<Item = &'a AttributeValue>,
+{
+    fn scalar_type(value: &AttributeValue) -> Option<(&'static str, u8)> {
+        match *value {
+            AttributeValue::I8(_) => Some(("Char", 1)),
+            AttributeValue::I16(_) => Some(("Int", 2)),
+            AttributeValue::I32(_) => Some(("Int", 4)),
+            AttributeValue::I64(_) => Some(("Int", 8)),
+            AttributeValue::U8(_) => Some(("UChar", 1)),
+            AttributeValue::U16(_) => Some(("UInt", 2)),
+            AttributeValue::U32(_) => Some(("UInt", 4)),
+            AttributeValue::U64(_) => Some(("UInt", 8)),
+            AttributeValue::F32(_) => Some(("Float", 4)),
+            AttributeValue::F64(_) => Some(("Float", 8)),
+            AttributeValue::List(_) | AttributeValue::String(_) => None,
+        }
+    }
+
+    let mut common = None;
+    for value in values {
+        let items = match value.as_list() {
+            Some(items) => items,
+            None => ::std::slice::from_ref(value),
+        };
+        for item in items {
+            match (scalar_type(item), common) {
+                (None, _) => return ("Float", 8),
+                (Some(t), None) => common = Some(t),
+                (Some(t), Some(c)) if t != c => return ("Float", 8),
+                _ => (),
+            }
+        }
+    }
+    common.unwrap_or(("Float", 8))
+}
+
+fn write_attributes<W: Write>(
+    mut target: W,
+    center: &str,
+    attrs: &[&AttributeMap],
+) -> Result<(), Error> {
+    for (name, components) in numeric_attributes(attrs.iter().cloned()) {
+        let key = AttributeName::from(name.as_str());
+        let (number_type, precision) = number_type(attrs.iter().filter_map(|attr| attr.get(&key)));
+        let (attribute_type, dimensions) = match components {
+            1 => ("Scalar", format!("{}", attrs.len())),
+            3 => ("Vector", format!("{} 3", attrs.len())),
+            c => ("Matrix", format!("{} {}", attrs.len(), c)),
+        };
+        writeln!(
+            target,
+            "      <Attribute Name=\"{}\" AttributeType=\"{}\" Center=\"{}\">",
+            xml::escape(&name),
+            attribute_type,
+            center
+        )?;
+        writeln!(
+            target,
+            "        <DataItem Dimensions=\"{}\" NumberType=\"{}\" Precision=\"{}\" Format=\"XML\">",
+            dimensions, number_type, precision
+        )?;
+        let zeros = vec!["0"; components].join(" ");
+        for attr in attrs {
+            writeln!(
+                target,
+                "          {}",
+                attr.get(&key).map_or(zeros.clone(), |v| v.to_string())
+            )?;
+        }
+        writeln!(target, "        </DataItem>")?;
+        writeln!(target, "      </Attribute>")?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct XdmfSerializer {}
+
+impl XdmfSerializer {
+    pub fn new() -> Self {
+        XdmfSerializer {}
+    }
+}
+
+impl Serializer for XdmfSerializer {
+    fn serialize<M, W>(&self, mesh: &M, mut target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write,
+    {
+        let dimension = mesh.metadata().dimension() as usize;
+        if dimension != 2 && dimension != 3 {
+            return Err(Error::Unsupported(format!(
+                "XDMF does not support meshes of dimension {}.",
+                dimension
+            )));
+        }
+        let points = geometry::collect_points(mesh, dimension)?;
+
+        let mut node_attrs = Vec::with_capacity(points.len());
+        let mut groups = Vec::new();
+        for group in mesh.groups() {
+            let metadata = group.metadata();
+            match metadata.kind() {
+                EntityKind::Node => {
+                    node_attrs
+                        .extend(group.map(|node| AttributeMap::from_container(node.attributes())));
+                }
+                EntityKind::Element => {
+                    let mut elements = Vec::with_capacity(metadata.len());
+                    for element in group {
+                        let nodes = element.node_indices()?;
+                        if let Some(&i) = nodes.iter().find(|&&i| i >= points.len()) {
+                            return Err(Error::BrokenInvariant(format!("Undefined node {}.", i)));
+                        }
+                        let (topology_type, nodes) =
+                            topology_type(&element, nodes, &points, dimension)?;
+                        let attr = AttributeMap::from_container(element.attributes());
+                        elements.push((topology_type, nodes, attr));
+                    }
+                    if !elements.is_empty() {
+                        groups.push((metadata.name().get_original().0.to_string(), elements));
+                    }
+                }
+                EntityKind::Vector | EntityKind::Other => (),
+            }
+        }
+
+        writeln!(target, "<?xml version=\"1.0\"?>")?;
+        writeln!(target, "<!-- XDMF file, generated by multimesh -->")?;
+        writeln!(target, "<Xdmf Version=\"3.0\">")?;
+        writeln!(target, "  <Domain>")?;
+        if groups.len() > 1 {
+            writeln!(
+                target,
+                "    <Grid Name=\"mesh\" GridType=\"Collection\" CollectionType=\"Spatial\">"
+            )?;
+        }
+
+        for (name, elements) in &groups {
+            // Number the nodes used by the grid.
+            let used: BTreeSet<usize> = elements.iter().flat_map(|e| e.1.iter().cloned()).collect();
+            let local: BTreeMap<usize, usize> =
+                used.iter().enumerate().map(|(l, &i)| (i, l)).collect();
+
+            writeln!(
+                target,
+                "    <Grid Name=\"{}\" GridType=\"Uniform\">",
+                xml::escape(name)
+            )?;
+
+            let (first_type, _) = elements[0].0;
+            let uniform = elements
+                .iter()
+                .all(|e| e.0 == elements[0].0 && e.1.len() == elements[0].1.len());
+            if uniform {
+                let nary = elements[0].1.len();
+                writeln!(
+                    target,
+                    "      <Topology TopologyType=\"{}\" NumberOfElements=\"{}\" NodesPerElement=\"{}\">",
+                    first_type,
+                    elements.len(),
+                    nary
+                )?;
+                writeln!(
+                    target,
+                    "        <DataItem Dimensions=\"{} {}\" NumberType=\"Int\" Format=\"XML\">",
+                    elements.len(),
+                    nary
+                )?;
+            } else {
+                let size: usize = elements
+                    .iter()
+                    .map(|e| e.1.len() + if e.0 .1 <= POLYGON_ID { 2 } else { 1 })
+                    .sum();
+                writeln!(
+                    target,
+                    "      <Topology TopologyType=\"Mixed\" NumberOfElements=\"{}\">",
+                    elements.len()
+                )?;
+                writeln!(
+                    target,
+                    "        <DataItem Dimensions=\"{}\" NumberType=\"Int\" Format=\"XML\">",
+                    size
+                )?;
+            }
+            for ((_, id), nodes, _) in elements {
+                let mut line: Vec<String> = Vec::with_capacity(nodes.len() + 2);
+                if !uniform {
+                    line.push(id.to_string());
+                    if *id <= POLYGON_ID {
+                        line.push(nodes.len().to_string());
+                    }
+                }
+                line.extend(nodes.iter().map(|i| local[i].to_string()));
+                writeln!(target, "          {}", line.join(" "))?;
+            }
+            writeln!(target, "        </DataItem>")?;
+            writeln!(target, "      </Topology>")?;
+
+            writeln!(
+                target,
+                "      <Geometry GeometryType=\"{}\">",
+                if dimension == 2 { "XY" } else { "XYZ" }
+            )?;
+            writeln!(
+                target,
+                "        <DataItem Dimensions=\"{} {}\" NumberType=\"Float\" Precision=\"8\" Format=\"XML\">",
+                used.len(),
+                dimension
+            )?;
+            for &i in &used {
+                let line: Vec<String> = points[i][..dimension]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect();
+                writeln!(target, "          {}", line.join(" "))?;
+            }
+            writeln!(target, "        </DataItem>")?;
+            writeln!(target, "      </Geometry>")?;
+
+            let grid_node_attrs: Vec<&AttributeMap> =
+                used.iter().map(|&i| &node_attrs[i]).collect();
+            write_attributes(&mut target, "Node", &grid_node_attrs)?;
+            let grid_element_attrs: Vec<&AttributeMap> = elements.iter().map(|e| &e.2).collect();
+            write_attributes(&mut target, "Cell", &grid_element_attrs)?;
+
+            writeln!(target, "    </Grid>")?;
+        }
+
+        if groups.len() > 1 {
+            writeln!(target, "    </Grid>")?;
+        }
+        writeln!(target, "  </Domain>")?;
+        writeln!(target, "</Xdmf>")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data::face_vertex::Mesh;
+    use nalgebra::Point3;
+
+    #[test]
+    fn mixed_topology_with_referenced_geometry() {
+        let source = r#"<Xdmf Version="3.0"><Domain>
+  <DataItem Name="x" Format="XML">0 1 1 0 2</DataItem>
+  <Grid GridType="Collection">
+    <Grid Name="plate">
+      <Topology TopologyType="Mixed">
+        <DataItem Format="XML">5 0 1 2 3  4 1 4 2  2 2 0 1</DataItem>
+      </Topology>
+      <Geometry GeometryType="X_Y">
+        <DataItem Reference="XML">/Xdmf/Domain/DataItem[@Name="x"]</DataItem>
+        <DataItem Format="XML">0 0 1 1 0.5</DataItem>
+      </Geometry>
+    </Grid>
+  </Grid>
+</Domain></Xdmf>"#;
+        let mut mesh = Mesh::default();
+        XdmfDeserializer::deserialize_into(source.as_bytes(), &mut mesh).unwrap();
+        assert_eq!(mesh.metadata().dimension(), 2);
+
+        let nodes = mesh.node_groups()[0].entities();
+        assert_eq!(nodes[4].position(2).unwrap(), Point3::new(2., 0.5, 0.));
+        let elements = mesh.element_groups()[0].entities();
+        let indices: Vec<Vec<usize>> = elements.iter().map(|e| e.node_indices().unwrap()).collect();
+        assert_eq!(indices, vec![vec![0, 1, 2, 3], vec![1, 4, 2], vec![0, 1]]);
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
+// Triggered by `#[derive(Fail)]` of the failure crate.
+#![allow(non_local_definitions)]
 
 //! # Overview
 //! The goal of this crate is to provide a generic interface for serializing and
@@ -10,16 +12,25 @@
 //! which are suitable for a wide range of formats while still being efficient and
 //! ergonomic for real world and big data set usage.
 
+extern crate bytemuck;
 #[macro_use]
 extern crate failure;
+extern crate flate2;
 #[macro_use]
-extern crate lazy_static;
+extern crate log;
+extern crate memmap2;
 extern crate nalgebra;
 extern crate prettytable;
-extern crate regex;
+extern crate rayon;
+extern crate roxmltree;
+extern crate xz2;
+extern crate zip;
+extern crate zstd;
 
 mod util;
 
 pub mod data;
+pub mod de;
 pub mod error;
 pub mod format;
+pub mod ser;
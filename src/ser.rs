@@ -0,0 +1,13 @@
+//! Serialization of any [GetMesh] implementation into a format.
+
+use data::GetMesh;
+use error::Error;
+use std::io::Write;
+
+/// Writes a mesh from a [GetMesh] implementation into a target.
+pub trait Serializer {
+    fn serialize<M, W>(&self, mesh: &M, target: W) -> Result<(), Error>
+    where
+        M: GetMesh,
+        W: Write;
+}
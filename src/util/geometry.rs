@@ -0,0 +1,127 @@
+//! Helpers to pass geometry and connectivity through attribute containers.
+//!
+//! See [keys](::data::attribute::keys) for the conventions.
+
+use data::{
+    attribute::{
+        keys, AttributeContainer, AttributeContainerMut, AttributeMap, AttributeName,
+        AttributeValue,
+    },
+    CellType, Entity, EntityBox, EntityKind, EntityMut, GetMesh, GetMeshGroup,
+};
+use error::Error;
+
+/// Create an entity with the coordinates of `position` (at most three) set as attributes.
+pub(crate) fn entity_with_position(
+    kind: EntityKind,
+    position: &[f64],
+    mut attr: AttributeMap,
+) -> EntityBox {
+    for (key, value) in keys::POSITION.iter().zip(position) {
+        attr.set(AttributeName::key(key), (*value).into());
+    }
+    EntityBox::new(kind, attr)
+}
+
+/// Create an entity with the node indices `nodes` set as attribute.
+pub(crate) fn entity_with_nodes(
+    kind: EntityKind,
+    nodes: &[usize],
+    attr: AttributeMap,
+) -> EntityBox {
+    let mut entity = EntityBox::new(kind, attr);
+    entity.set_node_indices(nodes);
+    entity
+}
+
+/// Create an element of type `cell` with the node indices `nodes` in canonical order.
+pub(crate) fn element_with_cell(
+    cell: CellType,
+    nodes: &[usize],
+    mut attr: AttributeMap,
+) -> EntityBox {
+    attr.set(AttributeName::key(keys::CELL), cell.name().into());
+    entity_with_nodes(EntityKind::Element, nodes, attr)
+}
+
+/// Read the reference tag of an entity, if it has one.
+pub(crate) fn reference<E: Entity>(entity: &E) -> Option<&AttributeValue> {
+    entity.attributes().get(&AttributeName::key(keys::REF))
+}
+
+/// Read the cell type of an element, or infer it from its node indices `nodes` if it has none.
+///
+/// Elements with four, six or eight nodes are faces if they are flat, or if the mesh has only
+/// `dim` 2 dimensions.
+pub(crate) fn cell_type<E: Entity>(
+    element: &E,
+    nodes: &[usize],
+    points: &[[f64; 3]],
+    dim: usize,
+) -> Result<CellType, Error> {
+    if let Some(name) = element.attributes().get(&AttributeName::key(keys::CELL)) {
+        let name = name.to_string();
+        let cell = CellType::from_name(&name)
+            .ok_or_else(|| Error::BrokenInvariant(format!("Unknown cell type `{}`.", name)))?;
+        if cell.num_nodes() != nodes.len() {
+            return Err(Error::BrokenInvariant(format!(
+                "Cell of type {} with {} nodes.",
+                name,
+                nodes.len()
+            )));
+        }
+        return Ok(cell);
+    }
+    let flat = match nodes.len() {
+        _ if dim < 3 => true,
+        4 => is_flat(nodes, points),
+        // The corners of quadratic faces come first, followed by the midpoint of their first
+        // edge, while the node after the base of prisms and hexahedra lies above it.
+        6 => is_flat(&nodes[..4], points),
+        8 => is_flat(&[nodes[0], nodes[1], nodes[2], nodes[4]], points),
+        _ => false,
+    };
+    CellType::infer(nodes.len(), flat)
+        .ok_or_else(|| Error::Unsupported(format!("No cell type with {} nodes.", nodes.len())))
+}
+
+/// Read the positions of all nodes of a mesh, padded with zeros to three dimensions.
+pub(crate) fn collect_points<M: GetMesh>(mesh: &M, dim: usize) -> Result<Vec<[f64; 3]>, Error> {
+    let mut points = Vec::new();
+    for group in mesh.groups() {
+        if group.metadata().kind() == EntityKind::Node {
+            points.reserve(group.metadata().len());
+            match group.positions() {
+                Some(ref positions) if positions.width() >= dim => {
+                    for p in positions.iter() {
+                        let mut point = [0.; 3];
+                        point[..dim].copy_from_slice(&p[..dim]);
+                        points.push(point);
+                    }
+                }
+                _ => {
+                    for node in group {
+                        let p = node.position(dim)?;
+                        points.push([p.x, p.y, p.z]);
+                    }
+                }
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// Whether the four points with the indices `nodes` lie in one plane.
+///
+/// This distinguishes quadrilaterals from tetrahedra, as long as elements don't carry their
+/// type.
+pub(crate) fn is_flat(nodes: &[usize], points: &[[f64; 3]]) -> bool {
+    let p: Vec<&[f64; 3]> = nodes.iter().map(|&i| &points[i]).collect();
+    let d = |a: &[f64; 3], b: &[f64; 3]| [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let (u, v, w) = (d(p[0], p[1]), d(p[0], p[2]), d(p[0], p[3]));
+    let volume = u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0]);
+    let norm = |a: [f64; 3]| (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+    let scale = norm(u).max(norm(v)).max(norm(w));
+    volume.abs() <= 1e-12 * scale * scale * scale
+}
@@ -1,130 +1,535 @@
+//! Reads whitespace separated items from text, line by line.
+//!
+//! Only the current line is kept in memory, so files of any size can be read, as long as no
+//! line is longer than [MAX_LINE_LEN]. Lines starting
+//! with `#` are comments and skipped like empty lines. Items are separated by ASCII whitespace,
+//! and numbers are parsed from their bytes with [ParseItem].
+
 use error::Error;
 use failure::Fail;
-use regex::Regex;
 use std::{
-    collections::VecDeque,
-    fmt::Display,
-    str::{FromStr, Lines},
+    any::type_name,
+    convert::TryFrom,
+    io::{BufRead, Read},
+    str,
 };
 
-#[derive(Clone, Debug, Fail)]
+/// The longest line in bytes, without line break, which is read into memory.
+///
+/// Longer lines are syntax errors rather than an unbounded allocation, no supported format has
+/// lines anywhere near this length.
+pub const MAX_LINE_LEN: usize = 1 << 24;
+
+#[derive(Debug, Fail)]
 pub enum ItemReaderError {
     #[fail(display = "Unexpected EOF")]
     UnexpectedEof,
 
     #[fail(display = "Parsing value failed: {}", _0)]
     Parse(String),
+
+    #[fail(display = "Line {} is longer than {} bytes", _0, _1)]
+    LineTooLong(usize, usize),
+
+    #[fail(display = "Reading failed: {}", _0)]
+    Io(#[cause] ::std::io::Error),
+}
+
+impl From<::std::io::Error> for ItemReaderError {
+    fn from(e: ::std::io::Error) -> Self {
+        ItemReaderError::Io(e)
+    }
 }
 
-pub(crate) struct ItemReader<'s> {
-    lines: Lines<'s>,
-    line_buf: VecDeque<&'s str>,
+pub(crate) struct ItemReader<R> {
+    source: R,
+    /// The current line, without line break.
+    line: Vec<u8>,
+    /// Byte offset of the unread rest of `line`.
+    pos: usize,
+    /// Number of the current line, starting at one.
+    line_number: usize,
 }
 
-impl<'s> ItemReader<'s> {
-    pub(crate) fn new(data: &'s str) -> Self {
+impl<R: BufRead> ItemReader<R> {
+    pub(crate) fn new(source: R) -> Self {
         ItemReader {
-            lines: data.lines(),
-            line_buf: VecDeque::new(),
+            source,
+            line: Vec::new(),
+            pos: 0,
+            line_number: 0,
         }
     }
 
-    pub(crate) fn next_parse<T>(&mut self) -> Result<T, ItemReaderError>
-    where
-        T: FromStr,
-        <T as FromStr>::Err: Display,
-    {
-        self.next_result().and_then(|s| {
-            s.parse()
-                .map_err(|e| ItemReaderError::Parse(format!("{}", e)))
-        })
+    /// A reader whose first line has the number `first_line`, for parts of a source.
+    pub(crate) fn starting_at_line(source: R, first_line: usize) -> Self {
+        ItemReader {
+            line_number: first_line - 1,
+            ..ItemReader::new(source)
+        }
     }
 
-    pub(crate) fn next_result(&mut self) -> Result<&'s str, ItemReaderError> {
-        self.next().ok_or(ItemReaderError::UnexpectedEof)
+    /// The number of the line of the last item, starting at one.
+    pub(crate) fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Whether the current line has no more items.
+    pub(crate) fn at_line_end(&self) -> bool {
+        self.line[self.pos..].iter().all(|&b| is_space(b))
+    }
+
+    /// Append the next line to `text`, up to its `max_items`th item, and return the number of
+    /// items taken. The rest of the line stays to be read.
+    ///
+    /// Must only be called [at the end of a line](ItemReader::at_line_end), returns `None` at
+    /// the end of the source.
+    pub(crate) fn take_line(
+        &mut self,
+        text: &mut Vec<u8>,
+        max_items: usize,
+    ) -> Result<Option<usize>, ItemReaderError> {
+        debug_assert!(self.at_line_end());
+        if !self.read_line()? {
+            return Ok(None);
+        }
+        let mut taken = 0;
+        if self.line.first() == Some(&b'#') {
+            self.pos = self.line.len();
+        } else {
+            while taken < max_items && self.take_item().is_some() {
+                taken += 1;
+            }
+        }
+        text.extend_from_slice(&self.line[..self.pos]);
+        text.push(b'\n');
+        Ok(Some(taken))
+    }
+
+    /// Read the next item, `None` at the end of the source.
+    pub(crate) fn next(&mut self) -> Result<Option<&str>, ItemReaderError> {
+        match self.next_range(true)? {
+            Some((start, end)) => self.item(start, end).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn next_parse<T: ParseItem>(&mut self) -> Result<T, ItemReaderError> {
+        match self.next_range(true)? {
+            Some((start, end)) => parse_bytes(&self.line[start..end]).map_err(|e| self.in_line(e)),
+            None => Err(ItemReaderError::UnexpectedEof),
+        }
+    }
+
+    pub(crate) fn next_result(&mut self) -> Result<&str, ItemReaderError> {
+        self.next()?.ok_or(ItemReaderError::UnexpectedEof)
     }
 
     /// Read all items until the end of the current line.
-    pub(crate) fn next_until_eol(&mut self) -> Option<&'s str> {
-        self.next_item(false)
-    }
-
-    pub(crate) fn next_parse_until_eol<T>(&mut self) -> Result<T, ItemReaderError>
-    where
-        T: FromStr,
-        <T as FromStr>::Err: Display,
-    {
-        self.next_until_eol()
-            .ok_or(ItemReaderError::UnexpectedEof)
-            .and_then(|s| {
-                s.parse()
-                    .map_err(|e| ItemReaderError::Parse(format!("{}", e)))
-            })
-    }
-
-    fn next_item(&mut self, ignore_newline: bool) -> Option<&'s str> {
-        let probe_buf = |line_buf: &mut VecDeque<&'s str>| {
-            while let Some(item) = line_buf.remove(0) {
-                if !item.trim().is_empty() {
-                    return Some(item);
-                }
+    pub(crate) fn next_until_eol(&mut self) -> Result<Option<&str>, ItemReaderError> {
+        match self.next_range(false)? {
+            Some((start, end)) => self.item(start, end).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn next_parse_until_eol<T: ParseItem>(&mut self) -> Result<T, ItemReaderError> {
+        match self.next_range(false)? {
+            Some((start, end)) => parse_bytes(&self.line[start..end]).map_err(|e| self.in_line(e)),
+            None => Err(ItemReaderError::UnexpectedEof),
+        }
+    }
+
+    /// Add the current line number to a parse error.
+    fn in_line(&self, e: ItemReaderError) -> ItemReaderError {
+        match e {
+            ItemReaderError::Parse(message) => {
+                ItemReaderError::Parse(format!("{} in line {}", message, self.line_number))
+            }
+            e => e,
+        }
+    }
+
+    fn item(&self, start: usize, end: usize) -> Result<&str, ItemReaderError> {
+        str::from_utf8(&self.line[start..end]).map_err(|e| {
+            ItemReaderError::Parse(format!("Invalid UTF-8 in line {}: {}", self.line_number, e))
+        })
+    }
+
+    /// Find the next item, in the current line or in the following lines if `ignore_newline`
+    /// is set. Otherwise only one more line is read.
+    fn next_range(
+        &mut self,
+        ignore_newline: bool,
+    ) -> Result<Option<(usize, usize)>, ItemReaderError> {
+        if let Some(range) = self.take_item() {
+            return Ok(Some(range));
+        }
+
+        while self.read_line()? {
+            if self.line.first() == Some(&b'#') {
+                self.pos = self.line.len();
+            } else if let Some(range) = self.take_item() {
+                return Ok(Some(range));
+            }
+
+            if !ignore_newline {
+                return Ok(None);
             }
-            None
-        };
+        }
+
+        Ok(None)
+    }
 
-        if let Some(item) = probe_buf(&mut self.line_buf) {
-            return Some(item);
+    /// Take the next item of the current line.
+    fn take_item(&mut self) -> Option<(usize, usize)> {
+        let line = &self.line;
+        let mut start = self.pos;
+        while start < line.len() && is_space(line[start]) {
+            start += 1;
+        }
+        if start == line.len() {
+            self.pos = start;
+            return None;
+        }
+        let mut end = start + 1;
+        while end < line.len() && !is_space(line[end]) {
+            end += 1;
+        }
+        self.pos = end;
+        Some((start, end))
+    }
+
+    /// Replace the current line by the next one, `false` at the end of the source.
+    fn read_line(&mut self) -> Result<bool, ItemReaderError> {
+        self.line.clear();
+        self.pos = 0;
+        let mut source = (&mut self.source).take(MAX_LINE_LEN as u64 + 1);
+        if source.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(false);
+        }
+        if self.line.len() > MAX_LINE_LEN && self.line.last() != Some(&b'\n') {
+            return Err(ItemReaderError::LineTooLong(
+                self.line_number + 1,
+                MAX_LINE_LEN,
+            ));
         }
+        if self.line.last() == Some(&b'\n') {
+            self.line.pop();
+            if self.line.last() == Some(&b'\r') {
+                self.line.pop();
+            }
+        }
+        self.line_number += 1;
+        Ok(true)
+    }
+}
+
+/// Whether a byte separates items, like `\s` of regular expressions in ASCII.
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)
+}
+
+/// Parse an item, see [ParseItem].
+pub(crate) fn parse_item<T: ParseItem>(item: &str) -> Result<T, ItemReaderError> {
+    parse_bytes(item.as_bytes())
+}
+
+fn parse_bytes<T: ParseItem>(item: &[u8]) -> Result<T, ItemReaderError> {
+    T::parse_item(item).ok_or_else(|| {
+        ItemReaderError::Parse(format!(
+            "`{}` is not a valid {}",
+            String::from_utf8_lossy(item),
+            type_name::<T>()
+        ))
+    })
+}
 
-        lazy_static! {
-            static ref RE: Regex = Regex::new(r"\s+").unwrap();
+/// Values which can be parsed from the bytes of an item.
+///
+/// Integers are parsed digit by digit. Floats with at most 19 significant digits and small
+/// exponents are computed exactly from their digits (the fast path of Clinger's algorithm),
+/// all others are parsed by the standard library.
+pub(crate) trait ParseItem: Sized {
+    fn parse_item(item: &[u8]) -> Option<Self>;
+}
+
+/// The magnitude of an integer with optional sign, and whether it is negative.
+fn parse_magnitude(item: &[u8]) -> Option<(u64, bool)> {
+    let (negative, digits) = match item.first() {
+        Some(b'-') => (true, &item[1..]),
+        Some(b'+') => (false, &item[1..]),
+        _ => (false, item),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &byte in digits {
+        let digit = byte.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
         }
+        value = value.checked_mul(10)?.checked_add(u64::from(digit))?;
+    }
+    Some((value, negative))
+}
 
-        while let Some(line) = self.lines.next() {
-            if !line.starts_with("#") && !line.trim().is_empty() {
-                self.line_buf.extend(RE.split(line));
-                if let Some(item) = probe_buf(&mut self.line_buf) {
-                    return Some(item);
+macro_rules! parse_unsigned {
+    ($($t:ty),*) => {$(
+        impl ParseItem for $t {
+            fn parse_item(item: &[u8]) -> Option<Self> {
+                match parse_magnitude(item)? {
+                    (value, false) => <$t>::try_from(value).ok(),
+                    (0, true) => Some(0),
+                    _ => None,
                 }
             }
+        }
+    )*};
+}
 
-            if !ignore_newline {
-                return None;
+macro_rules! parse_signed {
+    ($($t:ty),*) => {$(
+        impl ParseItem for $t {
+            fn parse_item(item: &[u8]) -> Option<Self> {
+                let (magnitude, negative) = parse_magnitude(item)?;
+                let value = if negative {
+                    0i128 - i128::from(magnitude)
+                } else {
+                    i128::from(magnitude)
+                };
+                <$t>::try_from(value).ok()
             }
         }
+    )*};
+}
+
+parse_unsigned!(u8, u16, u32, u64, usize);
+parse_signed!(i8, i16, i32, i64);
+
+/// The digits of a decimal number as `mantissa * 10^exponent`, `None` if it has more than
+/// 19 significant digits or isn't a plain decimal number.
+fn parse_decimal(item: &[u8]) -> Option<(bool, u64, i32)> {
+    let (negative, mut rest) = match item.first() {
+        Some(b'-') => (true, &item[1..]),
+        Some(b'+') => (false, &item[1..]),
+        _ => (false, item),
+    };
 
-        None
+    let mut mantissa: u64 = 0;
+    let mut significant = 0;
+    let mut exponent: i32 = 0;
+    let mut any_digits = false;
+    let mut after_point = false;
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'0'..=b'9' => {
+                any_digits = true;
+                if mantissa != 0 || byte != b'0' {
+                    significant += 1;
+                    if significant > 19 {
+                        return None;
+                    }
+                }
+                mantissa = mantissa * 10 + u64::from(byte - b'0');
+                if after_point {
+                    exponent -= 1;
+                }
+            }
+            b'.' if !after_point => after_point = true,
+            b'e' | b'E' => {
+                let (value, negative) = parse_magnitude(tail)?;
+                let value = i32::try_from(value).ok()?;
+                exponent = exponent.checked_add(if negative { -value } else { value })?;
+                rest = &[];
+                break;
+            }
+            _ => return None,
+        }
+        rest = tail;
+    }
+    if !any_digits || !rest.is_empty() {
+        return None;
     }
+    Some((negative, mantissa, exponent))
 }
 
-impl<'s> Iterator for ItemReader<'s> {
-    type Item = &'s str;
+macro_rules! parse_float {
+    ($t:ty, $max_mantissa:expr, $powers:expr) => {
+        impl ParseItem for $t {
+            fn parse_item(item: &[u8]) -> Option<Self> {
+                if let Some((negative, mantissa, exponent)) = parse_decimal(item) {
+                    // Both the mantissa and the power of ten are exact, so is their product
+                    // or quotient after one rounding.
+                    let powers: &[$t] = &$powers;
+                    if mantissa <= $max_mantissa
+                        && (exponent.unsigned_abs() as usize) < powers.len()
+                    {
+                        let value = mantissa as $t;
+                        let value = if exponent < 0 {
+                            value / powers[(-exponent) as usize]
+                        } else {
+                            value * powers[exponent as usize]
+                        };
+                        return Some(if negative { -value } else { value });
+                    }
+                }
+                str::from_utf8(item).ok()?.parse().ok()
+            }
+        }
+    };
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next_item(true)
+parse_float!(
+    f64,
+    1 << 53,
+    [
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+        1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+    ]
+);
+parse_float!(
+    f32,
+    1 << 24,
+    [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10]
+);
+
+impl ParseItem for String {
+    fn parse_item(item: &[u8]) -> Option<Self> {
+        str::from_utf8(item).ok().map(str::to_string)
     }
 }
 
 impl From<ItemReaderError> for Error {
     fn from(e: ItemReaderError) -> Error {
-        Error::OtherInternal(Box::new(e.compat()))
+        match e {
+            ItemReaderError::Io(e) => Error::Io(e),
+            e @ ItemReaderError::LineTooLong(..) => Error::Syntax(e.to_string()),
+            e => Error::OtherInternal(Box::new(e.compat())),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io;
 
     #[test]
     fn item_reader() {
         let source = " abc   xyz  -1\n\r\t \n  abc xyz";
-        let mut reader = ItemReader::new(source);
-
-        assert_eq!(reader.next(), "abc".into());
-        assert_eq!(reader.next(), "xyz".into());
-        assert_eq!(reader.next(), "-1".into());
-        assert_eq!(reader.next(), "abc".into());
-        assert_eq!(reader.next(), "xyz".into());
-        assert_eq!(reader.next(), None);
+        let mut reader = ItemReader::new(source.as_bytes());
+
+        assert_eq!(reader.next().unwrap(), Some("abc"));
+        assert_eq!(reader.next().unwrap(), Some("xyz"));
+        assert_eq!(reader.next_parse::<i32>().unwrap(), -1);
+        assert_eq!(reader.next().unwrap(), Some("abc"));
+        assert_eq!(reader.line_number(), 3);
+        assert_eq!(reader.next().unwrap(), Some("xyz"));
+        assert_eq!(reader.next().unwrap(), None);
+    }
+
+    #[test]
+    fn line_too_long() {
+        let source = b"1 2\n".chain(io::repeat(b'3').take(2 * MAX_LINE_LEN as u64));
+        let mut reader = ItemReader::new(io::BufReader::new(source));
+
+        assert_eq!(reader.next_parse::<u8>().unwrap(), 1);
+        assert_eq!(reader.next_parse::<u8>().unwrap(), 2);
+        let error = Error::from(reader.next().unwrap_err());
+        assert!(matches!(error, Error::Syntax(ref e) if e.starts_with("Line 2 is longer")));
+
+        // Lines of the maximum length are fine.
+        let mut line = vec![b' '; MAX_LINE_LEN];
+        line[MAX_LINE_LEN - 1] = b'4';
+        line.extend_from_slice(b"\n5");
+        let mut reader = ItemReader::new(&line[..]);
+        assert_eq!(reader.next_parse::<u8>().unwrap(), 4);
+        assert_eq!(reader.next_parse::<u8>().unwrap(), 5);
+    }
+
+    #[test]
+    fn lines_and_comments() {
+        let source = "1 2\n# 3 4\n5\r\n\n6 7\n";
+        let mut reader = ItemReader::new(source.as_bytes());
+
+        assert_eq!(reader.next_until_eol().unwrap(), Some("1"));
+        assert_eq!(reader.next_until_eol().unwrap(), Some("2"));
+        // The comment line ends the search for items of the next line.
+        assert_eq!(reader.next_until_eol().unwrap(), None);
+        assert_eq!(reader.next_until_eol().unwrap(), Some("5"));
+        assert_eq!(reader.next_until_eol().unwrap(), None);
+        assert_eq!(reader.next().unwrap(), Some("6"));
+        assert_eq!(reader.line_number(), 5);
+        assert!(reader.next_parse::<f64>().is_ok());
+        assert!(matches!(
+            reader.next_result(),
+            Err(ItemReaderError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn bounded_memory() {
+        let source: String = (0..10_000).map(|i| format!("{} {}.5\n", i, i)).collect();
+        let mut reader = ItemReader::new(source.as_bytes());
+        for i in 0..10_000 {
+            assert_eq!(reader.next_parse::<usize>().unwrap(), i);
+            reader.next_result().unwrap();
+        }
+        assert!(reader.line.capacity() < 64);
+    }
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(parse_item::<usize>("42").ok(), Some(42));
+        assert_eq!(parse_item::<u8>("-0").ok(), Some(0));
+        assert!(parse_item::<u8>("256").is_err());
+        assert!(parse_item::<u64>("-1").is_err());
+        assert_eq!(
+            parse_item::<i64>("-9223372036854775808").ok(),
+            Some(i64::MIN)
+        );
+        assert!(parse_item::<i64>("9223372036854775808").is_err());
+        assert!(parse_item::<i32>("1.0").is_err());
+        assert!(parse_item::<i32>("+").is_err());
+
+        for text in &[
+            "0",
+            "-0.0",
+            "1.5",
+            ".5",
+            "5.",
+            "-0.042",
+            "1e-7",
+            "2.5E+3",
+            "0.1",
+            "3.14159265358979",
+            "123456789012345678901234",
+            "1e-300",
+            "4.9e-324",
+            "1.7976931348623157e308",
+            "inf",
+            "-NaN",
+            "0.000000000000000000000000001",
+        ] {
+            let expected: f64 = text.parse().unwrap();
+            let parsed: f64 = parse_item(text).unwrap();
+            assert!(
+                parsed.to_bits() == expected.to_bits() || (parsed.is_nan() && expected.is_nan()),
+                "{}",
+                text
+            );
+            let expected: f32 = text.parse().unwrap();
+            let parsed: f32 = parse_item(text).unwrap();
+            assert!(
+                parsed.to_bits() == expected.to_bits() || (parsed.is_nan() && expected.is_nan()),
+                "{}",
+                text
+            );
+        }
+        for text in &["", "-", ".", "1.2.3", "e5", "1e", "0x10", "1,5"] {
+            assert!(parse_item::<f64>(text).is_err(), "{}", text);
+        }
     }
 }
@@ -1,3 +1,7 @@
 // TODO
+pub(crate) mod geometry;
 pub(crate) mod item_reader;
+pub(crate) mod parallel;
 mod result;
+pub(crate) mod sniff;
+pub(crate) mod xml;
@@ -0,0 +1,211 @@
+//! Parses the entities of large sections on the thread pool of rayon, see [read_parallel].
+
+use data::MAX_RESERVE;
+use error::Error;
+use rayon::prelude::*;
+use std::io::BufRead;
+use util::item_reader::ItemReader;
+
+/// The number of items taken from the source per call of [read_parallel], which bounds the
+/// memory used for the text of lines.
+const ROUND_ITEMS: usize = 1 << 20;
+
+/// The number of chunks per thread, so that threads which finish early can take over.
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// The entities read by [read_parallel], followed by the error which stopped it, if any.
+pub(crate) struct Parsed<T> {
+    pub(crate) entities: Vec<T>,
+    pub(crate) error: Option<Error>,
+}
+
+/// A part of the taken lines, which starts with an entity.
+struct Chunk {
+    /// The byte range in the text of the lines.
+    start: usize,
+    end: usize,
+    /// The number of the first line.
+    first_line: usize,
+    items: usize,
+}
+
+/// Read some of the `count` entities of `width` items each which follow in `reader`.
+///
+/// Whole lines are taken from `reader`, cut into chunks which start with an entity and parsed
+/// by `parse` in parallel. The entities come back in order and the chunks keep the line numbers
+/// of `reader`, so the result is the same as reading the entities one by one, up to the first
+/// error.
+///
+/// Returns no entities if the current line has more items, then the caller has to read the next
+/// entity by itself.
+pub(crate) fn read_parallel<R, T, F>(
+    reader: &mut ItemReader<R>,
+    count: usize,
+    width: usize,
+    parse: F,
+) -> Parsed<T>
+where
+    R: BufRead,
+    T: Send,
+    F: Fn(&mut ItemReader<&[u8]>) -> Result<T, Error> + Sync,
+{
+    let mut text = Vec::new();
+    // The lines after which an entity starts: their end in `text`, the number of items up to
+    // them and the number of the next line.
+    let mut boundaries = Vec::new();
+    let first_line = reader.line_number() + 1;
+    let max_items = match count.checked_mul(width) {
+        Some(max_items) => max_items,
+        None => {
+            return Parsed {
+                entities: Vec::new(),
+                error: Some(Error::Syntax(format!("Too many entities: {}", count))),
+            }
+        }
+    };
+    let mut items = 0;
+    let mut line = first_line;
+    let mut read_error = None;
+    if reader.at_line_end() {
+        while items < max_items && (items < ROUND_ITEMS || items % width != 0) {
+            match reader.take_line(&mut text, max_items - items) {
+                Ok(Some(taken)) => items += taken,
+                Ok(None) => break,
+                Err(e) => {
+                    read_error = Some(e.into());
+                    break;
+                }
+            }
+            line += 1;
+            if items % width == 0 {
+                boundaries.push((text.len(), items, line));
+            }
+        }
+    }
+    // Note: Only happens at the end of the source, where the last entity is incomplete.
+    if items % width != 0 && read_error.is_none() {
+        boundaries.push((text.len(), items, line));
+    }
+
+    let chunk_items = (items / (rayon::current_num_threads() * CHUNKS_PER_THREAD)).max(width);
+    let mut chunks = Vec::new();
+    let mut start = (0, 0, first_line);
+    for (i, &boundary) in boundaries.iter().enumerate() {
+        if boundary.1 - start.1 >= chunk_items || i + 1 == boundaries.len() {
+            chunks.push(Chunk {
+                start: start.0,
+                end: boundary.0,
+                first_line: start.2,
+                items: boundary.1 - start.1,
+            });
+            start = boundary;
+        }
+    }
+
+    let parsed: Vec<Parsed<T>> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let source = &text[chunk.start..chunk.end];
+            let mut reader = ItemReader::starting_at_line(source, chunk.first_line);
+            let len = chunk.items.div_ceil(width);
+            let mut entities = Vec::with_capacity(len);
+            for _ in 0..len {
+                match parse(&mut reader) {
+                    Ok(entity) => entities.push(entity),
+                    Err(e) => {
+                        return Parsed {
+                            entities,
+                            error: Some(e),
+                        }
+                    }
+                }
+            }
+            Parsed {
+                entities,
+                error: None,
+            }
+        })
+        .collect();
+
+    // Note: Entities of incomplete lines before a read error aren't parsed, like in sequential
+    // reading, which fails while reading them.
+    let mut result = Parsed {
+        entities: Vec::with_capacity((items / width).min(MAX_RESERVE)),
+        error: None,
+    };
+    for chunk in parsed {
+        result.entities.extend(chunk.entities);
+        if chunk.error.is_some() {
+            result.error = chunk.error;
+            return result;
+        }
+    }
+    result.error = read_error;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(text: &str, count: usize) -> (Vec<(u32, u32)>, Option<String>, usize) {
+        let mut reader = ItemReader::new(text.as_bytes());
+        let parsed = read_parallel(&mut reader, count, 2, |reader| {
+            Ok((reader.next_parse()?, reader.next_parse()?))
+        });
+        let rest = reader.next_parse().unwrap_or(0);
+        (parsed.entities, parsed.error.map(|e| e.to_string()), rest)
+    }
+
+    #[test]
+    fn read_in_order() {
+        let text: String = (0..10_000).map(|i| format!("{} {}\n", i, 2 * i)).collect();
+        let (entities, error, _) = pairs(&text, 10_000);
+        assert_eq!(entities.len(), 10_000);
+        assert!(entities
+            .iter()
+            .enumerate()
+            .all(|(i, &p)| p == (i as u32, 2 * i as u32)));
+        assert_eq!(error, None);
+
+        // Entities spanning lines, comments and items after the entities.
+        let (entities, error, rest) = pairs("1\n2 3\n# 4 5\n4 5\n\n6 7 8\n", 3);
+        assert_eq!(entities, vec![(1, 2), (3, 4), (5, 6)]);
+        assert_eq!((error, rest), (None, 7));
+
+        // The current line has more items.
+        let mut reader = ItemReader::new(&b"1 2 3 4\n"[..]);
+        reader.next().unwrap();
+        let parsed = read_parallel(&mut reader, 1, 2, |r| Ok(r.next_parse::<u32>()?));
+        assert!(parsed.entities.is_empty());
+    }
+
+    #[test]
+    fn too_many_items() {
+        let (entities, error, _) = pairs(
+            "1 2
+",
+            usize::MAX,
+        );
+        assert!(entities.is_empty());
+        assert_eq!(
+            error.unwrap(),
+            format!("Syntax error: Too many entities: {}", usize::MAX)
+        );
+    }
+
+    #[test]
+    fn stop_at_first_error() {
+        let mut text: String = (0..1000).map(|i| format!("{} {}\n", i, i)).collect();
+        text.push_str("1000 x\n1001 1001\n1002");
+        let (entities, error, _) = pairs(&text, 1003);
+        assert_eq!(entities.len(), 1000);
+        assert!(error.unwrap().contains("`x` is not a valid u32"));
+
+        // The last entity is incomplete.
+        let text = &text[..text.find("1000 x").unwrap() + 4];
+        let (entities, error, _) = pairs(text, 1001);
+        assert_eq!(entities.len(), 1000);
+        assert!(error.unwrap().contains("Unexpected EOF"));
+    }
+}
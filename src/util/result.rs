@@ -52,6 +52,8 @@ impl<T, W, E> From<Result<T, E>> for WResult<T, W, E> {
     }
 }
 
+// `From` can't be implemented for `Result` because of the orphan rules.
+#[allow(clippy::from_over_into)]
 impl<T, W, E> Into<Result<T, E>> for WResult<T, W, E> {
     fn into(self) -> Result<T, E> {
         self.into_result()
@@ -0,0 +1,69 @@
+//! Helpers to recognize formats by the first bytes of a file, see [Deserializer::sniff].
+//!
+//! [Deserializer::sniff]: ::de::Deserializer::sniff
+
+use std::borrow::Cow;
+
+/// The head as text, or `None` if it looks binary.
+///
+/// A character cut off at the end of the head is replaced, like any other invalid UTF-8.
+pub(crate) fn text(head: &[u8]) -> Option<Cow<'_, str>> {
+    if head.is_empty() || head.contains(&0) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(head);
+    let controls = text
+        .chars()
+        .filter(|c| c.is_control() && !c.is_whitespace())
+        .count();
+    if controls * 10 > text.len() {
+        return None;
+    }
+    Some(text)
+}
+
+/// The non-blank lines which don't start with `comment`, trimmed.
+pub(crate) fn lines<'a>(text: &'a str, comment: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(move |line| !line.is_empty() && !line.starts_with(comment))
+}
+
+/// The local name of the root element of an XML document.
+///
+/// Skips the XML declaration, processing instructions, comments and the document type.
+pub(crate) fn xml_root(text: &str) -> Option<&str> {
+    let mut rest = text.trim_start_matches('\u{feff}').trim_start();
+    loop {
+        if rest.starts_with("<?") {
+            rest = &rest[rest.find("?>")? + 2..];
+        } else if rest.starts_with("<!--") {
+            rest = &rest[rest.find("-->")? + 3..];
+        } else if rest.starts_with("<!") {
+            rest = &rest[rest.find('>')? + 1..];
+        } else if let Some(element) = rest.strip_prefix('<') {
+            let end = element
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .unwrap_or(element.len());
+            let name = &element[..end];
+            return Some(name.rsplit(':').next().unwrap_or(name));
+        } else {
+            return None;
+        }
+        rest = rest.trim_start();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_element() {
+        let xml = "<?xml version=\"1.0\"?>\n<!-- <fake> -->\n<!DOCTYPE x>\n<ns:Xdmf Version=\"3\">";
+        assert_eq!(xml_root(xml), Some("Xdmf"));
+        assert_eq!(xml_root("<amf>"), Some("amf"));
+        assert_eq!(xml_root("mesh3d"), None);
+        assert!(text(b"\x00\x01\x02\x03").is_none());
+    }
+}
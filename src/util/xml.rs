@@ -0,0 +1,79 @@
+//! Helpers for XML based formats, on top of the `roxmltree` DOM.
+
+use error::Error;
+use roxmltree::Node;
+use std::{borrow::Cow, str::FromStr};
+
+/// Get an attribute of an element, failing if it's missing.
+pub(crate) fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str, Error> {
+    node.attribute(name).ok_or_else(|| {
+        Error::Syntax(format!(
+            "Missing attribute `{}` of <{}>.",
+            name,
+            node.tag_name().name()
+        ))
+    })
+}
+
+/// Get an attribute of an element and parse it, failing if it's missing.
+pub(crate) fn parse_attribute<T>(node: Node, name: &str) -> Result<T, Error>
+where
+    T: FromStr,
+{
+    let value = attribute(node, name)?;
+    value.trim().parse().map_err(|_| {
+        Error::Syntax(format!(
+            "Bad value of attribute `{}` of <{}>: {}",
+            name,
+            node.tag_name().name(),
+            value
+        ))
+    })
+}
+
+/// Iterate over the child elements with the (local) tag name `name`.
+pub(crate) fn children<'a, 'i: 'a>(
+    node: Node<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'i>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+/// Get the first child element with the (local) tag name `name`.
+pub(crate) fn child<'a, 'i: 'a>(node: Node<'a, 'i>, name: &'a str) -> Option<Node<'a, 'i>> {
+    children(node, name).next()
+}
+
+/// Get the first child element with the (local) tag name `name`, failing if there is none.
+pub(crate) fn required_child<'a, 'i: 'a>(
+    node: Node<'a, 'i>,
+    name: &'a str,
+) -> Result<Node<'a, 'i>, Error> {
+    child(node, name).ok_or_else(|| {
+        Error::Syntax(format!(
+            "Missing element <{}> in <{}>.",
+            name,
+            node.tag_name().name()
+        ))
+    })
+}
+
+/// Escape a string for use in XML text or attribute values.
+pub(crate) fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(|c| "<>&\"'".contains(c)) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
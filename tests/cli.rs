@@ -0,0 +1,167 @@
+use std::{env, fs, process::Command};
+
+fn multimesh() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_multimesh"))
+}
+
+#[test]
+fn convert_and_info() {
+    let output = env::temp_dir().join("multimesh-cli-two-tets.xdmf");
+    let status = multimesh()
+        .args(["convert", "tests/files/two-tets.vol"])
+        .arg(&output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let info = multimesh().arg("info").arg(&output).output().unwrap();
+    fs::remove_file(&output).unwrap();
+    assert!(info.status.success());
+    let stdout = String::from_utf8(info.stdout).unwrap();
+    assert!(stdout.contains("Format: xdmf"));
+    assert!(stdout.contains("copper"));
+}
+
+#[test]
+fn convert_compressed() {
+    let dir = env::temp_dir();
+    let compressed = dir.join("multimesh-cli-two-tets.vol.zst");
+    let renamed = dir.join("multimesh-cli-two-tets.unknown");
+    let status = multimesh()
+        .args(["convert", "tests/files/two-tets.vol"])
+        .arg(&compressed)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        &fs::read(&compressed).unwrap()[..4],
+        &[0x28, 0xb5, 0x2f, 0xfd]
+    );
+
+    // Recognized by the extension before `.zst`, and by the decompressed content.
+    fs::copy(&compressed, &renamed).unwrap();
+    for path in &[&compressed, &renamed] {
+        let info = multimesh().arg("info").arg(path).output().unwrap();
+        assert!(info.status.success());
+        let stdout = String::from_utf8(info.stdout).unwrap();
+        assert!(stdout.contains("Format: netgen"));
+        assert!(stdout.contains("copper"));
+    }
+    fs::remove_file(&compressed).unwrap();
+    fs::remove_file(&renamed).unwrap();
+}
+
+#[test]
+fn convert_skips_edges() {
+    // The edge segment of the Netgen mesh has no equivalent in UGRID.
+    let output = env::temp_dir().join("multimesh-cli-two-tets.ugrid");
+    let convert = multimesh()
+        .args(["convert", "tests/files/two-tets.vol"])
+        .arg(&output)
+        .output()
+        .unwrap();
+    let written = fs::read_to_string(&output).unwrap();
+    fs::remove_file(&output).unwrap();
+    assert!(convert.status.success());
+    let stderr = String::from_utf8(convert.stderr).unwrap();
+    assert!(stderr.contains("warning: UGRID can't store vertices and edges, skipped 1"));
+    assert_eq!(written.lines().next(), Some("5 4 0 2 0 0 0"));
+}
+
+#[test]
+fn convert_to_medit() {
+    // Groups with free names go into the sections of the cell types of their elements.
+    for (input, sections) in &[
+        ("two-tets.vol", vec!["Edges", "Triangles", "Tetrahedra"]),
+        (
+            "hex-pyramid.msh",
+            vec!["Triangles", "Quadrilaterals", "Pyramids", "Hexahedra"],
+        ),
+        ("cube-wedge.dat", vec!["Prisms", "Hexahedra"]),
+        ("two-objects.amf", vec!["Triangles"]),
+    ] {
+        let output = env::temp_dir().join(format!("multimesh-cli-{}.mesh", input));
+        let status = multimesh()
+            .arg("convert")
+            .arg(format!("tests/files/{}", input))
+            .arg(&output)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{}", input);
+
+        let info = multimesh().arg("info").arg(&output).output().unwrap();
+        fs::remove_file(&output).unwrap();
+        assert!(info.status.success());
+        let stdout = String::from_utf8(info.stdout).unwrap();
+        for section in sections {
+            assert!(stdout.contains(section), "{} in {}", section, input);
+        }
+    }
+}
+
+#[test]
+fn failed_convert_leaves_no_output() {
+    // DOLFIN meshes have one cell type, which can't be a pyramid.
+    let output = env::temp_dir().join("multimesh-cli-hex-pyramid.xml");
+    let convert = multimesh()
+        .args(["convert", "tests/files/hex-pyramid.msh"])
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert_eq!(convert.status.code(), Some(2));
+    assert!(!output.exists());
+    let temporary = fs::read_dir(env::temp_dir())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            let name = entry.file_name();
+            name.to_string_lossy()
+                .starts_with("multimesh-cli-hex-pyramid.xml.")
+        });
+    assert!(!temporary);
+}
+
+#[test]
+fn info_json() {
+    let info = multimesh()
+        .args(["info", "--json", "tests/files/two-tets.vol"])
+        .output()
+        .unwrap();
+    assert!(info.status.success());
+    let stdout = String::from_utf8(info.stdout).unwrap();
+    assert!(stdout.starts_with("{\"dimension\":3,\"groups\":[{\"name\":\"points\""));
+    assert!(stdout.contains("{\"ref\":\"1\",\"count\":2}"));
+}
+
+#[test]
+fn convert_to_unknown_format() {
+    let output = multimesh()
+        .args(["convert", "tests/files/two-tets.vol", "two-tets.unknown"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--to"));
+}
+
+#[test]
+fn validate() {
+    let valid = multimesh()
+        .args(["validate", "tests/files/two-tets.vol"])
+        .output()
+        .unwrap();
+    assert!(valid.status.success());
+
+    // With its triangles reversed, the base of the wedge in the roof points away from its top.
+    let path = env::temp_dir().join("multimesh-cli-inverted-roof.dat");
+    let fixture = fs::read_to_string("tests/files/cube-wedge.dat").unwrap();
+    fs::write(&path, fixture.replace("1 3 2 2 4 6 5 5", "1 2 3 3 4 5 6 6")).unwrap();
+    let inverted = multimesh()
+        .args(["validate", "--format", "tecplot"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(inverted.status.code(), Some(1));
+    let stdout = String::from_utf8(inverted.stdout).unwrap();
+    assert!(stdout.starts_with("roof[0]: Inverted prism"));
+}
@@ -1,8 +1,23 @@
 extern crate multimesh;
 
-use multimesh::data::face_vertex::Mesh;
+use multimesh::data::{
+    attribute::{AttributeContainer, AttributeValue},
+    columnar::ColumnarMesh,
+    face_vertex::Mesh,
+    validation, Entity, GetMesh,
+};
 use multimesh::de::Deserializer;
-use multimesh::format::medit::{MeditDeserializer, MeditSerializer};
+use multimesh::format::amf::{AmfDeserializer, AmfSerializer, Compression};
+use multimesh::format::dolfin::{DolfinDeserializer, DolfinSerializer};
+use multimesh::format::dxf::DxfDeserializer;
+use multimesh::format::fluent::FluentDeserializer;
+use multimesh::format::frd::FrdDeserializer;
+use multimesh::format::lsdyna::{LsDynaDeserializer, LsDynaSerializer};
+use multimesh::format::medit::{MeditDeserializer, MeditSerializer, ParallelMeditDeserializer};
+use multimesh::format::netgen::{NetgenDeserializer, NetgenSerializer};
+use multimesh::format::tecplot::{DataPacking, TecplotDeserializer, TecplotSerializer};
+use multimesh::format::ugrid::{Encoding, UgridDeserializer, UgridSerializer};
+use multimesh::format::xdmf::{XdmfDeserializer, XdmfSerializer};
 use multimesh::ser::Serializer;
 use std::fs::File;
 
@@ -15,6 +30,57 @@ fn simple_de_medit() {
     assert_eq!(mesh.metadata().dimension(), 3);
 }
 
+/// The mesh read up to the first error, and the error.
+fn read_until_error<D: Deserializer>(data: &[u8]) -> (String, Option<String>) {
+    let mut mesh = Mesh::default();
+    let result = D::deserialize_into(data, &mut mesh);
+    (format!("{:?}", mesh), result.err().map(|e| e.to_string()))
+}
+
+#[test]
+fn huge_counts_medit() {
+    // The counts are far beyond any memory, but only the missing vertices are an error.
+    for count in &["99999999999999999", "18446744073709551615"] {
+        let data = format!(
+            "MeshVersionFormatted 1\nDimension 3\nVertices\n{}\n0 0 0 0\n",
+            count
+        );
+        let data = data.as_bytes();
+        assert!(MeditDeserializer::deserialize_into(data, &mut Mesh::default()).is_err());
+        let mut columnar = ColumnarMesh::default();
+        assert!(MeditDeserializer::deserialize_into(data, &mut columnar).is_err());
+        let mut columnar = ColumnarMesh::default();
+        assert!(ParallelMeditDeserializer::deserialize_into(data, &mut columnar).is_err());
+    }
+}
+
+#[test]
+fn parallel_de_medit() {
+    let data = include_str!("files/blender-monkey.mesh");
+    let vertex = data.find("0.857738 0.293362 0.462285").unwrap();
+    let broken = vec![
+        data.to_string(),
+        data.replacen("0.857738 0.293362", "0.857738 0.29.3362", 1),
+        data.replacen(
+            "0.857738 0.293362 0.462285 1",
+            "0.857738 0.293362 0.462285",
+            1,
+        ),
+        data[..vertex + 10].to_string(),
+    ];
+    for data in &broken {
+        let parallel = read_until_error::<ParallelMeditDeserializer>(data.as_bytes());
+        assert_eq!(
+            parallel,
+            read_until_error::<MeditDeserializer>(data.as_bytes())
+        );
+    }
+    let (_, error) = read_until_error::<ParallelMeditDeserializer>(broken[1].as_bytes());
+    assert!(error
+        .unwrap()
+        .contains("`0.29.3362` is not a valid f64 in line 301"));
+}
+
 #[test]
 fn simple_ser_medit() {
     let data = include_bytes!("files/blender-monkey.mesh");
@@ -25,3 +91,665 @@ fn simple_ser_medit() {
     let output = File::create("tests/output1.mesh").unwrap();
     ser.serialize(&mesh, output).unwrap();
 }
+
+#[test]
+fn simple_de_fluent() {
+    let data = include_bytes!("files/hex-pyramid.msh");
+    let mut mesh: Mesh = Mesh::default();
+    FluentDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(mesh.node_groups().len(), 1);
+    assert_eq!(mesh.node_groups()[0].entities().len(), 9);
+
+    let names: Vec<&str> = mesh
+        .element_groups()
+        .iter()
+        .map(|group| group.name().get_original().0)
+        .collect();
+    assert_eq!(names, vec!["fluid", "interior-fluid", "wall"]);
+
+    let nodes: Vec<String> = mesh.element_groups()[0]
+        .entities()
+        .iter()
+        .map(|cell| cell.attributes().get(&"nodes".into()).unwrap().to_string())
+        .collect();
+    assert_eq!(nodes, vec!["7 6 5 4 3 2 1 0", "4 5 6 7 8"]);
+    assert_eq!(mesh.element_groups()[2].entities().len(), 9);
+}
+
+fn group_summary(mesh: &Mesh) -> Vec<(String, usize)> {
+    mesh.all_groups()
+        .map(|group| {
+            (
+                group.name().get_original().0.to_string(),
+                group.entities().len(),
+            )
+        })
+        .collect()
+}
+
+/// Assert that two meshes have the same groups, with the same attributes and entities.
+fn assert_same_mesh(a: &Mesh, b: &Mesh) {
+    assert_eq!(a.metadata().dimension(), b.metadata().dimension());
+    assert_eq!(a.all_groups().count(), b.all_groups().count());
+    assert_eq!(group_summary(a), group_summary(b));
+    for (g1, g2) in a.all_groups().zip(b.all_groups()) {
+        assert_eq!(g1.kind(), g2.kind());
+        assert_eq!(g1.attributes(), g2.attributes());
+        assert_eq!(g1.entities().len(), g2.entities().len());
+        for (e1, e2) in g1.entities().iter().zip(g2.entities()) {
+            assert_eq!(e1.attributes(), e2.attributes());
+        }
+    }
+}
+
+#[test]
+fn simple_de_netgen() {
+    let data = include_bytes!("files/two-tets.vol");
+    let mut mesh: Mesh = Mesh::default();
+    NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("points".to_string(), 5),
+            ("steel".to_string(), 1),
+            ("copper".to_string(), 1),
+            ("fixed".to_string(), 2),
+            ("free".to_string(), 2),
+            ("edges-1".to_string(), 1),
+        ]
+    );
+
+    let copper = &mesh.element_groups()[1].entities()[0];
+    // Netgen orients tetrahedra the other way round than the canonical ordering.
+    assert_eq!(
+        copper
+            .attributes()
+            .get(&"nodes".into())
+            .unwrap()
+            .to_string(),
+        "1 2 3 4"
+    );
+    assert_eq!(
+        copper.attributes().get(&"cell".into()).unwrap(),
+        "tetrahedron"
+    );
+    assert_eq!(copper.attributes().get_u64(&"ref".into()), Some(2));
+    let free = &mesh.element_groups()[3].entities()[0];
+    assert_eq!(free.attributes().get_u64(&"domin".into()), Some(2));
+}
+
+#[test]
+fn roundtrip_netgen() {
+    let data = include_bytes!("files/two-tets.vol");
+    let mut mesh: Mesh = Mesh::default();
+    NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    let mut output = Vec::new();
+    NetgenSerializer::new()
+        .serialize(&mesh, &mut output)
+        .unwrap();
+    let mut mesh2: Mesh = Mesh::default();
+    NetgenDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+    assert_same_mesh(&mesh, &mesh2);
+}
+
+/// Volume of the tetrahedron spanned by the first four nodes of an element.
+fn tetrahedron_volume(mesh: &Mesh, nodes: &str) -> f64 {
+    let all_nodes: Vec<_> = mesh
+        .node_groups()
+        .iter()
+        .flat_map(|group| group.entities())
+        .collect();
+    let p: Vec<Vec<f64>> = nodes
+        .split_whitespace()
+        .take(4)
+        .map(|i| {
+            let node = all_nodes[i.parse::<usize>().unwrap()];
+            ["x", "y", "z"]
+                .iter()
+                .map(|&key| node.attributes().get_f64(&key.into()).unwrap())
+                .collect()
+        })
+        .collect();
+    let d = |i: usize| [p[i][0] - p[0][0], p[i][1] - p[0][1], p[i][2] - p[0][2]];
+    let (u, v, w) = (d(1), d(2), d(3));
+    (u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0]))
+        / 6.
+}
+
+#[test]
+fn convert_netgen_to_xdmf_keeps_orientation() {
+    let data = include_bytes!("files/two-tets.vol");
+    let mut mesh: Mesh = Mesh::default();
+    NetgenDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    let mut output = Vec::new();
+    XdmfSerializer::new().serialize(&mesh, &mut output).unwrap();
+    let mut xdmf: Mesh = Mesh::default();
+    XdmfDeserializer::deserialize_into(&output[..], &mut xdmf).unwrap();
+
+    let tetrahedra: Vec<String> = xdmf
+        .element_groups()
+        .iter()
+        .flat_map(|group| group.entities())
+        .filter(|e| e.attributes().get(&"cell".into()).unwrap() == "tetrahedron")
+        .map(|e| e.attributes().get(&"nodes".into()).unwrap().to_string())
+        .collect();
+    assert_eq!(tetrahedra.len(), 2);
+    for nodes in tetrahedra {
+        assert!(tetrahedron_volume(&xdmf, &nodes) > 0.);
+    }
+}
+
+#[test]
+fn simple_de_tecplot() {
+    let data = include_bytes!("files/cube-wedge.dat");
+    let mut mesh: Mesh = Mesh::default();
+    TecplotDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("cube".to_string(), 8),
+            ("roof".to_string(), 6),
+            ("cube".to_string(), 1),
+            ("roof".to_string(), 1),
+        ]
+    );
+
+    let apex = &mesh.node_groups()[1].entities()[2];
+    assert_eq!(apex.attributes().get_f64(&"T".into()), Some(30.));
+    // The degenerate brick of the roof is a prism.
+    let roof = &mesh.element_groups()[1].entities()[0];
+    assert_eq!(
+        roof.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "8 10 9 11 13 12"
+    );
+    assert_eq!(
+        roof.attributes().get(&"cell".into()).unwrap().to_string(),
+        "prism"
+    );
+    assert!(validation::validate(&mesh).is_empty());
+}
+
+#[test]
+fn huge_counts_tecplot() {
+    let data = "VARIABLES = X Y\nZONE N=99999999999999999, E=99999999999999999, \
+                ZONETYPE=FETRIANGLE\n0 0\n1 0\n";
+    let (_, error) = read_until_error::<TecplotDeserializer>(data.as_bytes());
+    assert!(error.is_some());
+}
+
+#[test]
+fn roundtrip_tecplot() {
+    let data = include_bytes!("files/cube-wedge.dat");
+    let mut mesh: Mesh = Mesh::default();
+    TecplotDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    for &packing in &[DataPacking::Point, DataPacking::Block] {
+        let mut output = Vec::new();
+        TecplotSerializer::new(packing)
+            .serialize(&mesh, &mut output)
+            .unwrap();
+        let mut mesh2: Mesh = Mesh::default();
+        TecplotDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+        assert_same_mesh(&mesh, &mesh2);
+    }
+}
+
+#[test]
+fn roundtrip_tecplot_cell_types() {
+    // Pyramids and prisms are written as degenerate bricks and read back as they were.
+    let data = include_bytes!("files/hex-pyramid.msh");
+    let mut mesh: Mesh = Mesh::default();
+    FluentDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+    let mut output = Vec::new();
+    TecplotSerializer::new(DataPacking::Point)
+        .serialize(&mesh, &mut output)
+        .unwrap();
+    let mut mesh2: Mesh = Mesh::default();
+    TecplotDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+    let cells = |mesh: &Mesh| -> Vec<String> {
+        mesh.element_groups()
+            .iter()
+            .flat_map(|group| group.entities())
+            .map(|e| e.attributes().get(&"cell".into()).unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(cells(&mesh), cells(&mesh2));
+    assert!(cells(&mesh2).contains(&"pyramid".to_string()));
+    assert!(validation::validate(&mesh2).is_empty());
+}
+
+#[test]
+fn roundtrip_ugrid_volumes() {
+    // UGRID numbers pyramids differently, which must not invert them.
+    let data = include_bytes!("files/hex-pyramid.msh");
+    let mut mesh: Mesh = Mesh::default();
+    FluentDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+    assert!(validation::validate(&mesh).is_empty());
+
+    let mut output = Vec::new();
+    UgridSerializer::new(Encoding::Ascii)
+        .serialize(&mesh, &mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().next(), Some("9 4 6 0 1 0 1"));
+    let mut mesh2: Mesh = Mesh::default();
+    UgridDeserializer::deserialize_into(output.as_bytes(), &mut mesh2).unwrap();
+    assert!(validation::validate(&mesh2).is_empty());
+
+    let pyramids = |mesh: &Mesh| -> Vec<Vec<usize>> {
+        mesh.element_groups()
+            .iter()
+            .flat_map(|group| group.entities())
+            .filter(|e| e.attributes().get(&"cell".into()).unwrap().as_str() == Some("pyramid"))
+            .map(|e| e.node_indices().unwrap())
+            .collect()
+    };
+    assert_eq!(pyramids(&mesh), pyramids(&mesh2));
+}
+
+#[test]
+fn simple_de_dolfin() {
+    let data = include_bytes!("files/two-tets.xml");
+    let mut mesh: Mesh = Mesh::default();
+    DolfinDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("vertices".to_string(), 5),
+            ("tetrahedron".to_string(), 2),
+            ("boundaries".to_string(), 2),
+        ]
+    );
+
+    let corner = &mesh.node_groups()[0].entities()[4];
+    assert_eq!(
+        corner.attributes().get(&"corners".into()),
+        Some(&AttributeValue::I64(-1))
+    );
+    let cell = &mesh.element_groups()[0].entities()[1];
+    assert_eq!(
+        cell.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "1 2 3 4"
+    );
+    assert_eq!(cell.attributes().get_u64(&"subdomains".into()), Some(2));
+    let facet = &mesh.element_groups()[1].entities()[1];
+    assert_eq!(
+        facet.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "2 3 4"
+    );
+    assert_eq!(facet.attributes().get_u64(&"boundaries".into()), Some(8));
+}
+
+#[test]
+fn roundtrip_dolfin() {
+    let data = include_bytes!("files/two-tets.xml");
+    let mut mesh: Mesh = Mesh::default();
+    DolfinDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    let mut output = Vec::new();
+    DolfinSerializer::new()
+        .serialize(&mesh, &mut output)
+        .unwrap();
+    let mut mesh2: Mesh = Mesh::default();
+    DolfinDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+    assert_same_mesh(&mesh, &mesh2);
+}
+
+#[test]
+fn simple_de_xdmf() {
+    let data = include_bytes!("files/two-tets.xdmf");
+    let mut mesh: Mesh = Mesh::default();
+    XdmfDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("mesh".to_string(), 5),
+            ("boundaries".to_string(), 5),
+            ("mesh".to_string(), 2),
+            ("boundaries".to_string(), 2),
+        ]
+    );
+
+    let node = &mesh.node_groups()[0].entities()[1];
+    assert_eq!(
+        node.attributes()
+            .get(&"velocity".into())
+            .unwrap()
+            .to_string(),
+        "1 0 0"
+    );
+    let facet = &mesh.element_groups()[1].entities()[1];
+    assert_eq!(
+        facet.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "7 8 9"
+    );
+    assert_eq!(facet.attributes().get_u64(&"boundaries".into()), Some(8));
+}
+
+#[test]
+fn roundtrip_xdmf() {
+    let data = include_bytes!("files/two-tets.xdmf");
+    let mut mesh: Mesh = Mesh::default();
+    XdmfDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    let mut output = Vec::new();
+    XdmfSerializer::new().serialize(&mesh, &mut output).unwrap();
+    let mut mesh2: Mesh = Mesh::default();
+    XdmfDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+    assert_same_mesh(&mesh, &mesh2);
+}
+
+#[test]
+fn simple_de_frd() {
+    let data = include_bytes!("files/hex20-tet.frd");
+    let mut mesh: Mesh = Mesh::default();
+    FrdDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("nodes".to_string(), 21),
+            ("material-1".to_string(), 1),
+            ("material-2".to_string(), 1),
+            ("results".to_string(), 3),
+        ]
+    );
+
+    let hex = &mesh.element_groups()[0].entities()[0];
+    // CalculiX writes the vertical edges before the top edges, they are swapped on reading.
+    let nodes = hex
+        .attributes()
+        .get(&"nodes".into())
+        .unwrap()
+        .to_usize_list()
+        .unwrap();
+    assert_eq!(nodes, (0..20).collect::<Vec<_>>());
+    assert_eq!(
+        hex.attributes().get(&"cell".into()).unwrap(),
+        "hexahedron20"
+    );
+    let tet = &mesh.element_groups()[1].entities()[0];
+    assert_eq!(
+        tet.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "4 5 6 20"
+    );
+    assert_eq!(tet.attributes().get_u64(&"ref".into()), Some(2));
+
+    let apex = &mesh.node_groups()[0].entities()[20];
+    assert_eq!(
+        apex.attributes().get(&"DISP@2".into()).unwrap().to_string(),
+        "0 0 -0.042"
+    );
+    assert_eq!(
+        apex.attributes()
+            .get(&"STRESS@2".into())
+            .unwrap()
+            .to_string(),
+        "1 2 -3 0 0 0"
+    );
+    assert!(mesh.node_groups()[0].entities()[0]
+        .attributes()
+        .get(&"STRESS@2".into())
+        .is_none());
+
+    let disp = &mesh.other_groups()[0].entities()[1];
+    assert_eq!(disp.attributes().get_u64(&"step".into()), Some(2));
+    assert_eq!(
+        disp.attributes().get(&"components".into()).unwrap(),
+        "D1 D2 D3"
+    );
+}
+
+#[test]
+fn simple_de_lsdyna() {
+    let data = include_bytes!("files/parts.k");
+    let mut mesh: Mesh = Mesh::default();
+    LsDynaDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("nodes".to_string(), 14),
+            ("plate".to_string(), 2),
+            ("part-2".to_string(), 2),
+            ("part-3".to_string(), 1),
+            ("bottom".to_string(), 4),
+            ("set-8".to_string(), 2),
+        ]
+    );
+
+    let nodes = |group: usize, i: usize| -> String {
+        let entity = &mesh.element_groups()[group].entities()[i];
+        entity
+            .attributes()
+            .get(&"nodes".into())
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(nodes(0, 1), "1 9 2");
+    assert_eq!(nodes(1, 0), "0 1 2 3 4 5 6 7");
+    assert_eq!(nodes(1, 1), "4 5 6 8");
+    assert_eq!(nodes(2, 0), "5 6 7 8 13 12 11 11 12 13");
+    let tet = &mesh.element_groups()[1].entities()[1];
+    assert_eq!(tet.attributes().get_u64(&"pid".into()), Some(2));
+    let set = &mesh.other_groups()[1].entities()[1];
+    assert_eq!(
+        set.attributes().get(&"nodes".into()).unwrap().to_string(),
+        "10"
+    );
+}
+
+#[test]
+fn roundtrip_lsdyna() {
+    let data = include_bytes!("files/parts.k");
+    let mut mesh: Mesh = Mesh::default();
+    LsDynaDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    let mut output = Vec::new();
+    LsDynaSerializer::new()
+        .serialize(&mesh, &mut output)
+        .unwrap();
+    let mut mesh2: Mesh = Mesh::default();
+    LsDynaDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+    assert_same_mesh(&mesh, &mesh2);
+}
+
+#[test]
+fn simple_de_amf() {
+    let data = include_bytes!("files/two-objects.amf");
+    let mut mesh: Mesh = Mesh::default();
+    AmfDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("tetrahedron".to_string(), 4),
+            ("object-7".to_string(), 4),
+            ("tetrahedron".to_string(), 4),
+            ("bottom".to_string(), 1),
+            ("object-7-2".to_string(), 3),
+        ]
+    );
+
+    let nodes = mesh.node_groups()[0].entities();
+    assert_eq!(
+        nodes[3]
+            .attributes()
+            .get(&"color".into())
+            .unwrap()
+            .to_string(),
+        "1 1 1 0.5"
+    );
+    assert_eq!(
+        mesh.node_groups()[1].entities()[0]
+            .attributes()
+            .get(&"color".into()),
+        None
+    );
+
+    let elements = mesh.element_groups();
+    let material = elements[0].attributes();
+    assert_eq!(material.get(&"material".into()).unwrap(), "1");
+    assert_eq!(material.get(&"material-name".into()).unwrap(), "PLA");
+    assert_eq!(
+        material.get(&"material-color".into()).unwrap().to_string(),
+        "0.8 0.1 0.1"
+    );
+    assert_eq!(
+        elements[1]
+            .attributes()
+            .get(&"color".into())
+            .unwrap()
+            .to_string(),
+        "0 0 0.5"
+    );
+    assert_eq!(
+        elements[1].entities()[0]
+            .attributes()
+            .get(&"color".into())
+            .unwrap()
+            .to_string(),
+        "1 1 0"
+    );
+    assert_eq!(
+        elements[1].entities()[0]
+            .attributes()
+            .get(&"nodes".into())
+            .unwrap()
+            .to_string(),
+        "4 6 5"
+    );
+}
+
+#[test]
+fn roundtrip_amf() {
+    let data = include_bytes!("files/two-objects.amf");
+    let mut mesh: Mesh = Mesh::default();
+    AmfDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    for compression in &[Compression::None, Compression::Zip] {
+        let mut output = Vec::new();
+        AmfSerializer::new(*compression)
+            .serialize(&mesh, &mut output)
+            .unwrap();
+        assert_eq!(output.starts_with(b"PK"), *compression == Compression::Zip);
+        let mut mesh2: Mesh = Mesh::default();
+        AmfDeserializer::deserialize_into(&output[..], &mut mesh2).unwrap();
+
+        assert_same_mesh(&mesh, &mesh2);
+    }
+}
+
+#[test]
+fn simple_de_dxf() {
+    let data = include_bytes!("files/room.dxf");
+    let mut mesh: Mesh = Mesh::default();
+    DxfDeserializer::deserialize_into(&data[..], &mut mesh).unwrap();
+
+    assert_eq!(mesh.metadata().dimension(), 3);
+    assert_eq!(
+        group_summary(&mesh),
+        vec![
+            ("vertices".to_string(), 8),
+            ("floor".to_string(), 2),
+            ("wall".to_string(), 2),
+        ]
+    );
+
+    let faces: Vec<Vec<String>> = mesh
+        .element_groups()
+        .iter()
+        .map(|group| {
+            group
+                .entities()
+                .iter()
+                .map(|face| face.attributes().get(&"nodes".into()).unwrap().to_string())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        faces,
+        vec![vec!["0 1 2", "0 2 3"], vec!["0 1 4 5", "1 6 7 4"]]
+    );
+}
+
+/// Read `data` with `replace` replaced by `with`, which has to fail with a message containing
+/// `error`, and all truncations of `data`, which must not panic.
+fn assert_malformed<D: Deserializer>(data: &str, replace: &str, with: &str, error: &str) {
+    assert!(data.contains(replace));
+    let (_, message) = read_until_error::<D>(data.replacen(replace, with, 1).as_bytes());
+    let message = message.unwrap();
+    assert!(message.contains(error), "{}", message);
+
+    for len in (0..data.len()).step_by(data.len() / 50 + 1) {
+        read_until_error::<D>(&data.as_bytes()[..len]);
+    }
+}
+
+#[test]
+fn malformed_input() {
+    assert_malformed::<TecplotDeserializer>(
+        include_str!("files/cube-wedge.dat"),
+        "1 2 3 4 5 6 7 8",
+        "1 2 3 4 5 6 7 9",
+        "Node 9 not in zone 1",
+    );
+    assert_malformed::<DolfinDeserializer>(
+        include_str!("files/two-tets.xml"),
+        "v3=\"4\"",
+        "v3=\"5\"",
+        "Undefined vertex 5",
+    );
+    assert_malformed::<XdmfDeserializer>(
+        include_str!("files/two-tets.xdmf"),
+        "1 2 3 4\n",
+        "1 2 3 9\n",
+        "Undefined node 9",
+    );
+    assert_malformed::<AmfDeserializer>(
+        include_str!("files/two-objects.amf"),
+        "<v3>1</v3>",
+        "<v3>9</v3>",
+        "Undefined vertex 9",
+    );
+    assert_malformed::<LsDynaDeserializer>(
+        include_str!("files/parts.k"),
+        "4,2,5,6,7,9,9,9,9,9",
+        "4,2,5,6,7,99,99,99,99,99",
+        "Undefined node 99",
+    );
+    assert_malformed::<FrdDeserializer>(
+        include_str!("files/hex20-tet.frd"),
+        " -2        11        12",
+        " -2        11        99",
+        "Undefined node 99",
+    );
+    assert_malformed::<FluentDeserializer>(
+        include_str!("files/hex-pyramid.msh"),
+        "3 8 5 9 2 0",
+        "3 8 5 f 2 0",
+        "Face refers to undefined node f",
+    );
+}